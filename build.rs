@@ -0,0 +1,148 @@
+//! Assembles the MASM sources under `masm/` into serialized artifacts in `OUT_DIR`, and
+//! generates `masm_roots.rs` with the MAST roots of the note and deploy scripts and
+//! `masm_events.rs` with the event kinds of the shared event log. A MASM error fails the
+//! Rust build instead of surfacing at runtime.
+
+use std::{env, fmt::Write as _, fs, path::Path};
+
+use miden_protocol::{Word, utils::serde::Serializable};
+use miden_standards::{code_builder::CodeBuilder, note::P2idNote};
+
+include!("src/masm_sources.rs");
+
+fn main() {
+    println!("cargo:rerun-if-changed=masm");
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    let out_dir = Path::new(&out_dir);
+
+    let event_log = read("masm/shared/event_log.masm");
+    let amm = splice_amm_code(&read("masm/accounts/amm.masm"), &event_log);
+    let liquidity = splice_liquidity_code(
+        &read("masm/accounts/liquidity.masm"),
+        Word::from(P2idNote::script_root()),
+        &event_log,
+    );
+    let governance = read("masm/accounts/governance.masm");
+
+    let with = |ns: &str, source: &str| {
+        CodeBuilder::new()
+            .with_linked_module(ns, source)
+            .unwrap_or_else(|err| fail(&format!("linking {ns}"), err))
+    };
+    let swap = with(AMM_CONTRACT_NS, &amm)
+        .compile_note_script(read("masm/notes/amm_swap_note.masm"))
+        .unwrap_or_else(|err| fail("amm_swap_note.masm", err));
+    let add = with(LIQUIDITY_CONTRACT_NS, &liquidity)
+        .compile_note_script(read("masm/notes/add_liquidity_note.masm"))
+        .unwrap_or_else(|err| fail("add_liquidity_note.masm", err));
+    let remove = with(LIQUIDITY_CONTRACT_NS, &liquidity)
+        .compile_note_script(read("masm/notes/remove_liquidity_note.masm"))
+        .unwrap_or_else(|err| fail("remove_liquidity_note.masm", err));
+    let sync = with(LIQUIDITY_CONTRACT_NS, &liquidity)
+        .compile_note_script(read("masm/notes/sync_note.masm"))
+        .unwrap_or_else(|err| fail("sync_note.masm", err));
+    let skim = with(LIQUIDITY_CONTRACT_NS, &liquidity)
+        .compile_note_script(read("masm/notes/skim_note.masm"))
+        .unwrap_or_else(|err| fail("skim_note.masm", err));
+    let propose = with(GOVERNANCE_NS, &governance)
+        .compile_note_script(read("masm/notes/propose_allowlist_note.masm"))
+        .unwrap_or_else(|err| fail("propose_allowlist_note.masm", err));
+    let execute = with(GOVERNANCE_NS, &governance)
+        .compile_note_script(read("masm/notes/execute_allowlist_note.masm"))
+        .unwrap_or_else(|err| fail("execute_allowlist_note.masm", err));
+    let deploy = with(AMM_CONTRACT_NS, &amm)
+        .compile_tx_script(read("masm/scripts/deploy_script.masm"))
+        .unwrap_or_else(|err| fail("deploy_script.masm", err));
+    let amm_component = CodeBuilder::new()
+        .compile_component_code(AMM_CONTRACT_NS, amm.as_str())
+        .unwrap_or_else(|err| fail("amm.masm", err));
+    let liquidity_component = CodeBuilder::new()
+        .compile_component_code(LIQUIDITY_CONTRACT_NS, liquidity.as_str())
+        .unwrap_or_else(|err| fail("liquidity.masm", err));
+    let lp_metadata_component = CodeBuilder::new()
+        .compile_component_code(LP_METADATA_NS, read("masm/accounts/lp_metadata.masm"))
+        .unwrap_or_else(|err| fail("lp_metadata.masm", err));
+    let governance_component = CodeBuilder::new()
+        .compile_component_code(GOVERNANCE_NS, governance.as_str())
+        .unwrap_or_else(|err| fail("governance.masm", err));
+
+    write(out_dir, "amm_swap_note.masb", swap.to_bytes());
+    write(out_dir, "add_liquidity_note.masb", add.to_bytes());
+    write(out_dir, "remove_liquidity_note.masb", remove.to_bytes());
+    write(out_dir, "sync_note.masb", sync.to_bytes());
+    write(out_dir, "skim_note.masb", skim.to_bytes());
+    write(out_dir, "propose_allowlist_note.masb", propose.to_bytes());
+    write(out_dir, "execute_allowlist_note.masb", execute.to_bytes());
+    write(out_dir, "deploy_script.masb", deploy.to_bytes());
+    write(out_dir, "amm.masl", amm_component.as_library().to_bytes());
+    write(
+        out_dir,
+        "liquidity.masl",
+        liquidity_component.as_library().to_bytes(),
+    );
+    write(
+        out_dir,
+        "lp_metadata.masl",
+        lp_metadata_component.as_library().to_bytes(),
+    );
+    write(
+        out_dir,
+        "governance.masl",
+        governance_component.as_library().to_bytes(),
+    );
+
+    let mut roots = String::new();
+    for (name, root) in [
+        ("SWAP_NOTE_SCRIPT_ROOT", Word::from(swap.root())),
+        ("ADD_LIQUIDITY_NOTE_SCRIPT_ROOT", Word::from(add.root())),
+        (
+            "REMOVE_LIQUIDITY_NOTE_SCRIPT_ROOT",
+            Word::from(remove.root()),
+        ),
+        ("SYNC_NOTE_SCRIPT_ROOT", Word::from(sync.root())),
+        ("SKIM_NOTE_SCRIPT_ROOT", Word::from(skim.root())),
+        (
+            "PROPOSE_ALLOWLIST_NOTE_SCRIPT_ROOT",
+            Word::from(propose.root()),
+        ),
+        (
+            "EXECUTE_ALLOWLIST_NOTE_SCRIPT_ROOT",
+            Word::from(execute.root()),
+        ),
+        ("DEPLOY_SCRIPT_ROOT", Word::from(deploy.root())),
+    ] {
+        let felts: Vec<String> = root
+            .iter()
+            .map(|felt| format!("Felt::new_unchecked({})", felt.as_canonical_u64()))
+            .collect();
+        writeln!(
+            roots,
+            "pub const {name}: Word = Word::new([{}]);",
+            felts.join(", ")
+        )
+        .expect("writing to a String");
+    }
+    write(out_dir, "masm_roots.rs", roots.into_bytes());
+
+    // the numeric `const EVENT_* = N` lines of the event log, for `src/events.rs`
+    let mut events = String::new();
+    for (name, value) in event_log.lines().filter_map(|line| {
+        let (name, value) = line.strip_prefix("const ")?.split_once(" = ")?;
+        Some((name, value.parse::<u64>().ok()?)).filter(|_| name.starts_with("EVENT_"))
+    }) {
+        writeln!(events, "pub const {name}: u64 = {value};").expect("writing to a String");
+    }
+    write(out_dir, "masm_events.rs", events.into_bytes());
+}
+
+fn read(path: &str) -> String {
+    fs::read_to_string(path).unwrap_or_else(|err| panic!("reading {path}: {err}"))
+}
+
+fn write(out_dir: &Path, name: &str, bytes: Vec<u8>) {
+    fs::write(out_dir.join(name), bytes).unwrap_or_else(|err| panic!("writing {name}: {err}"));
+}
+
+fn fail(what: &str, err: impl std::fmt::Display) -> ! {
+    panic!("assembling {what} failed:\n{err}")
+}
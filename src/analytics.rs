@@ -0,0 +1,315 @@
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+
+use miden_client::{
+    account::AccountId,
+    asset::Asset,
+    block::BlockNumber,
+    note::{Note, NoteScriptRoot},
+    rpc::NodeRpcClient,
+};
+use miden_protocol::block::ProvenBlock;
+
+use crate::{
+    common::{FEE_DENOM, PoolState},
+    history::{AmmChainWalker, AmmNotes, amm_delta},
+    manifest::AmmManifest,
+};
+
+// =================================================================================================
+// CONSTANTS
+// =================================================================================================
+
+/// Seconds in a (365-day) year, the period [`PoolStats::apr`] is annualized to.
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+// =================================================================================================
+// TIME SERIES
+// =================================================================================================
+
+/// The pool at the end of a block that changed it, and the swaps the block executed.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct PoolSample {
+    pub block_num: u32,
+    /// Block timestamp, in seconds since the Unix epoch.
+    pub timestamp: u32,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+    pub fee_bps: u64,
+    /// Price of X in units of Y implied by the reserves (0 for an empty pool).
+    pub price: f64,
+    /// [`PoolState::value_per_share`]: its growth is what the fees earned per LP token.
+    pub value_per_share: f64,
+    pub swaps: u32,
+    /// X and Y paid into the pool by the block's swaps.
+    pub volume_x: u128,
+    pub volume_y: u128,
+}
+
+impl PoolSample {
+    fn new(block_num: u32, timestamp: u32, pool: &PoolState) -> Self {
+        let price = if pool.reserve_x == 0 {
+            0.0
+        } else {
+            pool.reserve_y as f64 / pool.reserve_x as f64
+        };
+        PoolSample {
+            block_num,
+            timestamp,
+            reserve_x: pool.reserve_x,
+            reserve_y: pool.reserve_y,
+            lp_supply: pool.lp_supply,
+            fee_bps: pool.fee_bps,
+            price,
+            value_per_share: pool.value_per_share(),
+            swaps: 0,
+            volume_x: 0,
+            volume_y: 0,
+        }
+    }
+
+    /// Fees the block's swaps paid, in X and Y: the input amounts times the fee.
+    pub fn fees(&self) -> (f64, f64) {
+        let rate = self.fee_bps as f64 / FEE_DENOM as f64;
+        (self.volume_x as f64 * rate, self.volume_y as f64 * rate)
+    }
+}
+
+/// The history of one pool: a [`PoolSample`] per block that changed the AMM account, in
+/// block order.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct PoolSeries {
+    pub samples: Vec<PoolSample>,
+}
+
+impl PoolSeries {
+    /// The latest sample at or before `block_num`.
+    pub fn at_block(&self, block_num: u32) -> Option<&PoolSample> {
+        self.samples.iter().rev().find(|s| s.block_num <= block_num)
+    }
+
+    /// Volume, fees and LP returns between the pool state at `from_block` and at `to_block`.
+    /// `None` if the pool did not exist yet at `to_block`.
+    pub fn stats(&self, from_block: u32, to_block: u32) -> Option<PoolStats> {
+        self.window(|s| s.block_num <= from_block, |s| s.block_num <= to_block)
+    }
+
+    /// [`PoolSeries::stats`] from the state at `timestamp` to the latest sample, e.g. "what
+    /// did LPs earn this week".
+    pub fn stats_since(&self, timestamp: u32) -> Option<PoolStats> {
+        self.window(|s| s.timestamp <= timestamp, |_| true)
+    }
+
+    /// The window opens at the last sample matching `open` (or the first sample, for a pool
+    /// created later) and closes at the last sample matching `close`.
+    fn window(
+        &self,
+        open: impl Fn(&PoolSample) -> bool,
+        close: impl Fn(&PoolSample) -> bool,
+    ) -> Option<PoolStats> {
+        let close = self.samples.iter().rposition(close)?;
+        let open = self.samples.iter().rposition(open).unwrap_or(0).min(close);
+        Some(PoolStats::new(&self.samples[open..=close]))
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("serializing pool series")
+    }
+}
+
+/// Aggregates over a window of a [`PoolSeries`]. The opening sample is the state the window
+/// starts from, so its own swaps are not counted.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PoolStats {
+    pub from_block: u32,
+    pub to_block: u32,
+    pub from_timestamp: u32,
+    pub to_timestamp: u32,
+    pub open_price: f64,
+    pub close_price: f64,
+    pub swaps: u64,
+    pub volume_x: u128,
+    pub volume_y: u128,
+    pub fees_x: f64,
+    pub fees_y: f64,
+    pub open_value_per_share: f64,
+    pub close_value_per_share: f64,
+    /// Relative growth of `sqrt(k)/S` over the window: what the fees earned per LP token.
+    /// `None` if the pool had no LP supply when the window opened.
+    pub fee_growth: Option<f64>,
+    /// `fee_growth` annualized (simple interest over [`SECONDS_PER_YEAR`]). `None` as well
+    /// for a window of zero duration.
+    pub apr: Option<f64>,
+}
+
+impl PoolStats {
+    fn new(samples: &[PoolSample]) -> Self {
+        let (first, last) = (samples[0], samples[samples.len() - 1]);
+        let mut stats = PoolStats {
+            from_block: first.block_num,
+            to_block: last.block_num,
+            from_timestamp: first.timestamp,
+            to_timestamp: last.timestamp,
+            open_price: first.price,
+            close_price: last.price,
+            swaps: 0,
+            volume_x: 0,
+            volume_y: 0,
+            fees_x: 0.0,
+            fees_y: 0.0,
+            open_value_per_share: first.value_per_share,
+            close_value_per_share: last.value_per_share,
+            fee_growth: None,
+            apr: None,
+        };
+        for sample in &samples[1..] {
+            let (fees_x, fees_y) = sample.fees();
+            stats.swaps += sample.swaps as u64;
+            stats.volume_x += sample.volume_x;
+            stats.volume_y += sample.volume_y;
+            stats.fees_x += fees_x;
+            stats.fees_y += fees_y;
+        }
+        if first.value_per_share > 0.0 {
+            let growth = last.value_per_share / first.value_per_share - 1.0;
+            let duration = last.timestamp.saturating_sub(first.timestamp);
+            stats.fee_growth = Some(growth);
+            stats.apr = (duration > 0).then(|| growth * SECONDS_PER_YEAR as f64 / duration as f64);
+        }
+        stats
+    }
+}
+
+// =================================================================================================
+// CHAIN WALK
+// =================================================================================================
+
+/// Builds the [`PoolSeries`] of a public AMM account from chain data.
+///
+/// The pool state after each block comes from the AMM's account delta in that block, so
+/// everything that moved the reserves is covered, including syncs of donated balances. A
+/// block's delta is the net of all its AMM transactions, though, so the volume comes from
+/// the swap notes the AMM consumed in the block instead: register the public notes sent to
+/// the AMM with [`PoolAnalytics::add_note`] before applying the block that consumed them.
+pub struct PoolAnalytics {
+    amm_id: AccountId,
+    pool_x_faucet: AccountId,
+    swap_root: NoteScriptRoot,
+    pool: PoolState,
+    notes: AmmNotes,
+    series: PoolSeries,
+}
+
+impl PoolAnalytics {
+    /// Prepares a walk over the history of the pool deployed with `manifest`, starting from
+    /// an empty pool.
+    pub fn new(manifest: &AmmManifest) -> Self {
+        PoolAnalytics {
+            amm_id: manifest.amm_id,
+            pool_x_faucet: manifest.pool_x_faucet,
+            swap_root: manifest.swap_note_script.root(),
+            pool: PoolState::new(manifest.fee_bps),
+            notes: AmmNotes::default(),
+            series: PoolSeries::default(),
+        }
+    }
+
+    /// The pool state after the last applied block.
+    pub fn pool(&self) -> PoolState {
+        self.pool
+    }
+
+    pub fn series(&self) -> &PoolSeries {
+        &self.series
+    }
+
+    pub fn into_series(self) -> PoolSeries {
+        self.series
+    }
+
+    /// Registers a public note sent to the AMM.
+    pub fn add_note(&mut self, note: &Note) {
+        self.notes.insert(note.clone());
+    }
+
+    /// Applies one block. Adds a sample if the block updated the AMM account; fails if the
+    /// account is private (its deltas are not on chain) or if an AMM transaction consumed a
+    /// note that was never registered.
+    pub fn apply_block(&mut self, block: &ProvenBlock) -> Result<()> {
+        let header = block.header();
+        let Some(delta) = amm_delta(block, self.amm_id)? else {
+            return Ok(());
+        };
+        self.pool.apply_delta(delta);
+
+        let mut sample =
+            PoolSample::new(header.block_num().as_u32(), header.timestamp(), &self.pool);
+        let amm_txs = block
+            .body()
+            .transactions()
+            .as_slice()
+            .iter()
+            .filter(|tx| tx.account_id() == self.amm_id);
+        for tx in amm_txs {
+            for input in tx.input_notes().iter() {
+                let note = self.notes.consumed(input.nullifier(), tx.id())?;
+                if let Some((amount, x_in)) = self.swap_input(note)? {
+                    sample.swaps += 1;
+                    if x_in {
+                        sample.volume_x += amount as u128;
+                    } else {
+                        sample.volume_y += amount as u128;
+                    }
+                }
+            }
+        }
+        self.series.samples.push(sample);
+        Ok(())
+    }
+
+    /// The input of a swap note: (amount, x_in). `None` for other notes.
+    fn swap_input(&self, note: &Note) -> Result<Option<(u64, bool)>> {
+        if note.script().root() != self.swap_root {
+            return Ok(None);
+        }
+        let assets: Vec<&Asset> = note.assets().iter().collect();
+        let [Asset::Fungible(asset_in)] = assets.as_slice() else {
+            bail!(
+                "swap note {} must carry exactly one fungible asset",
+                note.id()
+            );
+        };
+        Ok(Some((
+            asset_in.amount().as_u64(),
+            asset_in.faucet_id() == self.pool_x_faucet,
+        )))
+    }
+}
+
+// =================================================================================================
+// CLIENT HELPERS (live network)
+// =================================================================================================
+
+/// Walks the chain up to `block_to` and returns the history of the pool deployed with
+/// `manifest`.
+///
+/// Walks the AMM's history with an [`AmmChainWalker`] and feeds the notes sent to the AMM and
+/// the blocks with AMM transactions (plus genesis, for pools created there) to a
+/// [`PoolAnalytics`].
+pub async fn fetch_pool_series(
+    rpc: &dyn NodeRpcClient,
+    manifest: &AmmManifest,
+    block_to: BlockNumber,
+) -> Result<PoolSeries> {
+    let mut analytics = PoolAnalytics::new(manifest);
+    let mut walker = AmmChainWalker::new(manifest.amm_id);
+    let blocks = walker.advance(rpc, block_to).await?;
+    for note in walker.notes().iter() {
+        analytics.add_note(note);
+    }
+    for block in blocks {
+        analytics.apply_block(&block.fetch(rpc).await?)?;
+    }
+    Ok(analytics.into_series())
+}
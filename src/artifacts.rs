@@ -0,0 +1,150 @@
+use std::sync::LazyLock;
+
+use miden_client::{
+    Deserializable, Felt, Word, account::AccountComponentCode, assembly::Library, note::NoteScript,
+    transaction::TransactionScript,
+};
+
+// =================================================================================================
+// SCRIPT ROOTS
+// =================================================================================================
+
+// `SWAP_NOTE_SCRIPT_ROOT`, `ADD_LIQUIDITY_NOTE_SCRIPT_ROOT`,
+// `REMOVE_LIQUIDITY_NOTE_SCRIPT_ROOT`, `SYNC_NOTE_SCRIPT_ROOT`, `SKIM_NOTE_SCRIPT_ROOT`,
+// `PROPOSE_ALLOWLIST_NOTE_SCRIPT_ROOT`, `EXECUTE_ALLOWLIST_NOTE_SCRIPT_ROOT` and
+// `DEPLOY_SCRIPT_ROOT`, generated by `build.rs` from the assembled scripts. They change only
+// when the MASM (or the assembler) does.
+include!(concat!(env!("OUT_DIR"), "/masm_roots.rs"));
+
+// =================================================================================================
+// PRECOMPILED MASM
+// =================================================================================================
+
+// Serialized by `build.rs`; a MASM error fails the build, so these always deserialize.
+static SWAP_NOTE: LazyLock<NoteScript> = LazyLock::new(|| {
+    note_script(include_bytes!(concat!(
+        env!("OUT_DIR"),
+        "/amm_swap_note.masb"
+    )))
+});
+static ADD_LIQUIDITY_NOTE: LazyLock<NoteScript> = LazyLock::new(|| {
+    note_script(include_bytes!(concat!(
+        env!("OUT_DIR"),
+        "/add_liquidity_note.masb"
+    )))
+});
+static REMOVE_LIQUIDITY_NOTE: LazyLock<NoteScript> = LazyLock::new(|| {
+    note_script(include_bytes!(concat!(
+        env!("OUT_DIR"),
+        "/remove_liquidity_note.masb"
+    )))
+});
+static SYNC_NOTE: LazyLock<NoteScript> =
+    LazyLock::new(|| note_script(include_bytes!(concat!(env!("OUT_DIR"), "/sync_note.masb"))));
+static SKIM_NOTE: LazyLock<NoteScript> =
+    LazyLock::new(|| note_script(include_bytes!(concat!(env!("OUT_DIR"), "/skim_note.masb"))));
+static PROPOSE_ALLOWLIST_NOTE: LazyLock<NoteScript> = LazyLock::new(|| {
+    note_script(include_bytes!(concat!(
+        env!("OUT_DIR"),
+        "/propose_allowlist_note.masb"
+    )))
+});
+static EXECUTE_ALLOWLIST_NOTE: LazyLock<NoteScript> = LazyLock::new(|| {
+    note_script(include_bytes!(concat!(
+        env!("OUT_DIR"),
+        "/execute_allowlist_note.masb"
+    )))
+});
+static DEPLOY_SCRIPT: LazyLock<TransactionScript> = LazyLock::new(|| {
+    TransactionScript::read_from_bytes(include_bytes!(concat!(
+        env!("OUT_DIR"),
+        "/deploy_script.masb"
+    )))
+    .expect("deploy_script.masb is produced by build.rs")
+});
+static AMM_COMPONENT: LazyLock<AccountComponentCode> =
+    LazyLock::new(|| component_code(include_bytes!(concat!(env!("OUT_DIR"), "/amm.masl"))));
+static LIQUIDITY_COMPONENT: LazyLock<AccountComponentCode> =
+    LazyLock::new(|| component_code(include_bytes!(concat!(env!("OUT_DIR"), "/liquidity.masl"))));
+static LP_METADATA_COMPONENT: LazyLock<AccountComponentCode> = LazyLock::new(|| {
+    component_code(include_bytes!(concat!(
+        env!("OUT_DIR"),
+        "/lp_metadata.masl"
+    )))
+});
+static GOVERNANCE_COMPONENT: LazyLock<AccountComponentCode> = LazyLock::new(|| {
+    component_code(include_bytes!(concat!(
+        env!("OUT_DIR"),
+        "/governance.masl"
+    )))
+});
+
+fn note_script(bytes: &[u8]) -> NoteScript {
+    NoteScript::read_from_bytes(bytes).expect("note script artifact is produced by build.rs")
+}
+
+fn component_code(bytes: &[u8]) -> AccountComponentCode {
+    Library::read_from_bytes(bytes)
+        .expect("component library artifact is produced by build.rs")
+        .into()
+}
+
+/// `amm_swap_note.masm`, linked against the AMM component.
+pub fn swap_note_script() -> NoteScript {
+    SWAP_NOTE.clone()
+}
+
+/// `add_liquidity_note.masm`, linked against the liquidity component.
+pub fn add_liquidity_note_script() -> NoteScript {
+    ADD_LIQUIDITY_NOTE.clone()
+}
+
+/// `remove_liquidity_note.masm`, linked against the liquidity component.
+pub fn remove_liquidity_note_script() -> NoteScript {
+    REMOVE_LIQUIDITY_NOTE.clone()
+}
+
+/// `sync_note.masm`, linked against the liquidity component.
+pub fn sync_note_script() -> NoteScript {
+    SYNC_NOTE.clone()
+}
+
+/// `skim_note.masm`, linked against the liquidity component.
+pub fn skim_note_script() -> NoteScript {
+    SKIM_NOTE.clone()
+}
+
+/// `propose_allowlist_note.masm`, linked against the governance component.
+pub fn propose_allowlist_note_script() -> NoteScript {
+    PROPOSE_ALLOWLIST_NOTE.clone()
+}
+
+/// `execute_allowlist_note.masm`, linked against the governance component.
+pub fn execute_allowlist_note_script() -> NoteScript {
+    EXECUTE_ALLOWLIST_NOTE.clone()
+}
+
+/// `deploy_script.masm`, linked against the AMM component.
+pub fn deploy_tx_script() -> TransactionScript {
+    DEPLOY_SCRIPT.clone()
+}
+
+/// `amm.masm` as component code.
+pub fn amm_component_code() -> AccountComponentCode {
+    AMM_COMPONENT.clone()
+}
+
+/// `liquidity.masm`, with the P2ID script root injected, as component code.
+pub fn liquidity_component_code() -> AccountComponentCode {
+    LIQUIDITY_COMPONENT.clone()
+}
+
+/// `lp_metadata.masm`: the fungible-faucet metadata accessors, without mint or burn.
+pub fn lp_metadata_component_code() -> AccountComponentCode {
+    LP_METADATA_COMPONENT.clone()
+}
+
+/// `governance.masm`: the timelocked note-allowlist changes.
+pub fn governance_component_code() -> AccountComponentCode {
+    GOVERNANCE_COMPONENT.clone()
+}
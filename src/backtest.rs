@@ -0,0 +1,520 @@
+use std::{collections::BTreeMap, io::Read, path::Path};
+
+use anyhow::{Context, Result, bail, ensure};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{FEE_DENOM, PoolState, quote_remove_liquidity},
+    errors::AmmError,
+    position::fee_share,
+};
+
+// =================================================================================================
+// TRADE LOG
+// =================================================================================================
+
+/// One entry of a trade log. Accounts are free-form names: the simulator only needs to tell
+/// LPs apart.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Trade {
+    /// `amount_in` of X (`x_in = true`) or Y swapped for the other asset.
+    Swap {
+        account: String,
+        amount_in: u64,
+        x_in: bool,
+    },
+    Deposit {
+        account: String,
+        amount_x: u64,
+        amount_y: u64,
+    },
+    /// Burns `lp_amount` of the account's LP tokens.
+    Withdraw { account: String, lp_amount: u64 },
+}
+
+/// A trade log row, shared by the CSV and JSON formats:
+///
+/// ```text
+/// op,account,amount_x,amount_y,lp_amount
+/// deposit,alice,100000,400000,
+/// swap_x,bob,5000,,
+/// swap_y,bob,,20000,
+/// withdraw,alice,,,50000
+/// ```
+///
+/// In JSON the log is an array of objects with the same fields; unused amounts may be left
+/// out.
+#[derive(Debug, Deserialize)]
+struct TradeRecord {
+    op: String,
+    account: String,
+    amount_x: Option<u64>,
+    amount_y: Option<u64>,
+    lp_amount: Option<u64>,
+}
+
+impl TryFrom<TradeRecord> for Trade {
+    type Error = anyhow::Error;
+
+    fn try_from(record: TradeRecord) -> Result<Self> {
+        let TradeRecord {
+            op,
+            account,
+            amount_x,
+            amount_y,
+            lp_amount,
+        } = record;
+        let (x, y, lp) = (
+            amount_x.unwrap_or(0),
+            amount_y.unwrap_or(0),
+            lp_amount.unwrap_or(0),
+        );
+        let trade = match op.as_str() {
+            "swap_x" => {
+                ensure!(y == 0 && lp == 0, "swap_x takes amount_x only");
+                ensure!(x != 0, "swap_x needs a nonzero amount_x");
+                Trade::Swap {
+                    account,
+                    amount_in: x,
+                    x_in: true,
+                }
+            }
+            "swap_y" => {
+                ensure!(x == 0 && lp == 0, "swap_y takes amount_y only");
+                ensure!(y != 0, "swap_y needs a nonzero amount_y");
+                Trade::Swap {
+                    account,
+                    amount_in: y,
+                    x_in: false,
+                }
+            }
+            "deposit" => {
+                ensure!(lp == 0, "deposit takes amount_x and amount_y only");
+                ensure!(
+                    x != 0 && y != 0,
+                    "deposit needs nonzero amount_x and amount_y"
+                );
+                Trade::Deposit {
+                    account,
+                    amount_x: x,
+                    amount_y: y,
+                }
+            }
+            "withdraw" => {
+                ensure!(x == 0 && y == 0, "withdraw takes lp_amount only");
+                ensure!(lp != 0, "withdraw needs a nonzero lp_amount");
+                Trade::Withdraw {
+                    account,
+                    lp_amount: lp,
+                }
+            }
+            other => bail!("unknown trade op {other:?}"),
+        };
+        Ok(trade)
+    }
+}
+
+impl Trade {
+    pub fn account(&self) -> &str {
+        match self {
+            Trade::Swap { account, .. }
+            | Trade::Deposit { account, .. }
+            | Trade::Withdraw { account, .. } => account,
+        }
+    }
+
+    /// Reads a CSV trade log with the header `op,account,amount_x,amount_y,lp_amount`.
+    pub fn read_csv(reader: impl Read) -> Result<Vec<Self>> {
+        csv::Reader::from_reader(reader)
+            .deserialize()
+            .enumerate()
+            .map(|(i, record)| {
+                let record: TradeRecord =
+                    record.with_context(|| format!("parsing trade log row {}", i + 1))?;
+                Trade::try_from(record).with_context(|| format!("trade log row {}", i + 1))
+            })
+            .collect()
+    }
+
+    /// Reads a JSON trade log: an array of objects with the CSV columns as fields.
+    pub fn read_json(reader: impl Read) -> Result<Vec<Self>> {
+        let records: Vec<TradeRecord> =
+            serde_json::from_reader(reader).context("parsing JSON trade log")?;
+        records
+            .into_iter()
+            .enumerate()
+            .map(|(i, record)| Trade::try_from(record).with_context(|| format!("trade {i}")))
+            .collect()
+    }
+
+    /// Reads a `.csv` or `.json` trade log.
+    pub fn read_log_file(path: impl AsRef<Path>) -> Result<Vec<Self>> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("opening trade log {}", path.display()))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => Trade::read_csv(file),
+            Some("json") => Trade::read_json(file),
+            _ => bail!("trade log {} is neither .csv nor .json", path.display()),
+        }
+        .with_context(|| format!("reading trade log {}", path.display()))
+    }
+}
+
+/// What the pool did with a [`Trade`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TradeOutcome {
+    Swapped {
+        amount_out: u64,
+    },
+    /// `donated_x` / `donated_y` are the parts of the deposit the minted LP tokens do not
+    /// redeem for at the pre-deposit reserves: the pool gives them to the existing LPs.
+    Deposited {
+        lp_minted: u64,
+        donated_x: u64,
+        donated_y: u64,
+    },
+    Withdrawn {
+        amount_x: u64,
+        amount_y: u64,
+    },
+    /// The pool rejects the trade (the transaction would fail) and nothing changes.
+    Rejected(AmmError),
+}
+
+// =================================================================================================
+// SIMULATED POOL
+// =================================================================================================
+
+/// The book of one LP in a [`SimPool`]. Values are in units of Y, at the spot price of the
+/// pool when the deposit or withdrawal happened.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct SimPosition {
+    pub lp_balance: u64,
+    pub deposited_x: u128,
+    pub deposited_y: u128,
+    pub withdrawn_x: u128,
+    pub withdrawn_y: u128,
+    pub donated_x: u128,
+    pub donated_y: u128,
+    /// Fee income, measured like [`LpPosition::value`](crate::position::LpPosition::value)
+    /// does: the share of the withdrawn amounts that the growth of
+    /// [`PoolState::value_per_share`] since entry paid. In a report, the redeemable amounts'
+    /// share is included.
+    pub fees_x: f64,
+    pub fees_y: f64,
+    /// [`PoolState::value_per_share`] after the deposits; the LP-weighted mean over them.
+    pub entry_value_per_share: f64,
+    pub cost_y: f64,
+    pub realized_y: f64,
+}
+
+/// A pool state machine with the MASM semantics — floor division, `MIN_LIQUIDITY` locking on
+/// the first deposit, unbalanced deposits donating their excess — that additionally keeps a
+/// book per LP and the swap volume and fees, for backtesting without a VM.
+///
+/// The pool arithmetic is [`PoolState`]'s, so every trade has exactly the outcome the AMM
+/// account would produce for the same sequence of notes.
+#[derive(Clone, Debug)]
+pub struct SimPool {
+    state: PoolState,
+    positions: BTreeMap<String, SimPosition>,
+    trades: usize,
+    rejected: Vec<RejectedTrade>,
+    volume_x: u128,
+    volume_y: u128,
+    fees_x: f64,
+    fees_y: f64,
+}
+
+/// A trade the pool rejected, by its index in the replayed log.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RejectedTrade {
+    pub index: usize,
+    pub error: String,
+}
+
+/// Spot price of X in units of Y; `None` for an empty pool.
+fn spot_price(state: &PoolState) -> Option<f64> {
+    (state.reserve_x != 0).then(|| state.reserve_y as f64 / state.reserve_x as f64)
+}
+
+/// A failure of the pool model as the AMM error the account would fail with. Reserves or an
+/// LP supply past the u64 range overflow the account's arithmetic as well.
+fn pool_error(err: anyhow::Error) -> Result<AmmError> {
+    let message = err.to_string();
+    match message.as_str() {
+        "reserve overflow" | "LP supply overflow" => Ok(AmmError::ValueOverflow),
+        _ => AmmError::from_message(&message).ok_or(err),
+    }
+}
+
+impl SimPool {
+    /// An empty pool with the given fee.
+    pub fn new(fee_bps: u64) -> Self {
+        SimPool::from_state(PoolState::new(fee_bps))
+    }
+
+    /// A pool starting from `state`. Its LP supply belongs to no simulated account.
+    pub fn from_state(state: PoolState) -> Self {
+        SimPool {
+            state,
+            positions: BTreeMap::new(),
+            trades: 0,
+            rejected: Vec::new(),
+            volume_x: 0,
+            volume_y: 0,
+            fees_x: 0.0,
+            fees_y: 0.0,
+        }
+    }
+
+    pub fn state(&self) -> PoolState {
+        self.state
+    }
+
+    pub fn position(&self, account: &str) -> Option<&SimPosition> {
+        self.positions.get(account)
+    }
+
+    /// Applies one trade. A trade the pool rejects is reported as
+    /// [`TradeOutcome::Rejected`]; an error means the log itself is inconsistent (a
+    /// withdrawal of more LP tokens than the account holds).
+    pub fn apply(&mut self, trade: &Trade) -> Result<TradeOutcome> {
+        let outcome = match trade {
+            Trade::Swap {
+                amount_in, x_in, ..
+            } => self.swap(*amount_in, *x_in)?,
+            Trade::Deposit {
+                account,
+                amount_x,
+                amount_y,
+            } => self.deposit(account, *amount_x, *amount_y)?,
+            Trade::Withdraw { account, lp_amount } => self.withdraw(account, *lp_amount)?,
+        };
+        if let TradeOutcome::Rejected(error) = outcome {
+            self.rejected.push(RejectedTrade {
+                index: self.trades,
+                error: error.to_string(),
+            });
+        }
+        self.trades += 1;
+        Ok(outcome)
+    }
+
+    /// Applies every trade of `trades` in order.
+    pub fn replay(&mut self, trades: &[Trade]) -> Result<Vec<TradeOutcome>> {
+        trades
+            .iter()
+            .enumerate()
+            .map(|(i, trade)| self.apply(trade).with_context(|| format!("trade {i}")))
+            .collect()
+    }
+
+    fn swap(&mut self, amount_in: u64, x_in: bool) -> Result<TradeOutcome> {
+        let amount_out = match self.state.apply_swap(amount_in, x_in) {
+            Ok(amount_out) => amount_out,
+            Err(err) => return Ok(TradeOutcome::Rejected(pool_error(err)?)),
+        };
+        let fee = amount_in as f64 * self.state.fee_bps as f64 / FEE_DENOM as f64;
+        let (volume, fees) = if x_in {
+            (&mut self.volume_x, &mut self.fees_x)
+        } else {
+            (&mut self.volume_y, &mut self.fees_y)
+        };
+        *volume += amount_in as u128;
+        *fees += fee;
+        Ok(TradeOutcome::Swapped { amount_out })
+    }
+
+    fn deposit(&mut self, account: &str, amount_x: u64, amount_y: u64) -> Result<TradeOutcome> {
+        let before = self.state;
+        let lp_minted = match self.state.apply_add_liquidity(amount_x, amount_y) {
+            Ok(lp_minted) => lp_minted,
+            Err(err) => {
+                self.state = before;
+                return Ok(TradeOutcome::Rejected(pool_error(err)?));
+            }
+        };
+        // what the minted LP redeems for at the pre-deposit reserves, rounded up
+        let (donated_x, donated_y) = if before.lp_supply == 0 {
+            (0, 0)
+        } else {
+            let redeemed = |reserve: u64| {
+                (lp_minted as u128 * reserve as u128).div_ceil(before.lp_supply as u128) as u64
+            };
+            (
+                amount_x - redeemed(before.reserve_x),
+                amount_y - redeemed(before.reserve_y),
+            )
+        };
+        let price = spot_price(&before).unwrap_or(amount_y as f64 / amount_x as f64);
+        let value_per_share = self.state.value_per_share();
+        let position = self.positions.entry(account.to_string()).or_default();
+        position.entry_value_per_share = (position.entry_value_per_share
+            * position.lp_balance as f64
+            + value_per_share * lp_minted as f64)
+            / (position.lp_balance + lp_minted) as f64;
+        position.lp_balance += lp_minted;
+        position.deposited_x += amount_x as u128;
+        position.deposited_y += amount_y as u128;
+        position.donated_x += donated_x as u128;
+        position.donated_y += donated_y as u128;
+        position.cost_y += amount_x as f64 * price + amount_y as f64;
+        Ok(TradeOutcome::Deposited {
+            lp_minted,
+            donated_x,
+            donated_y,
+        })
+    }
+
+    fn withdraw(&mut self, account: &str, lp_amount: u64) -> Result<TradeOutcome> {
+        let (held, entry) = self
+            .positions
+            .get(account)
+            .map_or((0, 0.0), |p| (p.lp_balance, p.entry_value_per_share));
+        ensure!(
+            lp_amount <= held,
+            "{account} withdraws {lp_amount} LP tokens but holds {held}"
+        );
+        let price = spot_price(&self.state);
+        let fee_share = fee_share(entry, &self.state);
+        let (amount_x, amount_y) = match self.state.apply_remove_liquidity(lp_amount) {
+            Ok(amounts) => amounts,
+            Err(err) => return Ok(TradeOutcome::Rejected(pool_error(err)?)),
+        };
+        let position = self
+            .positions
+            .get_mut(account)
+            .expect("an account holding LP tokens has a position");
+        position.lp_balance -= lp_amount;
+        position.withdrawn_x += amount_x as u128;
+        position.withdrawn_y += amount_y as u128;
+        position.fees_x += amount_x as f64 * fee_share;
+        position.fees_y += amount_y as f64 * fee_share;
+        position.realized_y +=
+            amount_x as f64 * price.expect("a pool paying out has reserves") + amount_y as f64;
+        Ok(TradeOutcome::Withdrawn { amount_x, amount_y })
+    }
+
+    /// The backtest report at the current pool state.
+    pub fn report(&self) -> BacktestReport {
+        let price = spot_price(&self.state).unwrap_or(0.0);
+        let lps = self
+            .positions
+            .iter()
+            .map(|(account, position)| {
+                let (redeemable_x, redeemable_y) = if position.lp_balance == 0 {
+                    (0, 0)
+                } else {
+                    quote_remove_liquidity(
+                        position.lp_balance,
+                        self.state.reserve_x,
+                        self.state.reserve_y,
+                        self.state.lp_supply,
+                    )
+                };
+                let value = |x: u128, y: u128| x as f64 * price + y as f64;
+                let value_y = value(
+                    position.withdrawn_x + redeemable_x as u128,
+                    position.withdrawn_y + redeemable_y as u128,
+                );
+                let hold_value_y = value(position.deposited_x, position.deposited_y);
+                let mut position = position.clone();
+                let fee_share = fee_share(position.entry_value_per_share, &self.state);
+                position.fees_x += redeemable_x as f64 * fee_share;
+                position.fees_y += redeemable_y as f64 * fee_share;
+                let fees_value_y = position.fees_x * price + position.fees_y;
+                LpReport {
+                    account: account.clone(),
+                    redeemable_x,
+                    redeemable_y,
+                    value_y,
+                    hold_value_y,
+                    fees_value_y,
+                    pnl_y: position.realized_y + value(redeemable_x as u128, redeemable_y as u128)
+                        - position.cost_y,
+                    impermanent_loss_y: value_y - fees_value_y - hold_value_y,
+                    position,
+                }
+            })
+            .collect();
+        BacktestReport {
+            reserve_x: self.state.reserve_x,
+            reserve_y: self.state.reserve_y,
+            lp_supply: self.state.lp_supply,
+            fee_bps: self.state.fee_bps,
+            spot_price: price,
+            trades: self.trades,
+            rejected: self.rejected.clone(),
+            volume_x: self.volume_x,
+            volume_y: self.volume_y,
+            fees_x: self.fees_x,
+            fees_y: self.fees_y,
+            lps,
+        }
+    }
+}
+
+/// Replays `trades` on an empty pool with the given fee and reports the result.
+pub fn backtest(fee_bps: u64, trades: &[Trade]) -> Result<BacktestReport> {
+    let mut pool = SimPool::new(fee_bps);
+    pool.replay(trades)?;
+    Ok(pool.report())
+}
+
+// =================================================================================================
+// REPORT
+// =================================================================================================
+
+/// Per-LP results. All values are in units of Y; `value_y`, `hold_value_y` and
+/// `fees_value_y` use the final spot price, `pnl_y` compares against the deposits valued at
+/// the price they were made at.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LpReport {
+    pub account: String,
+    #[serde(flatten)]
+    pub position: SimPosition,
+    /// What the remaining LP balance redeems for now.
+    pub redeemable_x: u64,
+    pub redeemable_y: u64,
+    /// Everything withdrawn plus what is still redeemable.
+    pub value_y: f64,
+    /// The deposits, had they been held instead.
+    pub hold_value_y: f64,
+    pub fees_value_y: f64,
+    /// Realized withdrawals plus the redeemable amounts, minus the cost of the deposits.
+    pub pnl_y: f64,
+    /// `value_y` without the fees, versus holding: negative when the price moved against
+    /// the position.
+    pub impermanent_loss_y: f64,
+}
+
+/// The outcome of a backtest: the final pool, the volume and fees over the replayed log, and
+/// the books of every LP.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BacktestReport {
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+    pub fee_bps: u64,
+    /// Final price of X in units of Y (0 for an empty pool).
+    pub spot_price: f64,
+    pub trades: usize,
+    pub rejected: Vec<RejectedTrade>,
+    pub volume_x: u128,
+    pub volume_y: u128,
+    pub fees_x: f64,
+    pub fees_y: f64,
+    pub lps: Vec<LpReport>,
+}
+
+impl BacktestReport {
+    pub fn lp(&self, account: &str) -> Option<&LpReport> {
+        self.lps.iter().find(|lp| lp.account == account)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("serializing backtest report")
+    }
+}
@@ -0,0 +1,171 @@
+use std::{collections::BTreeMap, fmt, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use miden_client::{
+    Serializable,
+    transaction::{ExecutedTransaction, LocalTransactionProver},
+};
+
+// =================================================================================================
+// CONSTANTS
+// =================================================================================================
+
+/// Default tolerance of [`CycleReport::regressions`]: a metric may grow by this many percent
+/// before it counts as a regression.
+pub const DEFAULT_REGRESSION_THRESHOLD_PCT: f64 = 5.0;
+
+// =================================================================================================
+// CYCLE REPORT
+// =================================================================================================
+
+/// VM cycle counts and sizes per benchmark, e.g. `"swap_x_to_y" -> {"total_cycles": 21403,
+/// ...}`. The counts are deterministic for a given MASM source and kernel, so two reports
+/// compare metric by metric.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CycleReport {
+    pub benches: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+impl CycleReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records `metric` of `bench`, replacing an earlier value.
+    pub fn record(&mut self, bench: &str, metric: &str, value: u64) {
+        self.benches
+            .entry(bench.to_string())
+            .or_default()
+            .insert(metric.to_string(), value);
+    }
+
+    /// Records the cycles of every phase of an executed AMM transaction, its trace length
+    /// and its size as a proven transaction. The size is measured with a dummy proof, so it
+    /// covers everything but the proof itself.
+    pub fn record_transaction(
+        &mut self,
+        bench: &str,
+        executed: &ExecutedTransaction,
+    ) -> Result<()> {
+        let measurements = executed.measurements();
+        let note_execution: usize = measurements
+            .note_execution
+            .iter()
+            .map(|(_, cycles)| cycles)
+            .sum();
+        for (metric, cycles) in [
+            ("prologue", measurements.prologue),
+            ("notes_processing", measurements.notes_processing),
+            ("note_execution", note_execution),
+            ("tx_script_processing", measurements.tx_script_processing),
+            ("epilogue", measurements.epilogue),
+            ("auth_procedure", measurements.auth_procedure),
+            ("total_cycles", measurements.total_cycles()),
+            ("trace_length", measurements.trace_length()),
+        ] {
+            self.record(bench, metric, cycles as u64);
+        }
+        let proven = LocalTransactionProver::default()
+            .prove_dummy(executed.clone())
+            .context("dummy-proving the transaction")?;
+        self.record(bench, "tx_bytes", proven.to_bytes().len() as u64);
+        Ok(())
+    }
+
+    pub fn get(&self, bench: &str, metric: &str) -> Option<u64> {
+        self.benches.get(bench)?.get(metric).copied()
+    }
+
+    /// Every metric that grew by more than `threshold_pct` percent over `baseline`. Metrics
+    /// missing from either report are not compared.
+    pub fn regressions(&self, baseline: &CycleReport, threshold_pct: f64) -> Vec<MetricChange> {
+        self.changes(baseline)
+            .filter(|change| change.pct() > threshold_pct)
+            .collect()
+    }
+
+    /// Every metric that shrank by more than `threshold_pct` percent: time to update the
+    /// baseline.
+    pub fn improvements(&self, baseline: &CycleReport, threshold_pct: f64) -> Vec<MetricChange> {
+        self.changes(baseline)
+            .filter(|change| change.pct() < -threshold_pct)
+            .collect()
+    }
+
+    fn changes<'a>(&'a self, baseline: &'a CycleReport) -> impl Iterator<Item = MetricChange> + 'a {
+        self.benches.iter().flat_map(move |(bench, metrics)| {
+            metrics.iter().filter_map(move |(metric, current)| {
+                let baseline = baseline.get(bench, metric)?;
+                Some(MetricChange {
+                    bench: bench.clone(),
+                    metric: metric.clone(),
+                    baseline,
+                    current: *current,
+                })
+            })
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("serializing cycle report")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("parsing cycle report")
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        std::fs::write(path, self.to_json()? + "\n")
+            .with_context(|| format!("writing cycle report {}", path.display()))
+    }
+
+    pub fn read_from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("reading cycle report {}", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("decoding {}", path.display()))
+    }
+}
+
+/// A metric that changed between a baseline and the current report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MetricChange {
+    pub bench: String,
+    pub metric: String,
+    pub baseline: u64,
+    pub current: u64,
+}
+
+impl MetricChange {
+    /// Change relative to the baseline, in percent.
+    pub fn pct(&self) -> f64 {
+        if self.baseline == 0 {
+            return if self.current == 0 {
+                0.0
+            } else {
+                f64::INFINITY
+            };
+        }
+        (self.current as f64 - self.baseline as f64) * 100.0 / self.baseline as f64
+    }
+}
+
+impl fmt::Display for MetricChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}: {} -> {} ({:+.1}%)",
+            self.bench,
+            self.metric,
+            self.baseline,
+            self.current,
+            self.pct()
+        )
+    }
+}
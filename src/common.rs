@@ -1,32 +1,47 @@
-use std::{collections::BTreeSet, sync::Arc};
+use std::{
+    collections::BTreeSet,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
-use anyhow::{Context, Result};
-use rand::RngCore;
-use tokio::time::{Duration, sleep};
+use anyhow::{Context, Result, bail};
+use rand::{RngCore, SeedableRng, rngs::StdRng};
+use tokio::{
+    sync::Notify,
+    time::{Duration, Instant, sleep},
+};
 
 use miden_client::{
     Client, Felt, Word,
     account::{
-        Account, AccountBuilder, AccountComponent, AccountId, AccountType, StorageSlot,
-        StorageSlotName,
+        Account, AccountBuilder, AccountComponent, AccountDelta, AccountId, AccountType,
+        StorageSlot, StorageSlotName,
         component::{
             AccountComponentMetadata, AuthNetworkAccount, BasicWallet, BurnPolicyConfig,
-            FungibleFaucet, MintPolicyConfig, PolicyRegistration, TokenName, TokenPolicyManager,
+            FungibleFaucet, MintPolicyConfig, PolicyRegistration, TokenMetadata, TokenName,
+            TokenPolicyManager,
         },
     },
-    assembly::CodeBuilder,
-    asset::{AssetAmount, FungibleAsset, TokenSymbol},
+    asset::{AssetAmount, AssetCallbackFlag, AssetCallbacks, FungibleAsset, TokenSymbol},
     auth::{AuthSchemeId, AuthSecretKey, AuthSingleSig},
-    keystore::{FilesystemKeyStore, Keystore},
+    block::BlockNumber,
+    keystore::Keystore,
     note::{
         NetworkAccountTarget, Note, NoteAssets, NoteAttachments, NoteExecutionHint, NoteRecipient,
-        NoteScript, NoteStorage, NoteTag, NoteType, P2idNote, P2idNoteStorage,
-        PartialNoteMetadata,
+        NoteScript, NoteStorage, NoteTag, NoteType, P2idNote, P2idNoteStorage, PartialNoteMetadata,
     },
+    rpc::NodeRpcClient,
     store::TransactionFilter,
-    transaction::{TransactionId, TransactionRequestBuilder, TransactionScript, TransactionStatus},
+    transaction::{
+        DiscardCause, TransactionId, TransactionRequestBuilder, TransactionScript,
+        TransactionStatus,
+    },
 };
 
+use crate::{artifacts, errors::AmmError, governance::Governance};
+
 // =================================================================================================
 // CONSTANTS
 // =================================================================================================
@@ -35,28 +50,49 @@ use miden_client::{
 pub const FEE_DENOM: u64 = 10_000;
 /// Uniswap-v2-style minimum liquidity, permanently locked in the LP supply on the first deposit.
 pub const MIN_LIQUIDITY: u64 = 1_000;
+/// Decimals of the LP token when they are not derived from the pair.
+pub const LP_DEFAULT_DECIMALS: u8 = 8;
 
 /// MASM sources, resolved at compile time so binaries/tests are CWD-independent.
-pub const AMM_CODE: &str = include_str!("../masm/accounts/amm.masm");
-/// Raw liquidity component source with a `{p2id_script_root}` placeholder — always compile
-/// via [`liquidity_code`], which injects the real P2ID script root.
+/// Raw amm component source with an `{event_log}` placeholder — always compile via
+/// [`amm_code`], which splices in the shared event log.
+pub const AMM_CODE_TEMPLATE: &str = include_str!("../masm/accounts/amm.masm");
+/// Raw liquidity component source with `{p2id_script_root}` and `{event_log}` placeholders —
+/// always compile via [`liquidity_code`], which injects the real P2ID script root and the
+/// shared event log.
 pub const LIQUIDITY_CODE_TEMPLATE: &str = include_str!("../masm/accounts/liquidity.masm");
+/// Event log procedures and constants shared by the amm and liquidity components.
+pub const EVENT_LOG_CODE: &str = include_str!("../masm/shared/event_log.masm");
 pub const SWAP_NOTE_CODE: &str = include_str!("../masm/notes/amm_swap_note.masm");
 pub const ADD_LIQUIDITY_NOTE_CODE: &str = include_str!("../masm/notes/add_liquidity_note.masm");
 pub const REMOVE_LIQUIDITY_NOTE_CODE: &str =
     include_str!("../masm/notes/remove_liquidity_note.masm");
+pub const SYNC_NOTE_CODE: &str = include_str!("../masm/notes/sync_note.masm");
+pub const SKIM_NOTE_CODE: &str = include_str!("../masm/notes/skim_note.masm");
 pub const DEPLOY_SCRIPT_CODE: &str = include_str!("../masm/scripts/deploy_script.masm");
+pub const LP_METADATA_CODE: &str = include_str!("../masm/accounts/lp_metadata.masm");
+pub const GOVERNANCE_CODE: &str = include_str!("../masm/accounts/governance.masm");
+pub const PROPOSE_ALLOWLIST_NOTE_CODE: &str =
+    include_str!("../masm/notes/propose_allowlist_note.masm");
+pub const EXECUTE_ALLOWLIST_NOTE_CODE: &str =
+    include_str!("../masm/notes/execute_allowlist_note.masm");
+
+include!("masm_sources.rs");
 
-/// Library namespaces the MASM modules are compiled under.
-pub const AMM_CONTRACT_NS: &str = "external_contract::amm_contract";
-pub const LIQUIDITY_CONTRACT_NS: &str = "external_contract::liquidity_contract";
+/// The amm component source with the shared event log spliced in.
+pub fn amm_code() -> String {
+    splice_amm_code(AMM_CODE_TEMPLATE, EVENT_LOG_CODE)
+}
 
-/// The liquidity component source with the P2ID script root injected. The component
-/// computes payout recipients in-VM (bound to the note sender), which requires the
-/// canonical P2ID note-script root as a push-word constant.
+/// The liquidity component source with the P2ID script root injected and the shared event
+/// log spliced in. The component computes payout recipients in-VM (bound to the note
+/// sender), which requires the canonical P2ID note-script root as a push-word constant.
 pub fn liquidity_code() -> String {
-    let root = Word::from(P2idNote::script_root());
-    LIQUIDITY_CODE_TEMPLATE.replace("{p2id_script_root}", &format!("{root}"))
+    splice_liquidity_code(
+        LIQUIDITY_CODE_TEMPLATE,
+        Word::from(P2idNote::script_root()),
+        EVENT_LOG_CODE,
+    )
 }
 
 /// Named storage slots of the AMM account.
@@ -72,21 +108,34 @@ pub fn config_slot() -> StorageSlotName {
 pub fn lp_supply_slot() -> StorageSlotName {
     StorageSlotName::new("miden_amm::amm::lp_supply").expect("valid slot name")
 }
+pub fn reserves_slot() -> StorageSlotName {
+    StorageSlotName::new("miden_amm::amm::reserves").expect("valid slot name")
+}
+pub fn event_count_slot() -> StorageSlotName {
+    StorageSlotName::new("miden_amm::amm::event_count").expect("valid slot name")
+}
+pub fn event_log_slot() -> StorageSlotName {
+    StorageSlotName::new("miden_amm::amm::event_log").expect("valid slot name")
+}
 
 // =================================================================================================
 // REFERENCE MATH (Rust mirrors of the MASM formulas, used by tests and quoting)
 // =================================================================================================
 
 /// Constant-product output amount with fee: dy = dx*(D-f)*y / (x*D + dx*(D-f)).
-/// Mirrors `amm.masm::get_amount_y_out` exactly (u128 intermediates, floor division).
+/// Mirrors `amm.masm::get_amount_y_out` exactly: dx*(D-f) and the denominator are u128,
+/// and the numerator (up to 2^142) is divided in two steps over the 32-bit halves of `y`,
+/// which yields the exact floor for any u64 inputs.
 pub fn quote_swap_output(dx: u64, x: u64, y: u64, fee_bps: u64) -> u64 {
     assert!(fee_bps <= FEE_DENOM);
     let feec = (FEE_DENOM - fee_bps) as u128;
     let dx_f = (dx as u128) * feec;
-    assert!(dx_f <= u64::MAX as u128, "dx * fee complement must fit in a u64");
-    let num = (y as u128) * dx_f;
     let den = (x as u128) * (FEE_DENOM as u128) + dx_f;
-    u64::try_from(num / den).expect("output amount fits in u64")
+    let (y_hi, y_lo) = ((y >> 32) as u128, (y & 0xffff_ffff) as u128);
+    let a = y_hi * dx_f;
+    let (qa, ra) = (a / den, a % den);
+    let qb = ((ra << 32) + y_lo * dx_f) / den;
+    u64::try_from((qa << 32) + qb).expect("output amount fits in u64")
 }
 
 /// LP minted (and resulting total supply) for the first deposit: sqrt(dx*dy) with
@@ -98,23 +147,209 @@ pub fn quote_initial_lp(dx: u64, dy: u64) -> (u64, u64) {
     (r - MIN_LIQUIDITY, r)
 }
 
-/// LP minted for a follow-up deposit: min(dx*S/x, dy*S/y).
-pub fn quote_lp_mint(dx: u64, dy: u64, x: u64, y: u64, supply: u64) -> u64 {
-    let lp_x = (dx as u128) * (supply as u128) / (x as u128);
-    let lp_y = (dy as u128) * (supply as u128) / (y as u128);
-    u64::try_from(lp_x.min(lp_y)).expect("minted LP fits in u64")
+/// floor(a*b/d) with an exact u128 product. Mirrors `liquidity.masm::mul_div_u64`, which
+/// fails with `ERR_VALUE_OVERFLOW` (here `None`) when the quotient is not a field element.
+pub fn mul_div_u64(a: u64, b: u64, d: u64) -> Option<u64> {
+    let q = (a as u128) * (b as u128) / (d as u128);
+    u64::try_from(q).ok().filter(|q| *q < Felt::ORDER)
+}
+
+/// LP minted for a follow-up deposit: min(dx*S/x, dy*S/y). `None` where
+/// `liquidity.masm::add_liquidity` fails with `ERR_VALUE_OVERFLOW`: either side's quotient
+/// is not a field element, even if the other side's is the smaller one.
+pub fn quote_lp_mint(dx: u64, dy: u64, x: u64, y: u64, supply: u64) -> Option<u64> {
+    let lp_x = mul_div_u64(dx, supply, x)?;
+    let lp_y = mul_div_u64(dy, supply, y)?;
+    Some(lp_x.min(lp_y))
 }
 
 /// Pro-rata payout for burning `lp` of `supply`: (lp*x/S, lp*y/S).
 pub fn quote_remove_liquidity(lp: u64, x: u64, y: u64, supply: u64) -> (u64, u64) {
-    let ax = (lp as u128) * (x as u128) / (supply as u128);
-    let ay = (lp as u128) * (y as u128) / (supply as u128);
     (
-        u64::try_from(ax).expect("payout fits in u64"),
-        u64::try_from(ay).expect("payout fits in u64"),
+        mul_div_u64(lp, x, supply).expect("payout fits in a felt"),
+        mul_div_u64(lp, y, supply).expect("payout fits in a felt"),
     )
 }
 
+/// Pool state as the MASM sees it: reserves are the `reserves` slot (not the vault balances,
+/// see [`pool_excess`]), `lp_supply` is the `lp_supply` slot. The `apply_*` methods mirror
+/// `amm.masm::swap` and `liquidity.masm::{add_liquidity, remove_liquidity}` including their
+/// failure conditions (slippage bounds excepted: those belong to the note, not the pool).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolState {
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+    pub fee_bps: u64,
+}
+
+impl PoolState {
+    /// An empty pool with the given fee.
+    pub fn new(fee_bps: u64) -> Self {
+        PoolState {
+            fee_bps,
+            ..Default::default()
+        }
+    }
+
+    /// `sqrt(x·y) / S`: what one LP token is worth in units of the pool's invariant; 0 for a
+    /// pool without LP supply. Swaps and burns never lower it and price moves leave it
+    /// unchanged, so its growth is what the fees (and donations) earned per LP token.
+    pub fn value_per_share(&self) -> f64 {
+        if self.lp_supply == 0 {
+            return 0.0;
+        }
+        ((self.reserve_x as u128 * self.reserve_y as u128) as f64).sqrt() / self.lp_supply as f64
+    }
+
+    /// Reads the pool state from the AMM account (`reserves`, `lp_supply`, `config`). Fails
+    /// if the account's pool keys are not `pool_x` / `pool_y`.
+    pub fn from_account(
+        account: &Account,
+        pool_x: impl Into<PoolAsset>,
+        pool_y: impl Into<PoolAsset>,
+    ) -> Result<Self> {
+        for (slot, asset) in [
+            (pool_x_key_slot(), pool_x.into()),
+            (pool_y_key_slot(), pool_y.into()),
+        ] {
+            let key = account
+                .storage()
+                .get_item(&slot)
+                .with_context(|| format!("reading {slot}"))?;
+            anyhow::ensure!(
+                key == asset.key_word()?,
+                "{slot} does not hold faucet {}",
+                asset.faucet_id.to_hex()
+            );
+        }
+        let reserves: Word = account
+            .storage()
+            .get_item(&reserves_slot())
+            .context("reading reserves slot")?;
+        let supply: Word = account
+            .storage()
+            .get_item(&lp_supply_slot())
+            .context("reading lp_supply slot")?;
+        let config: Word = account
+            .storage()
+            .get_item(&config_slot())
+            .context("reading config slot")?;
+        Ok(PoolState {
+            reserve_x: reserves[0].as_canonical_u64(),
+            reserve_y: reserves[1].as_canonical_u64(),
+            lp_supply: supply[0].as_canonical_u64(),
+            fee_bps: config[0].as_canonical_u64(),
+        })
+    }
+
+    /// Adopts the reserves, LP supply and fee an AMM account delta sets; what the delta does
+    /// not touch keeps its value.
+    pub fn apply_delta(&mut self, delta: &AccountDelta) {
+        for (slot, value) in delta.storage().values() {
+            if *slot == reserves_slot() {
+                self.reserve_x = value[0].as_canonical_u64();
+                self.reserve_y = value[1].as_canonical_u64();
+            } else if *slot == lp_supply_slot() {
+                self.lp_supply = value[0].as_canonical_u64();
+            } else if *slot == config_slot() {
+                self.fee_bps = value[0].as_canonical_u64();
+            }
+        }
+    }
+
+    /// Swaps `amount_in` of X (`x_in = true`) or Y into the pool; returns the output amount.
+    pub fn apply_swap(&mut self, amount_in: u64, x_in: bool) -> Result<u64> {
+        let (reserve_in, reserve_out) = if x_in {
+            (self.reserve_x, self.reserve_y)
+        } else {
+            (self.reserve_y, self.reserve_x)
+        };
+        // an empty pool pays nothing out; one with only the output side would pay it all,
+        // which the MASM's constant-product check refuses
+        anyhow::ensure!(reserve_out != 0, "computed output amount is zero");
+        anyhow::ensure!(reserve_in != 0, "swap decreased the constant product");
+        let out = quote_swap_output(amount_in, reserve_in, reserve_out, self.fee_bps);
+        anyhow::ensure!(out != 0, "computed output amount is zero");
+        let new_in = reserve_in.checked_add(amount_in).context("reserve overflow")?;
+        let new_out = reserve_out - out;
+        if x_in {
+            (self.reserve_x, self.reserve_y) = (new_in, new_out);
+        } else {
+            (self.reserve_y, self.reserve_x) = (new_in, new_out);
+        }
+        Ok(out)
+    }
+
+    /// Deposits `dx` of X and `dy` of Y; returns the LP minted to the depositor. On failure
+    /// the pool is left unchanged.
+    ///
+    /// As in Uniswap v2, the whole deposit joins the reserves. The LP minted pays only for
+    /// the side closer to the pool's ratio, so the excess of the other side is donated to
+    /// the LPs and moves the price; `min_lp_out` on the note bounds what the depositor loses.
+    pub fn apply_add_liquidity(&mut self, dx: u64, dy: u64) -> Result<u64> {
+        let (lp, lp_supply) = if self.lp_supply == 0 {
+            let r = u64::try_from(((dx as u128) * (dy as u128)).isqrt())
+                .expect("sqrt of u128 fits in u64");
+            anyhow::ensure!(
+                r > MIN_LIQUIDITY,
+                "initial deposit too small to lock minimum liquidity"
+            );
+            (r - MIN_LIQUIDITY, r)
+        } else {
+            anyhow::ensure!(
+                self.reserve_x != 0 && self.reserve_y != 0,
+                "pool has LP supply but empty reserves"
+            );
+            let lp = quote_lp_mint(dx, dy, self.reserve_x, self.reserve_y, self.lp_supply)
+                .context(AmmError::ValueOverflow.message())?;
+            anyhow::ensure!(lp != 0, "deposit mints zero LP tokens");
+            let lp_supply = self.lp_supply.checked_add(lp).context("LP supply overflow")?;
+            (lp, lp_supply)
+        };
+        let reserve_x = self.reserve_x.checked_add(dx).context("reserve overflow")?;
+        let reserve_y = self.reserve_y.checked_add(dy).context("reserve overflow")?;
+        (self.reserve_x, self.reserve_y, self.lp_supply) = (reserve_x, reserve_y, lp_supply);
+        Ok(lp)
+    }
+
+    /// Burns `lp` LP tokens; returns the (X, Y) payout.
+    pub fn apply_remove_liquidity(&mut self, lp: u64) -> Result<(u64, u64)> {
+        anyhow::ensure!(lp <= self.lp_supply, "burn amount exceeds LP supply");
+        anyhow::ensure!(self.lp_supply != 0, "pool has no LP supply");
+        let (ax, ay) = quote_remove_liquidity(lp, self.reserve_x, self.reserve_y, self.lp_supply);
+        anyhow::ensure!(ax != 0 && ay != 0, "burn pays out zero of one pool asset");
+        self.reserve_x -= ax;
+        self.reserve_y -= ay;
+        self.lp_supply -= lp;
+        Ok((ax, ay))
+    }
+}
+
+/// Vault balances of the two pool assets above the tracked reserves: what a skim note pays
+/// out, and what a sync note would hand to the LPs. Mirrors `liquidity.masm::skim`
+/// (a side whose balance is at or below its reserve contributes zero).
+pub fn pool_excess(
+    account: &Account,
+    pool_x: impl Into<PoolAsset>,
+    pool_y: impl Into<PoolAsset>,
+) -> Result<(u64, u64)> {
+    let (pool_x, pool_y) = (pool_x.into(), pool_y.into());
+    let pool = PoolState::from_account(account, pool_x, pool_y)?;
+    let balance = |asset: PoolAsset| -> Result<u64> {
+        let key = asset.asset(1)?.vault_key();
+        Ok(account
+            .vault()
+            .get_balance(key)
+            .context("reading pool balance")?
+            .as_u64())
+    };
+    Ok((
+        balance(pool_x)?.saturating_sub(pool.reserve_x),
+        balance(pool_y)?.saturating_sub(pool.reserve_y),
+    ))
+}
+
 // =================================================================================================
 // AMM ACCOUNT CONSTRUCTION
 // =================================================================================================
@@ -125,77 +360,346 @@ pub fn quote_remove_liquidity(lp: u64, x: u64, y: u64, supply: u64) -> (u64, u64
 #[derive(Clone)]
 pub struct AmmBuild {
     pub account: Account,
+    /// Seed the account id was derived from.
+    pub init_seed: [u8; 32],
     pub swap_note_script: NoteScript,
     pub add_liquidity_note_script: NoteScript,
     pub remove_liquidity_note_script: NoteScript,
+    pub sync_note_script: NoteScript,
+    pub skim_note_script: NoteScript,
+    pub propose_allowlist_note_script: NoteScript,
+    pub execute_allowlist_note_script: NoteScript,
     pub deploy_tx_script: TransactionScript,
     pub pool_x_faucet: AccountId,
     pub pool_y_faucet: AccountId,
+    pub pool_x_callbacks: AssetCallbackFlag,
+    pub pool_y_callbacks: AssetCallbackFlag,
     pub fee_bps: u64,
+    pub lp_token: LpToken,
+    pub governance: Option<Governance>,
+}
+
+impl AmmBuild {
+    pub fn pool_x(&self) -> PoolAsset {
+        PoolAsset::new(self.pool_x_faucet, self.pool_x_callbacks)
+    }
+
+    pub fn pool_y(&self) -> PoolAsset {
+        PoolAsset::new(self.pool_y_faucet, self.pool_y_callbacks)
+    }
+}
+
+/// One side of a pool: the faucet and whether the assets it issues carry transfer-policy
+/// callbacks. The callback flag is part of the asset's vault key, so the pool's key slots,
+/// its reserves and every asset it receives or pays out depend on it.
+///
+/// `From<AccountId>` assumes callbacks are disabled (true for faucets created by
+/// [`create_basic_faucet`]); use [`PoolAsset::from_faucet`] or [`PoolAsset::fetch`] for
+/// any other faucet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolAsset {
+    pub faucet_id: AccountId,
+    pub callbacks: AssetCallbackFlag,
+}
+
+impl PoolAsset {
+    pub fn new(faucet_id: AccountId, callbacks: AssetCallbackFlag) -> Self {
+        PoolAsset {
+            faucet_id,
+            callbacks,
+        }
+    }
+
+    /// Reads the callback flag from the faucet's storage. Like the kernel when it mints, a
+    /// faucet has callbacks if either asset-callback slot holds a non-empty procedure root.
+    pub fn from_faucet(faucet: &Account) -> Self {
+        let storage = faucet.storage();
+        let has_callback = |slot| {
+            storage
+                .get_item(slot)
+                .is_ok_and(|root: Word| !root.is_empty())
+        };
+        let callbacks = if has_callback(AssetCallbacks::on_before_asset_added_to_account_slot())
+            || has_callback(AssetCallbacks::on_before_asset_added_to_note_slot())
+        {
+            AssetCallbackFlag::Enabled
+        } else {
+            AssetCallbackFlag::Disabled
+        };
+        PoolAsset::new(faucet.id(), callbacks)
+    }
+
+    /// Fetches the faucet's public state from the node and reads its callback flag.
+    pub async fn fetch(rpc: &dyn NodeRpcClient, faucet_id: AccountId) -> Result<Self> {
+        let faucet = rpc
+            .get_account_details(faucet_id)
+            .await
+            .with_context(|| format!("fetching faucet {}", faucet_id.to_hex()))?
+            .with_context(|| {
+                format!(
+                    "faucet {} has no public state; pass its callback flag explicitly",
+                    faucet_id.to_hex()
+                )
+            })?;
+        Ok(PoolAsset::from_faucet(&faucet))
+    }
+
+    /// `amount` of this faucet's asset, carrying the faucet's callback flag.
+    pub fn asset(&self, amount: u64) -> Result<FungibleAsset> {
+        Ok(FungibleAsset::new(self.faucet_id, amount)
+            .context("building pool asset")?
+            .with_callbacks(self.callbacks))
+    }
+
+    /// The vault key word under which the pool stores this asset.
+    pub fn key_word(&self) -> Result<Word> {
+        Ok(self.asset(1)?.to_key_word())
+    }
+}
+
+impl From<AccountId> for PoolAsset {
+    fn from(faucet_id: AccountId) -> Self {
+        PoolAsset::new(faucet_id, AssetCallbackFlag::Disabled)
+    }
 }
 
-/// The vault key word under which the pool stores `faucet_id`'s fungible asset.
-/// Assumes the pool faucets have no transfer-policy callbacks (true for faucets created
-/// by [`create_basic_faucet`]); callbacks would flip a metadata bit and change the key.
-pub fn pool_asset_key_word(faucet_id: AccountId) -> Result<Word> {
-    Ok(FungibleAsset::new(faucet_id, 1)
-        .context("building probe asset for vault key")?
-        .to_key_word())
+/// The vault key word under which the pool stores `asset`'s fungible asset. A bare
+/// [`AccountId`] is taken as a faucet without callbacks (see [`PoolAsset`]).
+pub fn pool_asset_key_word(asset: impl Into<PoolAsset>) -> Result<Word> {
+    asset.into().key_word()
+}
+
+/// Display metadata of the pool's LP token: name, symbol and decimals, stored in the
+/// standard fungible-faucet slots so wallets and explorers read them like any other token
+/// (`FungibleFaucet::try_from(account.storage())`). The token supply in those slots
+/// follows `lp_supply`; the max supply is the largest fungible amount.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LpToken {
+    pub name: TokenName,
+    pub symbol: TokenSymbol,
+    pub decimals: u8,
+}
+
+impl Default for LpToken {
+    /// `LP` / `LP` with [`LP_DEFAULT_DECIMALS`], for pools built without pair metadata.
+    fn default() -> Self {
+        LpToken {
+            name: TokenName::new("LP").expect("valid token name"),
+            symbol: TokenSymbol::new_unchecked("LP"),
+            decimals: LP_DEFAULT_DECIMALS,
+        }
+    }
+}
+
+impl LpToken {
+    /// `LP-X-Y` for the pair's symbols; the symbol is `LPXY`, cut to the 12 letters a token
+    /// symbol can hold.
+    pub fn for_pair(symbol_x: &TokenSymbol, symbol_y: &TokenSymbol, decimals: u8) -> Result<Self> {
+        let symbol: String = format!("LP{symbol_x}{symbol_y}")
+            .chars()
+            .take(TokenSymbol::MAX_SYMBOL_LENGTH)
+            .collect();
+        Ok(LpToken {
+            name: TokenName::new(&format!("LP-{symbol_x}-{symbol_y}")).context("LP token name")?,
+            symbol: TokenSymbol::new(&symbol).context("LP token symbol")?,
+            decimals,
+        })
+    }
+
+    /// [`LpToken::for_pair`] with the symbols of the two pool faucets. LP amounts are the
+    /// geometric mean of the deposits, so the decimals are the mean of the pair's.
+    pub fn from_faucets(faucet_x: &Account, faucet_y: &Account) -> Result<Self> {
+        let token = |faucet: &Account| {
+            FungibleFaucet::try_from(faucet.storage())
+                .with_context(|| format!("reading token metadata of {}", faucet.id().to_hex()))
+        };
+        let (x, y) = (token(faucet_x)?, token(faucet_y)?);
+        let decimals = (x.decimals() + y.decimals()) / 2;
+        LpToken::for_pair(x.symbol(), y.symbol(), decimals)
+    }
+
+    /// Reads the LP token metadata back from the AMM account's storage.
+    pub fn from_account(account: &Account) -> Result<Self> {
+        let token =
+            FungibleFaucet::try_from(account.storage()).context("reading LP token metadata")?;
+        Ok(LpToken {
+            name: token.token_name().clone(),
+            symbol: token.symbol().clone(),
+            decimals: token.decimals(),
+        })
+    }
+
+    fn storage_slots(&self) -> Result<Vec<StorageSlot>> {
+        let config = FungibleFaucet::builder()
+            .name(self.name.clone())
+            .symbol(self.symbol.clone())
+            .decimals(self.decimals)
+            .max_supply(FungibleAsset::MAX_AMOUNT)
+            .build()
+            .context("building LP token config")?
+            .token_config_slot_value();
+        let mut slots = vec![config];
+        slots.extend(TokenMetadata::new(self.name.clone()).into_storage_slots());
+        Ok(slots)
+    }
 }
 
 /// Builds the AMM as a Miden network account (Uniswap-v2-style pool for the given pair):
-/// public account + `AuthNetworkAccount` whose note allowlist contains exactly the swap /
-/// add-liquidity / remove-liquidity note scripts and whose tx-script allowlist contains the
-/// deploy script.
+/// public account + `AuthNetworkAccount` whose note allowlist starts out as this crate's note
+/// scripts (swap, add/remove liquidity, sync, skim and the two governance notes) and whose
+/// tx-script allowlist contains the deploy script. Without [`AmmOptions::governance`] the
+/// note allowlist can never change.
 ///
 /// The account is the pool's LP-token faucet: LP tokens are minted/burned by the liquidity
 /// component via protocol-level faucet syscalls, with total supply tracked in the
-/// `lp_supply` storage slot. Pool reserves live in the account vault; the pair and fee are
-/// fixed at creation in named storage slots.
+/// `lp_supply` storage slot. Pool balances live in the account vault and the priced reserves
+/// in the `reserves` slot; the pair and fee are fixed at creation in named storage slots.
+///
+/// The pool sides are [`PoolAsset`]s; pass a bare [`AccountId`] for a faucet without
+/// transfer-policy callbacks.
 ///
 /// `existing = true` builds an already-deployed account (MockChain tests);
 /// `existing = false` builds a fresh account that must be deployed with `deploy_tx_script`.
 pub fn build_amm_account(
     init_seed: [u8; 32],
-    pool_x_faucet: AccountId,
-    pool_y_faucet: AccountId,
+    pool_x: impl Into<PoolAsset>,
+    pool_y: impl Into<PoolAsset>,
+    fee_bps: u64,
+    existing: bool,
+) -> Result<AmmBuild> {
+    build_amm_account_with(
+        init_seed,
+        pool_x,
+        pool_y,
+        fee_bps,
+        &AmmOptions::default(),
+        existing,
+    )
+}
+
+/// Optional settings of [`build_amm_account_with`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AmmOptions {
+    /// Display metadata of the LP token, e.g. [`LpToken::from_faucets`] for the pair.
+    pub lp_token: LpToken,
+    /// Who may change the note allowlist after deployment; without it the allowlist is fixed.
+    pub governance: Option<Governance>,
+}
+
+impl AmmOptions {
+    pub fn with_lp_token(mut self, lp_token: LpToken) -> Self {
+        self.lp_token = lp_token;
+        self
+    }
+
+    pub fn with_governance(mut self, governance: Governance) -> Self {
+        self.governance = Some(governance);
+        self
+    }
+}
+
+/// [`build_amm_account`] with the LP token metadata and allowlist governance of `options`.
+pub fn build_amm_account_with(
+    init_seed: [u8; 32],
+    pool_x: impl Into<PoolAsset>,
+    pool_y: impl Into<PoolAsset>,
     fee_bps: u64,
+    options: &AmmOptions,
     existing: bool,
 ) -> Result<AmmBuild> {
     assert!(fee_bps <= FEE_DENOM, "fee_bps must be <= {FEE_DENOM}");
+    let (pool_x, pool_y) = (pool_x.into(), pool_y.into());
 
-    // compile note scripts + deploy script first: their roots go into the auth allowlists
-    let swap_note_script = CodeBuilder::new()
-        .with_linked_module(AMM_CONTRACT_NS, AMM_CODE)
-        .context("linking amm contract into swap note script")?
-        .compile_note_script(SWAP_NOTE_CODE)
-        .context("compiling swap note script")?;
-    let liquidity_source = liquidity_code();
-    let add_liquidity_note_script = CodeBuilder::new()
-        .with_linked_module(LIQUIDITY_CONTRACT_NS, liquidity_source.as_str())
-        .context("linking liquidity contract into add-liquidity note script")?
-        .compile_note_script(ADD_LIQUIDITY_NOTE_CODE)
-        .context("compiling add-liquidity note script")?;
-    let remove_liquidity_note_script = CodeBuilder::new()
-        .with_linked_module(LIQUIDITY_CONTRACT_NS, liquidity_source.as_str())
-        .context("linking liquidity contract into remove-liquidity note script")?
-        .compile_note_script(REMOVE_LIQUIDITY_NOTE_CODE)
-        .context("compiling remove-liquidity note script")?;
-    let deploy_tx_script = CodeBuilder::new()
-        .with_linked_module(AMM_CONTRACT_NS, AMM_CODE)
-        .context("linking amm contract into deploy script")?
-        .compile_tx_script(DEPLOY_SCRIPT_CODE)
-        .context("compiling deploy script")?;
-
-    // amm component: swap logic + immutable pool configuration
-    let amm_component_code = CodeBuilder::new()
-        .compile_component_code(AMM_CONTRACT_NS, AMM_CODE)
-        .context("compiling amm component")?;
+    let parts = build_amm_parts(
+        pool_x.key_word()?,
+        pool_y.key_word()?,
+        fee_bps,
+        &options.lp_token,
+        options.governance.as_ref(),
+    )?;
+    let builder = parts.account_builder(init_seed);
+    let account = if existing {
+        builder
+            .build_existing()
+            .context("building existing AMM account")?
+    } else {
+        builder.build().context("building AMM account")?
+    };
+
+    Ok(AmmBuild {
+        account,
+        init_seed,
+        swap_note_script: parts.swap_note_script,
+        add_liquidity_note_script: parts.add_liquidity_note_script,
+        remove_liquidity_note_script: parts.remove_liquidity_note_script,
+        sync_note_script: parts.sync_note_script,
+        skim_note_script: parts.skim_note_script,
+        propose_allowlist_note_script: parts.propose_allowlist_note_script,
+        execute_allowlist_note_script: parts.execute_allowlist_note_script,
+        deploy_tx_script: parts.deploy_tx_script,
+        pool_x_faucet: pool_x.faucet_id,
+        pool_y_faucet: pool_y.faucet_id,
+        pool_x_callbacks: pool_x.callbacks,
+        pool_y_callbacks: pool_y.callbacks,
+        fee_bps,
+        lp_token: options.lp_token.clone(),
+        governance: options.governance,
+    })
+}
+
+/// The compiled scripts and account components of an AMM, before they are assembled into
+/// an account.
+pub(crate) struct AmmParts {
+    pub swap_note_script: NoteScript,
+    pub add_liquidity_note_script: NoteScript,
+    pub remove_liquidity_note_script: NoteScript,
+    pub sync_note_script: NoteScript,
+    pub skim_note_script: NoteScript,
+    pub propose_allowlist_note_script: NoteScript,
+    pub execute_allowlist_note_script: NoteScript,
+    pub deploy_tx_script: TransactionScript,
+    /// Components in account order, named for reports; the first is the auth component.
+    pub components: Vec<(&'static str, AccountComponent)>,
+}
+
+impl AmmParts {
+    pub fn account_builder(&self, init_seed: [u8; 32]) -> AccountBuilder {
+        let (_, auth) = &self.components[0];
+        self.components[1..].iter().fold(
+            AccountBuilder::new(init_seed)
+                .account_type(AccountType::Public)
+                .with_auth_component(auth.clone()),
+            |builder, (_, component)| builder.with_component(component.clone()),
+        )
+    }
+}
+
+/// The AMM's scripts and components, from the MASM artifacts `build.rs` assembled from the
+/// crate's sources. The pool configuration is passed as the raw words of its slots.
+pub(crate) fn build_amm_parts(
+    pool_x_key: Word,
+    pool_y_key: Word,
+    fee_bps: u64,
+    lp_token: &LpToken,
+    governance: Option<&Governance>,
+) -> Result<AmmParts> {
+    // note scripts + deploy script come precompiled: their roots go into the auth allowlists
+    let swap_note_script = artifacts::swap_note_script();
+    let add_liquidity_note_script = artifacts::add_liquidity_note_script();
+    let remove_liquidity_note_script = artifacts::remove_liquidity_note_script();
+    let sync_note_script = artifacts::sync_note_script();
+    let skim_note_script = artifacts::skim_note_script();
+    let propose_allowlist_note_script = artifacts::propose_allowlist_note_script();
+    let execute_allowlist_note_script = artifacts::execute_allowlist_note_script();
+    let deploy_tx_script = artifacts::deploy_tx_script();
+
+    // amm component: swap logic + immutable pool configuration + tracked reserves + the
+    // event log the swap and liquidity procedures append to
     let amm_component = AccountComponent::new(
-        amm_component_code,
+        artifacts::amm_component_code(),
         vec![
-            StorageSlot::with_value(pool_x_key_slot(), pool_asset_key_word(pool_x_faucet)?),
-            StorageSlot::with_value(pool_y_key_slot(), pool_asset_key_word(pool_y_faucet)?),
+            StorageSlot::with_value(pool_x_key_slot(), pool_x_key),
+            StorageSlot::with_value(pool_y_key_slot(), pool_y_key),
             StorageSlot::with_value(
                 config_slot(),
                 [
@@ -206,53 +710,68 @@ pub fn build_amm_account(
                 ]
                 .into(),
             ),
+            StorageSlot::with_value(reserves_slot(), Word::default()),
+            StorageSlot::with_value(event_count_slot(), Word::default()),
+            StorageSlot::with_empty_map(event_log_slot()),
         ],
         AccountComponentMetadata::new(AMM_CONTRACT_NS),
     )
     .context("building amm component")?;
 
     // liquidity component: LP mint/burn + supply tracking
-    let liquidity_component_code = CodeBuilder::new()
-        .compile_component_code(LIQUIDITY_CONTRACT_NS, liquidity_source.as_str())
-        .context("compiling liquidity component")?;
     let liquidity_component = AccountComponent::new(
-        liquidity_component_code,
+        artifacts::liquidity_component_code(),
         vec![StorageSlot::with_value(lp_supply_slot(), Word::default())],
         AccountComponentMetadata::new(LIQUIDITY_CONTRACT_NS),
     )
     .context("building liquidity component")?;
 
+    // lp metadata component: read-only token getters over the LP name / symbol / supply
+    let lp_metadata_component = AccountComponent::new(
+        artifacts::lp_metadata_component_code(),
+        lp_token.storage_slots()?,
+        AccountComponentMetadata::new(LP_METADATA_NS),
+    )
+    .context("building lp metadata component")?;
+
+    // governance component: timelocked changes to the note allowlist below
+    let governance_component = AccountComponent::new(
+        artifacts::governance_component_code(),
+        Governance::storage_slots(governance),
+        AccountComponentMetadata::new(GOVERNANCE_NS),
+    )
+    .context("building governance component")?;
+
     // network-account auth: only our note scripts / deploy script may run against this account
     let network_auth = AuthNetworkAccount::with_allowed_notes(BTreeSet::from([
         swap_note_script.root(),
         add_liquidity_note_script.root(),
         remove_liquidity_note_script.root(),
+        sync_note_script.root(),
+        skim_note_script.root(),
+        propose_allowlist_note_script.root(),
+        execute_allowlist_note_script.root(),
     ]))
     .context("building network auth allowlist")?
     .with_allowed_tx_scripts(BTreeSet::from([deploy_tx_script.root()]));
 
-    let builder = AccountBuilder::new(init_seed)
-        .account_type(AccountType::Public)
-        .with_auth_component(network_auth)
-        .with_component(BasicWallet)
-        .with_component(amm_component)
-        .with_component(liquidity_component);
-
-    let account = if existing {
-        builder.build_existing().context("building existing AMM account")?
-    } else {
-        builder.build().context("building AMM account")?
-    };
-
-    Ok(AmmBuild {
-        account,
+    Ok(AmmParts {
         swap_note_script,
         add_liquidity_note_script,
         remove_liquidity_note_script,
+        sync_note_script,
+        skim_note_script,
+        propose_allowlist_note_script,
+        execute_allowlist_note_script,
         deploy_tx_script,
-        pool_x_faucet,
-        pool_y_faucet,
-        fee_bps,
+        components: vec![
+            ("AuthNetworkAccount", network_auth.into()),
+            ("BasicWallet", BasicWallet.into()),
+            (AMM_CONTRACT_NS, amm_component),
+            (LIQUIDITY_CONTRACT_NS, liquidity_component),
+            (LP_METADATA_NS, lp_metadata_component),
+            (GOVERNANCE_NS, governance_component),
+        ],
     })
 }
 
@@ -315,7 +834,7 @@ impl PayoutInfo {
 /// Wraps note pieces into a network note targeted at the AMM: tagged with the AMM account
 /// and carrying the `NetworkAccountTarget` attachment the network transaction builder
 /// requires (without it the note is silently orphaned).
-fn build_amm_network_note(
+pub(crate) fn build_amm_network_note(
     sender: AccountId,
     amm_id: AccountId,
     assets: NoteAssets,
@@ -335,23 +854,25 @@ fn build_amm_network_note(
 }
 
 /// Creates a swap note: `asset_in` goes to the pool, and the pool pays at least
-/// `min_amount_out` of `asset_out_faucet`'s asset to `payout` (a P2ID note back to the
-/// swapper). Returns the network note to submit.
+/// `min_amount_out` of `asset_out` to `payout` (a P2ID note back to the swapper). Returns
+/// the network note to submit. `asset_in` must carry its faucet's callback flag, and
+/// `asset_out` is a [`PoolAsset`] (or a faucet id without callbacks).
 ///
 /// Swap note storage layout (12 felts) — must match `amm.masm::swap`:
 ///   [0..3] ASSET_OUT_KEY, [4..7] payout RECIPIENT digest,
 ///   [8] min_amount_out, [9] payout tag, [10] payout note type, [11] pad
+#[allow(clippy::too_many_arguments)]
 pub fn create_swap_note(
     sender: AccountId,
     amm_id: AccountId,
     asset_in: FungibleAsset,
-    asset_out_faucet: AccountId,
+    asset_out: impl Into<PoolAsset>,
     min_amount_out: u64,
     payout: &PayoutInfo,
     swap_note_script: NoteScript,
     serial_num: Word,
 ) -> Result<Note> {
-    let out_key = pool_asset_key_word(asset_out_faucet)?;
+    let out_key = pool_asset_key_word(asset_out)?;
     let recipient_digest = payout.recipient.digest();
     let storage = vec![
         out_key[0],
@@ -371,7 +892,8 @@ pub fn create_swap_note(
     build_amm_network_note(sender, amm_id, assets, swap_note_script, storage, serial_num)
 }
 
-/// Storage layout shared by the two liquidity notes (8 felts) — must match `liquidity.masm`:
+/// Storage layout shared by the two liquidity notes and the skim note (8 felts) — must
+/// match `liquidity.masm`:
 ///   [0..3] payout note SERIAL_NUM, [4] min_a (min_lp_out for add, min_x_out for remove),
 ///   [5] min_b (min_y_out for remove), [6..7] pad.
 ///
@@ -404,6 +926,7 @@ fn liquidity_note_storage(
 /// Creates an add-liquidity note carrying both pool assets. The AMM mints at least
 /// `min_lp_out` LP tokens into a private P2ID payout note bound to the depositor
 /// (the sender of this note).
+#[allow(clippy::too_many_arguments)]
 pub fn create_add_liquidity_note(
     sender: AccountId,
     amm_id: AccountId,
@@ -431,6 +954,7 @@ pub fn create_add_liquidity_note(
 /// account itself). The AMM burns them and pays out at least `min_x_out` / `min_y_out` of
 /// the pool assets into a single private P2ID payout note bound to the withdrawer
 /// (the sender of this note).
+#[allow(clippy::too_many_arguments)]
 pub fn create_remove_liquidity_note(
     sender: AccountId,
     amm_id: AccountId,
@@ -456,23 +980,238 @@ pub fn create_remove_liquidity_note(
     )
 }
 
+/// Creates a sync note: the AMM adopts its vault balances as the reserves. Carries no
+/// assets and pays nothing out, so anyone can send one.
+pub fn create_sync_note(
+    sender: AccountId,
+    amm_id: AccountId,
+    sync_note_script: NoteScript,
+    serial_num: Word,
+) -> Result<Note> {
+    build_amm_network_note(
+        sender,
+        amm_id,
+        NoteAssets::default(),
+        sync_note_script,
+        Vec::new(),
+        serial_num,
+    )
+}
+
+/// Creates a skim note: the AMM pays the vault balances above its reserves (see
+/// [`pool_excess`]) into a private P2ID payout note bound to the sender, using the liquidity
+/// note storage layout with unused bounds. Fails in the VM when there is nothing to skim.
+pub fn create_skim_note(
+    sender: AccountId,
+    amm_id: AccountId,
+    payout: &PayoutInfo,
+    skim_note_script: NoteScript,
+    serial_num: Word,
+) -> Result<Note> {
+    let storage = liquidity_note_storage(sender, payout, 0, 0)?;
+    build_amm_network_note(
+        sender,
+        amm_id,
+        NoteAssets::default(),
+        skim_note_script,
+        storage,
+        serial_num,
+    )
+}
+
+// =================================================================================================
+// WAITING
+// =================================================================================================
+
+/// Deadline, backoff and cancellation for the client wait helpers.
+#[derive(Clone, Debug)]
+pub struct WaitOptions {
+    /// Waiting ends with [`WaitOutcome::TimedOut`] once this much time has passed.
+    pub timeout: Duration,
+    /// Pause after the first poll; multiplied by `backoff_factor` after every further poll.
+    pub initial_delay: Duration,
+    /// Upper bound for the pause between two polls.
+    pub max_delay: Duration,
+    pub backoff_factor: u32,
+    /// Waiting ends with [`WaitOutcome::Cancelled`] once this token is cancelled.
+    pub cancel: Option<CancelToken>,
+}
+
+impl Default for WaitOptions {
+    fn default() -> Self {
+        WaitOptions {
+            timeout: Duration::from_secs(300),
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(15),
+            backoff_factor: 2,
+            cancel: None,
+        }
+    }
+}
+
+impl WaitOptions {
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_backoff(mut self, initial_delay: Duration, max_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+}
+
+/// Cancels waits from another task. Clones share the same state.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    inner: Arc<CancelState>,
+}
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the token is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Why a wait ended.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WaitOutcome<T> {
+    /// What was waited for happened.
+    Done(T),
+    /// The transaction was discarded and will never commit.
+    Discarded(DiscardCause),
+    /// The deadline passed after `polls` polls.
+    TimedOut { waited: Duration, polls: u32 },
+    /// The cancel token fired after `polls` polls.
+    Cancelled { polls: u32 },
+}
+
+impl<T> WaitOutcome<T> {
+    pub fn is_done(&self) -> bool {
+        matches!(self, WaitOutcome::Done(_))
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> WaitOutcome<U> {
+        match self {
+            WaitOutcome::Done(value) => WaitOutcome::Done(f(value)),
+            WaitOutcome::Discarded(cause) => WaitOutcome::Discarded(cause),
+            WaitOutcome::TimedOut { waited, polls } => WaitOutcome::TimedOut { waited, polls },
+            WaitOutcome::Cancelled { polls } => WaitOutcome::Cancelled { polls },
+        }
+    }
+
+    /// The waited-for value, or an error saying why waiting ended without it.
+    pub fn into_result(self) -> Result<T> {
+        match self {
+            WaitOutcome::Done(value) => Ok(value),
+            WaitOutcome::Discarded(cause) => bail!("transaction discarded ({cause})"),
+            WaitOutcome::TimedOut { waited, polls } => {
+                bail!("timed out after {waited:?} ({polls} polls)")
+            }
+            WaitOutcome::Cancelled { polls } => bail!("cancelled after {polls} polls"),
+        }
+    }
+}
+
+/// Calls `poll` until it returns an outcome, pausing with exponential backoff in between,
+/// and ends early on timeout or cancellation.
+pub(crate) async fn poll_until<T>(
+    options: &WaitOptions,
+    mut poll: impl AsyncFnMut() -> Result<Option<WaitOutcome<T>>>,
+) -> Result<WaitOutcome<T>> {
+    let start = Instant::now();
+    let mut delay = options.initial_delay;
+    let mut polls = 0;
+    loop {
+        if options
+            .cancel
+            .as_ref()
+            .is_some_and(CancelToken::is_cancelled)
+        {
+            return Ok(WaitOutcome::Cancelled { polls });
+        }
+        polls += 1;
+        if let Some(outcome) = poll().await? {
+            return Ok(outcome);
+        }
+
+        let waited = start.elapsed();
+        let remaining = options.timeout.saturating_sub(waited);
+        if remaining.is_zero() {
+            return Ok(WaitOutcome::TimedOut { waited, polls });
+        }
+        let pause = sleep(delay.min(remaining));
+        match &options.cancel {
+            Some(cancel) => tokio::select! {
+                _ = pause => {}
+                _ = cancel.cancelled() => return Ok(WaitOutcome::Cancelled { polls }),
+            },
+            None => pause.await,
+        }
+        delay = delay
+            .saturating_mul(options.backoff_factor)
+            .min(options.max_delay);
+    }
+}
+
 // =================================================================================================
 // CLIENT HELPERS (live network)
 // =================================================================================================
 
-/// Creates a basic wallet account with single-sig auth.
-pub async fn create_basic_account(
-    client: &mut Client<FilesystemKeyStore>,
-    keystore: &Arc<FilesystemKeyStore>,
+/// Creates a basic wallet account with single-sig Falcon512Poseidon2 auth.
+pub async fn create_basic_account<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    keystore: &Arc<K>,
+) -> Result<Account> {
+    create_basic_account_with_scheme(client, keystore, AuthSchemeId::Falcon512Poseidon2).await
+}
+
+/// [`create_basic_account`] with single-sig auth under `auth_scheme`.
+pub async fn create_basic_account_with_scheme<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    keystore: &Arc<K>,
+    auth_scheme: AuthSchemeId,
 ) -> Result<Account> {
     let mut init_seed = [0u8; 32];
     client.rng().fill_bytes(&mut init_seed);
-    let key_pair = AuthSecretKey::new_falcon512_poseidon2_with_rng(client.rng());
+    let key_pair = new_auth_key(client, auth_scheme)?;
     let account = AccountBuilder::new(init_seed)
         .account_type(AccountType::Public)
         .with_auth_component(AuthSingleSig::new(
             key_pair.public_key().to_commitment(),
-            AuthSchemeId::Falcon512Poseidon2,
+            auth_scheme,
         ))
         .with_component(BasicWallet)
         .build()
@@ -485,20 +1224,32 @@ pub async fn create_basic_account(
     Ok(account)
 }
 
-/// Creates a fungible faucet with an allow-all mint/burn token policy.
-pub async fn create_basic_faucet(
-    client: &mut Client<FilesystemKeyStore>,
-    keystore: &Arc<FilesystemKeyStore>,
+/// Creates a fungible faucet with an allow-all mint/burn token policy and single-sig
+/// Falcon512Poseidon2 auth.
+pub async fn create_basic_faucet<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    keystore: &Arc<K>,
     symbol: &str,
+) -> Result<Account> {
+    create_basic_faucet_with_scheme(client, keystore, symbol, AuthSchemeId::Falcon512Poseidon2)
+        .await
+}
+
+/// [`create_basic_faucet`] with single-sig auth under `auth_scheme`.
+pub async fn create_basic_faucet_with_scheme<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    keystore: &Arc<K>,
+    symbol: &str,
+    auth_scheme: AuthSchemeId,
 ) -> Result<Account> {
     let mut init_seed = [0u8; 32];
     client.rng().fill_bytes(&mut init_seed);
-    let key_pair = AuthSecretKey::new_falcon512_poseidon2_with_rng(client.rng());
+    let key_pair = new_auth_key(client, auth_scheme)?;
     let account = AccountBuilder::new(init_seed)
         .account_type(AccountType::Public)
         .with_auth_component(AuthSingleSig::new(
             key_pair.public_key().to_commitment(),
-            AuthSchemeId::Falcon512Poseidon2,
+            auth_scheme,
         ))
         .with_component(
             FungibleFaucet::builder()
@@ -526,80 +1277,137 @@ pub async fn create_basic_faucet(
     Ok(account)
 }
 
+/// A fresh secret key for `auth_scheme`. ECDSA key generation needs a cryptographic RNG,
+/// which the client's `FeltRng` is not, so the key is drawn from a `StdRng` seeded by it.
+fn new_auth_key<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    auth_scheme: AuthSchemeId,
+) -> Result<AuthSecretKey> {
+    let mut seed = [0u8; 32];
+    client.rng().fill_bytes(&mut seed);
+    AuthSecretKey::with_scheme_and_rng(auth_scheme, &mut StdRng::from_seed(seed))
+        .with_context(|| format!("generating a {auth_scheme:?} key"))
+}
+
 /// Mints `amount` of `faucet`'s asset for `target` and waits until the target consumed it,
 /// so the tokens end up in the target's vault.
-pub async fn mint_and_consume(
-    client: &mut Client<FilesystemKeyStore>,
+pub async fn mint_and_consume<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
     faucet: AccountId,
     target: AccountId,
     amount: u64,
 ) -> Result<()> {
+    mint_and_consume_with(client, faucet, target, amount, &WaitOptions::default())
+        .await?
+        .into_result()
+        .with_context(|| {
+            format!(
+                "minting {amount} of {} for {}",
+                faucet.to_hex(),
+                target.to_hex()
+            )
+        })?;
+    Ok(())
+}
+
+/// [`mint_and_consume`] with explicit wait options, which apply to each of its three waits
+/// (mint committed, minted note consumable, consume committed). Ends with the id of the
+/// consume transaction.
+pub async fn mint_and_consume_with<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    faucet: AccountId,
+    target: AccountId,
+    amount: u64,
+    options: &WaitOptions,
+) -> Result<WaitOutcome<TransactionId>> {
     let asset = FungibleAsset::new(faucet, amount).context("building mint asset")?;
     let mint_req = TransactionRequestBuilder::new()
         .build_mint_fungible_asset(asset, target, NoteType::Public, client.rng())
         .context("building mint request")?;
-    let tx_id = client.submit_new_transaction(faucet, mint_req).await?;
-    wait_for_tx(client, tx_id).await?;
-
-    // consume the minted note
-    loop {
-        client.sync_state().await?;
-        let consumable = client.get_consumable_notes(Some(target)).await?;
-        let notes: Vec<Note> = consumable
-            .iter()
-            .map(|(rec, _)| rec.clone().try_into())
-            .collect::<Result<Vec<_>, _>>()
-            .context("converting consumable note records")?;
-        if !notes.is_empty() {
-            let consume_req = TransactionRequestBuilder::new()
-                .build_consume_notes(notes)
-                .context("building consume request")?;
-            let tx_id = client.submit_new_transaction(target, consume_req).await?;
-            wait_for_tx(client, tx_id).await?;
-            break;
-        }
-        println!("no consumable notes yet for {}, waiting...", target.to_hex());
-        sleep(Duration::from_secs(3)).await;
+    let minted = mint_req
+        .expected_output_own_notes()
+        .into_iter()
+        .next()
+        .context("mint request creates no note")?;
+    let mint_tx = client.submit_new_transaction(faucet, mint_req).await?;
+    match wait_for_tx_with(client, mint_tx, options).await? {
+        WaitOutcome::Done(_) => {}
+        ended => return Ok(ended.map(|_| mint_tx)),
     }
-    Ok(())
+    match wait_for_note_with(client, target, &minted, options).await? {
+        WaitOutcome::Done(()) => {}
+        ended => return Ok(ended.map(|_| mint_tx)),
+    }
+
+    let consume_req = TransactionRequestBuilder::new()
+        .build_consume_notes(vec![minted])
+        .context("building consume request")?;
+    let consume_tx = client.submit_new_transaction(target, consume_req).await?;
+    Ok(wait_for_tx_with(client, consume_tx, options)
+        .await?
+        .map(|_| consume_tx))
 }
 
 /// Waits until a note with the given id shows up as consumable for `account_id`.
-pub async fn wait_for_note(
-    client: &mut Client<FilesystemKeyStore>,
+pub async fn wait_for_note<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
     account_id: AccountId,
     expected: &Note,
 ) -> Result<()> {
-    loop {
+    wait_for_note_with(client, account_id, expected, &WaitOptions::default())
+        .await?
+        .into_result()
+        .with_context(|| format!("waiting for note {}", expected.id()))
+}
+
+/// [`wait_for_note`] with explicit wait options.
+pub async fn wait_for_note_with<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    account_id: AccountId,
+    expected: &Note,
+    options: &WaitOptions,
+) -> Result<WaitOutcome<()>> {
+    poll_until(options, async || {
         client.sync_state().await?;
         let notes = client.get_consumable_notes(Some(account_id)).await?;
-        if notes.iter().any(|(rec, _)| rec.id() == Some(expected.id())) {
-            println!("✅ note found {:?}", expected.id());
-            break;
-        }
-        println!("note {:?} not found yet, waiting...", expected.id());
-        sleep(Duration::from_secs(3)).await;
-    }
-    Ok(())
+        let found = notes.iter().any(|(rec, _)| rec.id() == Some(expected.id()));
+        Ok(found.then_some(WaitOutcome::Done(())))
+    })
+    .await
 }
 
-/// Waits for a transaction to be committed.
-pub async fn wait_for_tx(
-    client: &mut Client<FilesystemKeyStore>,
+/// Waits for a transaction to be committed. Fails if it is discarded or does not commit
+/// within the default timeout.
+pub async fn wait_for_tx<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
     tx_id: TransactionId,
 ) -> Result<()> {
-    loop {
+    wait_for_tx_with(client, tx_id, &WaitOptions::default())
+        .await?
+        .into_result()
+        .with_context(|| format!("waiting for transaction {}", tx_id.to_hex()))?;
+    Ok(())
+}
+
+/// [`wait_for_tx`] with explicit wait options. Ends with the block the transaction was
+/// committed in, or as soon as the client reports it discarded.
+pub async fn wait_for_tx_with<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    tx_id: TransactionId,
+    options: &WaitOptions,
+) -> Result<WaitOutcome<BlockNumber>> {
+    poll_until(options, async || {
         client.sync_state().await?;
         let txs = client
             .get_transactions(TransactionFilter::Ids(vec![tx_id]))
             .await?;
-        let committed = !txs.is_empty() && matches!(txs[0].status, TransactionStatus::Committed { .. });
-        if committed {
-            println!("✅ transaction {} committed", tx_id.to_hex());
-            break;
-        }
-        println!("transaction {} not yet committed, waiting...", tx_id.to_hex());
-        sleep(Duration::from_secs(2)).await;
-    }
-    Ok(())
+        Ok(match txs.first().map(|tx| &tx.status) {
+            Some(TransactionStatus::Committed { block_number, .. }) => {
+                Some(WaitOutcome::Done(*block_number))
+            }
+            Some(TransactionStatus::Discarded(cause)) => Some(WaitOutcome::Discarded(*cause)),
+            _ => None,
+        })
+    })
+    .await
 }
@@ -0,0 +1,238 @@
+use std::{error::Error, fmt};
+
+use miden_protocol::errors::MasmError;
+
+use crate::common::{AMM_CODE_TEMPLATE, EVENT_LOG_CODE, GOVERNANCE_CODE, LIQUIDITY_CODE_TEMPLATE};
+
+// =================================================================================================
+// AMM ERRORS
+// =================================================================================================
+
+/// A failed assertion in the AMM's account code. One variant per `ERR_*` constant in
+/// `amm.masm`, `liquidity.masm` and `governance.masm`; the two `ERR_SLIPPAGE` constants share
+/// a message and therefore a variant.
+///
+/// The VM reports an assertion either with its message or — when the executor runs without
+/// debug info — only with the message's hash, so variants are matched by both.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AmmError {
+    // amm.masm
+    /// `ERR_BAD_NOTE_STORAGE` (swap)
+    BadSwapNoteStorage,
+    /// `ERR_BAD_NOTE_ASSETS`
+    BadSwapNoteAssets,
+    /// `ERR_WRONG_PAIR` (swap; also `init`, when both sides come from one faucet)
+    WrongSwapPair,
+    /// `ERR_FEE_TOO_LARGE`
+    FeeTooLarge,
+    /// `ERR_DY_OVERFLOW`
+    OutputOverflow,
+    /// `ERR_ZERO_OUTPUT`
+    ZeroOutput,
+    /// `ERR_SLIPPAGE` (swap, add and remove liquidity)
+    Slippage,
+    /// `ERR_K_DECREASED`
+    KDecreased,
+
+    // liquidity.masm
+    /// `ERR_BAD_NOTE_STORAGE` (liquidity, skim)
+    BadLiquidityNoteStorage,
+    /// `ERR_BAD_ADD_ASSETS`
+    BadAddAssets,
+    /// `ERR_BAD_REMOVE_ASSETS`
+    BadRemoveAssets,
+    /// `ERR_WRONG_PAIR` (add liquidity)
+    WrongDepositPair,
+    /// `ERR_NOT_LP_TOKEN`
+    NotLpToken,
+    /// `ERR_INSUFFICIENT_INITIAL_LIQUIDITY`
+    InsufficientInitialLiquidity,
+    /// `ERR_ZERO_LP_MINTED`
+    ZeroLpMinted,
+    /// `ERR_ZERO_PAYOUT`
+    ZeroPayout,
+    /// `ERR_EMPTY_POOL`
+    EmptyPool,
+    /// `ERR_LP_EXCEEDS_SUPPLY`
+    LpExceedsSupply,
+    /// `ERR_VALUE_OVERFLOW`
+    ValueOverflow,
+    /// `ERR_UNEXPECTED_ASSETS`
+    UnexpectedAssets,
+    /// `ERR_NOTHING_TO_SKIM`
+    NothingToSkim,
+    /// `ERR_LP_VALUE_DECREASED`
+    LpValueDecreased,
+
+    // governance.masm
+    /// `ERR_BAD_GOVERNANCE_NOTE_STORAGE`
+    BadGovernanceNoteStorage,
+    /// `ERR_GOVERNANCE_NOTE_ASSETS`
+    GovernanceNoteAssets,
+    /// `ERR_GOVERNANCE_DISABLED`
+    GovernanceDisabled,
+    /// `ERR_NOT_GOVERNANCE_ADMIN`
+    NotGovernanceAdmin,
+    /// `ERR_BAD_ALLOWLIST_ACTION`
+    BadAllowlistAction,
+    /// `ERR_NO_PENDING_CHANGE`
+    NoPendingChange,
+    /// `ERR_TIMELOCK_NOT_ELAPSED`
+    TimelockNotElapsed,
+    /// `ERR_REMOVES_GOVERNANCE_NOTE`
+    RemovesGovernanceNote,
+}
+
+const ASSERTION_MESSAGE_MARKER: &str = "assertion failed with error message: ";
+const ASSERTION_CODE_MARKER: &str = "assertion failed with error code: ";
+
+impl AmmError {
+    pub const ALL: [AmmError; 30] = [
+        AmmError::BadSwapNoteStorage,
+        AmmError::BadSwapNoteAssets,
+        AmmError::WrongSwapPair,
+        AmmError::FeeTooLarge,
+        AmmError::OutputOverflow,
+        AmmError::ZeroOutput,
+        AmmError::Slippage,
+        AmmError::KDecreased,
+        AmmError::BadLiquidityNoteStorage,
+        AmmError::BadAddAssets,
+        AmmError::BadRemoveAssets,
+        AmmError::WrongDepositPair,
+        AmmError::NotLpToken,
+        AmmError::InsufficientInitialLiquidity,
+        AmmError::ZeroLpMinted,
+        AmmError::ZeroPayout,
+        AmmError::EmptyPool,
+        AmmError::LpExceedsSupply,
+        AmmError::ValueOverflow,
+        AmmError::UnexpectedAssets,
+        AmmError::NothingToSkim,
+        AmmError::LpValueDecreased,
+        AmmError::BadGovernanceNoteStorage,
+        AmmError::GovernanceNoteAssets,
+        AmmError::GovernanceDisabled,
+        AmmError::NotGovernanceAdmin,
+        AmmError::BadAllowlistAction,
+        AmmError::NoPendingChange,
+        AmmError::TimelockNotElapsed,
+        AmmError::RemovesGovernanceNote,
+    ];
+
+    /// The error message, exactly as declared in the MASM source.
+    pub fn message(self) -> &'static str {
+        match self {
+            AmmError::BadSwapNoteStorage => "swap note must carry exactly 12 storage elements",
+            AmmError::BadSwapNoteAssets => "swap note must carry exactly one input asset",
+            AmmError::WrongSwapPair => "swap assets do not match the pool pair",
+            AmmError::FeeTooLarge => "fee_bps exceeds the fee denominator",
+            AmmError::OutputOverflow => "computed output amount does not fit in a u64",
+            AmmError::ZeroOutput => "computed output amount is zero",
+            AmmError::Slippage => "slippage higher than user set accepted range",
+            AmmError::KDecreased => "swap decreased the constant product",
+            AmmError::BadLiquidityNoteStorage => {
+                "liquidity note must carry exactly 8 storage elements"
+            }
+            AmmError::BadAddAssets => "add_liquidity note must carry exactly two input assets",
+            AmmError::BadRemoveAssets => "remove_liquidity note must carry exactly one input asset",
+            AmmError::WrongDepositPair => "deposited assets do not match the pool pair",
+            AmmError::NotLpToken => "remove_liquidity note asset is not the pool's LP token",
+            AmmError::InsufficientInitialLiquidity => {
+                "initial deposit too small to lock minimum liquidity"
+            }
+            AmmError::ZeroLpMinted => "deposit mints zero LP tokens",
+            AmmError::ZeroPayout => "burn pays out zero of one pool asset",
+            AmmError::EmptyPool => "pool has LP supply but empty reserves",
+            AmmError::LpExceedsSupply => "burn amount exceeds LP supply",
+            AmmError::ValueOverflow => "computed value does not fit in a u64",
+            AmmError::UnexpectedAssets => "sync and skim notes must not carry assets",
+            AmmError::NothingToSkim => "pool balances do not exceed the reserves",
+            AmmError::LpValueDecreased => "liquidity change decreased the reserves per LP token",
+            AmmError::BadGovernanceNoteStorage => {
+                "governance note must carry exactly 8 storage elements"
+            }
+            AmmError::GovernanceNoteAssets => "governance notes must not carry assets",
+            AmmError::GovernanceDisabled => "pool has no governance",
+            AmmError::NotGovernanceAdmin => "note sender is not the pool's governance admin",
+            AmmError::BadAllowlistAction => "allowlist action must be 1 (add) or 2 (remove)",
+            AmmError::NoPendingChange => "no allowlist change is pending for this script root",
+            AmmError::TimelockNotElapsed => "allowlist change is still timelocked",
+            AmmError::RemovesGovernanceNote => {
+                "allowlist change must not remove the governance note's own script root"
+            }
+        }
+    }
+
+    /// The error code the assembler derives from the message (what `assert.err` reports).
+    pub fn code(self) -> u64 {
+        MasmError::from_static_str(self.message())
+            .code()
+            .as_canonical_u64()
+    }
+
+    pub fn from_message(message: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.message() == message)
+    }
+
+    pub fn from_code(code: u64) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.code() == code)
+    }
+
+    /// Decodes a failed AMM assertion from an executor error (e.g. `ClientError` or
+    /// `TransactionExecutorError`), searching its whole source chain.
+    pub fn from_execution_error(err: &(dyn Error + 'static)) -> Option<Self> {
+        let mut current = Some(err);
+        while let Some(e) = current {
+            if let Some(amm_error) = Self::from_error_text(&e.to_string()) {
+                return Some(amm_error);
+            }
+            current = e.source();
+        }
+        None
+    }
+
+    /// Decodes a failed AMM assertion from an error string, such as the `last_error` of a
+    /// network note status or the Display of an executor error. Assertions that are not
+    /// raised by the AMM's account code (kernel, note scripts) yield `None`.
+    pub fn from_error_text(text: &str) -> Option<Self> {
+        if let Some((_, rest)) = text.split_once(ASSERTION_MESSAGE_MARKER) {
+            let message = rest.lines().next().unwrap_or(rest).trim();
+            return Self::from_message(message);
+        }
+        let (_, rest) = text.split_once(ASSERTION_CODE_MARKER)?;
+        let code = rest
+            .split(|c: char| !c.is_ascii_digit())
+            .next()?
+            .parse()
+            .ok()?;
+        Self::from_code(code)
+    }
+}
+
+impl fmt::Display for AmmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl Error for AmmError {}
+
+/// Every `ERR_*` message declared in the AMM's account components, read from the embedded
+/// MASM sources. Used to check that [`AmmError`] stays in sync with the MASM.
+pub fn masm_error_messages() -> Vec<&'static str> {
+    [
+        AMM_CODE_TEMPLATE,
+        LIQUIDITY_CODE_TEMPLATE,
+        EVENT_LOG_CODE,
+        GOVERNANCE_CODE,
+    ]
+    .into_iter()
+    .flat_map(str::lines)
+    .filter_map(|line| {
+        let rest = line.trim().strip_prefix("const ERR_")?;
+        let (_, value) = rest.split_once('=')?;
+        value.trim().strip_prefix('"')?.strip_suffix('"')
+    })
+    .collect()
+}
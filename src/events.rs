@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, bail, ensure};
+use serde::Serialize;
+
+use miden_client::{Felt, Word, account::AccountId, transaction::ExecutedTransaction};
+use miden_protocol::{
+    account::{Account, StorageMapKey, StorageSlotContent, delta::AccountDelta},
+    block::ProvenBlock,
+};
+
+use crate::{
+    common::{event_count_slot, event_log_slot},
+    history::amm_delta,
+};
+
+// =================================================================================================
+// CONSTANTS
+// =================================================================================================
+
+/// The `EVENT_*` constants of `masm/shared/event_log.masm`, exported by `build.rs`: the
+/// event kinds as recorded in the log, and the capacity of the log.
+mod masm {
+    include!(concat!(env!("OUT_DIR"), "/masm_events.rs"));
+}
+
+/// Events the `event_log` slot retains; older entries are overwritten.
+pub use masm::EVENT_LOG_CAPACITY;
+use masm::{
+    EVENT_ADD_LIQUIDITY, EVENT_REMOVE_LIQUIDITY, EVENT_SKIM, EVENT_SWAP_X_IN, EVENT_SWAP_Y_IN,
+    EVENT_SYNC,
+};
+
+/// Entries per event: header, amounts, pool state.
+const EVENT_PARTS: usize = 3;
+
+// =================================================================================================
+// EVENTS
+// =================================================================================================
+
+/// What an AMM note did to the pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AmmAction {
+    Swap {
+        /// `true` for X in, Y out.
+        x_in: bool,
+        amount_in: u64,
+        amount_out: u64,
+    },
+    AddLiquidity {
+        amount_x: u64,
+        amount_y: u64,
+        lp_minted: u64,
+    },
+    RemoveLiquidity {
+        lp_burned: u64,
+        amount_x: u64,
+        amount_y: u64,
+    },
+    /// Vault balances adopted into the reserves; 0 for a side that did not grow.
+    Sync { amount_x: u64, amount_y: u64 },
+    /// Vault balances above the reserves, paid to the sender.
+    Skim { amount_x: u64, amount_y: u64 },
+}
+
+/// One entry of the AMM's event log: a swap, deposit, withdrawal, sync or skim, who sent the
+/// note, and the pool right after it. Every change of the reserves or the LP supply records
+/// one, so the log doubles as a feed of the pool state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct AmmEvent {
+    /// Position in the pool's history, from 0; consecutive across transactions.
+    pub seq: u64,
+    #[serde(serialize_with = "serialize_account_id")]
+    pub sender: AccountId,
+    #[serde(flatten)]
+    pub action: AmmAction,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+}
+
+fn serialize_account_id<S: serde::Serializer>(id: &AccountId, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&id.to_hex())
+}
+
+impl AmmEvent {
+    /// Decodes the three log entries of one event.
+    fn decode(header: Word, amounts: Word, state: Word) -> Result<Self> {
+        let [kind, seq, sender_suffix, sender_prefix]: [Felt; 4] = header.into();
+        let [amount_x, amount_y, lp_amount, _] = as_u64s(amounts);
+        let [reserve_x, reserve_y, lp_supply, _] = as_u64s(state);
+        let action = match kind.as_canonical_u64() {
+            EVENT_SWAP_X_IN => AmmAction::Swap {
+                x_in: true,
+                amount_in: amount_x,
+                amount_out: amount_y,
+            },
+            EVENT_SWAP_Y_IN => AmmAction::Swap {
+                x_in: false,
+                amount_in: amount_y,
+                amount_out: amount_x,
+            },
+            EVENT_ADD_LIQUIDITY => AmmAction::AddLiquidity {
+                amount_x,
+                amount_y,
+                lp_minted: lp_amount,
+            },
+            EVENT_REMOVE_LIQUIDITY => AmmAction::RemoveLiquidity {
+                lp_burned: lp_amount,
+                amount_x,
+                amount_y,
+            },
+            EVENT_SYNC => AmmAction::Sync { amount_x, amount_y },
+            EVENT_SKIM => AmmAction::Skim { amount_x, amount_y },
+            other => bail!("unknown AMM event kind {other}"),
+        };
+        Ok(AmmEvent {
+            seq: seq.as_canonical_u64(),
+            sender: AccountId::try_from_elements(sender_suffix, sender_prefix)
+                .context("event sender is not an account ID")?,
+            action,
+            reserve_x,
+            reserve_y,
+            lp_supply,
+        })
+    }
+}
+
+fn as_u64s(word: Word) -> [u64; 4] {
+    <[Felt; 4]>::from(word).map(|felt| felt.as_canonical_u64())
+}
+
+/// Decodes `event_log` entries into events ordered by `seq`. Every event must be complete.
+fn decode_entries<'a>(
+    entries: impl Iterator<Item = (&'a StorageMapKey, &'a Word)>,
+) -> Result<Vec<AmmEvent>> {
+    let mut parts: BTreeMap<u64, [Option<Word>; EVENT_PARTS]> = BTreeMap::new();
+    for (key, value) in entries {
+        let [index, part, ..] = as_u64s(key.as_word());
+        ensure!(
+            index < EVENT_LOG_CAPACITY && (part as usize) < EVENT_PARTS,
+            "unexpected event log key {key}"
+        );
+        parts.entry(index).or_default()[part as usize] = Some(*value);
+    }
+
+    let mut events = parts
+        .into_iter()
+        .map(|(index, parts)| match parts {
+            [Some(header), Some(amounts), Some(state)] => AmmEvent::decode(header, amounts, state),
+            _ => bail!("event log entry {index} is incomplete"),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    events.sort_by_key(|event| event.seq);
+    Ok(events)
+}
+
+// =================================================================================================
+// DECODERS
+// =================================================================================================
+
+/// The events an account delta of the AMM recorded, in order, given the account's
+/// `event_count` before the delta. Fails unless the delta holds exactly the events
+/// `event_count_before..` up to its new `event_count`: more than [`EVENT_LOG_CAPACITY`]
+/// events in one delta overwrite each other in the ring buffer, and a stale
+/// `event_count_before` would misnumber the rest.
+///
+/// A full-state delta (a newly created account) yields the whole log instead.
+pub fn events_from_delta(delta: &AccountDelta, event_count_before: u64) -> Result<Vec<AmmEvent>> {
+    let events = match delta
+        .storage()
+        .maps()
+        .find(|(slot, _)| **slot == event_log_slot())
+    {
+        Some((_, map)) => decode_entries(map.entries().iter())?,
+        None => Vec::new(),
+    };
+    if delta.is_full_state() {
+        return Ok(events);
+    }
+
+    let count = delta
+        .storage()
+        .values()
+        .find(|(slot, _)| **slot == event_count_slot())
+        .map_or(event_count_before, |(_, value)| value[0].as_canonical_u64());
+    ensure!(
+        count >= event_count_before
+            && count - event_count_before == events.len() as u64
+            && events
+                .first()
+                .is_none_or(|first| first.seq == event_count_before),
+        "delta recorded events {event_count_before}..{count} but holds {} events from seq {}",
+        events.len(),
+        events.first().map_or(count, |first| first.seq)
+    );
+    Ok(events)
+}
+
+/// The events an executed AMM transaction recorded, in order.
+pub fn events_from_transaction(executed: &ExecutedTransaction) -> Result<Vec<AmmEvent>> {
+    let event_count_before = executed
+        .initial_account()
+        .storage()
+        .header()
+        .find_slot_header_by_name(&event_count_slot())
+        .map_or(0, |slot| slot.value()[0].as_canonical_u64());
+    events_from_delta(executed.account_delta(), event_count_before)
+}
+
+/// The events the AMM account `amm_id` recorded in a block, in order, given its
+/// `event_count` before the block (see [`events_from_delta`]). Fails if the account is
+/// private (its deltas are not on chain).
+pub fn events_from_block(
+    block: &ProvenBlock,
+    amm_id: AccountId,
+    event_count_before: u64,
+) -> Result<Vec<AmmEvent>> {
+    match amm_delta(block, amm_id)? {
+        Some(delta) => events_from_delta(delta, event_count_before),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// The events the AMM account still holds in its log: the last [`EVENT_LOG_CAPACITY`], in
+/// order.
+pub fn event_log(account: &Account) -> Result<Vec<AmmEvent>> {
+    let slot = account
+        .storage()
+        .get(&event_log_slot())
+        .context("AMM account has no event log slot")?;
+    let StorageSlotContent::Map(map) = slot.content() else {
+        bail!("event log slot is not a map");
+    };
+    decode_entries(map.entries())
+}
@@ -0,0 +1,216 @@
+use std::collections::BTreeSet;
+
+use anyhow::{Context, Result, bail};
+
+use miden_client::{
+    Felt, Word,
+    account::{Account, AccountId, StorageSlot, StorageSlotContent, StorageSlotName},
+    block::BlockNumber,
+    note::{Note, NoteAssets, NoteScript, NoteScriptRoot},
+};
+use miden_standards::account::auth::NetworkAccountNoteAllowlist;
+
+use crate::common::build_amm_network_note;
+
+// =================================================================================================
+// GOVERNANCE CONFIGURATION
+// =================================================================================================
+
+/// Named storage slots of the governance component.
+pub fn governance_config_slot() -> StorageSlotName {
+    StorageSlotName::new("miden_amm::governance::config").expect("valid slot name")
+}
+pub fn pending_allowlist_slot() -> StorageSlotName {
+    StorageSlotName::new("miden_amm::governance::pending").expect("valid slot name")
+}
+
+/// Who may change a live pool's note allowlist, and how long a change waits before anyone
+/// can apply it. Stored as `[admin_suffix, admin_prefix, timelock_blocks, enabled]`; a pool
+/// built without governance has `enabled = 0` and rejects every proposal, so its allowlist
+/// stays the one it was deployed with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Governance {
+    /// The only account whose propose notes the pool accepts.
+    pub admin: AccountId,
+    /// Blocks between a proposal and the first block it can be executed in.
+    pub timelock_blocks: u32,
+}
+
+impl Governance {
+    pub fn new(admin: AccountId, timelock_blocks: u32) -> Self {
+        Governance {
+            admin,
+            timelock_blocks,
+        }
+    }
+
+    /// Reads the governance configuration back from the AMM account's storage; `None` for a
+    /// pool built without governance.
+    pub fn from_account(account: &Account) -> Result<Option<Self>> {
+        let slot = governance_config_slot();
+        let config = account
+            .storage()
+            .get_item(&slot)
+            .with_context(|| format!("reading {slot}"))?;
+        if config[3] == Felt::new_unchecked(0) {
+            return Ok(None);
+        }
+        let admin = AccountId::try_from_elements(config[0], config[1])
+            .context("decoding the governance admin")?;
+        let timelock_blocks = u32::try_from(config[2].as_canonical_u64())
+            .context("governance timelock exceeds u32")?;
+        Ok(Some(Governance::new(admin, timelock_blocks)))
+    }
+
+    /// The governance component's storage: the configuration and an empty pending map.
+    pub(crate) fn storage_slots(governance: Option<&Governance>) -> Vec<StorageSlot> {
+        let config = match governance {
+            Some(governance) => [
+                governance.admin.suffix(),
+                governance.admin.prefix().as_felt(),
+                Felt::from(governance.timelock_blocks),
+                Felt::new_unchecked(1),
+            ]
+            .into(),
+            None => Word::default(),
+        };
+        vec![
+            StorageSlot::with_value(governance_config_slot(), config),
+            StorageSlot::with_empty_map(pending_allowlist_slot()),
+        ]
+    }
+}
+
+// =================================================================================================
+// ALLOWLIST CHANGES
+// =================================================================================================
+
+/// What a proposal does to a note-script root; the felt values must match `governance.masm`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AllowlistAction {
+    Add = 1,
+    Remove = 2,
+}
+
+impl AllowlistAction {
+    fn from_felt(felt: Felt) -> Result<Self> {
+        match felt.as_canonical_u64() {
+            1 => Ok(AllowlistAction::Add),
+            2 => Ok(AllowlistAction::Remove),
+            other => bail!("unknown allowlist action {other}"),
+        }
+    }
+}
+
+/// An allowlist change waiting out its timelock in the pool's `pending` map.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingAllowlistChange {
+    pub script_root: NoteScriptRoot,
+    pub action: AllowlistAction,
+    /// First block whose transactions can execute the change.
+    pub eta: BlockNumber,
+}
+
+impl PendingAllowlistChange {
+    pub fn is_ready(&self, block_num: BlockNumber) -> bool {
+        block_num >= self.eta
+    }
+}
+
+/// The allowlist changes proposed on `account` and not yet executed, ordered by script root.
+/// A later proposal for the same root replaces the earlier one.
+pub fn pending_allowlist_changes(account: &Account) -> Result<Vec<PendingAllowlistChange>> {
+    let slot_name = pending_allowlist_slot();
+    let slot = account
+        .storage()
+        .get(&slot_name)
+        .with_context(|| format!("storage slot {slot_name} missing"))?;
+    let StorageSlotContent::Map(map) = slot.content() else {
+        bail!("{slot_name} is not a storage map");
+    };
+    map.entries()
+        .map(|(key, value)| {
+            let eta = u32::try_from(value[1].as_canonical_u64())
+                .context("pending change eta exceeds u32")?;
+            Ok(PendingAllowlistChange {
+                script_root: NoteScriptRoot::from_raw(key.as_word()),
+                action: AllowlistAction::from_felt(value[0])?,
+                eta: BlockNumber::from(eta),
+            })
+        })
+        .collect()
+}
+
+/// The note-script roots `account`'s network auth currently accepts.
+pub fn allowed_note_scripts(account: &Account) -> Result<BTreeSet<NoteScriptRoot>> {
+    Ok(NetworkAccountNoteAllowlist::try_from(account.storage())
+        .context("reading the note allowlist")?
+        .into_allowed_script_roots())
+}
+
+// =================================================================================================
+// GOVERNANCE NOTES
+// =================================================================================================
+
+/// Storage layout shared by the two governance notes (8 felts) — must match
+/// `governance.masm`: [0..3] SCRIPT_ROOT, [4] action (propose only), [5..7] pad.
+fn governance_note_storage(script_root: NoteScriptRoot, action: Felt) -> Vec<Felt> {
+    let root = script_root.as_word();
+    vec![
+        root[0],
+        root[1],
+        root[2],
+        root[3],
+        action,
+        Felt::new_unchecked(0),
+        Felt::new_unchecked(0),
+        Felt::new_unchecked(0),
+    ]
+}
+
+/// Creates a propose note: the AMM queues `action` for `script_root`, executable from
+/// `timelock_blocks` after the block the note is consumed in. Only notes sent by the pool's
+/// governance admin are accepted; `sender` must be that account.
+///
+/// The root must be of a script linked against the pool's components the way
+/// [`crate::artifacts`] links this crate's notes. The pool refuses to remove the propose
+/// note's own root, and to execute the removal of the execute note's root: either would leave
+/// the allowlist unchangeable (see `governance.masm`).
+pub fn create_propose_allowlist_note(
+    sender: AccountId,
+    amm_id: AccountId,
+    script_root: NoteScriptRoot,
+    action: AllowlistAction,
+    propose_note_script: NoteScript,
+    serial_num: Word,
+) -> Result<Note> {
+    let storage = governance_note_storage(script_root, Felt::new_unchecked(action as u64));
+    build_amm_network_note(
+        sender,
+        amm_id,
+        NoteAssets::default(),
+        propose_note_script,
+        storage,
+        serial_num,
+    )
+}
+
+/// Creates an execute note: the AMM applies the pending change for `script_root` once its
+/// timelock has elapsed. Carries no assets; anyone can send one.
+pub fn create_execute_allowlist_note(
+    sender: AccountId,
+    amm_id: AccountId,
+    script_root: NoteScriptRoot,
+    execute_note_script: NoteScript,
+    serial_num: Word,
+) -> Result<Note> {
+    let storage = governance_note_storage(script_root, Felt::new_unchecked(0));
+    build_amm_network_note(
+        sender,
+        amm_id,
+        NoteAssets::default(),
+        execute_note_script,
+        storage,
+        serial_num,
+    )
+}
@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Context, Result, bail};
+
+use miden_client::{
+    Word,
+    account::AccountId,
+    block::BlockNumber,
+    note::{Note, NoteId, NoteTag, Nullifier},
+    rpc::{
+        NodeRpcClient,
+        domain::{note::SyncedNoteDetails, transaction::TransactionRecord},
+    },
+    transaction::TransactionId,
+};
+use miden_protocol::{
+    account::delta::{AccountDelta, AccountUpdateDetails},
+    block::ProvenBlock,
+};
+
+// =================================================================================================
+// NOTES AND DELTAS
+// =================================================================================================
+
+/// The public notes sent to an AMM, by nullifier. Every note the AMM can consume is among
+/// them: the AMM's note scripts all run from public notes tagged with the AMM account.
+#[derive(Clone, Debug, Default)]
+pub struct AmmNotes {
+    by_nullifier: BTreeMap<Nullifier, Note>,
+}
+
+impl AmmNotes {
+    /// Registers a note sent to the AMM.
+    pub fn insert(&mut self, note: Note) {
+        self.by_nullifier.insert(note.nullifier(), note);
+    }
+
+    /// The registered notes, by nullifier.
+    pub fn iter(&self) -> impl Iterator<Item = &Note> {
+        self.by_nullifier.values()
+    }
+
+    /// The note AMM transaction `tx_id` consumed as `nullifier`; fails if it was never
+    /// registered.
+    pub fn consumed(&self, nullifier: Nullifier, tx_id: TransactionId) -> Result<&Note> {
+        self.by_nullifier
+            .get(&nullifier)
+            .with_context(|| format!("AMM transaction {tx_id} consumed an unknown note"))
+    }
+}
+
+/// The delta of the AMM account `amm_id` in `block`, or `None` if the block did not update
+/// it. Fails if the account is private: its state deltas are not on chain.
+pub fn amm_delta(block: &ProvenBlock, amm_id: AccountId) -> Result<Option<&AccountDelta>> {
+    let Some(update) = block
+        .body()
+        .updated_accounts()
+        .iter()
+        .find(|update| update.account_id() == amm_id)
+    else {
+        return Ok(None);
+    };
+    let AccountUpdateDetails::Delta(delta) = update.details() else {
+        bail!(
+            "AMM account {} is private; its state deltas are not on chain",
+            amm_id.to_hex()
+        );
+    };
+    Ok(Some(delta))
+}
+
+// =================================================================================================
+// CLIENT HELPERS (live network)
+// =================================================================================================
+
+/// An AMM transaction: the notes it consumed, in order, and the ids of the notes it created.
+#[derive(Clone, Debug)]
+pub struct AmmTransaction {
+    pub id: TransactionId,
+    pub consumed: Vec<Note>,
+    pub output_notes: Vec<NoteId>,
+}
+
+/// A block with AMM transactions, in the order they were applied to the account.
+#[derive(Clone, Debug)]
+pub struct AmmBlock {
+    pub block_num: BlockNumber,
+    pub transactions: Vec<AmmTransaction>,
+}
+
+impl AmmBlock {
+    /// Fetches the block, e.g. for the AMM's delta in it ([`amm_delta`]).
+    pub async fn fetch(&self, rpc: &dyn NodeRpcClient) -> Result<ProvenBlock> {
+        rpc.get_block_by_number(self.block_num, false)
+            .await
+            .with_context(|| format!("fetching block {}", self.block_num))
+    }
+}
+
+/// Walks an AMM's on-chain history: registers the public notes sent to the AMM and lists the
+/// blocks with AMM transactions, each with the notes those transactions consumed.
+///
+/// The walk can be resumed: [`AmmChainWalker::advance`] continues after the last block it
+/// walked and keeps the notes registered so far, since a note is often consumed blocks after
+/// it was created.
+#[derive(Clone, Debug)]
+pub struct AmmChainWalker {
+    amm_id: AccountId,
+    notes: AmmNotes,
+    next_block: BlockNumber,
+}
+
+impl AmmChainWalker {
+    /// Prepares a walk from genesis.
+    pub fn new(amm_id: AccountId) -> Self {
+        AmmChainWalker {
+            amm_id,
+            notes: AmmNotes::default(),
+            next_block: BlockNumber::GENESIS,
+        }
+    }
+
+    pub fn amm_id(&self) -> AccountId {
+        self.amm_id
+    }
+
+    /// The notes sent to the AMM in the blocks walked so far.
+    pub fn notes(&self) -> &AmmNotes {
+        &self.notes
+    }
+
+    /// Walks the blocks after the last walked one up to `block_to`. Returns the blocks with
+    /// AMM transactions (plus genesis on the first walk, for pools created there), each with
+    /// its AMM transactions in state-commitment order.
+    pub async fn advance(
+        &mut self,
+        rpc: &dyn NodeRpcClient,
+        block_to: BlockNumber,
+    ) -> Result<Vec<AmmBlock>> {
+        let block_from = self.next_block;
+        if block_from > block_to {
+            return Ok(Vec::new());
+        }
+
+        let tags = BTreeSet::from([NoteTag::with_account_target(self.amm_id)]);
+        let (_, details) = rpc
+            .sync_notes_with_details(block_from, block_to, &tags)
+            .await
+            .context("scanning notes sent to the AMM")?;
+        for details in details.into_values() {
+            if let SyncedNoteDetails::Public(note) = details {
+                self.notes.insert(note);
+            }
+        }
+
+        let txs = rpc
+            .sync_transactions(block_from, block_to, vec![self.amm_id])
+            .await
+            .context("fetching AMM transactions")?;
+        let txs = order_by_state_chain(txs)?;
+        let mut blocks: BTreeMap<BlockNumber, Vec<AmmTransaction>> = BTreeMap::new();
+        if block_from == BlockNumber::GENESIS {
+            blocks.insert(BlockNumber::GENESIS, Vec::new());
+        }
+        for tx in txs {
+            let header = &tx.transaction_header;
+            let consumed = header
+                .input_notes()
+                .iter()
+                .map(|input| self.notes.consumed(input.nullifier(), header.id()).cloned())
+                .collect::<Result<_>>()?;
+            blocks
+                .entry(tx.block_num)
+                .or_default()
+                .push(AmmTransaction {
+                    id: header.id(),
+                    consumed,
+                    output_notes: header.output_notes().iter().map(|n| n.id()).collect(),
+                });
+        }
+
+        self.next_block = block_to + 1;
+        Ok(blocks
+            .into_iter()
+            .map(|(block_num, transactions)| AmmBlock {
+                block_num,
+                transactions,
+            })
+            .collect())
+    }
+}
+
+/// Orders an account's transactions by following its state commitments: each transaction
+/// starts from the state the previous one ended in.
+fn order_by_state_chain(txs: Vec<TransactionRecord>) -> Result<Vec<TransactionRecord>> {
+    let finals: BTreeSet<Word> = txs
+        .iter()
+        .map(|tx| tx.transaction_header.final_state_commitment())
+        .collect();
+    let mut by_initial: BTreeMap<Word, TransactionRecord> = txs
+        .into_iter()
+        .map(|tx| (tx.transaction_header.initial_state_commitment(), tx))
+        .collect();
+    let Some(mut next) = by_initial.keys().find(|c| !finals.contains(*c)).copied() else {
+        return Ok(Vec::new());
+    };
+
+    let mut ordered = Vec::with_capacity(by_initial.len());
+    while let Some(tx) = by_initial.remove(&next) {
+        next = tx.transaction_header.final_state_commitment();
+        ordered.push(tx);
+    }
+    anyhow::ensure!(
+        by_initial.is_empty(),
+        "AMM transaction history is not a single chain"
+    );
+    Ok(ordered)
+}
@@ -1 +1,18 @@
+pub mod analytics;
+pub mod artifacts;
+pub mod backtest;
+pub mod bench;
 pub mod common;
+pub mod errors;
+pub mod events;
+pub mod governance;
+pub mod history;
+pub mod manifest;
+pub mod mock_node;
+pub mod ntb;
+pub mod payouts;
+pub mod position;
+pub mod quote;
+pub mod recovery;
+pub mod simulation;
+pub mod verify;
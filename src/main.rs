@@ -1,3 +1,153 @@
-fn main() {
-    println!("Hello, world!");
+use anyhow::{Context, Result, bail};
+
+use miden_amm::{
+    analytics::{PoolSeries, PoolStats, fetch_pool_series},
+    manifest::AmmManifest,
+};
+use miden_client::rpc::{Endpoint, GrpcClient, NodeRpcClient};
+
+const USAGE: &str = "\
+usage: miden-amm analytics <manifest> [--rpc <url>] [--days <n>] [--json]
+
+  <manifest>   deployment manifest of the pool (AmmManifest::write_to_file)
+  --rpc <url>  node endpoint (default: testnet)
+  --days <n>   window of the summary, ending at the chain tip (default: 7)
+  --json       print the series and the summary as JSON";
+
+/// Timeout of the node's RPC calls.
+const RPC_TIMEOUT_MS: u64 = 10_000;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+struct AnalyticsArgs {
+    manifest: String,
+    endpoint: Endpoint,
+    days: u64,
+    /// `days` in seconds.
+    window: u64,
+    json: bool,
+}
+
+fn parse_analytics_args(args: &[String]) -> Result<AnalyticsArgs> {
+    let mut manifest = None;
+    let mut endpoint = Endpoint::testnet();
+    let mut days: u64 = 7;
+    let mut json = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rpc" => {
+                let url = args.next().context("--rpc needs a url")?;
+                endpoint = Endpoint::try_from(url.as_str())
+                    .map_err(|err| anyhow::anyhow!("invalid endpoint {url}: {err}"))?;
+            }
+            "--days" => {
+                let n = args.next().context("--days needs a number")?;
+                days = n.parse().with_context(|| format!("invalid --days {n}"))?;
+            }
+            "--json" => json = true,
+            flag if flag.starts_with("--") => bail!("unknown option {flag}"),
+            path if manifest.is_none() => manifest = Some(path.to_string()),
+            extra => bail!("unexpected argument {extra}"),
+        }
+    }
+    let window = days
+        .checked_mul(SECONDS_PER_DAY)
+        .with_context(|| format!("--days {days} is too large"))?;
+    Ok(AnalyticsArgs {
+        manifest: manifest.context("missing <manifest>")?,
+        endpoint,
+        days,
+        window,
+        json,
+    })
+}
+
+async fn run_analytics(args: AnalyticsArgs) -> Result<()> {
+    let manifest = AmmManifest::read_from_file(&args.manifest)?;
+    let rpc = GrpcClient::new(&args.endpoint, RPC_TIMEOUT_MS);
+    let (tip, _) = rpc
+        .get_block_header_by_number(None, false)
+        .await
+        .context("fetching the chain tip")?;
+    let series = fetch_pool_series(&rpc, &manifest, tip.block_num()).await?;
+    let since = u64::from(tip.timestamp()).saturating_sub(args.window);
+    let stats = series.stats_since(u32::try_from(since).expect("at most the tip's timestamp"));
+
+    if args.json {
+        let report = serde_json::json!({ "series": series, "stats": stats });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    println!("pool {}", manifest.amm_id.to_hex());
+    print_series(&series);
+    match stats {
+        Some(stats) => print_stats(&stats, args.days),
+        None => println!("\nthe pool has no history up to block {}", tip.block_num()),
+    }
+    Ok(())
+}
+
+fn print_series(series: &PoolSeries) {
+    println!(
+        "\n{:>8} {:>11} {:>20} {:>20} {:>20} {:>14} {:>6} {:>20} {:>20}",
+        "block",
+        "timestamp",
+        "reserve_x",
+        "reserve_y",
+        "lp_supply",
+        "price",
+        "swaps",
+        "volume_x",
+        "volume_y"
+    );
+    for s in &series.samples {
+        println!(
+            "{:>8} {:>11} {:>20} {:>20} {:>20} {:>14.6} {:>6} {:>20} {:>20}",
+            s.block_num,
+            s.timestamp,
+            s.reserve_x,
+            s.reserve_y,
+            s.lp_supply,
+            s.price,
+            s.swaps,
+            s.volume_x,
+            s.volume_y
+        );
+    }
+}
+
+fn print_stats(stats: &PoolStats, days: u64) {
+    let pct = |v: Option<f64>| v.map_or("n/a".to_string(), |v| format!("{:.4}%", v * 100.0));
+    println!(
+        "\nlast {days} days (blocks {}..={}):",
+        stats.from_block, stats.to_block
+    );
+    println!(
+        "  price          {:.6} -> {:.6}",
+        stats.open_price, stats.close_price
+    );
+    println!(
+        "  swaps          {} (volume {} X, {} Y)",
+        stats.swaps, stats.volume_x, stats.volume_y
+    );
+    println!(
+        "  fees           {:.0} X, {:.0} Y",
+        stats.fees_x, stats.fees_y
+    );
+    println!("  fees per LP    {}", pct(stats.fee_growth));
+    println!("  APR            {}", pct(stats.apr));
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("analytics") => run_analytics(parse_analytics_args(&args[1..])?).await,
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        _ => bail!("{USAGE}"),
+    }
 }
@@ -0,0 +1,334 @@
+use std::{collections::BTreeSet, path::Path};
+
+use anyhow::{Context, Result, bail, ensure};
+
+use miden_client::{
+    Client, Deserializable, Serializable, Word,
+    account::{Account, AccountId},
+    asset::AssetCallbackFlag,
+    keystore::Keystore,
+    note::{NoteScript, NoteScriptRoot},
+    transaction::{TransactionScript, TransactionScriptRoot},
+    utils::{ByteReader, ByteWriter, DeserializationError},
+};
+use miden_standards::account::auth::{
+    NetworkAccountNoteAllowlist, NetworkAccountTxScriptAllowlist,
+};
+
+use crate::{
+    common::{AmmBuild, LpToken, PoolAsset, PoolState, pool_x_key_slot, pool_y_key_slot},
+    governance::Governance,
+};
+
+// =================================================================================================
+// CONSTANTS
+// =================================================================================================
+
+/// Leading bytes of a serialized manifest.
+const MANIFEST_MAGIC: [u8; 4] = *b"AMMD";
+
+/// Bumped whenever the serialized layout changes; older files are rejected, not guessed at.
+pub const MANIFEST_VERSION: u8 = 4;
+
+// =================================================================================================
+// DEPLOYMENT MANIFEST
+// =================================================================================================
+
+/// Everything needed to talk to a deployed pool without recompiling its MASM.
+///
+/// The AMM's allowlist commits to the exact roots of its note scripts and deploy script, so
+/// notes for a deployed pool must be built from the scripts it was deployed with — a
+/// recompile after a compiler or source change would produce notes the NTB never picks up.
+/// Write the manifest next to the deployment ([`AmmManifest::write_to_file`]) and rebuild
+/// the [`AmmBuild`] from it and the on-chain account ([`AmmManifest::into_build`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AmmManifest {
+    pub amm_id: AccountId,
+    /// Seed the account id was derived from; kept for redeploying the same pool elsewhere.
+    pub init_seed: [u8; 32],
+    pub pool_x_faucet: AccountId,
+    pub pool_y_faucet: AccountId,
+    pub pool_x_callbacks: AssetCallbackFlag,
+    pub pool_y_callbacks: AssetCallbackFlag,
+    pub fee_bps: u64,
+    pub swap_note_script: NoteScript,
+    pub add_liquidity_note_script: NoteScript,
+    pub remove_liquidity_note_script: NoteScript,
+    pub sync_note_script: NoteScript,
+    pub skim_note_script: NoteScript,
+    pub propose_allowlist_note_script: NoteScript,
+    pub execute_allowlist_note_script: NoteScript,
+    pub deploy_tx_script: TransactionScript,
+}
+
+impl AmmManifest {
+    /// The manifest of a freshly built pool.
+    pub fn from_build(build: &AmmBuild) -> Self {
+        AmmManifest {
+            amm_id: build.account.id(),
+            init_seed: build.init_seed,
+            pool_x_faucet: build.pool_x_faucet,
+            pool_y_faucet: build.pool_y_faucet,
+            pool_x_callbacks: build.pool_x_callbacks,
+            pool_y_callbacks: build.pool_y_callbacks,
+            fee_bps: build.fee_bps,
+            swap_note_script: build.swap_note_script.clone(),
+            add_liquidity_note_script: build.add_liquidity_note_script.clone(),
+            remove_liquidity_note_script: build.remove_liquidity_note_script.clone(),
+            sync_note_script: build.sync_note_script.clone(),
+            skim_note_script: build.skim_note_script.clone(),
+            propose_allowlist_note_script: build.propose_allowlist_note_script.clone(),
+            execute_allowlist_note_script: build.execute_allowlist_note_script.clone(),
+            deploy_tx_script: build.deploy_tx_script.clone(),
+        }
+    }
+
+    pub fn pool_x(&self) -> PoolAsset {
+        PoolAsset::new(self.pool_x_faucet, self.pool_x_callbacks)
+    }
+
+    pub fn pool_y(&self) -> PoolAsset {
+        PoolAsset::new(self.pool_y_faucet, self.pool_y_callbacks)
+    }
+
+    /// Roots of the seven note scripts: the AMM's note allowlist as deployed.
+    pub fn note_script_roots(&self) -> BTreeSet<NoteScriptRoot> {
+        BTreeSet::from([
+            self.swap_note_script.root(),
+            self.add_liquidity_note_script.root(),
+            self.remove_liquidity_note_script.root(),
+            self.sync_note_script.root(),
+            self.skim_note_script.root(),
+            self.propose_allowlist_note_script.root(),
+            self.execute_allowlist_note_script.root(),
+        ])
+    }
+
+    /// Root of the deploy script: the AMM's tx-script allowlist.
+    pub fn tx_script_roots(&self) -> BTreeSet<TransactionScriptRoot> {
+        BTreeSet::from([self.deploy_tx_script.root()])
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_bytes())
+            .with_context(|| format!("writing manifest {}", path.display()))
+    }
+
+    pub fn read_from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes =
+            std::fs::read(path).with_context(|| format!("reading manifest {}", path.display()))?;
+        Self::read_from_bytes(&bytes)
+            .with_context(|| format!("decoding manifest {}", path.display()))
+    }
+
+    /// Checks that `account` is the pool this manifest describes: same id, the same pair and
+    /// fee in its configuration slots, and allowlists containing exactly the manifest's script
+    /// roots. The note allowlist of a pool with [`Governance`] may have changed since the
+    /// deployment and is not compared.
+    pub fn verify_account(&self, account: &Account) -> Result<()> {
+        ensure!(
+            account.id() == self.amm_id,
+            "manifest is for {} but the account is {}",
+            self.amm_id.to_hex(),
+            account.id().to_hex()
+        );
+
+        let storage = account.storage();
+        for (slot, asset) in [
+            (pool_x_key_slot(), self.pool_x()),
+            (pool_y_key_slot(), self.pool_y()),
+        ] {
+            let on_chain = storage
+                .get_item(&slot)
+                .with_context(|| format!("reading {slot}"))?;
+            ensure!(
+                on_chain == asset.key_word()?,
+                "{slot} does not match faucet {} with callbacks {:?}",
+                asset.faucet_id.to_hex(),
+                asset.callbacks
+            );
+        }
+        let pool = PoolState::from_account(account, self.pool_x(), self.pool_y())?;
+        ensure!(
+            pool.fee_bps == self.fee_bps,
+            "fee is {} bps on-chain but {} bps in the manifest",
+            pool.fee_bps,
+            self.fee_bps
+        );
+
+        let notes = NetworkAccountNoteAllowlist::try_from(storage)
+            .context("reading the note allowlist")?
+            .into_allowed_script_roots();
+        let governed = Governance::from_account(account)?.is_some();
+        if !governed && notes != self.note_script_roots() {
+            bail!(
+                "note allowlist mismatch: on-chain {:?}, manifest {:?}",
+                notes,
+                self.note_script_roots()
+            );
+        }
+        let tx_scripts = NetworkAccountTxScriptAllowlist::try_from(storage)
+            .context("reading the tx-script allowlist")?
+            .into_allowed_script_roots();
+        if tx_scripts != self.tx_script_roots() {
+            bail!(
+                "tx-script allowlist mismatch: on-chain {:?}, manifest {:?}",
+                tx_scripts,
+                self.tx_script_roots()
+            );
+        }
+        Ok(())
+    }
+
+    /// The [`AmmBuild`] of the deployed pool, from this manifest and the pool's current
+    /// on-chain `account`. Fails if the account does not match the manifest.
+    pub fn into_build(self, account: Account) -> Result<AmmBuild> {
+        self.verify_account(&account)?;
+        let lp_token = LpToken::from_account(&account).unwrap_or_default();
+        let governance = Governance::from_account(&account)?;
+        Ok(AmmBuild {
+            account,
+            init_seed: self.init_seed,
+            swap_note_script: self.swap_note_script,
+            add_liquidity_note_script: self.add_liquidity_note_script,
+            remove_liquidity_note_script: self.remove_liquidity_note_script,
+            sync_note_script: self.sync_note_script,
+            skim_note_script: self.skim_note_script,
+            propose_allowlist_note_script: self.propose_allowlist_note_script,
+            execute_allowlist_note_script: self.execute_allowlist_note_script,
+            deploy_tx_script: self.deploy_tx_script,
+            pool_x_faucet: self.pool_x_faucet,
+            pool_y_faucet: self.pool_y_faucet,
+            pool_x_callbacks: self.pool_x_callbacks,
+            pool_y_callbacks: self.pool_y_callbacks,
+            fee_bps: self.fee_bps,
+            lp_token,
+            governance,
+        })
+    }
+}
+
+/// Reloads a deployed pool: reads the manifest at `path`, fetches the AMM account through
+/// `client` (importing it if it is not tracked yet) and verifies it against the manifest.
+pub async fn load_deployed_amm<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    path: impl AsRef<Path>,
+) -> Result<AmmBuild> {
+    let manifest = AmmManifest::read_from_file(path)?;
+    client.sync_state().await?;
+    if client.get_account(manifest.amm_id).await?.is_none() {
+        client
+            .import_account_by_id(manifest.amm_id)
+            .await
+            .context("importing the AMM account")?;
+    }
+    let account = client
+        .get_account(manifest.amm_id)
+        .await?
+        .context("AMM account not found")?;
+    manifest.into_build(account)
+}
+
+// =================================================================================================
+// SERIALIZATION
+// =================================================================================================
+
+impl Serializable for AmmManifest {
+    fn write_into<W: ByteWriter>(&self, target: &mut W) {
+        target.write_bytes(&MANIFEST_MAGIC);
+        target.write_u8(MANIFEST_VERSION);
+        self.amm_id.write_into(target);
+        target.write_bytes(&self.init_seed);
+        self.pool_x_faucet.write_into(target);
+        self.pool_y_faucet.write_into(target);
+        self.pool_x_callbacks.write_into(target);
+        self.pool_y_callbacks.write_into(target);
+        target.write_u64(self.fee_bps);
+        // each script is preceded by its root, so a file whose MAST does not hash to the
+        // recorded root is rejected on load
+        for script in [
+            &self.swap_note_script,
+            &self.add_liquidity_note_script,
+            &self.remove_liquidity_note_script,
+            &self.sync_note_script,
+            &self.skim_note_script,
+            &self.propose_allowlist_note_script,
+            &self.execute_allowlist_note_script,
+        ] {
+            script.root().as_word().write_into(target);
+            script.write_into(target);
+        }
+        self.deploy_tx_script.root().as_word().write_into(target);
+        self.deploy_tx_script.write_into(target);
+    }
+}
+
+impl Deserializable for AmmManifest {
+    fn read_from<R: ByteReader>(source: &mut R) -> Result<Self, DeserializationError> {
+        let magic: [u8; 4] = source.read_array()?;
+        if magic != MANIFEST_MAGIC {
+            return Err(DeserializationError::InvalidValue(
+                "not an AMM deployment manifest".into(),
+            ));
+        }
+        let version = source.read_u8()?;
+        if version != MANIFEST_VERSION {
+            return Err(DeserializationError::InvalidValue(format!(
+                "unsupported manifest version {version} (expected {MANIFEST_VERSION})"
+            )));
+        }
+        let amm_id = AccountId::read_from(source)?;
+        let init_seed = source.read_array()?;
+        let pool_x_faucet = AccountId::read_from(source)?;
+        let pool_y_faucet = AccountId::read_from(source)?;
+        let pool_x_callbacks = AssetCallbackFlag::read_from(source)?;
+        let pool_y_callbacks = AssetCallbackFlag::read_from(source)?;
+        let fee_bps = source.read_u64()?;
+        let mut read_note_script = |name: &str| -> Result<NoteScript, DeserializationError> {
+            let root = Word::read_from(source)?;
+            let script = NoteScript::read_from(source)?;
+            check_root(name, root, script.root().as_word())?;
+            Ok(script)
+        };
+        let swap_note_script = read_note_script("swap note script")?;
+        let add_liquidity_note_script = read_note_script("add-liquidity note script")?;
+        let remove_liquidity_note_script = read_note_script("remove-liquidity note script")?;
+        let sync_note_script = read_note_script("sync note script")?;
+        let skim_note_script = read_note_script("skim note script")?;
+        let propose_allowlist_note_script = read_note_script("propose-allowlist note script")?;
+        let execute_allowlist_note_script = read_note_script("execute-allowlist note script")?;
+        let root = Word::read_from(source)?;
+        let deploy_tx_script = TransactionScript::read_from(source)?;
+        check_root("deploy script", root, deploy_tx_script.root().as_word())?;
+        Ok(AmmManifest {
+            amm_id,
+            init_seed,
+            pool_x_faucet,
+            pool_y_faucet,
+            pool_x_callbacks,
+            pool_y_callbacks,
+            fee_bps,
+            swap_note_script,
+            add_liquidity_note_script,
+            remove_liquidity_note_script,
+            sync_note_script,
+            skim_note_script,
+            propose_allowlist_note_script,
+            execute_allowlist_note_script,
+            deploy_tx_script,
+        })
+    }
+}
+
+fn check_root(name: &str, recorded: Word, actual: Word) -> Result<(), DeserializationError> {
+    if recorded != actual {
+        return Err(DeserializationError::InvalidValue(format!(
+            "{name} hashes to {} but the manifest records {}",
+            actual.to_hex(),
+            recorded.to_hex()
+        )));
+    }
+    Ok(())
+}
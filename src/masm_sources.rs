@@ -0,0 +1,26 @@
+// Shared by `build.rs` and `src/common.rs` through `include!`, so the precompiled artifacts
+// are assembled from exactly the sources `amm_code()` / `liquidity_code()` return. Expects
+// `Word` in scope.
+
+/// Library namespaces the MASM modules are compiled under.
+pub const AMM_CONTRACT_NS: &str = "external_contract::amm_contract";
+pub const LIQUIDITY_CONTRACT_NS: &str = "external_contract::liquidity_contract";
+pub const LP_METADATA_NS: &str = "external_contract::lp_metadata";
+pub const GOVERNANCE_NS: &str = "external_contract::governance";
+
+/// Splices the shared event log into the raw amm component source.
+pub(crate) fn splice_amm_code(template: &str, event_log: &str) -> String {
+    template.replace("{event_log}", event_log)
+}
+
+/// Injects the P2ID script root into the raw liquidity component source and splices in the
+/// shared event log.
+pub(crate) fn splice_liquidity_code(
+    template: &str,
+    p2id_script_root: Word,
+    event_log: &str,
+) -> String {
+    template
+        .replace("{p2id_script_root}", &format!("{p2id_script_root}"))
+        .replace("{event_log}", event_log)
+}
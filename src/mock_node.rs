@@ -0,0 +1,432 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+
+use miden_client::{
+    account::{Account, AccountId},
+    address::NetworkId,
+    block::{BlockHeader, BlockNumber},
+    note::{NoteId, NoteScript, NoteTag, Nullifier},
+    rpc::{
+        GrpcError, NodeRpcClient, RpcEndpoint, RpcError, RpcLimits, RpcStatusInfo,
+        domain::{
+            account::{AccountProof, GetAccountRequest},
+            account_vault::AccountVaultInfo,
+            note::{FetchedNote, NoteSyncBlock},
+            nullifier::NullifierUpdate,
+            status::NetworkNoteStatusInfo,
+            storage_map::StorageMapInfo,
+            sync::{ChainMmrInfo, SyncTarget},
+            transaction::TransactionRecord,
+        },
+    },
+    testing::mock::MockRpcApi,
+    transaction::{LocalTransactionProver, TransactionProver, TransactionProverError},
+};
+use miden_protocol::{
+    Word,
+    account::{AccountStorage, PartialAccount, StorageMap, StorageSlot, StorageSlotType},
+    asset::AssetVault,
+    batch::{ProposedBatch, ProvenBatch},
+    block::ProvenBlock,
+    crypto::merkle::mmr::MmrProof,
+    transaction::{ProvenTransaction, TransactionInputs},
+};
+use miden_testing::{MockChain, TransactionContextBuilder};
+
+use crate::ntb::{NtbEmulator, NtbExecution, NtbNoteStatus, execute_network_note};
+
+// =================================================================================================
+// MOCK NODE
+// =================================================================================================
+
+/// In-process stand-in for a Miden node, backed by a [`MockChain`], that a `Client` can be
+/// built against (`ClientBuilder::new().rpc(Arc::new(node))`).
+///
+/// Everything is served by miden-client's `MockRpcApi`, with two node behaviours it lacks:
+/// every submitted transaction or batch is committed in a block of its own right away, and
+/// the network notes it creates are then run through an [`NtbEmulator`], whose statuses
+/// answer `get_network_note_status`. A client therefore sees its transactions committed on
+/// the first sync, and the AMM's network transactions committed right after them.
+#[derive(Clone)]
+pub struct MockNode {
+    rpc: MockRpcApi,
+    ntb: Arc<Mutex<NtbEmulator>>,
+}
+
+impl MockNode {
+    pub fn new(mock_chain: MockChain) -> Self {
+        Self::with_ntb(mock_chain, NtbEmulator::new())
+    }
+
+    pub fn with_ntb(mock_chain: MockChain, ntb: NtbEmulator) -> Self {
+        MockNode {
+            rpc: MockRpcApi::new(mock_chain),
+            ntb: Arc::new(Mutex::new(ntb)),
+        }
+    }
+
+    /// The underlying `MockRpcApi`, e.g. to inspect the chain or prove extra blocks.
+    pub fn rpc(&self) -> &MockRpcApi {
+        &self.rpc
+    }
+
+    /// A snapshot of the current chain state.
+    pub fn mock_chain(&self) -> MockChain {
+        self.rpc.mock_chain.read().clone()
+    }
+
+    pub fn chain_tip(&self) -> BlockNumber {
+        self.rpc.get_chain_tip_block_num()
+    }
+
+    /// The emulator's status of a network note; `None` for notes the NTB ignores.
+    pub async fn network_note_status(&self, note_id: NoteId) -> Option<NtbNoteStatus> {
+        self.ntb.lock().await.note_status(note_id).cloned()
+    }
+
+    /// Runs the NTB until no network note is pending, committing each network transaction in
+    /// a block of its own. Called after every submission; exposed for notes committed by
+    /// other means (e.g. seeded into the genesis chain).
+    pub async fn run_ntb(&self) -> Result<Vec<NtbExecution>> {
+        let mut ntb = self.ntb.lock().await;
+        let mut executions = Vec::new();
+        loop {
+            // the chain lock cannot be held across the executor's awaits: execute against a
+            // snapshot, then commit the transaction to the shared chain
+            let snapshot = self.mock_chain();
+            let pending = ntb.pending_notes(&snapshot);
+            if pending.is_empty() {
+                return Ok(executions);
+            }
+            for (note, account_id) in pending {
+                let snapshot = self.mock_chain();
+                let attempt_block = snapshot.latest_block_header().block_num();
+                match execute_network_note(&snapshot, &note, account_id).await {
+                    Ok(executed) => {
+                        self.rpc
+                            .mock_chain
+                            .write()
+                            .add_pending_executed_transaction(&executed)?;
+                        self.rpc.prove_block();
+                        executions.push(ntb.record_success(
+                            &note,
+                            account_id,
+                            &executed,
+                            attempt_block,
+                            self.chain_tip(),
+                        ));
+                    }
+                    Err(err) => ntb.record_failure(&note, &err, attempt_block),
+                }
+            }
+        }
+    }
+
+    /// Rejects a submission the way the node's block producer would: if the chain cannot
+    /// build a block containing it (e.g. it consumes a note that does not exist).
+    fn check_submission(
+        &self,
+        endpoint: RpcEndpoint,
+        add: impl FnOnce(&mut MockChain),
+    ) -> Result<(), RpcError> {
+        let mut candidate = self.mock_chain();
+        add(&mut candidate);
+        candidate
+            .prove_next_block()
+            .map(|_| ())
+            .map_err(|err| RpcError::RequestError {
+                endpoint,
+                error_kind: GrpcError::InvalidArgument,
+                endpoint_error: None,
+                source: Some(err.into()),
+            })
+    }
+
+    /// Commits everything pending in a new block and lets the NTB process it.
+    async fn produce_block(&self) -> Result<BlockNumber, RpcError> {
+        self.rpc.prove_block();
+        let block_num = self.chain_tip();
+        self.run_ntb().await.map_err(|err| {
+            RpcError::InvalidResponse(format!("network transaction builder: {err:#}"))
+        })?;
+        Ok(block_num)
+    }
+}
+
+// =================================================================================================
+// MOCK PROVER
+// =================================================================================================
+
+/// Transaction prover for clients talking to a [`MockNode`]: re-executes the transaction
+/// witness and attaches a dummy proof, which the mock chain accepts. Real proving takes
+/// minutes per transaction in a debug build.
+#[derive(Clone)]
+pub struct MockProver {
+    node: MockNode,
+}
+
+impl MockProver {
+    pub fn new(node: &MockNode) -> Self {
+        MockProver { node: node.clone() }
+    }
+
+    async fn execute_and_prove(&self, tx_inputs: TransactionInputs) -> Result<ProvenTransaction> {
+        let account = full_account(&self.node.mock_chain(), tx_inputs.account())?;
+        let tx_args = tx_inputs.tx_args().clone();
+        let mut tx_context = TransactionContextBuilder::new(account)
+            .tx_inputs(tx_inputs)
+            .build()?;
+        // the builder resets the arguments; restore the client's, whose advice carries the
+        // signatures
+        tx_context.set_tx_args(tx_args);
+        let executed = tx_context
+            .execute()
+            .await
+            .context("re-executing transaction witness")?;
+        Ok(LocalTransactionProver::default().prove_dummy(executed)?)
+    }
+}
+
+#[async_trait]
+impl TransactionProver for MockProver {
+    async fn prove(
+        &self,
+        tx_inputs: TransactionInputs,
+    ) -> Result<ProvenTransaction, TransactionProverError> {
+        self.execute_and_prove(tx_inputs)
+            .await
+            .map_err(|err| TransactionProverError::other(format!("{err:#}")))
+    }
+}
+
+/// The full state behind a transaction's partial account: the committed account for existing
+/// accounts; for new ones, the account rebuilt from the full storage and empty vault that the
+/// witness carries.
+fn full_account(mock_chain: &MockChain, partial: &PartialAccount) -> Result<Account> {
+    if !partial.is_new() {
+        return Ok(mock_chain.committed_account(partial.id())?.clone());
+    }
+    let storage = partial.storage();
+    let slots = storage
+        .header()
+        .slots()
+        .map(|slot| match slot.slot_type() {
+            StorageSlotType::Value => {
+                Ok(StorageSlot::with_value(slot.name().clone(), slot.value()))
+            }
+            StorageSlotType::Map => {
+                let map = storage
+                    .maps()
+                    .find(|map| map.root() == slot.value())
+                    .with_context(|| format!("storage map {} missing from witness", slot.name()))?;
+                let entries: Vec<_> = map.entries().map(|(k, v)| (*k, *v)).collect();
+                Ok(StorageSlot::with_map(
+                    slot.name().clone(),
+                    StorageMap::with_entries(entries)?,
+                ))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Account::new(
+        partial.id(),
+        AssetVault::default(),
+        AccountStorage::new(slots)?,
+        partial.code().clone(),
+        partial.nonce(),
+        partial.seed(),
+    )?)
+}
+
+// =================================================================================================
+// NODE RPC CLIENT
+// =================================================================================================
+
+#[async_trait]
+impl NodeRpcClient for MockNode {
+    async fn set_genesis_commitment(&self, commitment: Word) -> Result<(), RpcError> {
+        self.rpc.set_genesis_commitment(commitment).await
+    }
+
+    fn has_genesis_commitment(&self) -> Option<Word> {
+        self.rpc.has_genesis_commitment()
+    }
+
+    async fn submit_proven_transaction(
+        &self,
+        proven_transaction: ProvenTransaction,
+        transaction_inputs: TransactionInputs,
+    ) -> Result<BlockNumber, RpcError> {
+        self.check_submission(RpcEndpoint::SubmitProvenTx, |chain| {
+            chain.add_pending_proven_transaction(proven_transaction.clone())
+        })?;
+        self.rpc
+            .submit_proven_transaction(proven_transaction, transaction_inputs)
+            .await?;
+        self.produce_block().await
+    }
+
+    async fn submit_proven_batch(
+        &self,
+        proven_batch: ProvenBatch,
+        proposed_batch: ProposedBatch,
+        transaction_inputs: Vec<TransactionInputs>,
+    ) -> Result<BlockNumber, RpcError> {
+        self.check_submission(RpcEndpoint::SubmitProvenBatch, |chain| {
+            chain.add_pending_batch(proven_batch.clone())
+        })?;
+        self.rpc
+            .submit_proven_batch(proven_batch, proposed_batch, transaction_inputs)
+            .await?;
+        self.produce_block().await
+    }
+
+    async fn get_block_header_by_number(
+        &self,
+        block_num: Option<BlockNumber>,
+        include_mmr_proof: bool,
+    ) -> Result<(BlockHeader, Option<MmrProof>), RpcError> {
+        self.rpc
+            .get_block_header_by_number(block_num, include_mmr_proof)
+            .await
+    }
+
+    async fn get_block_by_number(
+        &self,
+        block_num: BlockNumber,
+        include_proof: bool,
+    ) -> Result<ProvenBlock, RpcError> {
+        self.rpc.get_block_by_number(block_num, include_proof).await
+    }
+
+    async fn get_notes_by_id(&self, note_ids: &[NoteId]) -> Result<Vec<FetchedNote>, RpcError> {
+        self.rpc.get_notes_by_id(note_ids).await
+    }
+
+    async fn sync_chain_mmr(
+        &self,
+        current_block_height: BlockNumber,
+        upper_bound: SyncTarget,
+    ) -> Result<ChainMmrInfo, RpcError> {
+        self.rpc
+            .sync_chain_mmr(current_block_height, upper_bound)
+            .await
+    }
+
+    async fn get_account_details(
+        &self,
+        account_id: AccountId,
+    ) -> Result<Option<Account>, RpcError> {
+        self.rpc.get_account_details(account_id).await
+    }
+
+    async fn sync_notes(
+        &self,
+        block_from: BlockNumber,
+        block_to: BlockNumber,
+        note_tags: &BTreeSet<NoteTag>,
+    ) -> Result<Vec<NoteSyncBlock>, RpcError> {
+        self.rpc.sync_notes(block_from, block_to, note_tags).await
+    }
+
+    async fn sync_nullifiers(
+        &self,
+        prefix: &[u16],
+        block_from: BlockNumber,
+        block_to: BlockNumber,
+    ) -> Result<Vec<NullifierUpdate>, RpcError> {
+        self.rpc.sync_nullifiers(prefix, block_from, block_to).await
+    }
+
+    async fn get_account(
+        &self,
+        account_id: AccountId,
+        request: GetAccountRequest,
+    ) -> Result<(BlockNumber, AccountProof), RpcError> {
+        self.rpc.get_account(account_id, request).await
+    }
+
+    async fn get_nullifier_commit_heights(
+        &self,
+        requested_nullifiers: BTreeSet<Nullifier>,
+        block_from: BlockNumber,
+    ) -> Result<BTreeMap<Nullifier, Option<BlockNumber>>, RpcError> {
+        self.rpc
+            .get_nullifier_commit_heights(requested_nullifiers, block_from)
+            .await
+    }
+
+    async fn get_note_script_by_root(&self, root: Word) -> Result<Option<NoteScript>, RpcError> {
+        self.rpc.get_note_script_by_root(root).await
+    }
+
+    async fn sync_storage_maps(
+        &self,
+        block_from: BlockNumber,
+        block_to: BlockNumber,
+        account_id: AccountId,
+    ) -> Result<StorageMapInfo, RpcError> {
+        self.rpc
+            .sync_storage_maps(block_from, block_to, account_id)
+            .await
+    }
+
+    async fn sync_account_vault(
+        &self,
+        block_from: BlockNumber,
+        block_to: BlockNumber,
+        account_id: AccountId,
+    ) -> Result<AccountVaultInfo, RpcError> {
+        self.rpc
+            .sync_account_vault(block_from, block_to, account_id)
+            .await
+    }
+
+    async fn sync_transactions(
+        &self,
+        block_from: BlockNumber,
+        block_to: BlockNumber,
+        account_ids: Vec<AccountId>,
+    ) -> Result<Vec<TransactionRecord>, RpcError> {
+        self.rpc
+            .sync_transactions(block_from, block_to, account_ids)
+            .await
+    }
+
+    async fn get_network_id(&self) -> Result<NetworkId, RpcError> {
+        self.rpc.get_network_id().await
+    }
+
+    async fn get_rpc_limits(&self) -> Result<RpcLimits, RpcError> {
+        self.rpc.get_rpc_limits().await
+    }
+
+    fn has_rpc_limits(&self) -> Option<RpcLimits> {
+        self.rpc.has_rpc_limits()
+    }
+
+    async fn set_rpc_limits(&self, limits: RpcLimits) {
+        self.rpc.set_rpc_limits(limits).await
+    }
+
+    async fn get_status_unversioned(&self) -> Result<RpcStatusInfo, RpcError> {
+        self.rpc.get_status_unversioned().await
+    }
+
+    /// Served from the NTB emulator. Like the node, notes it never picked up (not a network
+    /// note, or not allowlisted by its target) are not found.
+    async fn get_network_note_status(
+        &self,
+        note_id: NoteId,
+    ) -> Result<NetworkNoteStatusInfo, RpcError> {
+        self.network_note_status(note_id)
+            .await
+            .map(|status| status.info())
+            .ok_or(RpcError::NoteNotFound(note_id))
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Context, Result};
+
+use miden_client::{
+    account::{Account, AccountId},
+    asset::{Asset, AssetCallbackFlag},
+    block::BlockNumber,
+    note::{NetworkAccountTarget, Note, NoteId, PartialNote},
+    rpc::domain::status::{NetworkNoteStatus, NetworkNoteStatusInfo},
+    transaction::{ExecutedTransaction, RawOutputNote, TransactionId},
+};
+use miden_protocol::block::account_tree::AccountWitness;
+use miden_standards::account::{
+    auth::NetworkAccount,
+    interface::{AccountInterface, AccountInterfaceExt},
+};
+use miden_testing::MockChain;
+
+/// How often the emulator retries a failing network note before discarding it.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+// =================================================================================================
+// NOTE STATUS
+// =================================================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NtbNoteState {
+    /// Awaiting execution, or failed fewer than `max_attempts` times.
+    Pending,
+    /// Consumed by a committed network transaction.
+    Committed,
+    /// Failed `max_attempts` times; never retried again.
+    Discarded,
+}
+
+/// The emulator's view of one network note — the data the node serves through
+/// `get_network_note_status`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NtbNoteStatus {
+    pub state: NtbNoteState,
+    pub attempt_count: u32,
+    pub last_error: Option<String>,
+    pub last_attempt_block_num: Option<BlockNumber>,
+    pub transaction_id: Option<TransactionId>,
+}
+
+impl NtbNoteStatus {
+    fn new() -> Self {
+        NtbNoteStatus {
+            state: NtbNoteState::Pending,
+            attempt_count: 0,
+            last_error: None,
+            last_attempt_block_num: None,
+            transaction_id: None,
+        }
+    }
+
+    /// The status in the shape the node's RPC returns it.
+    pub fn info(&self) -> NetworkNoteStatusInfo {
+        NetworkNoteStatusInfo {
+            status: match self.state {
+                NtbNoteState::Pending => NetworkNoteStatus::Pending,
+                NtbNoteState::Committed => NetworkNoteStatus::NullifierCommitted,
+                NtbNoteState::Discarded => NetworkNoteStatus::Discarded,
+            },
+            last_error: self.last_error.clone(),
+            attempt_count: self.attempt_count,
+            last_attempt_block_num: self.last_attempt_block_num.map(|b| b.as_u32()),
+        }
+    }
+}
+
+/// A network transaction the emulator executed and committed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NtbExecution {
+    pub note_id: NoteId,
+    pub account_id: AccountId,
+    pub transaction_id: TransactionId,
+    pub block_num: BlockNumber,
+    /// Ids of the notes the transaction produced (the AMM's private payouts).
+    pub output_notes: Vec<NoteId>,
+}
+
+// =================================================================================================
+// NTB EMULATOR
+// =================================================================================================
+
+/// Offline stand-in for the node's network transaction builder, driving a [`MockChain`].
+///
+/// Like the real NTB it only picks up committed public notes that carry a
+/// `NetworkAccountTarget` attachment whose target is a network account allowlisting the
+/// note's script; anything else is silently ignored. Notes are executed one per block
+/// against the target's latest committed state, oldest first, and a failing note is retried
+/// on later passes until it has failed `max_attempts` times.
+#[derive(Debug)]
+pub struct NtbEmulator {
+    max_attempts: u32,
+    statuses: BTreeMap<NoteId, NtbNoteStatus>,
+}
+
+impl Default for NtbEmulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NtbEmulator {
+    pub fn new() -> Self {
+        NtbEmulator {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            statuses: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Status of a network note the emulator has seen; `None` for notes it ignores.
+    pub fn note_status(&self, note_id: NoteId) -> Option<&NtbNoteStatus> {
+        self.statuses.get(&note_id)
+    }
+
+    /// Committed, unconsumed network notes the NTB would pick up, in chain order, with the
+    /// network account each one targets. Discarded notes are skipped.
+    pub fn pending_notes(&self, mock_chain: &MockChain) -> Vec<(Note, AccountId)> {
+        let mut notes: Vec<_> = mock_chain
+            .committed_notes()
+            .values()
+            .filter_map(|committed| {
+                let note = committed.note()?;
+                let target = NetworkAccountTarget::try_from(note.attachments()).ok()?;
+                let account_id = target.target_id();
+                let allowed = mock_chain
+                    .committed_account(account_id)
+                    .ok()
+                    .and_then(|account| NetworkAccount::new(account.clone()).ok())
+                    .is_some_and(|account| {
+                        account
+                            .allowed_notes()
+                            .allowed_script_roots()
+                            .contains(&note.script().root())
+                    });
+                let discarded = self
+                    .statuses
+                    .get(&note.id())
+                    .is_some_and(|s| s.state != NtbNoteState::Pending);
+                let consumed = mock_chain.is_note_consumed(&note.nullifier());
+                (allowed && !discarded && !consumed).then(|| {
+                    let location = committed.inclusion_proof().location();
+                    let order = (location.block_num(), location.block_note_tree_index());
+                    (order, note.clone(), account_id)
+                })
+            })
+            .collect();
+        notes.sort_by_key(|(order, ..)| *order);
+        notes
+            .into_iter()
+            .map(|(_, note, account_id)| (note, account_id))
+            .collect()
+    }
+
+    /// One NTB pass: attempts every pending network note once, committing each successful
+    /// transaction in its own block so the next note sees the updated account state.
+    pub async fn process_pending(
+        &mut self,
+        mock_chain: &mut MockChain,
+    ) -> Result<Vec<NtbExecution>> {
+        let mut executions = Vec::new();
+        for (note, account_id) in self.pending_notes(mock_chain) {
+            let block_num = mock_chain.latest_block_header().block_num();
+            match execute_network_note(mock_chain, &note, account_id).await {
+                Ok(executed) => {
+                    mock_chain.add_pending_executed_transaction(&executed)?;
+                    let block = mock_chain.prove_next_block()?;
+                    executions.push(self.record_success(
+                        &note,
+                        account_id,
+                        &executed,
+                        block_num,
+                        block.header().block_num(),
+                    ));
+                }
+                Err(err) => self.record_failure(&note, &err, block_num),
+            }
+        }
+        Ok(executions)
+    }
+
+    /// Records a successful attempt at `note`, made against block `attempt_block`, whose
+    /// transaction was committed in `commit_block`.
+    pub(crate) fn record_success(
+        &mut self,
+        note: &Note,
+        account_id: AccountId,
+        executed: &ExecutedTransaction,
+        attempt_block: BlockNumber,
+        commit_block: BlockNumber,
+    ) -> NtbExecution {
+        let status = self.attempt(note.id(), attempt_block);
+        status.state = NtbNoteState::Committed;
+        status.transaction_id = Some(executed.id());
+        NtbExecution {
+            note_id: note.id(),
+            account_id,
+            transaction_id: executed.id(),
+            block_num: commit_block,
+            output_notes: executed.output_notes().iter().map(|n| n.id()).collect(),
+        }
+    }
+
+    /// Records a failed attempt at `note`, discarding it once it has failed `max_attempts`
+    /// times.
+    pub(crate) fn record_failure(
+        &mut self,
+        note: &Note,
+        err: &anyhow::Error,
+        attempt_block: BlockNumber,
+    ) {
+        let max_attempts = self.max_attempts;
+        let status = self.attempt(note.id(), attempt_block);
+        status.last_error = Some(format!("{err:#}"));
+        if status.attempt_count >= max_attempts {
+            status.state = NtbNoteState::Discarded;
+        }
+    }
+
+    fn attempt(&mut self, note_id: NoteId, block_num: BlockNumber) -> &mut NtbNoteStatus {
+        let status = self
+            .statuses
+            .entry(note_id)
+            .or_insert_with(NtbNoteStatus::new);
+        status.attempt_count += 1;
+        status.last_attempt_block_num = Some(block_num);
+        status
+    }
+
+    /// Runs NTB passes until no network note is pending: every note the NTB picks up ends
+    /// committed or, after `max_attempts` failures, discarded.
+    pub async fn run_until_idle(
+        &mut self,
+        mock_chain: &mut MockChain,
+    ) -> Result<Vec<NtbExecution>> {
+        let mut executions = Vec::new();
+        while !self.pending_notes(mock_chain).is_empty() {
+            executions.extend(self.process_pending(mock_chain).await?);
+        }
+        Ok(executions)
+    }
+}
+
+/// Executes `note` against `account_id`'s latest committed state, as the NTB would, without
+/// touching the chain.
+pub(crate) async fn execute_network_note(
+    mock_chain: &MockChain,
+    note: &Note,
+    account_id: AccountId,
+) -> Result<ExecutedTransaction> {
+    let vault = mock_chain.committed_account(account_id)?.vault().assets();
+    let faucets = callback_faucets(mock_chain, note.assets().iter().cloned().chain(vault))?;
+    Ok(mock_chain
+        .build_tx_context(account_id, &[note.id()], &[])?
+        .foreign_accounts(faucets)
+        .build()?
+        .execute()
+        .await?)
+}
+
+// =================================================================================================
+// WALLET HELPERS (MockChain)
+// =================================================================================================
+
+/// Submits `notes` from `sender`'s wallet — the MockChain equivalent of a client transaction
+/// with own output notes — and commits the transaction in a new block.
+pub async fn submit_notes(
+    mock_chain: &mut MockChain,
+    sender: AccountId,
+    notes: &[Note],
+) -> Result<TransactionId> {
+    let account = mock_chain.committed_account(sender)?.clone();
+    let partial: Vec<PartialNote> = notes.iter().cloned().map(PartialNote::from).collect();
+    let script = AccountInterface::from_account(&account)
+        .build_send_notes_script(&partial, None)
+        .context("building send-notes script")?;
+    let faucets = callback_faucets(
+        mock_chain,
+        notes.iter().flat_map(|note| note.assets().iter().cloned()),
+    )?;
+    let executed = mock_chain
+        .build_tx_context(sender, &[], &[])?
+        .foreign_accounts(faucets)
+        .tx_script(script)
+        .extend_expected_output_notes(notes.iter().cloned().map(RawOutputNote::Full).collect())
+        .build()?
+        .execute()
+        .await
+        .context("executing send-notes transaction")?;
+    mock_chain.add_pending_executed_transaction(&executed)?;
+    mock_chain.prove_next_block()?;
+    Ok(executed.id())
+}
+
+/// Consumes `notes` (e.g. private payouts rebuilt from their `PayoutInfo`) into `account_id`
+/// and commits the transaction in a new block.
+pub async fn consume_notes(
+    mock_chain: &mut MockChain,
+    account_id: AccountId,
+    notes: &[Note],
+) -> Result<TransactionId> {
+    let faucets = callback_faucets(
+        mock_chain,
+        notes.iter().flat_map(|note| note.assets().iter().cloned()),
+    )?;
+    let executed = mock_chain
+        .build_tx_context(account_id, &[], notes)?
+        .foreign_accounts(faucets)
+        .build()?
+        .execute()
+        .await
+        .context("executing consume transaction")?;
+    mock_chain.add_pending_executed_transaction(&executed)?;
+    mock_chain.prove_next_block()?;
+    Ok(executed.id())
+}
+
+/// Foreign-account inputs for the faucets of the callback-enabled `assets`: the kernel calls
+/// into such a faucet whenever one of its assets enters a vault or a note, so the NTB (and a
+/// client) must load it alongside the transaction.
+fn callback_faucets(
+    mock_chain: &MockChain,
+    assets: impl IntoIterator<Item = Asset>,
+) -> Result<Vec<(Account, AccountWitness)>> {
+    let faucets: BTreeSet<AccountId> = assets
+        .into_iter()
+        .filter(|asset| asset.vault_key().callback_flag() == AssetCallbackFlag::Enabled)
+        .map(|asset| asset.faucet_id())
+        .collect();
+    faucets
+        .into_iter()
+        .map(|faucet_id| {
+            mock_chain
+                .get_foreign_account_inputs(faucet_id)
+                .with_context(|| format!("loading callback faucet {}", faucet_id.to_hex()))
+        })
+        .collect()
+}
@@ -0,0 +1,648 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use rusqlite::{Connection, OptionalExtension, params};
+
+use miden_client::{
+    Client, Word,
+    account::AccountId,
+    asset::{AssetCallbackFlag, FungibleAsset},
+    block::BlockNumber,
+    keystore::Keystore,
+    note::{Note, NoteId},
+    rpc::{NetworkNoteStatus, NodeRpcClient, RpcError},
+    store::TransactionFilter,
+    transaction::{TransactionId, TransactionRequestBuilder, TransactionStatus},
+};
+
+use crate::{
+    common::{AmmBuild, PayoutInfo, WaitOptions, WaitOutcome, poll_until, wait_for_tx},
+    history::AmmChainWalker,
+    recovery::{PayoutReplay, advance_replay},
+};
+
+// =================================================================================================
+// CONSTANTS
+// =================================================================================================
+
+/// File name of the payout tracker database, kept next to the client's sqlite store.
+pub const PAYOUT_STORE_FILE: &str = "payouts.sqlite3";
+
+/// Failed claims after which a payout whose AMM note was consumed is given up on, if the
+/// AMM's history does not explain the failure either.
+pub const MAX_CLAIM_ATTEMPTS: u32 = 10;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS pending_payouts (
+    payout_note_id  TEXT PRIMARY KEY,
+    amm_id          TEXT NOT NULL,
+    target          TEXT NOT NULL,
+    serial_num      TEXT NOT NULL,
+    assets          TEXT NOT NULL,
+    source_note_id  TEXT NOT NULL,
+    attempts        INTEGER NOT NULL DEFAULT 0,
+    last_error      TEXT,
+    claim_tx_id     TEXT,
+    claimed         INTEGER NOT NULL DEFAULT 0,
+    unresolvable    INTEGER NOT NULL DEFAULT 0
+);
+";
+
+// =================================================================================================
+// PENDING PAYOUTS
+// =================================================================================================
+
+/// A private payout note the AMM is expected to create, persisted at submission time.
+///
+/// Payout notes never show up in `get_consumable_notes`: the recipient can only consume
+/// them by rebuilding the full note from the serial number it chose. Everything needed for
+/// that rebuild lives in this record, so a restart between submitting the AMM note and
+/// claiming the payout loses nothing.
+///
+/// `assets` start out as the amounts expected at submission time (e.g. the quoted swap
+/// output). The note id commits to them, so when the AMM pays different amounts (the pool
+/// moved, and the AMM paid more than the slippage bound) the claimer replaces them with the
+/// amounts it finds on chain ([`PayoutStore::resolve`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingPayout {
+    pub amm_id: AccountId,
+    pub target: AccountId,
+    pub serial_num: Word,
+    pub assets: Vec<FungibleAsset>,
+    /// The AMM note (swap / add / remove liquidity) whose consumption creates the payout.
+    pub source_note_id: NoteId,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    /// The consume transaction, once one was accepted by the node.
+    pub claim_tx_id: Option<TransactionId>,
+    pub claimed: bool,
+    /// Given up on: the AMM note was discarded, or the payout could not be found on chain
+    /// (see [`MAX_CLAIM_ATTEMPTS`]). `last_error` says why.
+    pub unresolvable: bool,
+}
+
+impl PendingPayout {
+    /// The payout recipient, rebuilt from the recorded target and serial number.
+    pub fn payout_info(&self) -> PayoutInfo {
+        PayoutInfo::new(self.target, self.serial_num)
+    }
+
+    /// The full expected payout note.
+    pub fn expected_note(&self) -> Result<Note> {
+        self.payout_info()
+            .expected_note(self.amm_id, self.assets.clone())
+    }
+}
+
+/// Persistent store of pending payouts, backed by its own sqlite database.
+pub struct PayoutStore {
+    conn: Connection,
+}
+
+impl PayoutStore {
+    /// Opens (creating if needed) the payout database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let conn = Connection::open(path.as_ref())
+            .with_context(|| format!("opening payout store {}", path.as_ref().display()))?;
+        conn.execute_batch(SCHEMA)
+            .context("creating payout store schema")?;
+        Ok(PayoutStore { conn })
+    }
+
+    /// Opens the payout database in the same directory as the client's sqlite store.
+    pub fn open_next_to(client_store_path: impl AsRef<Path>) -> Result<Self> {
+        let dir = client_store_path
+            .as_ref()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        Self::open(dir.join(PAYOUT_STORE_FILE))
+    }
+
+    /// Records a payout the AMM will create when it consumes `source_note`. Returns the id of
+    /// the expected payout note, which is also the record's key (recording twice is a no-op).
+    pub fn record(
+        &self,
+        amm_id: AccountId,
+        payout: &PayoutInfo,
+        assets: Vec<FungibleAsset>,
+        source_note_id: NoteId,
+    ) -> Result<NoteId> {
+        let payout_note_id = payout.expected_note(amm_id, assets.clone())?.id();
+        self.conn
+            .execute(
+                "INSERT OR IGNORE INTO pending_payouts
+                    (payout_note_id, amm_id, target, serial_num, assets, source_note_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    payout_note_id.to_hex(),
+                    amm_id.to_hex(),
+                    payout.target.to_hex(),
+                    payout.serial_num.to_hex(),
+                    encode_assets(&assets),
+                    source_note_id.to_hex(),
+                ],
+            )
+            .context("recording pending payout")?;
+        Ok(payout_note_id)
+    }
+
+    /// Looks up a payout by the id of its expected note.
+    pub fn get(&self, payout_note_id: NoteId) -> Result<Option<PendingPayout>> {
+        self.conn
+            .query_row(
+                "SELECT amm_id, target, serial_num, assets, source_note_id, attempts,
+                        last_error, claim_tx_id, claimed, unresolvable
+                 FROM pending_payouts WHERE payout_note_id = ?1",
+                params![payout_note_id.to_hex()],
+                read_row,
+            )
+            .optional()
+            .context("reading pending payout")?
+            .transpose()
+    }
+
+    /// All payouts that have not been claimed or given up on yet, in insertion order.
+    pub fn pending(&self) -> Result<Vec<PendingPayout>> {
+        self.query("claimed = 0 AND unresolvable = 0")
+    }
+
+    /// All payouts given up on, in insertion order.
+    pub fn unresolvable(&self) -> Result<Vec<PendingPayout>> {
+        self.query("unresolvable = 1")
+    }
+
+    fn query(&self, filter: &str) -> Result<Vec<PendingPayout>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT amm_id, target, serial_num, assets, source_note_id, attempts,
+                        last_error, claim_tx_id, claimed, unresolvable
+                 FROM pending_payouts WHERE {filter} ORDER BY rowid"
+            ))
+            .context("preparing pending payout query")?;
+        let rows = stmt
+            .query_map([], read_row)
+            .context("querying pending payouts")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("reading pending payouts")?;
+        rows.into_iter().collect()
+    }
+
+    /// Records a failed claim attempt.
+    pub fn record_attempt(&self, payout_note_id: NoteId, error: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE pending_payouts SET attempts = attempts + 1, last_error = ?2
+                 WHERE payout_note_id = ?1",
+                params![payout_note_id.to_hex(), error],
+            )
+            .context("recording claim attempt")?;
+        Ok(())
+    }
+
+    /// Records the consume transaction submitted for a payout (`None` clears it, e.g. after
+    /// the node discarded it).
+    pub fn set_claim_tx(&self, payout_note_id: NoteId, tx_id: Option<TransactionId>) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE pending_payouts SET claim_tx_id = ?2 WHERE payout_note_id = ?1",
+                params![payout_note_id.to_hex(), tx_id.map(|id| id.to_hex())],
+            )
+            .context("recording claim transaction")?;
+        Ok(())
+    }
+
+    /// Marks a payout as claimed: its consume transaction is committed.
+    pub fn mark_claimed(&self, payout_note_id: NoteId) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE pending_payouts SET claimed = 1 WHERE payout_note_id = ?1",
+                params![payout_note_id.to_hex()],
+            )
+            .context("marking payout claimed")?;
+        Ok(())
+    }
+
+    /// Replaces a payout's expected assets with the amounts the AMM actually paid. Returns
+    /// the id of the payout note with those amounts, the record's new key. If that payout is
+    /// already recorded (e.g. by [`crate::recovery::record_recovered`]), the stale record is
+    /// dropped in favour of it.
+    pub fn resolve(&self, payout_note_id: NoteId, assets: Vec<FungibleAsset>) -> Result<NoteId> {
+        let payout = self
+            .get(payout_note_id)?
+            .with_context(|| format!("payout {payout_note_id} is not recorded"))?;
+        let resolved_id = payout
+            .payout_info()
+            .expected_note(payout.amm_id, assets.clone())?
+            .id();
+        if resolved_id == payout_note_id {
+            return Ok(resolved_id);
+        }
+
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("resolving payout amounts")?;
+        if self.get(resolved_id)?.is_some() {
+            tx.execute(
+                "DELETE FROM pending_payouts WHERE payout_note_id = ?1",
+                params![payout_note_id.to_hex()],
+            )
+        } else {
+            tx.execute(
+                "UPDATE pending_payouts SET payout_note_id = ?2, assets = ?3, claim_tx_id = NULL
+                 WHERE payout_note_id = ?1",
+                params![
+                    payout_note_id.to_hex(),
+                    resolved_id.to_hex(),
+                    encode_assets(&assets)
+                ],
+            )
+        }
+        .context("resolving payout amounts")?;
+        tx.commit().context("resolving payout amounts")?;
+        Ok(resolved_id)
+    }
+
+    /// Gives up on a payout; it drops out of [`PayoutStore::pending`].
+    pub fn mark_unresolvable(&self, payout_note_id: NoteId, reason: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE pending_payouts SET unresolvable = 1, last_error = ?2
+                 WHERE payout_note_id = ?1",
+                params![payout_note_id.to_hex(), reason],
+            )
+            .context("marking payout unresolvable")?;
+        Ok(())
+    }
+}
+
+/// Assets are stored as `faucet_hex:amount` pairs separated by commas, with a `:callbacks`
+/// suffix for assets of faucets with transfer-policy callbacks.
+fn encode_assets(assets: &[FungibleAsset]) -> String {
+    assets
+        .iter()
+        .map(|a| {
+            let suffix = match a.callbacks() {
+                AssetCallbackFlag::Disabled => "",
+                AssetCallbackFlag::Enabled => ":callbacks",
+            };
+            format!("{}:{}{suffix}", a.faucet_id().to_hex(), a.amount().as_u64())
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn decode_assets(encoded: &str) -> Result<Vec<FungibleAsset>> {
+    encoded
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(|pair| {
+            let (faucet, amount) = pair.split_once(':').context("malformed payout asset")?;
+            let (amount, callbacks) = match amount.split_once(':') {
+                None => (amount, AssetCallbackFlag::Disabled),
+                Some((amount, "callbacks")) => (amount, AssetCallbackFlag::Enabled),
+                Some(_) => bail!("malformed payout asset"),
+            };
+            let faucet = AccountId::from_hex(faucet).context("payout asset faucet id")?;
+            let amount = amount.parse().context("payout asset amount")?;
+            Ok(FungibleAsset::new(faucet, amount)
+                .context("payout asset")?
+                .with_callbacks(callbacks))
+        })
+        .collect()
+}
+
+type RawRow = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    u32,
+    Option<String>,
+    Option<String>,
+    bool,
+    bool,
+);
+
+fn read_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Result<PendingPayout>> {
+    let raw: RawRow = (
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+        row.get(5)?,
+        row.get(6)?,
+        row.get(7)?,
+        row.get(8)?,
+        row.get(9)?,
+    );
+    Ok(decode_row(raw))
+}
+
+fn decode_row(raw: RawRow) -> Result<PendingPayout> {
+    let (
+        amm_id,
+        target,
+        serial_num,
+        assets,
+        source,
+        attempts,
+        last_error,
+        claim_tx,
+        claimed,
+        unresolvable,
+    ) = raw;
+    Ok(PendingPayout {
+        amm_id: AccountId::from_hex(&amm_id).context("payout amm id")?,
+        target: AccountId::from_hex(&target).context("payout target")?,
+        serial_num: Word::try_from(serial_num.as_str()).context("payout serial number")?,
+        assets: decode_assets(&assets)?,
+        source_note_id: NoteId::try_from_hex(&source).context("payout source note id")?,
+        attempts,
+        last_error,
+        claim_tx_id: claim_tx
+            .map(|hex| Word::try_from(hex.as_str()).map(TransactionId::from_raw))
+            .transpose()
+            .context("payout claim tx id")?,
+        claimed,
+        unresolvable,
+    })
+}
+
+// =================================================================================================
+// CLIENT HELPERS (live network)
+// =================================================================================================
+
+/// Submits an AMM note from `sender`, recording the payout it will produce BEFORE the note
+/// leaves this process, then waits for the creating transaction to commit.
+pub async fn submit_tracked_amm_note<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    store: &PayoutStore,
+    sender: AccountId,
+    amm_id: AccountId,
+    note: Note,
+    payout: &PayoutInfo,
+    expected_assets: Vec<FungibleAsset>,
+) -> Result<NoteId> {
+    let payout_note_id = store.record(amm_id, payout, expected_assets, note.id())?;
+
+    let req = TransactionRequestBuilder::new()
+        .own_output_notes(vec![note])
+        .build()
+        .context("building note submission request")?;
+    let tx_id = client.submit_new_transaction(sender, req).await?;
+    wait_for_tx(client, tx_id).await?;
+    Ok(payout_note_id)
+}
+
+/// Consumes a (private) payout note into `target`'s vault and waits for the transaction to
+/// commit. Fails if the note does not exist on-chain yet.
+pub async fn claim_payout<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    target: AccountId,
+    payout_note: &Note,
+) -> Result<TransactionId> {
+    let tx_id = submit_claim(client, target, payout_note).await?;
+    wait_for_tx(client, tx_id).await?;
+    Ok(tx_id)
+}
+
+async fn submit_claim<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    target: AccountId,
+    payout_note: &Note,
+) -> Result<TransactionId> {
+    let req = TransactionRequestBuilder::new()
+        .input_notes([(payout_note.clone(), None)])
+        .build()
+        .context("building payout consume request")?;
+    Ok(client.submit_new_transaction(target, req).await?)
+}
+
+/// Outcome of one pass of [`claim_pending_payouts`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ClaimPass {
+    /// Payouts whose consume transaction committed during this pass.
+    pub claimed: Vec<NoteId>,
+    /// Payouts with a consume transaction in flight.
+    pub in_flight: Vec<NoteId>,
+    /// Payouts that could not be claimed yet (typically: the AMM has not executed the note).
+    pub not_ready: Vec<NoteId>,
+    /// Payouts whose recorded amounts were replaced by the ones paid on chain, as
+    /// `(recorded id, resolved id)`. The resolved payouts are claimed on the next pass.
+    pub resolved: Vec<(NoteId, NoteId)>,
+    /// Payouts given up on during this pass.
+    pub unresolvable: Vec<NoteId>,
+}
+
+impl ClaimPass {
+    /// True when no payout is left to claim.
+    pub fn is_done(&self) -> bool {
+        self.in_flight.is_empty() && self.not_ready.is_empty() && self.resolved.is_empty()
+    }
+}
+
+/// The replays of AMM history [`claim_pending_payouts`] resolves payouts with, kept between
+/// passes: a pass continues each pool's replay from the last block the previous pass
+/// replayed, instead of walking the pool's history from genesis again.
+#[derive(Default)]
+pub struct ClaimReplays {
+    by_amm: BTreeMap<AccountId, (PayoutReplay, AmmChainWalker)>,
+}
+
+impl ClaimReplays {
+    /// The replay of `build`'s history up to `block_to`, collecting at least `payouts`.
+    /// A payout the kept replay does not collect restarts it from genesis, with every
+    /// pending payout of the pool as a candidate.
+    async fn replay(
+        &mut self,
+        rpc: &dyn NodeRpcClient,
+        store: &PayoutStore,
+        build: &AmmBuild,
+        payouts: &[PendingPayout],
+        block_to: BlockNumber,
+    ) -> Result<&PayoutReplay> {
+        let amm_id = build.account.id();
+        let covered = self.by_amm.get(&amm_id).is_some_and(|(replay, _)| {
+            payouts
+                .iter()
+                .all(|payout| replay.is_candidate(&payout.payout_info()))
+        });
+        if !covered {
+            let candidates = store
+                .pending()?
+                .into_iter()
+                .filter(|payout| payout.amm_id == amm_id)
+                .map(|payout| payout.payout_info());
+            let replay = PayoutReplay::for_payouts(build, candidates);
+            self.by_amm
+                .insert(amm_id, (replay, AmmChainWalker::new(amm_id)));
+        }
+        let (replay, walker) = self.by_amm.get_mut(&amm_id).expect("replay just inserted");
+        advance_replay(rpc, replay, walker, block_to).await?;
+        Ok(replay)
+    }
+}
+
+/// Makes one non-blocking pass over the pending payouts: submits a consume transaction for
+/// every payout without one, and settles in-flight claims whose transaction committed (or
+/// was discarded, in which case the payout is retried on the next pass).
+///
+/// A claim is only submitted once the node reports the payout's AMM note consumed (or does
+/// not know the note). A payout whose AMM note the node discarded is unresolvable: the AMM
+/// never creates it. When the claim of a consumed note's payout fails, the AMM most likely
+/// paid other amounts than the recorded ones: the pool's history is replayed (the pool must
+/// be among `pools`; `replays` keeps the replay for the next pass), and the payout's record
+/// is resolved to the amounts actually paid. A payout the replay does not find either is
+/// given up on after [`MAX_CLAIM_ATTEMPTS`].
+pub async fn claim_pending_payouts<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    rpc: &dyn NodeRpcClient,
+    store: &PayoutStore,
+    pools: &[AmmBuild],
+    replays: &mut ClaimReplays,
+) -> Result<ClaimPass> {
+    client.sync_state().await?;
+    let mut pass = ClaimPass::default();
+    let mut failed: BTreeMap<AccountId, Vec<PendingPayout>> = BTreeMap::new();
+    for payout in store.pending()? {
+        let note = payout.expected_note()?;
+        let payout_id = note.id();
+
+        if let Some(tx_id) = payout.claim_tx_id {
+            let txs = client
+                .get_transactions(TransactionFilter::Ids(vec![tx_id]))
+                .await?;
+            match txs.first().map(|tx| &tx.status) {
+                Some(TransactionStatus::Committed { .. }) => {
+                    store.mark_claimed(payout_id)?;
+                    pass.claimed.push(payout_id);
+                }
+                Some(TransactionStatus::Discarded(cause)) => {
+                    store.set_claim_tx(payout_id, None)?;
+                    store.record_attempt(payout_id, &format!("claim discarded: {cause}"))?;
+                    pass.not_ready.push(payout_id);
+                }
+                _ => pass.in_flight.push(payout_id),
+            }
+            continue;
+        }
+
+        match rpc.get_network_note_status(payout.source_note_id).await {
+            Ok(info) => match info.status {
+                NetworkNoteStatus::Pending | NetworkNoteStatus::NullifierInflight => {
+                    pass.not_ready.push(payout_id);
+                    continue;
+                }
+                NetworkNoteStatus::Discarded => {
+                    let cause = info.last_error.as_deref().unwrap_or("no error reported");
+                    store.mark_unresolvable(payout_id, &format!("AMM note discarded: {cause}"))?;
+                    pass.unresolvable.push(payout_id);
+                    continue;
+                }
+                NetworkNoteStatus::NullifierCommitted => {}
+            },
+            // e.g. a recovered payout whose AMM note the node no longer tracks
+            Err(RpcError::NoteNotFound(_)) => {}
+            Err(err) => return Err(err).context("reading the AMM note status"),
+        }
+
+        match submit_claim(client, payout.target, &note).await {
+            Ok(tx_id) => {
+                store.set_claim_tx(payout_id, Some(tx_id))?;
+                pass.in_flight.push(payout_id);
+            }
+            Err(e) => {
+                store.record_attempt(payout_id, &e.to_string())?;
+                failed.entry(payout.amm_id).or_default().push(payout);
+            }
+        }
+    }
+
+    for (amm_id, payouts) in failed {
+        let build = pools
+            .iter()
+            .find(|build| build.account.id() == amm_id)
+            .with_context(|| format!("payouts of AMM {} but no such pool", amm_id.to_hex()))?;
+        let block_to = client.get_sync_height().await?;
+        let replay = replays
+            .replay(rpc, store, build, &payouts, block_to)
+            .await?;
+        for payout in payouts {
+            let payout_id = payout.expected_note()?.id();
+            let found = replay
+                .recovered()
+                .iter()
+                .find(|r| r.target == payout.target && r.serial_num == payout.serial_num);
+            match found {
+                Some(paid) if paid.assets != payout.assets => {
+                    let resolved_id = store.resolve(payout_id, paid.assets.clone())?;
+                    pass.resolved.push((payout_id, resolved_id));
+                }
+                // `attempts` was read before this pass's failure was recorded
+                _ if payout.attempts + 1 >= MAX_CLAIM_ATTEMPTS => {
+                    store.mark_unresolvable(
+                        payout_id,
+                        &format!("not claimable after {MAX_CLAIM_ATTEMPTS} attempts"),
+                    )?;
+                    pass.unresolvable.push(payout_id);
+                }
+                _ => pass.not_ready.push(payout_id),
+            }
+        }
+    }
+    Ok(pass)
+}
+
+/// What a run of [`run_payout_claimer`] settled.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ClaimReport {
+    /// Payouts claimed, in the order their claims committed (by their resolved id).
+    pub claimed: Vec<NoteId>,
+    /// Payouts given up on; [`PayoutStore::unresolvable`] has the reasons.
+    pub unresolvable: Vec<NoteId>,
+    /// Payouts still pending when the claimer stopped (timed out or cancelled).
+    pub pending: Vec<NoteId>,
+}
+
+impl ClaimReport {
+    /// True when every payout was claimed or given up on.
+    pub fn is_done(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Background claimer: repeats [`claim_pending_payouts`] until every recorded payout is
+/// claimed or given up on, pausing between passes and ending early as `options` say. Drive
+/// it from a dedicated task with its own client; it picks up where it left off after a
+/// restart because all state lives in the [`PayoutStore`].
+pub async fn run_payout_claimer<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    rpc: &dyn NodeRpcClient,
+    store: &PayoutStore,
+    pools: &[AmmBuild],
+    options: &WaitOptions,
+) -> Result<ClaimReport> {
+    let mut report = ClaimReport::default();
+    let mut replays = ClaimReplays::default();
+    poll_until(options, async || {
+        let pass = claim_pending_payouts(client, rpc, store, pools, &mut replays).await?;
+        report.claimed.extend(&pass.claimed);
+        report.unresolvable.extend(&pass.unresolvable);
+        report.pending = store
+            .pending()?
+            .iter()
+            .map(|p| p.expected_note().map(|note| note.id()))
+            .collect::<Result<_>>()?;
+        Ok(report.is_done().then_some(WaitOutcome::Done(())))
+    })
+    .await?;
+    Ok(report)
+}
@@ -0,0 +1,163 @@
+use anyhow::{Context, Result, ensure};
+
+use crate::{
+    common::{PoolState, quote_remove_liquidity},
+    quote::DepositQuote,
+};
+
+// =================================================================================================
+// LP POSITION
+// =================================================================================================
+
+/// An LP's stake in a pool, as recorded at deposit time: what went in, the LP tokens that
+/// came out, and the pool's `sqrt(x·y) / S` right after the deposit.
+///
+/// `sqrt(x·y) / S` — the invariant per LP token — only grows with the fees (and donations
+/// handed to the LPs), never with price moves. Comparing it now to its value at entry
+/// splits the position's current redeemable amounts into the fee income and the part a
+/// fee-less pool would have left, which is what the impermanent loss is measured on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LpPosition {
+    pub deposited_x: u64,
+    pub deposited_y: u64,
+    pub lp_amount: u64,
+    /// `sqrt(x·y) / S` after the deposit; the LP-weighted mean over merged deposits.
+    pub entry_value_per_share: f64,
+}
+
+/// The share of what LP tokens redeem for in `pool` that the growth of
+/// [`PoolState::value_per_share`] since `entry_value_per_share` paid: `1 - entry / now`, or
+/// 0 if it has not grown.
+pub fn fee_share(entry_value_per_share: f64, pool: &PoolState) -> f64 {
+    let growth = pool.value_per_share() / entry_value_per_share;
+    if growth > 1.0 {
+        1.0 - 1.0 / growth
+    } else {
+        0.0
+    }
+}
+
+impl LpPosition {
+    /// A deposit of `amount_x` and `amount_y` that minted `lp_minted`, leaving the pool at
+    /// `pool_after`.
+    pub fn new(amount_x: u64, amount_y: u64, lp_minted: u64, pool_after: &PoolState) -> Self {
+        LpPosition {
+            deposited_x: amount_x,
+            deposited_y: amount_y,
+            lp_amount: lp_minted,
+            entry_value_per_share: pool_after.value_per_share(),
+        }
+    }
+
+    /// The position a quoted deposit opens.
+    pub fn from_quote(quote: &DepositQuote) -> Self {
+        LpPosition::new(
+            quote.amount_x,
+            quote.amount_y,
+            quote.lp_out,
+            &quote.pool_after,
+        )
+    }
+
+    /// Adds a later deposit to the position. Fees count from each deposit's own entry. Fails
+    /// if the deposit minted no LP tokens.
+    pub fn add_deposit(
+        &mut self,
+        amount_x: u64,
+        amount_y: u64,
+        lp_minted: u64,
+        pool_after: &PoolState,
+    ) -> Result<()> {
+        ensure!(lp_minted != 0, "deposit mints zero LP tokens");
+        let lp_amount = self
+            .lp_amount
+            .checked_add(lp_minted)
+            .context("LP amount overflow")?;
+        self.entry_value_per_share = (self.entry_value_per_share * self.lp_amount as f64
+            + pool_after.value_per_share() * lp_minted as f64)
+            / lp_amount as f64;
+        self.deposited_x = self
+            .deposited_x
+            .checked_add(amount_x)
+            .context("deposit overflow")?;
+        self.deposited_y = self
+            .deposited_y
+            .checked_add(amount_y)
+            .context("deposit overflow")?;
+        self.lp_amount = lp_amount;
+        Ok(())
+    }
+
+    /// What burning the whole position would pay out now (`quote_remove_liquidity`).
+    pub fn redeemable(&self, pool: &PoolState) -> Result<(u64, u64)> {
+        ensure!(pool.lp_supply != 0, "pool has no LP supply");
+        ensure!(
+            self.lp_amount <= pool.lp_supply,
+            "burn amount exceeds LP supply"
+        );
+        Ok(quote_remove_liquidity(
+            self.lp_amount,
+            pool.reserve_x,
+            pool.reserve_y,
+            pool.lp_supply,
+        ))
+    }
+
+    /// Values the position against the current pool. Fails if the pool has no price (a
+    /// reserve is empty).
+    pub fn value(&self, pool: &PoolState) -> Result<PositionValue> {
+        let (redeemable_x, redeemable_y) = self.redeemable(pool)?;
+        ensure!(
+            pool.reserve_x != 0 && pool.reserve_y != 0,
+            "pool has LP supply but empty reserves"
+        );
+        let price = pool.reserve_y as f64 / pool.reserve_x as f64;
+
+        let fee_share = fee_share(self.entry_value_per_share, pool);
+        let (fees_x, fees_y) = (
+            redeemable_x as f64 * fee_share,
+            redeemable_y as f64 * fee_share,
+        );
+
+        let value_y = redeemable_x as f64 * price + redeemable_y as f64;
+        let fees_value_y = fees_x * price + fees_y;
+        let hold_value_y = self.deposited_x as f64 * price + self.deposited_y as f64;
+        Ok(PositionValue {
+            redeemable_x,
+            redeemable_y,
+            price,
+            value_x: value_y / price,
+            value_y,
+            hold_value_x: hold_value_y / price,
+            hold_value_y,
+            fees_x,
+            fees_y,
+            fees_value_y,
+            impermanent_loss: (value_y - fees_value_y) / hold_value_y - 1.0,
+        })
+    }
+}
+
+/// An [`LpPosition`] valued at a pool's spot price. `*_x` values are in units of X, `*_y`
+/// values in units of Y; both are the same amount at the spot price.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionValue {
+    pub redeemable_x: u64,
+    pub redeemable_y: u64,
+    /// Price of X in units of Y.
+    pub price: f64,
+    /// What the redeemable amounts are worth.
+    pub value_x: f64,
+    pub value_y: f64,
+    /// What the deposits would be worth, had they been held instead.
+    pub hold_value_x: f64,
+    pub hold_value_y: f64,
+    /// The part of the redeemable amounts the fees earned since the deposit.
+    pub fees_x: f64,
+    pub fees_y: f64,
+    pub fees_value_y: f64,
+    /// The value without the fees versus holding, as a fraction: 0 while the price is
+    /// where it was at the deposit, negative once it moves. Also counts what an
+    /// unbalanced deposit donated and, on the first deposit, the locked minimum liquidity.
+    pub impermanent_loss: f64,
+}
@@ -0,0 +1,157 @@
+use anyhow::{Result, ensure};
+
+use crate::common::{FEE_DENOM, PoolState};
+
+// =================================================================================================
+// CONSTANTS
+// =================================================================================================
+
+/// Denominator of a slippage tolerance: `tolerance_bps = 50` accepts 0.5% less than quoted.
+pub const TOLERANCE_DENOM: u64 = 10_000;
+
+/// The lowest amount a note should accept for a quoted `amount` at `tolerance_bps`:
+/// `floor(amount · (D − tolerance) / D)`.
+pub fn min_amount_out(amount: u64, tolerance_bps: u64) -> Result<u64> {
+    ensure!(
+        tolerance_bps <= TOLERANCE_DENOM,
+        "slippage tolerance {tolerance_bps} bps exceeds 100%"
+    );
+    let min = amount as u128 * (TOLERANCE_DENOM - tolerance_bps) as u128 / TOLERANCE_DENOM as u128;
+    Ok(min as u64)
+}
+
+// =================================================================================================
+// SWAP QUOTE
+// =================================================================================================
+
+/// What a swap of `amount_in` would do against a pool, for display and for the
+/// `min_amount_out` of `create_swap_note`. Prices are in units of the output asset per unit
+/// of the input asset.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SwapQuote {
+    /// `true` for X in, Y out.
+    pub x_in: bool,
+    pub amount_in: u64,
+    /// Exactly what the AMM pays out (`quote_swap_output`).
+    pub amount_out: u64,
+    /// The part of `amount_in` taken as the LP fee, `floor(amount_in · fee / D)`.
+    pub fee_paid: u64,
+    /// `reserve_out / reserve_in` before the swap.
+    pub spot_price: f64,
+    /// `amount_out / amount_in`.
+    pub execution_price: f64,
+    /// How much worse than the spot price the trade executes, the fee excluded, as a
+    /// fraction: `1 − execution_price / (spot_price · (1 − fee))`. Grows with the trade size
+    /// relative to the reserves.
+    pub price_impact: f64,
+    /// The pool after the swap.
+    pub pool_after: PoolState,
+    pub tolerance_bps: u64,
+    /// `amount_out` less the tolerance: the slippage bound for the swap note.
+    pub min_amount_out: u64,
+}
+
+impl SwapQuote {
+    /// Quotes swapping `amount_in` of X (`x_in = true`) or Y into `pool`. Fails where the
+    /// AMM would, with the same message (see [`PoolState::apply_swap`]).
+    pub fn new(pool: &PoolState, amount_in: u64, x_in: bool, tolerance_bps: u64) -> Result<Self> {
+        let (reserve_in, reserve_out) = if x_in {
+            (pool.reserve_x, pool.reserve_y)
+        } else {
+            (pool.reserve_y, pool.reserve_x)
+        };
+        // the prices below divide by these; the AMM rejects all three cases
+        ensure!(
+            amount_in != 0 && reserve_out != 0,
+            "computed output amount is zero"
+        );
+        ensure!(reserve_in != 0, "swap decreased the constant product");
+        let mut pool_after = *pool;
+        let amount_out = pool_after.apply_swap(amount_in, x_in)?;
+
+        let fee = pool.fee_bps as f64 / FEE_DENOM as f64;
+        let spot_price = reserve_out as f64 / reserve_in as f64;
+        let execution_price = amount_out as f64 / amount_in as f64;
+        Ok(SwapQuote {
+            x_in,
+            amount_in,
+            amount_out,
+            fee_paid: (amount_in as u128 * pool.fee_bps as u128 / FEE_DENOM as u128) as u64,
+            spot_price,
+            execution_price,
+            price_impact: 1.0 - execution_price / (spot_price * (1.0 - fee)),
+            pool_after,
+            tolerance_bps,
+            min_amount_out: min_amount_out(amount_out, tolerance_bps)?,
+        })
+    }
+}
+
+// =================================================================================================
+// LIQUIDITY QUOTES
+// =================================================================================================
+
+/// What depositing `amount_x` and `amount_y` would mint, and the `min_lp_out` of
+/// `create_add_liquidity_note`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepositQuote {
+    pub amount_x: u64,
+    pub amount_y: u64,
+    /// Exactly what the AMM mints to the depositor.
+    pub lp_out: u64,
+    /// The depositor's share of the LP supply after the deposit.
+    pub pool_share: f64,
+    pub pool_after: PoolState,
+    pub tolerance_bps: u64,
+    pub min_lp_out: u64,
+}
+
+impl DepositQuote {
+    /// Quotes a deposit into `pool` (see [`PoolState::apply_add_liquidity`]). On the first
+    /// deposit the tolerance still applies, although nothing can move the pool before it.
+    pub fn new(pool: &PoolState, amount_x: u64, amount_y: u64, tolerance_bps: u64) -> Result<Self> {
+        let mut pool_after = *pool;
+        let lp_out = pool_after.apply_add_liquidity(amount_x, amount_y)?;
+        Ok(DepositQuote {
+            amount_x,
+            amount_y,
+            lp_out,
+            pool_share: lp_out as f64 / pool_after.lp_supply as f64,
+            pool_after,
+            tolerance_bps,
+            min_lp_out: min_amount_out(lp_out, tolerance_bps)?,
+        })
+    }
+}
+
+/// What burning `lp_amount` would pay out, and the `min_x_out` / `min_y_out` of
+/// `create_remove_liquidity_note`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WithdrawQuote {
+    pub lp_amount: u64,
+    /// Exactly what the AMM pays out.
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub pool_after: PoolState,
+    pub tolerance_bps: u64,
+    pub min_x_out: u64,
+    pub min_y_out: u64,
+}
+
+impl WithdrawQuote {
+    /// Quotes burning `lp_amount` of `pool`'s LP tokens (see
+    /// [`PoolState::apply_remove_liquidity`]).
+    pub fn new(pool: &PoolState, lp_amount: u64, tolerance_bps: u64) -> Result<Self> {
+        let mut pool_after = *pool;
+        let (amount_x, amount_y) = pool_after.apply_remove_liquidity(lp_amount)?;
+        Ok(WithdrawQuote {
+            lp_amount,
+            amount_x,
+            amount_y,
+            pool_after,
+            tolerance_bps,
+            min_x_out: min_amount_out(amount_x, tolerance_bps)?,
+            min_y_out: min_amount_out(amount_y, tolerance_bps)?,
+        })
+    }
+}
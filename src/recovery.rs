@@ -0,0 +1,461 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, bail, ensure};
+
+use miden_client::{
+    Felt, Word,
+    account::{AccountDelta, AccountId},
+    asset::{Asset, FungibleAsset},
+    block::BlockNumber,
+    note::{Note, NoteId, NoteScriptRoot},
+    rpc::NodeRpcClient,
+};
+use miden_protocol::Hasher;
+
+use crate::{
+    common::{AmmBuild, PayoutInfo, PoolAsset, PoolState},
+    history::{AmmChainWalker, amm_delta},
+    payouts::PayoutStore,
+};
+
+// =================================================================================================
+// SERIAL NUMBER DERIVATION
+// =================================================================================================
+
+/// What a derived serial number is used for. Each kind is a separate derivation domain, so
+/// an AMM note serial can never collide with a payout serial for the same counter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SerialKind {
+    /// Serial number of a swap / add-liquidity / remove-liquidity note sent to the AMM.
+    AmmNote = 0,
+    /// Serial number of the private P2ID payout note the AMM creates.
+    Payout = 1,
+}
+
+/// Deterministic serial numbers from a wallet seed:
+/// `serial = hash(SEED || account_suffix || account_prefix || kind || counter)`.
+///
+/// Everything needed to claim a private payout is the serial number, so deriving it from a
+/// seed the user already backs up means losing local state never loses funds: see
+/// [`recover_payouts`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SerialDeriver {
+    seed: Word,
+}
+
+impl SerialDeriver {
+    /// Builds a deriver from raw seed bytes (hashed into a word).
+    pub fn from_seed(seed: &[u8]) -> Self {
+        SerialDeriver {
+            seed: Hasher::hash(seed),
+        }
+    }
+
+    /// The serial number of kind `kind` for `account` at position `counter`.
+    pub fn serial(&self, account: AccountId, kind: SerialKind, counter: u64) -> Word {
+        let mut elements = self.seed.as_elements().to_vec();
+        elements.extend([
+            account.suffix(),
+            account.prefix().as_felt(),
+            Felt::new_unchecked(kind as u64),
+            Felt::new_unchecked(counter),
+        ]);
+        Hasher::hash_elements(&elements)
+    }
+
+    /// The `counter`-th AMM note serial number for `sender`.
+    pub fn amm_note_serial(&self, sender: AccountId, counter: u64) -> Word {
+        self.serial(sender, SerialKind::AmmNote, counter)
+    }
+
+    /// The `counter`-th payout recipient for `target`.
+    pub fn payout(&self, target: AccountId, counter: u64) -> PayoutInfo {
+        PayoutInfo::new(target, self.serial(target, SerialKind::Payout, counter))
+    }
+}
+
+// =================================================================================================
+// PAYOUT RECOVERY
+// =================================================================================================
+
+/// A payout found on-chain for one of the candidate recipients.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecoveredPayout {
+    /// The derivation counter of the payout serial (for [`PayoutReplay::for_payouts`]: the
+    /// candidate's position).
+    pub counter: u64,
+    pub amm_id: AccountId,
+    pub target: AccountId,
+    pub serial_num: Word,
+    pub assets: Vec<FungibleAsset>,
+    /// The AMM note whose consumption created the payout.
+    pub source_note_id: NoteId,
+}
+
+impl RecoveredPayout {
+    /// The full payout note, ready to be consumed as an unauthenticated input note.
+    pub fn note(&self) -> Result<Note> {
+        PayoutInfo::new(self.target, self.serial_num)
+            .expected_note(self.amm_id, self.assets.clone())
+    }
+}
+
+/// Replays the notes consumed by an AMM, in chain order, through the reference math and
+/// collects the payouts that go to a set of candidate recipients.
+///
+/// Every state change of the pool comes from an allowlisted note, and all of those are
+/// public, so the pool state before each note — and with it the exact payout amounts — can
+/// be rebuilt from chain data alone. The replay also tracks the pool assets in the vault, so
+/// a sync adopts balances that reached the vault outside the pool's accounting. Notes with a
+/// script the replay does not know (e.g. one governance added to the allowlist later) are
+/// skipped; [`PayoutReplay::apply_account_delta`] re-anchors the state on the account's
+/// on-chain deltas. The payout's serial number is the only private part; candidates are
+/// regenerated from a [`SerialDeriver`] or taken from the payout tracker.
+pub struct PayoutReplay {
+    amm_id: AccountId,
+    pool_x: PoolAsset,
+    pool_y: PoolAsset,
+    swap_root: NoteScriptRoot,
+    add_liquidity_root: NoteScriptRoot,
+    remove_liquidity_root: NoteScriptRoot,
+    sync_root: NoteScriptRoot,
+    skim_root: NoteScriptRoot,
+    pool: PoolState,
+    /// Vault balances of the pool assets; above the reserves by whatever a sync would adopt.
+    vault_x: u64,
+    vault_y: u64,
+    /// Vault balances as of the last applied account delta (deltas are relative).
+    anchored_vault: (u64, u64),
+    /// Candidate payouts keyed by serial number (liquidity notes store the serial).
+    by_serial: BTreeMap<Word, (u64, AccountId)>,
+    /// Candidate payouts keyed by P2ID recipient digest (swap notes store the digest).
+    by_recipient: BTreeMap<Word, (u64, AccountId, Word)>,
+    recovered: Vec<RecoveredPayout>,
+}
+
+impl PayoutReplay {
+    /// Prepares a replay for `target`'s payouts with derivation counters `0..num_candidates`,
+    /// starting from an empty pool.
+    pub fn new(
+        build: &AmmBuild,
+        deriver: &SerialDeriver,
+        target: AccountId,
+        num_candidates: u64,
+    ) -> Self {
+        Self::for_payouts(
+            build,
+            (0..num_candidates).map(|counter| deriver.payout(target, counter)),
+        )
+    }
+
+    /// Prepares a replay for explicit payout recipients, e.g. the pending payouts of a
+    /// [`PayoutStore`], starting from an empty pool. The `counter` of a recovered payout is
+    /// the position of its candidate in `payouts`.
+    pub fn for_payouts(build: &AmmBuild, payouts: impl IntoIterator<Item = PayoutInfo>) -> Self {
+        let mut by_serial = BTreeMap::new();
+        let mut by_recipient = BTreeMap::new();
+        for (counter, payout) in (0..).zip(payouts) {
+            by_serial.insert(payout.serial_num, (counter, payout.target));
+            by_recipient.insert(
+                payout.recipient.digest(),
+                (counter, payout.target, payout.serial_num),
+            );
+        }
+        PayoutReplay {
+            amm_id: build.account.id(),
+            pool_x: build.pool_x(),
+            pool_y: build.pool_y(),
+            swap_root: build.swap_note_script.root(),
+            add_liquidity_root: build.add_liquidity_note_script.root(),
+            remove_liquidity_root: build.remove_liquidity_note_script.root(),
+            sync_root: build.sync_note_script.root(),
+            skim_root: build.skim_note_script.root(),
+            pool: PoolState::new(build.fee_bps),
+            vault_x: 0,
+            vault_y: 0,
+            anchored_vault: (0, 0),
+            by_serial,
+            by_recipient,
+            recovered: Vec::new(),
+        }
+    }
+
+    /// The replayed pool state.
+    pub fn pool(&self) -> PoolState {
+        self.pool
+    }
+
+    /// The replayed vault balances of the pool assets (X, Y).
+    pub fn vault(&self) -> (u64, u64) {
+        (self.vault_x, self.vault_y)
+    }
+
+    /// The payouts recovered so far.
+    pub fn recovered(&self) -> &[RecoveredPayout] {
+        &self.recovered
+    }
+
+    /// True if `payout` is among the candidates the replay collects.
+    pub fn is_candidate(&self, payout: &PayoutInfo) -> bool {
+        self.by_recipient.contains_key(&payout.recipient.digest())
+    }
+
+    pub fn into_recovered(self) -> Vec<RecoveredPayout> {
+        self.recovered
+    }
+
+    /// Applies one note consumed by the AMM. `output_notes` are the ids of the notes the
+    /// consuming transaction created; a payout is only reported if its rebuilt id is among
+    /// them, so a wrong replay can never produce a false positive.
+    pub fn apply_consumed_note(&mut self, note: &Note, output_notes: &[NoteId]) -> Result<()> {
+        let root = note.script().root();
+        let storage = note.recipient().storage().items();
+        let assets: Vec<FungibleAsset> = note
+            .assets()
+            .iter()
+            .map(|asset| match asset {
+                Asset::Fungible(asset) => Ok(*asset),
+                Asset::NonFungible(_) => bail!("AMM notes carry fungible assets only"),
+            })
+            .collect::<Result<_>>()?;
+
+        let found = if root == self.swap_root {
+            let [asset_in] = assets.as_slice() else {
+                bail!("swap note {} must carry exactly one asset", note.id());
+            };
+            let x_in = asset_in.faucet_id() == self.pool_x.faucet_id;
+            let asset_out = if x_in { self.pool_y } else { self.pool_x };
+            let amount_in = asset_in.amount().as_u64();
+            let out = self.pool.apply_swap(amount_in, x_in)?;
+            if x_in {
+                self.move_vault(amount_in as i64, -(out as i64))?;
+            } else {
+                self.move_vault(-(out as i64), amount_in as i64)?;
+            }
+            let recipient = word_at(storage, 4)?;
+            self.by_recipient
+                .get(&recipient)
+                .map(|&(counter, target, serial)| {
+                    (counter, target, serial, vec![asset_out.asset(out)])
+                })
+        } else if root == self.add_liquidity_root {
+            let [a, b] = assets.as_slice() else {
+                bail!(
+                    "add-liquidity note {} must carry exactly two assets",
+                    note.id()
+                );
+            };
+            let (dx, dy) = if a.faucet_id() == self.pool_x.faucet_id {
+                (a, b)
+            } else {
+                (b, a)
+            };
+            let (dx, dy) = (dx.amount().as_u64(), dy.amount().as_u64());
+            let lp = self.pool.apply_add_liquidity(dx, dy)?;
+            self.move_vault(dx as i64, dy as i64)?;
+            self.sender_bound(note, storage)?
+                .map(|(counter, target, serial)| {
+                    (
+                        counter,
+                        target,
+                        serial,
+                        vec![PoolAsset::from(self.amm_id).asset(lp)],
+                    )
+                })
+        } else if root == self.remove_liquidity_root {
+            let [lp] = assets.as_slice() else {
+                bail!(
+                    "remove-liquidity note {} must carry exactly one asset",
+                    note.id()
+                );
+            };
+            let (ax, ay) = self.pool.apply_remove_liquidity(lp.amount().as_u64())?;
+            self.move_vault(-(ax as i64), -(ay as i64))?;
+            self.sender_bound(note, storage)?
+                .map(|(counter, target, serial)| {
+                    (
+                        counter,
+                        target,
+                        serial,
+                        vec![self.pool_x.asset(ax), self.pool_y.asset(ay)],
+                    )
+                })
+        } else if root == self.sync_root {
+            (self.pool.reserve_x, self.pool.reserve_y) = (self.vault_x, self.vault_y);
+            None
+        } else if root == self.skim_root {
+            // the excess over the reserves goes to the sender, X before Y
+            let (ex, ey) = (
+                self.vault_x.saturating_sub(self.pool.reserve_x),
+                self.vault_y.saturating_sub(self.pool.reserve_y),
+            );
+            self.move_vault(-(ex as i64), -(ey as i64))?;
+            let excess = [(self.pool_x, ex), (self.pool_y, ey)]
+                .into_iter()
+                .filter(|&(_, amount)| amount != 0)
+                .map(|(asset, amount)| asset.asset(amount))
+                .collect::<Vec<_>>();
+            self.sender_bound(note, storage)?
+                .map(|(counter, target, serial)| (counter, target, serial, excess))
+        } else {
+            // governance notes only touch the allowlist; what a script governance added later
+            // does is unknown here, and picked up by the block's account delta
+            None
+        };
+
+        let Some((counter, target, serial_num, assets)) = found else {
+            return Ok(());
+        };
+        let recovered = RecoveredPayout {
+            counter,
+            amm_id: self.amm_id,
+            target,
+            serial_num,
+            assets: assets
+                .into_iter()
+                .collect::<Result<_, _>>()
+                .context("payout asset")?,
+            source_note_id: note.id(),
+        };
+        if output_notes.contains(&recovered.note()?.id()) {
+            self.recovered.push(recovered);
+        }
+        Ok(())
+    }
+
+    /// Re-anchors the replay on an on-chain delta of the AMM account: the reserves, LP supply
+    /// and fee it sets are adopted, and the vault is moved by its changes from where the
+    /// previous delta left it. Feed the deltas of every block with AMM transactions, each
+    /// after the notes consumed in that block (and first the genesis delta of a pool created
+    /// there), as [`replay_amm_history`] does.
+    pub fn apply_account_delta(&mut self, delta: &AccountDelta) -> Result<()> {
+        self.pool.apply_delta(delta);
+        let fungible = delta.vault().fungible();
+        let change = |asset: PoolAsset| -> Result<i64> {
+            let key = asset.asset(1)?.vault_key();
+            Ok(fungible.amount(&key).unwrap_or(0))
+        };
+        let (x, y) = self.anchored_vault;
+        let moved = (
+            x.checked_add_signed(change(self.pool_x)?),
+            y.checked_add_signed(change(self.pool_y)?),
+        );
+        let (Some(x), Some(y)) = moved else {
+            bail!("AMM account delta moves the vault out of range");
+        };
+        self.anchored_vault = (x, y);
+        (self.vault_x, self.vault_y) = (x, y);
+        Ok(())
+    }
+
+    fn move_vault(&mut self, dx: i64, dy: i64) -> Result<()> {
+        let moved = (
+            self.vault_x.checked_add_signed(dx),
+            self.vault_y.checked_add_signed(dy),
+        );
+        let (Some(x), Some(y)) = moved else {
+            bail!("replayed vault balance out of range");
+        };
+        (self.vault_x, self.vault_y) = (x, y);
+        Ok(())
+    }
+
+    /// Liquidity and skim payouts go to the note sender and carry the serial in storage[0..4].
+    fn sender_bound(
+        &self,
+        note: &Note,
+        storage: &[Felt],
+    ) -> Result<Option<(u64, AccountId, Word)>> {
+        let serial = word_at(storage, 0)?;
+        Ok(self
+            .by_serial
+            .get(&serial)
+            .filter(|&&(_, target)| note.metadata().sender() == target)
+            .map(|&(counter, target)| (counter, target, serial)))
+    }
+}
+
+fn word_at(storage: &[Felt], offset: usize) -> Result<Word> {
+    let felts: [Felt; 4] = storage
+        .get(offset..offset + 4)
+        .context("AMM note storage too short")?
+        .try_into()
+        .expect("slice of length 4");
+    Ok(felts.into())
+}
+
+// =================================================================================================
+// CLIENT HELPERS (live network)
+// =================================================================================================
+
+/// Recovers `target`'s payouts from the AMM's on-chain history after local data loss.
+///
+/// Scans blocks `0..=block_to` for the public notes sent to the AMM and for the AMM's
+/// transactions, replays the consumed notes in state-commitment order through a
+/// [`PayoutReplay`], and returns every payout whose serial number is among the first
+/// `num_candidates` derived from `deriver`. Feed the result to [`record_recovered`] to let
+/// the payout claimer consume them.
+pub async fn recover_payouts(
+    rpc: &dyn NodeRpcClient,
+    build: &AmmBuild,
+    deriver: &SerialDeriver,
+    target: AccountId,
+    num_candidates: u64,
+    block_to: BlockNumber,
+) -> Result<Vec<RecoveredPayout>> {
+    let replay = PayoutReplay::new(build, deriver, target, num_candidates);
+    replay_amm_history(rpc, replay, block_to).await
+}
+
+/// Runs `replay` over the AMM's on-chain history in blocks `0..=block_to` and returns the
+/// payouts it recovered: the scan behind [`recover_payouts`], for any set of candidates.
+pub async fn replay_amm_history(
+    rpc: &dyn NodeRpcClient,
+    mut replay: PayoutReplay,
+    block_to: BlockNumber,
+) -> Result<Vec<RecoveredPayout>> {
+    let mut walker = AmmChainWalker::new(replay.amm_id);
+    advance_replay(rpc, &mut replay, &mut walker, block_to).await?;
+    Ok(replay.into_recovered())
+}
+
+/// Runs `replay` over the blocks after the last one `walker` walked, up to `block_to`. The
+/// replay is re-anchored on the AMM's account delta in every block it touches. Keep both to
+/// continue the replay later without walking the history from genesis again.
+pub async fn advance_replay(
+    rpc: &dyn NodeRpcClient,
+    replay: &mut PayoutReplay,
+    walker: &mut AmmChainWalker,
+    block_to: BlockNumber,
+) -> Result<()> {
+    ensure!(
+        walker.amm_id() == replay.amm_id,
+        "replay and chain walk are of different AMMs"
+    );
+    for block in walker.advance(rpc, block_to).await? {
+        for tx in &block.transactions {
+            for note in &tx.consumed {
+                replay.apply_consumed_note(note, &tx.output_notes)?;
+            }
+        }
+
+        // the block's delta re-anchors the replay for the next block
+        if let Some(delta) = amm_delta(&block.fetch(rpc).await?, replay.amm_id)? {
+            replay.apply_account_delta(delta)?;
+        }
+    }
+    Ok(())
+}
+
+/// Records recovered payouts in the payout store so the background claimer picks them up.
+pub fn record_recovered(store: &PayoutStore, recovered: &[RecoveredPayout]) -> Result<()> {
+    for payout in recovered {
+        store.record(
+            payout.amm_id,
+            &PayoutInfo::new(payout.target, payout.serial_num),
+            payout.assets.clone(),
+            payout.source_note_id,
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use anyhow::{Context, Result, bail};
+
+use miden_client::{
+    Client,
+    account::AccountId,
+    asset::{Asset, FungibleAsset},
+    keystore::Keystore,
+    note::{NetworkAccountTarget, Note, NoteId, NoteTag, NoteType},
+    transaction::{ExecutedTransaction, TransactionRequestBuilder},
+};
+
+use crate::errors::AmmError;
+
+// =================================================================================================
+// SIMULATION RESULTS
+// =================================================================================================
+
+/// The payout note produced by a locally executed AMM transaction. Payout notes are private,
+/// so only the id, metadata fields and assets are known to the executor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimulatedPayout {
+    pub note_id: NoteId,
+    pub tag: NoteTag,
+    pub note_type: NoteType,
+    pub assets: Vec<FungibleAsset>,
+}
+
+impl SimulatedPayout {
+    /// Extracts the payout from an executed AMM transaction: every AMM note script creates
+    /// exactly one payout note.
+    pub fn from_executed(executed: &ExecutedTransaction) -> Result<Self> {
+        let notes: Vec<_> = executed.output_notes().iter().collect();
+        let [note] = notes.as_slice() else {
+            bail!(
+                "AMM transaction produced {} output notes, expected one payout",
+                notes.len()
+            );
+        };
+        let assets = note
+            .assets()
+            .iter()
+            .map(|asset| match asset {
+                Asset::Fungible(asset) => Ok(*asset),
+                Asset::NonFungible(_) => bail!("AMM payouts carry fungible assets only"),
+            })
+            .collect::<Result<_>>()?;
+        Ok(SimulatedPayout {
+            note_id: note.id(),
+            tag: note.metadata().tag(),
+            note_type: note.metadata().note_type(),
+            assets,
+        })
+    }
+
+    /// The payout amount of `faucet_id`'s asset (0 if the payout does not carry it).
+    pub fn amount_of(&self, faucet_id: AccountId) -> u64 {
+        self.assets
+            .iter()
+            .filter(|a| a.faucet_id() == faucet_id)
+            .map(|a| a.amount().as_u64())
+            .sum()
+    }
+}
+
+/// Result of executing an AMM note against the current pool state without submitting it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SimulationOutcome {
+    /// The AMM would accept the note and produce this payout.
+    Accepted(SimulatedPayout),
+    /// The AMM's MASM rejected the note with this error.
+    Rejected(AmmError),
+}
+
+// =================================================================================================
+// CLIENT HELPERS (live network)
+// =================================================================================================
+
+/// Dry-runs an AMM note: fetches the latest state of the public AMM account the note targets
+/// (importing it on first use), executes the note locally against it with the transaction
+/// executor and reports the payout the AMM would produce — or the [`AmmError`] that would
+/// make the network transaction builder reject it. Nothing is proven or broadcast. Failures
+/// outside the AMM's account code (kernel, note script) are returned as errors.
+///
+/// The AMM account is imported into `client`'s store on first use and stays there: later
+/// syncs track it like the client's own accounts. Callers who do not want that should simulate
+/// with a client of their own.
+///
+/// The result is only as fresh as the last sync: another note executed by the network
+/// between simulation and submission can still move the price.
+pub async fn simulate_amm_note<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    note: &Note,
+) -> Result<SimulationOutcome> {
+    let amm_id = NetworkAccountTarget::try_from(note.attachments())
+        .map_err(|e| anyhow::anyhow!("note is not a network note: {e}"))?
+        .target_id();
+
+    if client.get_account(amm_id).await?.is_none() {
+        client
+            .import_account_by_id(amm_id)
+            .await
+            .context("importing the AMM account")?;
+    }
+    client.sync_state().await?;
+
+    let req = TransactionRequestBuilder::new()
+        .input_notes([(note.clone(), None)])
+        .build()
+        .context("building simulation request")?;
+    match client.execute_transaction(amm_id, req).await {
+        Ok(result) => Ok(SimulationOutcome::Accepted(SimulatedPayout::from_executed(
+            result.executed_transaction(),
+        )?)),
+        Err(err) => match AmmError::from_execution_error(&err) {
+            Some(amm_error) => Ok(SimulationOutcome::Rejected(amm_error)),
+            None => Err(err).context("simulating AMM note"),
+        },
+    }
+}
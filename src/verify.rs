@@ -0,0 +1,243 @@
+use std::{collections::BTreeSet, fmt};
+
+use anyhow::{Context, Result};
+
+use miden_client::{
+    Word,
+    account::{Account, AccountId, StorageSlotName},
+    note::NoteScriptRoot,
+    rpc::NodeRpcClient,
+    transaction::TransactionScriptRoot,
+};
+use miden_standards::account::auth::{
+    NetworkAccountNoteAllowlist, NetworkAccountTxScriptAllowlist,
+};
+
+use crate::{
+    common::{FEE_DENOM, LpToken, build_amm_parts, config_slot, pool_x_key_slot, pool_y_key_slot},
+    governance::Governance,
+};
+
+// =================================================================================================
+// POOL VERIFICATION
+// =================================================================================================
+
+/// One way a deployed account differs from the AMM this crate builds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PoolMismatch {
+    /// The account has no public state, so its code cannot be inspected.
+    NotPublic,
+    /// The account code commitment differs from the one recomputed from the MASM sources.
+    CodeCommitment { expected: Word, actual: Word },
+    /// Procedures of an expected component that the account code does not contain.
+    MissingProcedures {
+        component: &'static str,
+        roots: Vec<Word>,
+    },
+    /// Procedures in the account code that belong to none of the expected components.
+    UnexpectedProcedures(Vec<Word>),
+    /// A storage slot of the expected components that the account lacks.
+    MissingStorageSlot(StorageSlotName),
+    /// A storage slot the expected components do not declare.
+    UnexpectedStorageSlot(StorageSlotName),
+    /// The configured fee exceeds `FEE_DENOM`, which no pool built by this crate can have.
+    InvalidFee(u64),
+    /// The note allowlist is not exactly the crate's seven note scripts, e.g. after a
+    /// governance change.
+    NoteAllowlist {
+        expected: BTreeSet<NoteScriptRoot>,
+        actual: BTreeSet<NoteScriptRoot>,
+    },
+    /// The tx-script allowlist is not exactly the crate's deploy script.
+    TxScriptAllowlist {
+        expected: BTreeSet<TransactionScriptRoot>,
+        actual: BTreeSet<TransactionScriptRoot>,
+    },
+}
+
+impl fmt::Display for PoolMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolMismatch::NotPublic => write!(f, "account state is not public"),
+            PoolMismatch::CodeCommitment { expected, actual } => write!(
+                f,
+                "code commitment is {} but the MASM sources give {}",
+                actual.to_hex(),
+                expected.to_hex()
+            ),
+            PoolMismatch::MissingProcedures { component, roots } => {
+                write!(f, "{} procedure(s) of {component} missing", roots.len())
+            }
+            PoolMismatch::UnexpectedProcedures(roots) => {
+                write!(f, "{} unexpected procedure(s)", roots.len())
+            }
+            PoolMismatch::MissingStorageSlot(name) => write!(f, "storage slot {name} missing"),
+            PoolMismatch::UnexpectedStorageSlot(name) => {
+                write!(f, "unexpected storage slot {name}")
+            }
+            PoolMismatch::InvalidFee(fee_bps) => {
+                write!(f, "fee of {fee_bps} bps exceeds {FEE_DENOM}")
+            }
+            PoolMismatch::NoteAllowlist { expected, actual } => write!(
+                f,
+                "note allowlist has {} root(s), {} of the expected {} missing",
+                actual.len(),
+                expected.difference(actual).count(),
+                expected.len()
+            ),
+            PoolMismatch::TxScriptAllowlist { expected, actual } => write!(
+                f,
+                "tx-script allowlist has {} root(s), {} of the expected {} missing",
+                actual.len(),
+                expected.difference(actual).count(),
+                expected.len()
+            ),
+        }
+    }
+}
+
+/// The result of checking a deployed account against the AMM built from this crate's MASM.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PoolReport {
+    pub amm_id: AccountId,
+    /// Every difference found; empty when the account runs exactly this crate's AMM.
+    pub mismatches: Vec<PoolMismatch>,
+}
+
+impl PoolReport {
+    pub fn is_verified(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Fetches `amm_id`'s public state from the node and checks it with [`verify_pool_account`].
+pub async fn verify_pool(rpc: &dyn NodeRpcClient, amm_id: AccountId) -> Result<PoolReport> {
+    let account = rpc
+        .get_account_details(amm_id)
+        .await
+        .with_context(|| format!("fetching account {}", amm_id.to_hex()))?;
+    match account {
+        Some(account) => verify_pool_account(&account),
+        None => Ok(PoolReport {
+            amm_id,
+            mismatches: vec![PoolMismatch::NotPublic],
+        }),
+    }
+}
+
+/// Checks that `account` runs `amm.masm` and `liquidity.masm` (with `liquidity_code()`'s
+/// injected P2ID root) behind an `AuthNetworkAccount` that allowlists exactly this crate's
+/// note and deploy scripts.
+///
+/// The expected account is rebuilt from the MASM sources with the account's own pool
+/// configuration, so the comparison covers code and storage layout, not which pair the
+/// pool trades. A note allowlist changed through governance is reported: the pool then
+/// accepts notes this crate did not compile.
+pub fn verify_pool_account(account: &Account) -> Result<PoolReport> {
+    let storage = account.storage();
+    let word = |slot: StorageSlotName| storage.get_item(&slot).unwrap_or_default();
+    let fee_bps = word(config_slot())[0].as_canonical_u64();
+    let lp_token = LpToken::from_account(account).unwrap_or_default();
+    let governance = Governance::from_account(account).ok().flatten();
+    let parts = build_amm_parts(
+        word(pool_x_key_slot()),
+        word(pool_y_key_slot()),
+        fee_bps,
+        &lp_token,
+        governance.as_ref(),
+    )?;
+    let expected = parts
+        .account_builder([0u8; 32])
+        .build_existing()
+        .context("building the expected AMM account")?;
+
+    let mut mismatches = Vec::new();
+    if !account.id().is_public() {
+        mismatches.push(PoolMismatch::NotPublic);
+    }
+
+    // code: the commitment, then which components explain the difference
+    let (expected_code, actual_code) = (expected.code().commitment(), account.code().commitment());
+    if expected_code != actual_code {
+        mismatches.push(PoolMismatch::CodeCommitment {
+            expected: expected_code,
+            actual: actual_code,
+        });
+    }
+    let actual_roots: BTreeSet<Word> = account.code().procedure_roots().collect();
+    let mut expected_roots = BTreeSet::new();
+    for (component, code) in &parts.components {
+        let roots: Vec<Word> = code.procedures().map(|(root, _)| root.into()).collect();
+        let missing: Vec<Word> = roots
+            .iter()
+            .filter(|root| !actual_roots.contains(root))
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            mismatches.push(PoolMismatch::MissingProcedures {
+                component,
+                roots: missing,
+            });
+        }
+        expected_roots.extend(roots);
+    }
+    let unexpected: Vec<Word> = actual_roots.difference(&expected_roots).copied().collect();
+    if !unexpected.is_empty() {
+        mismatches.push(PoolMismatch::UnexpectedProcedures(unexpected));
+    }
+
+    // storage layout: slot names only, values are per-pool state
+    let names = |account: &Account| -> BTreeSet<StorageSlotName> {
+        account
+            .storage()
+            .slots()
+            .iter()
+            .map(|slot| slot.name().clone())
+            .collect()
+    };
+    let (expected_slots, actual_slots) = (names(&expected), names(account));
+    for name in expected_slots.difference(&actual_slots) {
+        mismatches.push(PoolMismatch::MissingStorageSlot(name.clone()));
+    }
+    for name in actual_slots.difference(&expected_slots) {
+        mismatches.push(PoolMismatch::UnexpectedStorageSlot(name.clone()));
+    }
+    if fee_bps > FEE_DENOM {
+        mismatches.push(PoolMismatch::InvalidFee(fee_bps));
+    }
+
+    // allowlists: exactly the scripts compiled from this crate
+    let expected_notes = BTreeSet::from([
+        parts.swap_note_script.root(),
+        parts.add_liquidity_note_script.root(),
+        parts.remove_liquidity_note_script.root(),
+        parts.sync_note_script.root(),
+        parts.skim_note_script.root(),
+        parts.propose_allowlist_note_script.root(),
+        parts.execute_allowlist_note_script.root(),
+    ]);
+    let actual_notes = NetworkAccountNoteAllowlist::try_from(storage)
+        .map(NetworkAccountNoteAllowlist::into_allowed_script_roots)
+        .unwrap_or_default();
+    if actual_notes != expected_notes {
+        mismatches.push(PoolMismatch::NoteAllowlist {
+            expected: expected_notes,
+            actual: actual_notes,
+        });
+    }
+    let expected_tx_scripts = BTreeSet::from([parts.deploy_tx_script.root()]);
+    let actual_tx_scripts = NetworkAccountTxScriptAllowlist::try_from(storage)
+        .map(NetworkAccountTxScriptAllowlist::into_allowed_script_roots)
+        .unwrap_or_default();
+    if actual_tx_scripts != expected_tx_scripts {
+        mismatches.push(PoolMismatch::TxScriptAllowlist {
+            expected: expected_tx_scripts,
+            actual: actual_tx_scripts,
+        });
+    }
+
+    Ok(PoolReport {
+        amm_id: account.id(),
+        mismatches,
+    })
+}
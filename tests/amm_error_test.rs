@@ -0,0 +1,350 @@
+//! `AmmError` decoding. The MockChain tests drive every reachable failure path of
+//! `amm.masm` / `liquidity.masm` through the real transaction kernel and assert the executor
+//! error decodes to the right variant. `ERR_FEE_TOO_LARGE` (the builders refuse such a fee),
+//! `ERR_DY_OVERFLOW`, `ERR_EMPTY_POOL` and `ERR_VALUE_OVERFLOW` guard states the pool math
+//! cannot reach and are covered by the decoding tests only.
+
+mod common;
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use common::{FEE_BPS, add_notes, add_pair_faucets, add_pool, auth, serial};
+use miden_amm::{
+    common::{
+        PayoutInfo, create_add_liquidity_note, create_remove_liquidity_note, create_swap_note,
+        quote_initial_lp, quote_swap_output,
+    },
+    errors::{AmmError, masm_error_messages},
+};
+use miden_client::{
+    Felt,
+    account::{Account, AccountId},
+    asset::FungibleAsset,
+    note::{Note, NoteAssets, NoteRecipient, NoteStorage, NoteType, PartialNoteMetadata},
+    transaction::RawOutputNote,
+};
+use miden_testing::MockChain;
+
+/// Rebuilds `note` with different assets but identical script, storage and attachments.
+fn with_assets(note: &Note, assets: Vec<FungibleAsset>) -> Result<Note> {
+    Ok(Note::with_attachments(
+        NoteAssets::new(assets.into_iter().map(Into::into).collect())?,
+        PartialNoteMetadata::new(note.metadata().sender(), NoteType::Public)
+            .with_tag(note.metadata().tag()),
+        note.recipient().clone(),
+        note.attachments().clone(),
+    ))
+}
+
+/// Rebuilds `note` with `len` storage elements (zero-padded or truncated).
+fn with_storage_len(note: &Note, len: usize) -> Result<Note> {
+    let mut storage = note.recipient().storage().items().to_vec();
+    storage.resize(len, Felt::new_unchecked(0));
+    let recipient = NoteRecipient::new(
+        note.recipient().serial_num(),
+        note.recipient().script().clone(),
+        NoteStorage::new(storage)?,
+    );
+    Ok(Note::with_attachments(
+        note.assets().clone(),
+        PartialNoteMetadata::new(note.metadata().sender(), NoteType::Public)
+            .with_tag(note.metadata().tag()),
+        recipient,
+        note.attachments().clone(),
+    ))
+}
+
+/// Executes `note` against the AMM and asserts it fails with `expected`.
+async fn assert_rejected(
+    mock_chain: &MockChain,
+    amm_id: AccountId,
+    note: &Note,
+    expected: AmmError,
+) -> Result<()> {
+    let err = mock_chain
+        .build_tx_context(amm_id, &[note.id()], &[])?
+        .build()?
+        .execute()
+        .await
+        .expect_err("note must be rejected");
+    assert_eq!(
+        AmmError::from_execution_error(&err),
+        Some(expected),
+        "unexpected failure: {err}"
+    );
+    Ok(())
+}
+
+/// Executes a note that must succeed, applies it and seals a block.
+async fn consume(
+    mock_chain: &mut MockChain,
+    amm_account: &mut Account,
+    note: &Note,
+    expected_payout: Note,
+) -> Result<()> {
+    let executed = mock_chain
+        .build_tx_context(amm_account.id(), &[note.id()], &[])?
+        .extend_expected_output_notes(vec![RawOutputNote::Full(expected_payout)])
+        .build()?
+        .execute()
+        .await?;
+    amm_account.apply_delta(executed.account_delta())?;
+    mock_chain.add_pending_executed_transaction(&executed)?;
+    mock_chain.prove_next_block()?;
+    Ok(())
+}
+
+#[test]
+fn amm_errors_cover_every_masm_constant() {
+    let declared: BTreeSet<&str> = masm_error_messages().into_iter().collect();
+    let mapped: BTreeSet<&str> = AmmError::ALL.iter().map(|e| e.message()).collect();
+    assert_eq!(
+        declared, mapped,
+        "AmmError is out of sync with the MASM error constants"
+    );
+
+    let codes: BTreeSet<u64> = AmmError::ALL.iter().map(|e| e.code()).collect();
+    assert_eq!(
+        codes.len(),
+        AmmError::ALL.len(),
+        "error codes must be distinct"
+    );
+    for error in AmmError::ALL {
+        assert_eq!(AmmError::from_code(error.code()), Some(error));
+        assert_eq!(AmmError::from_message(error.message()), Some(error));
+    }
+}
+
+#[test]
+fn amm_errors_are_decoded_from_error_text() {
+    // with debug info the message is reported, e.g. in a network note's `last_error`
+    assert_eq!(
+        AmmError::from_error_text(
+            "failed to execute transaction kernel program:\n  x assertion failed with error \
+             message: burn amount exceeds LP supply\n  help: ..."
+        ),
+        Some(AmmError::LpExceedsSupply)
+    );
+    // without it only the code is
+    let code = AmmError::ZeroLpMinted.code();
+    assert_eq!(
+        AmmError::from_error_text(&format!("x assertion failed with error code: {code}")),
+        Some(AmmError::ZeroLpMinted)
+    );
+    // kernel assertions and unrelated errors are not AMM errors
+    assert_eq!(
+        AmmError::from_error_text("assertion failed with error message: note sender mismatch"),
+        None
+    );
+    assert_eq!(
+        AmmError::from_error_text("assertion failed with error code: 42"),
+        None
+    );
+    assert_eq!(AmmError::from_error_text("note not found"), None);
+}
+
+#[tokio::test]
+async fn swap_failures_decode_to_amm_errors() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let faucet_z = builder.add_existing_basic_faucet(auth(), "TKZ", 1_000_000_000, Some(8))?;
+    let alice = builder.add_existing_wallet_with_assets(auth(), [])?;
+
+    let build = add_pool(&mut builder, &faucet_x, &faucet_y)?;
+    let amm_id = build.account.id();
+
+    let (lp, _) = quote_initial_lp(100_000, 400_000);
+    let add_payout = PayoutInfo::new(alice.id(), serial(1000));
+    let add_note = create_add_liquidity_note(
+        alice.id(),
+        amm_id,
+        FungibleAsset::new(faucet_x.id(), 100_000)?,
+        FungibleAsset::new(faucet_y.id(), 400_000)?,
+        lp,
+        &add_payout,
+        build.add_liquidity_note_script.clone(),
+        serial(1),
+    )?;
+
+    let swap = |n: u64, asset_in: FungibleAsset, out: AccountId, min_out: u64| {
+        create_swap_note(
+            alice.id(),
+            amm_id,
+            asset_in,
+            out,
+            min_out,
+            &PayoutInfo::new(alice.id(), serial(2000 + n)),
+            build.swap_note_script.clone(),
+            serial(n),
+        )
+    };
+    let x = |amount| FungibleAsset::new(faucet_x.id(), amount);
+    let dy = quote_swap_output(30_000, 100_000, 400_000, FEE_BPS);
+    let fair = swap(10, x(30_000)?, faucet_y.id(), dy)?;
+
+    let cases = [
+        (with_storage_len(&fair, 8)?, AmmError::BadSwapNoteStorage),
+        (
+            with_assets(
+                &fair,
+                vec![x(30_000)?, FungibleAsset::new(faucet_y.id(), 1)?],
+            )?,
+            AmmError::BadSwapNoteAssets,
+        ),
+        (
+            swap(
+                11,
+                FungibleAsset::new(faucet_z.id(), 30_000)?,
+                faucet_y.id(),
+                1,
+            )?,
+            AmmError::WrongSwapPair,
+        ),
+        (
+            swap(12, x(30_000)?, faucet_x.id(), 1)?,
+            AmmError::WrongSwapPair,
+        ),
+        // 1 Y buys less than one unit of X
+        (
+            swap(14, FungibleAsset::new(faucet_y.id(), 1)?, faucet_x.id(), 0)?,
+            AmmError::ZeroOutput,
+        ),
+        (
+            swap(15, x(30_000)?, faucet_y.id(), dy + 1)?,
+            AmmError::Slippage,
+        ),
+    ];
+
+    add_notes(
+        &mut builder,
+        std::iter::once(&add_note).chain(cases.iter().map(|(note, _)| note)),
+    );
+    let mut mock_chain = builder.build()?;
+    let mut amm_account = build.account.clone();
+    consume(
+        &mut mock_chain,
+        &mut amm_account,
+        &add_note,
+        add_payout.expected_note(amm_id, vec![FungibleAsset::new(amm_id, lp)?])?,
+    )
+    .await?;
+
+    for (note, expected) in &cases {
+        assert_rejected(&mock_chain, amm_id, note, *expected).await?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn liquidity_failures_decode_to_amm_errors() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let faucet_z = builder.add_existing_basic_faucet(auth(), "TKZ", 1_000_000_000, Some(8))?;
+    let alice = builder.add_existing_wallet_with_assets(auth(), [])?;
+
+    let build = add_pool(&mut builder, &faucet_x, &faucet_y)?;
+    let amm_id = build.account.id();
+
+    let x = |amount| FungibleAsset::new(faucet_x.id(), amount);
+    let y = |amount| FungibleAsset::new(faucet_y.id(), amount);
+    let payout = |n: u64| PayoutInfo::new(alice.id(), serial(1000 + n));
+    let add = |n: u64, a: FungibleAsset, b: FungibleAsset, min_lp: u64| {
+        create_add_liquidity_note(
+            alice.id(),
+            amm_id,
+            a,
+            b,
+            min_lp,
+            &payout(n),
+            build.add_liquidity_note_script.clone(),
+            serial(n),
+        )
+    };
+    let remove = |n: u64, lp: u64, min_x: u64, min_y: u64| {
+        create_remove_liquidity_note(
+            alice.id(),
+            amm_id,
+            lp,
+            min_x,
+            min_y,
+            &payout(n),
+            build.remove_liquidity_note_script.clone(),
+            serial(n),
+        )
+    };
+
+    let (lp, supply) = quote_initial_lp(100_000, 400_000);
+    let deposit = add(1, x(100_000)?, y(400_000)?, lp)?;
+    let lp_asset = |amount| FungibleAsset::new(amm_id, amount);
+
+    // rejected by the empty pool
+    let before_deposit = [
+        // sqrt(10 * 10) does not exceed MIN_LIQUIDITY
+        (
+            add(10, x(10)?, y(10)?, 0)?,
+            AmmError::InsufficientInitialLiquidity,
+        ),
+        (
+            with_storage_len(&add(11, x(100)?, y(100)?, 0)?, 12)?,
+            AmmError::BadLiquidityNoteStorage,
+        ),
+        (
+            with_assets(&add(12, x(100)?, y(100)?, 0)?, vec![x(100)?])?,
+            AmmError::BadAddAssets,
+        ),
+        (
+            add(
+                13,
+                x(100_000)?,
+                FungibleAsset::new(faucet_z.id(), 400_000)?,
+                0,
+            )?,
+            AmmError::WrongDepositPair,
+        ),
+    ];
+    // rejected by the funded pool
+    let after_deposit = [
+        // min(1 * S / x, 1 * S / y) rounds to zero
+        (add(20, x(1)?, y(1)?, 0)?, AmmError::ZeroLpMinted),
+        (
+            add(21, x(1_000)?, y(4_000)?, 1_000_000)?,
+            AmmError::Slippage,
+        ),
+        (
+            with_assets(&remove(22, 1_000, 0, 0)?, vec![lp_asset(1_000)?, x(1)?])?,
+            AmmError::BadRemoveAssets,
+        ),
+        (
+            with_assets(&remove(23, 1_000, 0, 0)?, vec![x(1_000)?])?,
+            AmmError::NotLpToken,
+        ),
+        (remove(24, supply + 1, 0, 0)?, AmmError::LpExceedsSupply),
+        // one LP unit is worth less than one unit of X
+        (remove(25, 1, 0, 0)?, AmmError::ZeroPayout),
+        (remove(26, 1_000, 1_000_000, 0)?, AmmError::Slippage),
+    ];
+
+    let cases = before_deposit.iter().chain(&after_deposit);
+    add_notes(
+        &mut builder,
+        std::iter::once(&deposit).chain(cases.map(|(note, _)| note)),
+    );
+    let mut mock_chain = builder.build()?;
+    let mut amm_account = build.account.clone();
+
+    for (note, expected) in &before_deposit {
+        assert_rejected(&mock_chain, amm_id, note, *expected).await?;
+    }
+    consume(
+        &mut mock_chain,
+        &mut amm_account,
+        &deposit,
+        payout(1).expected_note(amm_id, vec![lp_asset(lp)?])?,
+    )
+    .await?;
+    for (note, expected) in &after_deposit {
+        assert_rejected(&mock_chain, amm_id, note, *expected).await?;
+    }
+    Ok(())
+}
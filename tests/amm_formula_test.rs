@@ -2,9 +2,10 @@
 //! exactly, so they double as a specification for `amm.masm` / `liquidity.masm`.
 
 use miden_amm::common::{
-    FEE_DENOM, MIN_LIQUIDITY, quote_initial_lp, quote_lp_mint, quote_remove_liquidity,
+    FEE_DENOM, MIN_LIQUIDITY, PoolState, quote_initial_lp, quote_lp_mint, quote_remove_liquidity,
     quote_swap_output,
 };
+use miden_client::asset::FungibleAsset;
 
 #[test]
 fn zero_fee_reduces_to_constant_product() {
@@ -63,7 +64,7 @@ fn proportional_deposit_mints_proportional_lp() {
     // Depositing 50% of reserves mints 50% of supply.
     let (x, y, s) = (100_000u64, 400_000u64, 200_000u64);
     let lp = quote_lp_mint(50_000, 200_000, x, y, s);
-    assert_eq!(lp, s / 2);
+    assert_eq!(lp, Some(s / 2));
 }
 
 #[test]
@@ -108,3 +109,120 @@ fn lps_earn_swap_fees() {
     assert!(ax1 > ax0);
     assert!(ay1 >= ay0);
 }
+
+#[test]
+fn failed_deposit_leaves_the_pool_unchanged() {
+    // mints LP, but x + dx does not fit in a u64
+    let mut pool = PoolState {
+        reserve_x: 1 << 63,
+        reserve_y: 1_000,
+        lp_supply: 1 << 20,
+        fee_bps: 30,
+    };
+    let before = pool;
+    let err = pool.apply_add_liquidity(1 << 63, 1_000).unwrap_err();
+    assert_eq!(err.to_string(), "reserve overflow");
+    assert_eq!(pool, before);
+}
+
+#[test]
+fn swaps_against_empty_reserves_fail() {
+    let mut pool = PoolState::new(30);
+    let err = pool.apply_swap(1_000, true).unwrap_err();
+    assert_eq!(err.to_string(), "computed output amount is zero");
+    assert!(pool.apply_swap(0, false).is_err());
+
+    // with only the output side funded, the swap would drain it
+    pool.reserve_y = 1_000;
+    let err = pool.apply_swap(1_000, true).unwrap_err();
+    assert_eq!(err.to_string(), "swap decreased the constant product");
+    assert_eq!(pool.reserve_y, 1_000);
+}
+
+#[test]
+fn off_ratio_deposit_donates_its_excess_to_the_lps() {
+    let mut pool = PoolState::new(30);
+    let alice = pool.apply_add_liquidity(100_000, 400_000).unwrap();
+    // priced at 1 X = 4 Y; bob brings twice the Y the ratio asks for
+    let bob = pool.apply_add_liquidity(10_000, 80_000).unwrap();
+    assert_eq!(
+        Some(bob),
+        quote_lp_mint(10_000, 80_000, 100_000, 400_000, 200_000)
+    );
+    assert_eq!((pool.reserve_x, pool.reserve_y), (110_000, 480_000));
+
+    // the excess moved the price; bob gets back less Y than he put in, and alice redeems
+    // more than before his deposit
+    let (_, bob_y) = quote_remove_liquidity(bob, 110_000, 480_000, pool.lp_supply);
+    assert!(bob_y < 80_000);
+    let (_, alice_y) = quote_remove_liquidity(alice, 110_000, 480_000, pool.lp_supply);
+    assert!(alice_y > quote_remove_liquidity(alice, 100_000, 400_000, 200_000).1);
+}
+
+/// `a * b` as a (high, low) pair of u128 halves.
+fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo, b_hi, b_lo) = (a >> 64, a & MASK, b >> 64, b & MASK);
+    let lo_lo = a_lo * b_lo;
+    let mid = a_hi * b_lo + (lo_lo >> 64);
+    let (mid, carry) = mid.overflowing_add(a_lo * b_hi);
+    let hi = a_hi * b_hi + (mid >> 64) + ((carry as u128) << 64);
+    (hi, (mid << 64) | (lo_lo & MASK))
+}
+
+/// Checks `dy == floor(num / den)` for num = y * dx_f beyond u128, via
+/// dy * den <= num < (dy + 1) * den in 256-bit arithmetic.
+fn assert_exact_quote(dx: u64, x: u64, y: u64, fee_bps: u64) {
+    let dy = quote_swap_output(dx, x, y, fee_bps);
+    let dx_f = (dx as u128) * ((FEE_DENOM - fee_bps) as u128);
+    let den = (x as u128) * (FEE_DENOM as u128) + dx_f;
+    let num = mul_wide(y as u128, dx_f);
+    assert!(
+        mul_wide(dy as u128, den) <= num,
+        "{dx} {x} {y}: dy too large"
+    );
+    assert!(
+        mul_wide(dy as u128 + 1, den) > num,
+        "{dx} {x} {y}: dy too small"
+    );
+}
+
+#[test]
+fn large_swaps_quote_exactly() {
+    let max = FungibleAsset::MAX_AMOUNT.as_u64();
+    // dx * (FEE_DENOM - fee) around and far beyond u64::MAX
+    let old_limit = u64::MAX / (FEE_DENOM - 30);
+    let amounts = [
+        1,
+        u32::MAX as u64,
+        old_limit,
+        old_limit + 1,
+        1 << 62,
+        max,
+        u64::MAX,
+    ];
+    for fee_bps in [0, 30, FEE_DENOM - 1] {
+        for dx in amounts {
+            for (x, y) in [
+                (1, max),
+                (max, max),
+                (1 << 40, u64::MAX),
+                (u64::MAX, 1 << 33),
+            ] {
+                assert_exact_quote(dx, x, y, fee_bps);
+            }
+        }
+    }
+}
+
+#[test]
+fn large_swaps_keep_the_invariant() {
+    let max = FungibleAsset::MAX_AMOUNT.as_u64();
+    let (x, y) = (max / 4, max / 2);
+    let dx = max - x;
+    let dy = quote_swap_output(dx, x, y, 30);
+    assert!(dy < y);
+    assert!(mul_wide((x + dx) as u128, (y - dy) as u128) >= mul_wide(x as u128, y as u128));
+    // the output approaches the whole reserve, never reaches it
+    assert!(quote_swap_output(u64::MAX, 1, u64::MAX, 0) < u64::MAX);
+}
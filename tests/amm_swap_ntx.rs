@@ -10,11 +10,14 @@
 use std::{path::PathBuf, sync::Arc};
 
 use anyhow::{Context, Result, bail};
-use miden_amm::common::{
-    FEE_DENOM, PayoutInfo, build_amm_account, create_add_liquidity_note,
-    create_remove_liquidity_note, create_swap_note, create_basic_account, create_basic_faucet,
-    lp_supply_slot, mint_and_consume, quote_initial_lp, quote_remove_liquidity,
-    quote_swap_output, wait_for_tx,
+use miden_amm::{
+    common::{
+        FEE_DENOM, PayoutInfo, build_amm_account, create_add_liquidity_note,
+        create_remove_liquidity_note, create_swap_note, create_basic_account,
+        create_basic_faucet, lp_supply_slot, mint_and_consume, quote_initial_lp,
+        quote_remove_liquidity, quote_swap_output, wait_for_tx,
+    },
+    errors::AmmError,
 };
 use miden_client::{
     Client, Word,
@@ -51,8 +54,7 @@ async fn wait_for_lp_supply(
             let word: Word = account
                 .storage()
                 .get_item(&lp_supply_slot())
-                .context("reading lp_supply slot")?
-                .into();
+                .context("reading lp_supply slot")?;
             let value = word[0].as_canonical_u64();
             println!("[poll {attempt}] lp_supply = {value} (waiting for {expected})");
             if value == expected {
@@ -66,6 +68,10 @@ async fn wait_for_lp_supply(
                         "network note status: {} (attempts: {}, last_error: {:?})",
                         info.status, info.attempt_count, info.last_error
                     );
+                    let decoded = info.last_error.as_deref().and_then(AmmError::from_error_text);
+                    if let Some(err) = decoded {
+                        println!("AMM rejected the note: {err:?} ({err})");
+                    }
                 }
                 Err(e) => println!("network note status query failed: {e}"),
             }
@@ -0,0 +1,279 @@
+//! Pool analytics: windows over a hand-built series, and a series walked from a MockChain
+//! (served by `MockNode`) checked block by block against the reference math.
+
+mod common;
+
+use anyhow::Result;
+use common::{FEE_BPS, add_notes, add_pair_faucets, add_pool, auth, serial};
+use miden_amm::{
+    analytics::{PoolAnalytics, PoolSample, PoolSeries, SECONDS_PER_YEAR, fetch_pool_series},
+    common::{
+        FEE_DENOM, PayoutInfo, PoolState, create_add_liquidity_note, create_remove_liquidity_note,
+        create_swap_note,
+    },
+    manifest::AmmManifest,
+    mock_node::MockNode,
+};
+use miden_client::{asset::FungibleAsset, note::Note, transaction::RawOutputNote};
+use miden_testing::MockChain;
+
+const DAY: u32 = 24 * 60 * 60;
+
+fn sample(block_num: u32, timestamp: u32, x: u64, y: u64, supply: u64, vol_x: u128) -> PoolSample {
+    PoolSample {
+        block_num,
+        timestamp,
+        reserve_x: x,
+        reserve_y: y,
+        lp_supply: supply,
+        fee_bps: FEE_BPS,
+        price: y as f64 / x as f64,
+        value_per_share: ((x as u128 * y as u128) as f64).sqrt() / supply as f64,
+        swaps: (vol_x != 0) as u32,
+        volume_x: vol_x,
+        volume_y: 0,
+    }
+}
+
+#[test]
+fn windows_aggregate_volume_and_annualize_share_growth() {
+    let series = PoolSeries {
+        samples: vec![
+            sample(3, 1_000, 100_000, 400_000, 200_000, 0),
+            sample(5, 1_000 + DAY, 110_000, 364_000, 200_000, 10_000),
+            sample(9, 1_000 + 3 * DAY, 120_000, 334_000, 200_000, 10_000),
+            sample(12, 1_000 + 7 * DAY, 130_000, 309_000, 200_000, 10_000),
+        ],
+    };
+    let growth = |from: usize, to: usize| {
+        series.samples[to].value_per_share / series.samples[from].value_per_share - 1.0
+    };
+
+    // the opening state's own swaps belong to the previous window
+    let stats = series.stats(5, 12).unwrap();
+    assert_eq!((stats.from_block, stats.to_block), (5, 12));
+    assert_eq!(
+        (stats.swaps, stats.volume_x, stats.volume_y),
+        (2, 20_000, 0)
+    );
+    assert_eq!(stats.fees_x, 20_000.0 * FEE_BPS as f64 / FEE_DENOM as f64);
+    assert_eq!(stats.fee_growth, Some(growth(1, 3)));
+    let apr = growth(1, 3) * SECONDS_PER_YEAR as f64 / (6 * DAY) as f64;
+    assert_eq!(stats.apr, Some(apr));
+    assert!(apr > 0.0);
+
+    // a window between samples opens at the state in force at its start
+    let stats = series.stats(7, 10).unwrap();
+    assert_eq!((stats.from_block, stats.to_block, stats.swaps), (5, 9, 1));
+
+    // "this week": from the last state at or before the timestamp to the latest sample
+    let stats = series.stats_since(1_000 + 2 * DAY).unwrap();
+    assert_eq!((stats.from_block, stats.to_block, stats.swaps), (5, 12, 2));
+
+    // a window before the pool existed opens at its first state
+    assert_eq!(series.stats(0, 5).unwrap().from_block, 3);
+    assert!(series.stats(0, 2).is_none());
+    assert_eq!(series.at_block(10).unwrap().block_num, 9);
+
+    // no time elapsed: growth but no APR
+    let stats = series.stats(9, 9).unwrap();
+    assert_eq!((stats.fee_growth, stats.apr), (Some(0.0), None));
+
+    // a pool without LP supply earns nothing per share
+    let empty = PoolSeries {
+        samples: vec![PoolSample {
+            lp_supply: 0,
+            value_per_share: 0.0,
+            ..series.samples[0]
+        }],
+    };
+    assert_eq!(empty.stats(0, 3).unwrap().fee_growth, None);
+}
+
+#[tokio::test]
+async fn series_is_rebuilt_from_blocks_and_account_deltas() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let alice = builder.add_existing_wallet_with_assets(auth(), [])?.id();
+    let bob = builder.add_existing_wallet_with_assets(auth(), [])?.id();
+    let build = add_pool(&mut builder, &faucet_x, &faucet_y)?;
+    let amm_id = build.account.id();
+    let x = |amount| FungibleAsset::new(faucet_x.id(), amount);
+    let y = |amount| FungibleAsset::new(faucet_y.id(), amount);
+
+    // alice deposits, bob swaps X in, then Y and X in within one transaction (the block's
+    // delta nets them, the volume must not), then alice withdraws half her LP
+    let mut pool = PoolState::new(FEE_BPS);
+    // the pool after each block, genesis first
+    let mut states = vec![pool];
+    let mut blocks: Vec<Vec<(Note, Note)>> = Vec::new();
+    let mut n = 0;
+    let mut next = |target| {
+        n += 1;
+        (PayoutInfo::new(target, serial(1_000 + n)), serial(n))
+    };
+
+    let lp = pool.apply_add_liquidity(1_000_000, 4_000_000)?;
+    let (payout, note_serial) = next(alice);
+    let note = create_add_liquidity_note(
+        alice,
+        amm_id,
+        x(1_000_000)?,
+        y(4_000_000)?,
+        lp,
+        &payout,
+        build.add_liquidity_note_script.clone(),
+        note_serial,
+    )?;
+    blocks.push(vec![(
+        note,
+        payout.expected_note(amm_id, vec![FungibleAsset::new(amm_id, lp)?])?,
+    )]);
+    states.push(pool);
+
+    for (block, amount_in, x_in) in [(1, 50_000, true), (2, 300_000, false), (2, 20_000, true)] {
+        let out = pool.apply_swap(amount_in, x_in)?;
+        let (asset_in, asset_out) = if x_in {
+            (x(amount_in)?, y(out)?)
+        } else {
+            (y(amount_in)?, x(out)?)
+        };
+        let (payout, note_serial) = next(bob);
+        let note = create_swap_note(
+            bob,
+            amm_id,
+            asset_in,
+            asset_out.faucet_id(),
+            out,
+            &payout,
+            build.swap_note_script.clone(),
+            note_serial,
+        )?;
+        let expected = payout.expected_note(amm_id, vec![asset_out])?;
+        if blocks.len() == block {
+            blocks.push(Vec::new());
+            states.push(pool);
+        }
+        blocks[block].push((note, expected));
+        states[block + 1] = pool;
+    }
+
+    let (ax, ay) = pool.apply_remove_liquidity(lp / 2)?;
+    let (payout, note_serial) = next(alice);
+    let note = create_remove_liquidity_note(
+        alice,
+        amm_id,
+        lp / 2,
+        ax,
+        ay,
+        &payout,
+        build.remove_liquidity_note_script.clone(),
+        note_serial,
+    )?;
+    blocks.push(vec![(
+        note,
+        payout.expected_note(amm_id, vec![x(ax)?, y(ay)?])?,
+    )]);
+    states.push(pool);
+
+    add_notes(&mut builder, blocks.iter().flatten().map(|(note, _)| note));
+    let mut mock_chain = builder.build()?;
+    let genesis_time = mock_chain.latest_block_header().timestamp();
+
+    // one block a day
+    for (day, block) in blocks.iter().enumerate() {
+        let note_ids: Vec<_> = block.iter().map(|(note, _)| note.id()).collect();
+        let payouts = block
+            .iter()
+            .map(|(_, payout)| RawOutputNote::Full(payout.clone()))
+            .collect();
+        let executed = mock_chain
+            .build_tx_context(amm_id, &note_ids, &[])?
+            .extend_expected_output_notes(payouts)
+            .build()?
+            .execute()
+            .await?;
+        mock_chain.add_pending_executed_transaction(&executed)?;
+        mock_chain.prove_next_block_at(genesis_time + (day as u32 + 1) * DAY)?;
+    }
+    let final_account = mock_chain.committed_account(amm_id)?.clone();
+
+    // applying a block without the notes it consumed is refused
+    let manifest = AmmManifest::from_build(&build);
+    let mut analytics = PoolAnalytics::new(&manifest);
+    let err = analytics
+        .apply_block(&mock_chain.proven_blocks()[1])
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("consumed an unknown note"),
+        "{err}"
+    );
+
+    let node = MockNode::new(mock_chain);
+    let series = fetch_pool_series(&node, &manifest, node.chain_tip()).await?;
+
+    // genesis (empty pool) plus one sample per AMM block
+    assert_eq!(series.samples.len(), states.len());
+    for (i, (sample, state)) in series.samples.iter().zip(states).enumerate() {
+        assert_eq!(sample.block_num, i as u32, "sample {i}");
+        assert_eq!(
+            sample.timestamp,
+            genesis_time + i as u32 * DAY,
+            "sample {i}"
+        );
+        assert_eq!(
+            (sample.reserve_x, sample.reserve_y, sample.lp_supply),
+            (state.reserve_x, state.reserve_y, state.lp_supply),
+            "sample {i}"
+        );
+        assert_eq!(sample.fee_bps, FEE_BPS, "sample {i}");
+    }
+    assert_eq!(
+        PoolState::from_account(&final_account, build.pool_x(), build.pool_y())?,
+        pool
+    );
+
+    let volumes: Vec<_> = series
+        .samples
+        .iter()
+        .map(|s| (s.swaps, s.volume_x, s.volume_y))
+        .collect();
+    assert_eq!(
+        volumes,
+        vec![
+            (0, 0, 0),
+            (0, 0, 0),
+            (1, 50_000, 0),
+            (2, 20_000, 300_000),
+            (0, 0, 0)
+        ]
+    );
+
+    // fees raise the value of a share; the withdrawal rounds in the pool's favour
+    let shares: Vec<f64> = series.samples.iter().map(|s| s.value_per_share).collect();
+    assert!(shares[2] > shares[1] && shares[3] > shares[2] && shares[4] >= shares[3]);
+    assert_eq!(series.samples[1].price, 4.0);
+
+    let stats = series.stats(1, 4).unwrap();
+    assert_eq!(
+        (stats.swaps, stats.volume_x, stats.volume_y),
+        (3, 70_000, 300_000)
+    );
+    let growth = shares[4] / shares[1] - 1.0;
+    assert_eq!(stats.fee_growth, Some(growth));
+    assert_eq!(
+        stats.apr,
+        Some(growth * SECONDS_PER_YEAR as f64 / (3 * DAY) as f64)
+    );
+    // sqrt(k) grows by about half the fee on each swap's input side
+    let fee_share = (70_000.0 / 1_000_000.0 + 300_000.0 / 4_000_000.0) * FEE_BPS as f64
+        / FEE_DENOM as f64
+        / 2.0;
+    assert!(
+        (growth - fee_share).abs() < fee_share * 0.1,
+        "{growth} vs {fee_share}"
+    );
+
+    assert!(series.to_json()?.contains("\"value_per_share\""));
+    Ok(())
+}
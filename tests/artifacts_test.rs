@@ -0,0 +1,100 @@
+//! The artifacts `build.rs` produces are the MASM sources assembled at runtime, byte for byte,
+//! and the generated root constants match them.
+
+use anyhow::Result;
+use miden_amm::{
+    artifacts::{
+        ADD_LIQUIDITY_NOTE_SCRIPT_ROOT, DEPLOY_SCRIPT_ROOT, EXECUTE_ALLOWLIST_NOTE_SCRIPT_ROOT,
+        PROPOSE_ALLOWLIST_NOTE_SCRIPT_ROOT, REMOVE_LIQUIDITY_NOTE_SCRIPT_ROOT,
+        SKIM_NOTE_SCRIPT_ROOT, SWAP_NOTE_SCRIPT_ROOT, SYNC_NOTE_SCRIPT_ROOT,
+        add_liquidity_note_script, amm_component_code, deploy_tx_script,
+        execute_allowlist_note_script, governance_component_code, liquidity_component_code,
+        lp_metadata_component_code, propose_allowlist_note_script, remove_liquidity_note_script,
+        skim_note_script, swap_note_script, sync_note_script,
+    },
+    common::{
+        ADD_LIQUIDITY_NOTE_CODE, AMM_CONTRACT_NS, DEPLOY_SCRIPT_CODE, EXECUTE_ALLOWLIST_NOTE_CODE,
+        GOVERNANCE_CODE, GOVERNANCE_NS, LIQUIDITY_CONTRACT_NS, LP_METADATA_CODE, LP_METADATA_NS,
+        PROPOSE_ALLOWLIST_NOTE_CODE, REMOVE_LIQUIDITY_NOTE_CODE, SKIM_NOTE_CODE, SWAP_NOTE_CODE,
+        SYNC_NOTE_CODE, amm_code, liquidity_code,
+    },
+};
+use miden_client::{Serializable, Word, assembly::CodeBuilder};
+
+#[test]
+fn artifacts_match_runtime_assembly() -> Result<()> {
+    let amm = amm_code();
+    let liquidity = liquidity_code();
+
+    let swap = CodeBuilder::new()
+        .with_linked_module(AMM_CONTRACT_NS, amm.as_str())?
+        .compile_note_script(SWAP_NOTE_CODE)?;
+    let add = CodeBuilder::new()
+        .with_linked_module(LIQUIDITY_CONTRACT_NS, liquidity.as_str())?
+        .compile_note_script(ADD_LIQUIDITY_NOTE_CODE)?;
+    let remove = CodeBuilder::new()
+        .with_linked_module(LIQUIDITY_CONTRACT_NS, liquidity.as_str())?
+        .compile_note_script(REMOVE_LIQUIDITY_NOTE_CODE)?;
+    let sync = CodeBuilder::new()
+        .with_linked_module(LIQUIDITY_CONTRACT_NS, liquidity.as_str())?
+        .compile_note_script(SYNC_NOTE_CODE)?;
+    let skim = CodeBuilder::new()
+        .with_linked_module(LIQUIDITY_CONTRACT_NS, liquidity.as_str())?
+        .compile_note_script(SKIM_NOTE_CODE)?;
+    let propose = CodeBuilder::new()
+        .with_linked_module(GOVERNANCE_NS, GOVERNANCE_CODE)?
+        .compile_note_script(PROPOSE_ALLOWLIST_NOTE_CODE)?;
+    let execute = CodeBuilder::new()
+        .with_linked_module(GOVERNANCE_NS, GOVERNANCE_CODE)?
+        .compile_note_script(EXECUTE_ALLOWLIST_NOTE_CODE)?;
+    let deploy = CodeBuilder::new()
+        .with_linked_module(AMM_CONTRACT_NS, amm.as_str())?
+        .compile_tx_script(DEPLOY_SCRIPT_CODE)?;
+    let amm_component = CodeBuilder::new().compile_component_code(AMM_CONTRACT_NS, amm.as_str())?;
+    let liquidity_component =
+        CodeBuilder::new().compile_component_code(LIQUIDITY_CONTRACT_NS, liquidity.as_str())?;
+    let lp_metadata =
+        CodeBuilder::new().compile_component_code(LP_METADATA_NS, LP_METADATA_CODE)?;
+    let governance = CodeBuilder::new().compile_component_code(GOVERNANCE_NS, GOVERNANCE_CODE)?;
+
+    assert_eq!(swap_note_script().root(), swap.root());
+    assert_eq!(add_liquidity_note_script().root(), add.root());
+    assert_eq!(remove_liquidity_note_script().root(), remove.root());
+    assert_eq!(sync_note_script().root(), sync.root());
+    assert_eq!(skim_note_script().root(), skim.root());
+    assert_eq!(propose_allowlist_note_script().root(), propose.root());
+    assert_eq!(execute_allowlist_note_script().root(), execute.root());
+    assert_eq!(deploy_tx_script().root(), deploy.root());
+    assert_eq!(
+        amm_component_code().as_library().to_bytes(),
+        amm_component.as_library().to_bytes()
+    );
+    assert_eq!(
+        liquidity_component_code().as_library().to_bytes(),
+        liquidity_component.as_library().to_bytes()
+    );
+    assert_eq!(
+        lp_metadata_component_code().as_library().to_bytes(),
+        lp_metadata.as_library().to_bytes()
+    );
+    assert_eq!(
+        governance_component_code().as_library().to_bytes(),
+        governance.as_library().to_bytes()
+    );
+
+    assert_eq!(Word::from(swap.root()), SWAP_NOTE_SCRIPT_ROOT);
+    assert_eq!(Word::from(add.root()), ADD_LIQUIDITY_NOTE_SCRIPT_ROOT);
+    assert_eq!(Word::from(remove.root()), REMOVE_LIQUIDITY_NOTE_SCRIPT_ROOT);
+    assert_eq!(Word::from(sync.root()), SYNC_NOTE_SCRIPT_ROOT);
+    assert_eq!(Word::from(skim.root()), SKIM_NOTE_SCRIPT_ROOT);
+    assert_eq!(
+        Word::from(propose.root()),
+        PROPOSE_ALLOWLIST_NOTE_SCRIPT_ROOT
+    );
+    assert_eq!(
+        Word::from(execute.root()),
+        EXECUTE_ALLOWLIST_NOTE_SCRIPT_ROOT
+    );
+    assert_eq!(Word::from(deploy.root()), DEPLOY_SCRIPT_ROOT);
+    Ok(())
+}
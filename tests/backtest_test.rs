@@ -0,0 +1,536 @@
+//! The backtesting simulator: trade log parsing, the LP books (donations, fee attribution,
+//! impermanent loss) and, on sampled traces, agreement with the AMM executed on MockChain.
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use common::{FEE_BPS, add_pool, auth, serial};
+use miden_amm::{
+    backtest::{SimPool, Trade, TradeOutcome, backtest},
+    common::{
+        MIN_LIQUIDITY, PayoutInfo, PoolState, create_add_liquidity_note,
+        create_remove_liquidity_note, create_swap_note,
+    },
+    errors::AmmError,
+    position::LpPosition,
+};
+use miden_client::{
+    account::AccountId, asset::FungibleAsset, note::Note, transaction::RawOutputNote,
+};
+use miden_testing::MockChain;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+fn deposit(account: &str, amount_x: u64, amount_y: u64) -> Trade {
+    Trade::Deposit {
+        account: account.into(),
+        amount_x,
+        amount_y,
+    }
+}
+
+fn swap(account: &str, amount_in: u64, x_in: bool) -> Trade {
+    Trade::Swap {
+        account: account.into(),
+        amount_in,
+        x_in,
+    }
+}
+
+fn withdraw(account: &str, lp_amount: u64) -> Trade {
+    Trade::Withdraw {
+        account: account.into(),
+        lp_amount,
+    }
+}
+
+// =================================================================================================
+// TRADE LOGS
+// =================================================================================================
+
+#[test]
+fn csv_and_json_logs_parse_to_the_same_trades() -> Result<()> {
+    let csv = "op,account,amount_x,amount_y,lp_amount\n\
+               deposit,alice,100000,400000,\n\
+               swap_x,bob,5000,,\n\
+               swap_y,bob,,20000,\n\
+               withdraw,alice,,,50000\n";
+    let json = r#"[
+        {"op": "deposit", "account": "alice", "amount_x": 100000, "amount_y": 400000},
+        {"op": "swap_x", "account": "bob", "amount_x": 5000},
+        {"op": "swap_y", "account": "bob", "amount_y": 20000},
+        {"op": "withdraw", "account": "alice", "lp_amount": 50000}
+    ]"#;
+    let expected = vec![
+        deposit("alice", 100_000, 400_000),
+        swap("bob", 5_000, true),
+        swap("bob", 20_000, false),
+        withdraw("alice", 50_000),
+    ];
+    assert_eq!(Trade::read_csv(csv.as_bytes())?, expected);
+    assert_eq!(Trade::read_json(json.as_bytes())?, expected);
+
+    let dir = std::env::temp_dir().join(format!("amm-backtest-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("log.csv"), csv)?;
+    std::fs::write(dir.join("log.json"), json)?;
+    assert_eq!(Trade::read_log_file(dir.join("log.csv"))?, expected);
+    assert_eq!(Trade::read_log_file(dir.join("log.json"))?, expected);
+    assert!(Trade::read_log_file(dir.join("log.txt")).is_err());
+    std::fs::remove_dir_all(&dir)?;
+
+    // amounts that do not belong to the op, missing or zero amounts, and unknown ops, are
+    // rejected
+    for bad in [
+        "op,account,amount_x,amount_y,lp_amount\nswap_x,bob,5000,1,\n",
+        "op,account,amount_x,amount_y,lp_amount\nwithdraw,bob,1,,5\n",
+        "op,account,amount_x,amount_y,lp_amount\nflash_loan,bob,1,,\n",
+        "op,account,amount_x,amount_y,lp_amount\ndeposit,bob,-1,5,\n",
+        "op,account,amount_x,amount_y,lp_amount\nswap_x,bob,,,\n",
+        "op,account,amount_x,amount_y,lp_amount\nswap_y,bob,,0,\n",
+        "op,account,amount_x,amount_y,lp_amount\ndeposit,bob,5,,\n",
+        "op,account,amount_x,amount_y,lp_amount\nwithdraw,bob,,,0\n",
+    ] {
+        assert!(Trade::read_csv(bad.as_bytes()).is_err(), "{bad}");
+    }
+    Ok(())
+}
+
+// =================================================================================================
+// LP BOOKS
+// =================================================================================================
+
+#[test]
+fn deposits_lock_minimum_liquidity_and_donate_the_excess() -> Result<()> {
+    let mut pool = SimPool::new(FEE_BPS);
+
+    // first deposit: sqrt(100_000 * 400_000) = 200_000, of which MIN_LIQUIDITY is locked
+    let first = pool.apply(&deposit("alice", 100_000, 400_000))?;
+    assert_eq!(
+        first,
+        TradeOutcome::Deposited {
+            lp_minted: 200_000 - MIN_LIQUIDITY,
+            donated_x: 0,
+            donated_y: 0,
+        }
+    );
+
+    // 50_000 X mint 100_000 LP; those redeem for 200_000 Y, so the other 200_000 Y are a gift
+    let second = pool.apply(&deposit("bob", 50_000, 400_000))?;
+    assert_eq!(
+        second,
+        TradeOutcome::Deposited {
+            lp_minted: 100_000,
+            donated_x: 0,
+            donated_y: 200_000,
+        }
+    );
+    assert_eq!(
+        pool.state(),
+        PoolState {
+            reserve_x: 150_000,
+            reserve_y: 800_000,
+            lp_supply: 300_000,
+            fee_bps: FEE_BPS,
+        }
+    );
+
+    // alice got the gift pro rata: 199_000 / 300_000 of the donated Y
+    let report = pool.report();
+    let alice = report.lp("alice").context("alice has a position")?;
+    assert_eq!((alice.redeemable_x, alice.redeemable_y), (99_500, 530_666));
+    let bob = report.lp("bob").context("bob has a position")?;
+    assert_eq!(bob.position.donated_y, 200_000);
+    assert!(bob.pnl_y < 0.0 && alice.pnl_y > 0.0);
+    Ok(())
+}
+
+#[test]
+fn swap_fees_are_split_by_lp_share() -> Result<()> {
+    let trades = [
+        deposit("alice", 1_000_000, 1_000_000),
+        deposit("bob", 1_000_000, 1_000_000),
+        swap("carol", 100_000, true),
+        swap("carol", 90_000, false),
+        swap("carol", 50_000, true),
+        swap("carol", 45_000, false),
+    ];
+    let report = backtest(FEE_BPS, &trades)?;
+    assert!(report.rejected.is_empty());
+    assert_eq!((report.volume_x, report.volume_y), (150_000, 135_000));
+    assert!((report.fees_x - 450.0).abs() < 1e-9 && (report.fees_y - 405.0).abs() < 1e-9);
+
+    let (alice, bob) = (
+        report.lp("alice").context("alice")?,
+        report.lp("bob").context("bob")?,
+    );
+    // an LP's fee income is what LpPosition reports for the same deposit
+    let mut after_bob = SimPool::new(FEE_BPS);
+    after_bob.replay(&trades[..2])?;
+    let final_state = PoolState {
+        reserve_x: report.reserve_x,
+        reserve_y: report.reserve_y,
+        lp_supply: report.lp_supply,
+        fee_bps: FEE_BPS,
+    };
+    let value = LpPosition::new(
+        1_000_000,
+        1_000_000,
+        bob.position.lp_balance,
+        &after_bob.state(),
+    )
+    .value(&final_state)?;
+    assert!((bob.position.fees_x / value.fees_x - 1.0).abs() < 1e-12);
+    assert!((bob.position.fees_y / value.fees_y - 1.0).abs() < 1e-12);
+
+    // both entered at the same value per share, so they split by LP balance; together they
+    // earn about the LP tokens' share of the nominal fees (the locked minimum liquidity
+    // earns the rest), which the price path shifts slightly
+    let ratio = alice.position.lp_balance as f64 / bob.position.lp_balance as f64;
+    assert!((alice.position.fees_y / bob.position.fees_y - ratio).abs() < 1e-5);
+    let lp_share = 1.0 - MIN_LIQUIDITY as f64 / report.lp_supply as f64;
+    let nominal = (report.fees_x * report.spot_price + report.fees_y) * lp_share;
+    let earned = alice.fees_value_y + bob.fees_value_y;
+    assert!(
+        (earned / nominal - 1.0).abs() < 0.05,
+        "{earned} vs {nominal}"
+    );
+    assert!(report.lp("carol").is_none(), "swappers have no LP book");
+
+    // the swaps roughly undo each other, so the fees outweigh the impermanent loss; alice
+    // also paid for the locked minimum liquidity and stays below holding
+    assert!(bob.fees_value_y > -bob.impermanent_loss_y);
+    assert!(bob.value_y > bob.hold_value_y);
+    assert!(alice.value_y < alice.hold_value_y);
+    Ok(())
+}
+
+#[test]
+fn impermanent_loss_follows_the_price_move() -> Result<()> {
+    // no fee, so the LP's result against holding is the impermanent loss alone
+    let mut pool = SimPool::new(0);
+    pool.apply(&deposit("alice", 1_000_000_000, 1_000_000_000))?;
+    pool.apply(&swap("trader", 1_000_000_000, false))?;
+
+    // the price of X quadrupled: IL = 2 * sqrt(4) / (1 + 4) - 1 = -20% of holding
+    let report = pool.report();
+    assert!((report.spot_price - 4.0).abs() < 1e-6);
+    let alice = report.lp("alice").context("alice")?;
+    assert_eq!(alice.fees_value_y, 0.0);
+    let il = alice.impermanent_loss_y / alice.hold_value_y;
+    assert!((il + 0.2).abs() < 1e-4, "IL {il}");
+
+    // withdrawing realizes it: the book moves from redeemable to withdrawn
+    let lp = alice.position.lp_balance;
+    pool.apply(&withdraw("alice", lp))?;
+    let after = pool.report();
+    let alice_after = after.lp("alice").context("alice")?;
+    assert_eq!((alice_after.redeemable_x, alice_after.redeemable_y), (0, 0));
+    assert_eq!(
+        (
+            alice_after.position.withdrawn_x,
+            alice_after.position.withdrawn_y
+        ),
+        (alice.redeemable_x as u128, alice.redeemable_y as u128)
+    );
+    assert!((alice_after.impermanent_loss_y - alice.impermanent_loss_y).abs() < 1.0);
+    Ok(())
+}
+
+#[test]
+fn rejected_trades_change_nothing() -> Result<()> {
+    let mut pool = SimPool::new(FEE_BPS);
+    assert_eq!(
+        pool.apply(&deposit("alice", 1_000, 1_000))?,
+        TradeOutcome::Rejected(AmmError::InsufficientInitialLiquidity)
+    );
+    assert_eq!(
+        pool.apply(&swap("bob", 1_000, true))?,
+        TradeOutcome::Rejected(AmmError::ZeroOutput)
+    );
+    // a swap of nothing into the empty pool is rejected too, not a division by zero
+    assert_eq!(
+        pool.apply(&swap("bob", 0, false))?,
+        TradeOutcome::Rejected(AmmError::ZeroOutput)
+    );
+    pool.apply(&deposit("alice", 100_000, 400_000))?;
+    let state = pool.state();
+    assert_eq!(
+        pool.apply(&deposit("bob", 1, 1))?,
+        TradeOutcome::Rejected(AmmError::ZeroLpMinted)
+    );
+    assert_eq!(
+        pool.apply(&withdraw("alice", 1))?,
+        TradeOutcome::Rejected(AmmError::ZeroPayout)
+    );
+    assert_eq!(pool.state(), state);
+
+    let report = pool.report();
+    assert_eq!(report.trades, 6);
+    let rejected: Vec<_> = report.rejected.iter().map(|r| r.index).collect();
+    assert_eq!(rejected, vec![0, 1, 2, 4, 5]);
+    assert_eq!(report.rejected[1].error, AmmError::ZeroOutput.to_string());
+    assert!(report.lp("bob").is_none());
+
+    // a log that withdraws more than an account holds is malformed, not a rejection
+    assert!(pool.apply(&withdraw("bob", 1)).is_err());
+    assert!(pool.apply(&withdraw("alice", 200_000)).is_err());
+    assert!(report.to_json()?.contains("\"impermanent_loss_y\""));
+    Ok(())
+}
+
+#[test]
+fn overflowing_trades_are_rejected() -> Result<()> {
+    // bob's deposit mints 2^60 * S on the X side, carol's swap overflows the X reserve
+    let csv = format!(
+        "op,account,amount_x,amount_y,lp_amount\n\
+         deposit,alice,1,4000000,\n\
+         deposit,bob,{},4000000,\n\
+         swap_x,carol,{},,\n",
+        1u64 << 60,
+        u64::MAX
+    );
+    let trades = Trade::read_csv(csv.as_bytes())?;
+    let mut pool = SimPool::new(FEE_BPS);
+    let outcomes = pool.replay(&trades)?;
+    assert_eq!(
+        outcomes[1..],
+        [
+            TradeOutcome::Rejected(AmmError::ValueOverflow),
+            TradeOutcome::Rejected(AmmError::ValueOverflow)
+        ]
+    );
+    assert_eq!(
+        (pool.state().reserve_x, pool.state().reserve_y),
+        (1, 4_000_000)
+    );
+
+    let report = backtest(FEE_BPS, &trades)?;
+    let rejected: Vec<_> = report.rejected.iter().map(|r| r.index).collect();
+    assert_eq!(rejected, vec![1, 2]);
+    assert_eq!(
+        report.rejected[0].error,
+        AmmError::ValueOverflow.to_string()
+    );
+    Ok(())
+}
+
+// =================================================================================================
+// MOCKCHAIN VALIDATION
+// =================================================================================================
+
+const ACCOUNTS: [&str; 3] = ["alice", "bob", "carol"];
+
+/// A random trace: an opening deposit followed by `len` trades, withdrawals drawn from the
+/// LP balances the simulator reports at that point.
+fn sample_trace(rng: &mut StdRng, len: usize) -> Result<Vec<Trade>> {
+    let mut pool = SimPool::new(FEE_BPS);
+    let mut trades = vec![deposit(
+        ACCOUNTS[rng.random_range(0..ACCOUNTS.len())],
+        rng.random_range(10_000..1_000_000),
+        rng.random_range(10_000..1_000_000),
+    )];
+    pool.apply(&trades[0])?;
+    while trades.len() <= len {
+        let account = ACCOUNTS[rng.random_range(0..ACCOUNTS.len())];
+        let amount = |rng: &mut StdRng| {
+            if rng.random_bool(0.2) {
+                rng.random_range(1..100)
+            } else {
+                rng.random_range(1..500_000)
+            }
+        };
+        let trade = match rng.random_range(0..4) {
+            0 => deposit(account, amount(rng), amount(rng)),
+            1 | 2 => swap(account, amount(rng), rng.random_bool(0.5)),
+            _ => {
+                let held = pool.position(account).map_or(0, |p| p.lp_balance);
+                if held == 0 {
+                    continue;
+                }
+                withdraw(account, rng.random_range(1..=held))
+            }
+        };
+        pool.apply(&trade)?;
+        trades.push(trade);
+    }
+    Ok(trades)
+}
+
+/// The note for `trade` with the simulator's results as exact slippage bounds, and the
+/// payout the pool must send for it (`None` for a rejected trade).
+fn trade_note(
+    trade: &Trade,
+    outcome: TradeOutcome,
+    amm_id: AccountId,
+    sender: AccountId,
+    faucets: (AccountId, AccountId),
+    scripts: &miden_amm::common::AmmBuild,
+    n: u64,
+) -> Result<(Note, Option<Note>)> {
+    let x = |amount| FungibleAsset::new(faucets.0, amount);
+    let y = |amount| FungibleAsset::new(faucets.1, amount);
+    let payout = PayoutInfo::new(sender, serial(1000 + n));
+    let (note, assets) = match (trade, outcome) {
+        (
+            Trade::Swap {
+                amount_in, x_in, ..
+            },
+            outcome,
+        ) => {
+            let (asset_in, faucet_out) = if *x_in {
+                (x(*amount_in)?, faucets.1)
+            } else {
+                (y(*amount_in)?, faucets.0)
+            };
+            let amount_out = match outcome {
+                TradeOutcome::Swapped { amount_out } => Some(amount_out),
+                _ => None,
+            };
+            let note = create_swap_note(
+                sender,
+                amm_id,
+                asset_in,
+                faucet_out,
+                amount_out.unwrap_or(0),
+                &payout,
+                scripts.swap_note_script.clone(),
+                serial(n),
+            )?;
+            let assets = amount_out
+                .map(|out| FungibleAsset::new(faucet_out, out))
+                .transpose()?;
+            (note, assets.map(|out| vec![out]))
+        }
+        (
+            Trade::Deposit {
+                amount_x, amount_y, ..
+            },
+            outcome,
+        ) => {
+            let lp_minted = match outcome {
+                TradeOutcome::Deposited { lp_minted, .. } => Some(lp_minted),
+                _ => None,
+            };
+            let note = create_add_liquidity_note(
+                sender,
+                amm_id,
+                x(*amount_x)?,
+                y(*amount_y)?,
+                lp_minted.unwrap_or(0),
+                &payout,
+                scripts.add_liquidity_note_script.clone(),
+                serial(n),
+            )?;
+            let assets = lp_minted
+                .map(|lp| FungibleAsset::new(amm_id, lp))
+                .transpose()?;
+            (note, assets.map(|lp| vec![lp]))
+        }
+        (Trade::Withdraw { lp_amount, .. }, outcome) => {
+            let amounts = match outcome {
+                TradeOutcome::Withdrawn { amount_x, amount_y } => Some((amount_x, amount_y)),
+                _ => None,
+            };
+            let (min_x, min_y) = amounts.unwrap_or((0, 0));
+            let note = create_remove_liquidity_note(
+                sender,
+                amm_id,
+                *lp_amount,
+                min_x,
+                min_y,
+                &payout,
+                scripts.remove_liquidity_note_script.clone(),
+                serial(n),
+            )?;
+            let assets = amounts
+                .map(|(ax, ay)| Ok::<_, anyhow::Error>(vec![x(ax)?, y(ay)?]))
+                .transpose()?;
+            (note, assets)
+        }
+    };
+    let payout = assets
+        .map(|assets| payout.expected_note(amm_id, assets))
+        .transpose()?;
+    Ok((note, payout))
+}
+
+/// Replays `trades` on MockChain and checks every payout and pool state against the
+/// simulator.
+async fn replay_on_chain(trades: &[Trade]) -> Result<()> {
+    let mut builder = MockChain::builder();
+    let faucet_x = builder.add_existing_basic_faucet(auth(), "TKX", 1_000_000_000_000, Some(8))?;
+    let faucet_y = builder.add_existing_basic_faucet(auth(), "TKY", 1_000_000_000_000, Some(8))?;
+    let mut wallets = BTreeMap::new();
+    for account in ACCOUNTS {
+        wallets.insert(
+            account,
+            builder.add_existing_wallet_with_assets(auth(), [])?.id(),
+        );
+    }
+    let build = add_pool(&mut builder, &faucet_x, &faucet_y)?;
+    let amm_id = build.account.id();
+
+    let mut pool = SimPool::new(FEE_BPS);
+    let mut steps = Vec::new();
+    for (i, trade) in trades.iter().enumerate() {
+        let outcome = pool.apply(trade)?;
+        let (note, payout) = trade_note(
+            trade,
+            outcome,
+            amm_id,
+            wallets[trade.account()],
+            (faucet_x.id(), faucet_y.id()),
+            &build,
+            i as u64 + 1,
+        )?;
+        builder.add_output_note(RawOutputNote::Full(note.clone()));
+        steps.push((note, payout, outcome, pool.state()));
+    }
+    let mut mock_chain = builder.build()?;
+
+    for (i, (note, payout, outcome, state)) in steps.into_iter().enumerate() {
+        let result = mock_chain
+            .build_tx_context(amm_id, &[note.id()], &[])?
+            .extend_expected_output_notes(payout.iter().cloned().map(RawOutputNote::Full).collect())
+            .build()?
+            .execute()
+            .await;
+        match (payout, outcome) {
+            (Some(payout), _) => {
+                let executed = result.with_context(|| format!("trade {i} ({outcome:?})"))?;
+                let produced: Vec<_> = executed.output_notes().iter().map(|n| n.id()).collect();
+                assert_eq!(produced, vec![payout.id()], "trade {i}");
+                mock_chain.add_pending_executed_transaction(&executed)?;
+                mock_chain.prove_next_block()?;
+            }
+            (None, TradeOutcome::Rejected(expected)) => {
+                let err = format!(
+                    "{:#}",
+                    anyhow::Error::from(result.err().context("rejected")?)
+                );
+                assert_eq!(AmmError::from_error_text(&err), Some(expected), "trade {i}");
+            }
+            (None, outcome) => panic!("trade {i}: no payout for {outcome:?}"),
+        }
+        let on_chain = PoolState::from_account(
+            mock_chain.committed_account(amm_id)?,
+            build.pool_x(),
+            build.pool_y(),
+        )?;
+        assert_eq!(on_chain, state, "trade {i}");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn sampled_traces_match_mock_chain() -> Result<()> {
+    for seed in [1, 2, 3] {
+        let trace = sample_trace(&mut StdRng::seed_from_u64(seed), 8)?;
+        replay_on_chain(&trace)
+            .await
+            .with_context(|| format!("trace {seed}: {trace:?}"))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,298 @@
+//! Cycle benchmarks: the pool notes (deposits, swaps, withdrawal, skim, sync) executed on
+//! MockChain, and the math procedures executed on the VM directly. The report is written to
+//! `target/amm-bench/cycles.json` and compared with the committed `bench/cycles_baseline.json`;
+//! a metric that grew by more than `AMM_BENCH_THRESHOLD_PCT` percent (default 5) fails the test.
+//!
+//! After an intended change, rerun with `AMM_BENCH_UPDATE=1` to rewrite the baseline.
+
+mod common;
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use common::{
+    FEE_BPS, VmProcedure, add_notes, add_pair_faucets, auth, liquidity_with_public_math, serial,
+    with_donation,
+};
+use miden_amm::{
+    bench::{CycleReport, DEFAULT_REGRESSION_THRESHOLD_PCT, MetricChange},
+    common::{
+        AMM_CONTRACT_NS, LIQUIDITY_CONTRACT_NS, PayoutInfo, amm_code, build_amm_account,
+        create_add_liquidity_note, create_remove_liquidity_note, create_skim_note,
+        create_swap_note, create_sync_note, quote_initial_lp,
+    },
+};
+use miden_client::{asset::FungibleAsset, note::Note};
+use miden_testing::MockChain;
+
+fn baseline_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("bench/cycles_baseline.json")
+}
+
+fn report_path() -> PathBuf {
+    std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target"))
+        .join("amm-bench/cycles.json")
+}
+
+// =================================================================================================
+// TRANSACTIONS
+// =================================================================================================
+
+/// Executes every note type against one pool, in an order in which each succeeds, and
+/// records each transaction.
+async fn bench_transactions(report: &mut CycleReport) -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let x = |amount| FungibleAsset::new(faucet_x.id(), amount);
+    let y = |amount| FungibleAsset::new(faucet_y.id(), amount);
+    let alice = builder.add_existing_wallet_with_assets(auth(), [])?.id();
+
+    let build = build_amm_account([7u8; 32], faucet_x.id(), faucet_y.id(), FEE_BPS, true)?;
+    let amm_id = build.account.id();
+    // a donation, so a skim has something to pay
+    builder.add_account(with_donation(&build, &[x(50_000)?.into()])?)?;
+
+    let payout = |n: u64| PayoutInfo::new(alice, serial(1000 + n));
+    let (lp_minted, _) = quote_initial_lp(1_000_000, 4_000_000);
+    let notes: Vec<(&str, Note)> = vec![
+        (
+            "first_deposit",
+            create_add_liquidity_note(
+                alice,
+                amm_id,
+                x(1_000_000)?,
+                y(4_000_000)?,
+                1,
+                &payout(1),
+                build.add_liquidity_note_script.clone(),
+                serial(1),
+            )?,
+        ),
+        (
+            "followup_deposit",
+            create_add_liquidity_note(
+                alice,
+                amm_id,
+                x(500_000)?,
+                y(2_000_000)?,
+                1,
+                &payout(2),
+                build.add_liquidity_note_script.clone(),
+                serial(2),
+            )?,
+        ),
+        (
+            "swap_x_to_y",
+            create_swap_note(
+                alice,
+                amm_id,
+                x(100_000)?,
+                faucet_y.id(),
+                1,
+                &payout(3),
+                build.swap_note_script.clone(),
+                serial(3),
+            )?,
+        ),
+        (
+            "swap_y_to_x",
+            create_swap_note(
+                alice,
+                amm_id,
+                y(400_000)?,
+                faucet_x.id(),
+                1,
+                &payout(4),
+                build.swap_note_script.clone(),
+                serial(4),
+            )?,
+        ),
+        (
+            "withdraw",
+            create_remove_liquidity_note(
+                alice,
+                amm_id,
+                lp_minted / 2,
+                1,
+                1,
+                &payout(5),
+                build.remove_liquidity_note_script.clone(),
+                serial(5),
+            )?,
+        ),
+        (
+            "skim",
+            create_skim_note(
+                alice,
+                amm_id,
+                &payout(6),
+                build.skim_note_script.clone(),
+                serial(6),
+            )?,
+        ),
+        (
+            "sync",
+            create_sync_note(alice, amm_id, build.sync_note_script.clone(), serial(7))?,
+        ),
+    ];
+    add_notes(&mut builder, notes.iter().map(|(_, note)| note));
+    let mut mock_chain = builder.build()?;
+
+    for (bench, note) in &notes {
+        let executed = mock_chain
+            .build_tx_context(amm_id, &[note.id()], &[])?
+            .build()?
+            .execute()
+            .await
+            .with_context(|| format!("executing {bench}"))?;
+        report.record_transaction(bench, &executed)?;
+        mock_chain.add_pending_executed_transaction(&executed)?;
+        mock_chain.prove_next_block()?;
+    }
+    Ok(())
+}
+
+// =================================================================================================
+// PROCEDURES
+// =================================================================================================
+
+fn u128_limbs(n: u128) -> [u64; 4] {
+    [0, 32, 64, 96].map(|shift| (n >> shift) as u32 as u64)
+}
+
+fn bench_procedures(report: &mut CycleReport) -> Result<()> {
+    let amm = amm_code();
+    let overhead = VmProcedure::cycle_counter(AMM_CONTRACT_NS, &amm, "")?.run(&[])?;
+    let liquidity = liquidity_with_public_math();
+    let max = FungibleAsset::MAX_AMOUNT.as_u64();
+    let cases: [(&str, &str, &str, &str, Vec<u64>); 6] = [
+        (
+            "get_amount_y_out",
+            AMM_CONTRACT_NS,
+            &amm,
+            "get_amount_y_out",
+            vec![100_000, 1_000_000, 4_000_000, FEE_BPS],
+        ),
+        (
+            "get_amount_y_out_max",
+            AMM_CONTRACT_NS,
+            &amm,
+            "get_amount_y_out",
+            vec![max - (1 << 61), 1 << 61, 1 << 62, FEE_BPS],
+        ),
+        (
+            "isqrt_u128_small",
+            LIQUIDITY_CONTRACT_NS,
+            &liquidity,
+            "isqrt_u128",
+            u128_limbs(4_000_000_000_000).to_vec(),
+        ),
+        (
+            "isqrt_u128_large",
+            LIQUIDITY_CONTRACT_NS,
+            &liquidity,
+            "isqrt_u128",
+            u128_limbs(max as u128 * max as u128).to_vec(),
+        ),
+        (
+            "mul_div_u64",
+            LIQUIDITY_CONTRACT_NS,
+            &liquidity,
+            "mul_div_u64",
+            vec![500_000, 2_000_000, 1_000_000],
+        ),
+        (
+            "mul_div_u64_max",
+            LIQUIDITY_CONTRACT_NS,
+            &liquidity,
+            "mul_div_u64",
+            vec![max, max - 1, max],
+        ),
+    ];
+    for (bench, namespace, source, procedure, inputs) in cases {
+        let cycles = VmProcedure::cycle_counter(namespace, source, procedure)?
+            .run(&inputs)
+            .with_context(|| format!("running {bench}"))?;
+        report.record(bench, "cycles", cycles - overhead);
+    }
+    Ok(())
+}
+
+// =================================================================================================
+// TESTS
+// =================================================================================================
+
+#[test]
+fn reports_compare_metric_by_metric() -> Result<()> {
+    let mut baseline = CycleReport::new();
+    baseline.record("swap", "total_cycles", 10_000);
+    baseline.record("swap", "tx_bytes", 4_000);
+    baseline.record("withdraw", "total_cycles", 20_000);
+
+    let mut current = CycleReport::from_json(&baseline.to_json()?)?;
+    assert_eq!(current, baseline);
+    current.record("swap", "total_cycles", 10_600);
+    current.record("swap", "tx_bytes", 4_100);
+    current.record("withdraw", "total_cycles", 18_000);
+    current.record("skim", "total_cycles", 30_000);
+
+    let regressions = current.regressions(&baseline, DEFAULT_REGRESSION_THRESHOLD_PCT);
+    assert_eq!(
+        regressions,
+        vec![MetricChange {
+            bench: "swap".into(),
+            metric: "total_cycles".into(),
+            baseline: 10_000,
+            current: 10_600,
+        }]
+    );
+    assert_eq!(
+        regressions[0].to_string(),
+        "swap.total_cycles: 10000 -> 10600 (+6.0%)"
+    );
+    assert!(current.regressions(&baseline, 10.0).is_empty());
+
+    let improvements = current.improvements(&baseline, DEFAULT_REGRESSION_THRESHOLD_PCT);
+    assert_eq!(improvements.len(), 1);
+    assert_eq!(improvements[0].bench, "withdraw");
+    Ok(())
+}
+
+#[tokio::test]
+async fn cycles_within_baseline() -> Result<()> {
+    let mut report = CycleReport::new();
+    bench_transactions(&mut report).await?;
+    bench_procedures(&mut report)?;
+    report.write_to_file(report_path())?;
+
+    // the first deposit runs the isqrt Newton loop on top of everything a follow-up does
+    let total = |bench| report.get(bench, "total_cycles").context("recorded");
+    assert!(total("first_deposit")? > total("followup_deposit")?);
+
+    if std::env::var_os("AMM_BENCH_UPDATE").is_some() {
+        return report.write_to_file(baseline_path());
+    }
+    let baseline = CycleReport::read_from_file(baseline_path())
+        .context("no baseline: run with AMM_BENCH_UPDATE=1 to record one")?;
+    let threshold = match std::env::var("AMM_BENCH_THRESHOLD_PCT") {
+        Ok(pct) => pct.parse().context("parsing AMM_BENCH_THRESHOLD_PCT")?,
+        Err(_) => DEFAULT_REGRESSION_THRESHOLD_PCT,
+    };
+    for improvement in report.improvements(&baseline, threshold) {
+        eprintln!("improved: {improvement}");
+    }
+    let regressions = report.regressions(&baseline, threshold);
+    anyhow::ensure!(
+        regressions.is_empty(),
+        "cycle regressions over {threshold}% (report in {}):\n{}",
+        report_path().display(),
+        regressions
+            .iter()
+            .map(|r| format!("  {r}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    Ok(())
+}
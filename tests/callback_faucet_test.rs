@@ -0,0 +1,333 @@
+//! A pool whose X side is a faucet with transfer-policy callbacks: the pool keys carry the
+//! callback flag, every pool transaction runs the faucet's callbacks, and a pool built
+//! under the old no-callbacks assumption rejects the faucet's assets.
+
+mod common;
+
+use anyhow::{Context, Result};
+use common::{FEE_BPS, auth, serial};
+use miden_amm::{
+    common::{
+        PayoutInfo, PoolAsset, PoolState, build_amm_account, create_add_liquidity_note,
+        create_remove_liquidity_note, create_swap_note, pool_asset_key_word, pool_x_key_slot,
+        quote_initial_lp, quote_remove_liquidity, quote_swap_output,
+    },
+    errors::AmmError,
+    ntb::{NtbEmulator, NtbNoteState, consume_notes, submit_notes},
+};
+use miden_client::{
+    account::{
+        Account, AccountBuilder, AccountComponent, AccountId, AccountType,
+        component::{
+            AccountComponentMetadata, BurnPolicyConfig, FungibleFaucet, MintPolicyConfig,
+            PolicyRegistration, TokenName, TokenPolicyManager,
+        },
+    },
+    assembly::CodeBuilder,
+    asset::{AssetAmount, AssetCallbackFlag, AssetCallbacks, FungibleAsset, TokenSymbol},
+};
+use miden_testing::{AccountState, MockChain, MockChainBuilder, TxContextInput};
+
+const BLOCK_LIST: &str = "miden_amm_test::block_list";
+
+fn balance_of(mock_chain: &MockChain, account_id: AccountId, asset: PoolAsset) -> Result<u64> {
+    Ok(mock_chain
+        .committed_account(account_id)?
+        .vault()
+        .get_balance(asset.asset(1)?.vault_key())
+        .context("reading vault balance")?
+        .as_u64())
+}
+
+/// A fungible faucet whose receive and send callbacks reject `blocked` as the native
+/// account, so every asset it issues carries `AssetCallbackFlag::Enabled`.
+fn add_block_list_faucet(builder: &mut MockChainBuilder, blocked: AccountId) -> Result<Account> {
+    let source = format!(
+        r#"
+use miden::protocol::native_account
+
+const ERR_BLOCKED = "account is blocked from holding TKX"
+
+proc assert_not_blocked
+    exec.native_account::get_id
+    # => [suffix, prefix]
+    push.{suffix} eq swap push.{prefix} eq and
+    eq.0 assert.err=ERR_BLOCKED
+end
+
+pub proc on_before_asset_added_to_account
+    exec.assert_not_blocked
+    dropw
+end
+
+pub proc on_before_asset_added_to_note
+    exec.assert_not_blocked
+    dropw
+end
+"#,
+        suffix = blocked.suffix().as_canonical_u64(),
+        prefix = blocked.prefix().as_u64(),
+    );
+    let code = CodeBuilder::new().compile_component_code(BLOCK_LIST, source.as_str())?;
+    let root = |name: &str| {
+        code.as_library()
+            .get_procedure_root_by_path(format!("{BLOCK_LIST}::{name}").as_str())
+            .context("callback procedure exported")
+    };
+    let callbacks = AssetCallbacks::new()
+        .on_before_asset_added_to_account(root("on_before_asset_added_to_account")?)
+        .on_before_asset_added_to_note(root("on_before_asset_added_to_note")?);
+    let block_list = AccountComponent::new(
+        code,
+        callbacks.into_storage_slots(),
+        AccountComponentMetadata::new(BLOCK_LIST),
+    )?;
+
+    let account_builder = AccountBuilder::new([42u8; 32])
+        .account_type(AccountType::Public)
+        .with_component(
+            FungibleFaucet::builder()
+                .name(TokenName::new("TKX")?)
+                .symbol(TokenSymbol::new("TKX")?)
+                .decimals(8)
+                .max_supply(AssetAmount::new(1_000_000_000)?)
+                .build()?,
+        )
+        .with_components(
+            TokenPolicyManager::new()
+                .with_mint_policy(MintPolicyConfig::AllowAll, PolicyRegistration::Active)?
+                .with_burn_policy(BurnPolicyConfig::AllowAll, PolicyRegistration::Active)?,
+        )
+        .with_component(block_list);
+    builder.add_account_from_builder(auth(), account_builder, AccountState::Exists)
+}
+
+#[tokio::test]
+async fn pool_with_a_callback_faucet() -> Result<()> {
+    // ---------------------------------------------------------------------------------
+    // TKX has callbacks and blocks mallory; TKY assets are held without callbacks
+    // ---------------------------------------------------------------------------------
+    let mut builder = MockChain::builder();
+    let faucet_y = builder.add_existing_basic_faucet(auth(), "TKY", 1_000_000_000, Some(8))?;
+    let y = |amount| FungibleAsset::new(faucet_y.id(), amount);
+    let mallory = builder.add_existing_wallet_with_assets(auth(), [y(100_000)?.into()])?;
+    let faucet_x = add_block_list_faucet(&mut builder, mallory.id())?;
+
+    let pool_x = PoolAsset::from_faucet(&faucet_x);
+    assert_eq!(pool_x.callbacks, AssetCallbackFlag::Enabled);
+    let x = |amount| pool_x.asset(amount);
+    let alice = builder
+        .add_existing_wallet_with_assets(auth(), [x(1_000_000)?.into(), y(4_000_000)?.into()])?;
+
+    let build = build_amm_account([7u8; 32], pool_x, faucet_y.id(), FEE_BPS, true)?;
+    let amm_id = build.account.id();
+    assert_eq!(build.pool_x(), pool_x);
+    let x_key = build.account.storage().get_item(&pool_x_key_slot())?;
+    assert_eq!(x_key, x(1)?.to_key_word());
+    assert_ne!(x_key, pool_asset_key_word(faucet_x.id())?);
+    builder.add_account(build.account.clone())?;
+
+    // the same pair under the old assumption that no faucet has callbacks
+    let naive = build_amm_account([8u8; 32], faucet_x.id(), faucet_y.id(), FEE_BPS, true)?;
+    builder.add_account(naive.account.clone())?;
+
+    // one faucet on both sides, differing only in the callback flag
+    let same_faucet = build_amm_account(
+        [9u8; 32],
+        pool_x,
+        PoolAsset::new(faucet_x.id(), AssetCallbackFlag::Disabled),
+        FEE_BPS,
+        false,
+    )?;
+
+    let mut mock_chain = builder.build()?;
+    let mut ntb = NtbEmulator::new().with_max_attempts(1);
+
+    // ---------------------------------------------------------------------------------
+    // deploying a pool whose two sides share a faucet fails the pair check
+    // ---------------------------------------------------------------------------------
+    let err = mock_chain
+        .build_tx_context(
+            TxContextInput::Account(same_faucet.account.clone()),
+            &[],
+            &[],
+        )?
+        .tx_script(same_faucet.deploy_tx_script.clone())
+        .build()?
+        .execute()
+        .await
+        .expect_err("a pool must pair two faucets");
+    assert_eq!(
+        AmmError::from_execution_error(&err),
+        Some(AmmError::WrongSwapPair)
+    );
+
+    // ---------------------------------------------------------------------------------
+    // add liquidity: the naive pool rejects TKX, the callback-aware pool accepts it
+    // ---------------------------------------------------------------------------------
+    let (lp_minted, supply) = quote_initial_lp(100_000, 400_000);
+    let add_payout = PayoutInfo::new(alice.id(), serial(1000));
+    let naive_add = create_add_liquidity_note(
+        alice.id(),
+        naive.account.id(),
+        x(100_000)?,
+        y(400_000)?,
+        lp_minted,
+        &PayoutInfo::new(alice.id(), serial(1001)),
+        naive.add_liquidity_note_script.clone(),
+        serial(1),
+    )?;
+    let add_note = create_add_liquidity_note(
+        alice.id(),
+        amm_id,
+        x(100_000)?,
+        y(400_000)?,
+        lp_minted,
+        &add_payout,
+        build.add_liquidity_note_script.clone(),
+        serial(2),
+    )?;
+    submit_notes(&mut mock_chain, alice.id(), &[naive_add.clone(), add_note]).await?;
+    let executed = ntb.run_until_idle(&mut mock_chain).await?;
+    assert_eq!(executed.len(), 1);
+    let rejected = ntb
+        .note_status(naive_add.id())
+        .expect("naive note attempted");
+    assert_eq!(rejected.state, NtbNoteState::Discarded);
+    assert_eq!(
+        rejected
+            .last_error
+            .as_deref()
+            .and_then(AmmError::from_error_text),
+        Some(AmmError::WrongDepositPair)
+    );
+    consume_notes(
+        &mut mock_chain,
+        alice.id(),
+        &[add_payout.expected_note(amm_id, vec![FungibleAsset::new(amm_id, lp_minted)?])?],
+    )
+    .await?;
+
+    // ---------------------------------------------------------------------------------
+    // swaps in both directions; TKX payouts carry the callback flag
+    // ---------------------------------------------------------------------------------
+    let dx = quote_swap_output(40_000, 400_000, 100_000, FEE_BPS);
+    let to_x_payout = PayoutInfo::new(alice.id(), serial(2000));
+    let to_x = create_swap_note(
+        alice.id(),
+        amm_id,
+        y(40_000)?,
+        build.pool_x(),
+        dx,
+        &to_x_payout,
+        build.swap_note_script.clone(),
+        serial(3),
+    )?;
+    submit_notes(&mut mock_chain, alice.id(), std::slice::from_ref(&to_x)).await?;
+    let executed = ntb.run_until_idle(&mut mock_chain).await?;
+    let to_x_claim = to_x_payout.expected_note(amm_id, vec![x(dx)?])?;
+    assert_eq!(executed[0].output_notes, vec![to_x_claim.id()]);
+    let x_before = balance_of(&mock_chain, alice.id(), pool_x)?;
+    consume_notes(&mut mock_chain, alice.id(), &[to_x_claim]).await?;
+    assert_eq!(balance_of(&mock_chain, alice.id(), pool_x)?, x_before + dx);
+
+    let (x1, y1) = (100_000 - dx, 440_000);
+    let dy = quote_swap_output(10_000, x1, y1, FEE_BPS);
+    let to_y_payout = PayoutInfo::new(alice.id(), serial(3000));
+    let to_y = create_swap_note(
+        alice.id(),
+        amm_id,
+        x(10_000)?,
+        faucet_y.id(),
+        dy,
+        &to_y_payout,
+        build.swap_note_script.clone(),
+        serial(4),
+    )?;
+    submit_notes(&mut mock_chain, alice.id(), std::slice::from_ref(&to_y)).await?;
+    assert_eq!(ntb.run_until_idle(&mut mock_chain).await?.len(), 1);
+    consume_notes(
+        &mut mock_chain,
+        alice.id(),
+        &[to_y_payout.expected_note(amm_id, vec![y(dy)?])?],
+    )
+    .await?;
+
+    // ---------------------------------------------------------------------------------
+    // the faucet's callbacks run: mallory's swap executes, but she cannot claim TKX
+    // ---------------------------------------------------------------------------------
+    let (x2, y2) = (x1 + 10_000, y1 - dy);
+    let mallory_dx = quote_swap_output(10_000, y2, x2, FEE_BPS);
+    let mallory_payout = PayoutInfo::new(mallory.id(), serial(4000));
+    let mallory_swap = create_swap_note(
+        mallory.id(),
+        amm_id,
+        y(10_000)?,
+        build.pool_x(),
+        mallory_dx,
+        &mallory_payout,
+        build.swap_note_script.clone(),
+        serial(5),
+    )?;
+    submit_notes(
+        &mut mock_chain,
+        mallory.id(),
+        std::slice::from_ref(&mallory_swap),
+    )
+    .await?;
+    assert_eq!(ntb.run_until_idle(&mut mock_chain).await?.len(), 1);
+    let blocked = consume_notes(
+        &mut mock_chain,
+        mallory.id(),
+        &[mallory_payout.expected_note(amm_id, vec![x(mallory_dx)?])?],
+    )
+    .await
+    .expect_err("mallory is blocked by the TKX faucet");
+    assert!(
+        format!("{blocked:#}").contains("blocked"),
+        "unexpected failure: {blocked:#}"
+    );
+    assert_eq!(balance_of(&mock_chain, mallory.id(), pool_x)?, 0);
+
+    // ---------------------------------------------------------------------------------
+    // remove liquidity pays out both sides with their own flags
+    // ---------------------------------------------------------------------------------
+    let (x3, y3) = (x2 - mallory_dx, y2 + 10_000);
+    let lp_burn = lp_minted / 2;
+    let (ax, ay) = quote_remove_liquidity(lp_burn, x3, y3, supply);
+    let remove_payout = PayoutInfo::new(alice.id(), serial(5000));
+    let remove_note = create_remove_liquidity_note(
+        alice.id(),
+        amm_id,
+        lp_burn,
+        ax,
+        ay,
+        &remove_payout,
+        build.remove_liquidity_note_script.clone(),
+        serial(6),
+    )?;
+    submit_notes(
+        &mut mock_chain,
+        alice.id(),
+        std::slice::from_ref(&remove_note),
+    )
+    .await?;
+    assert_eq!(ntb.run_until_idle(&mut mock_chain).await?.len(), 1);
+    consume_notes(
+        &mut mock_chain,
+        alice.id(),
+        &[remove_payout.expected_note(amm_id, vec![x(ax)?, y(ay)?])?],
+    )
+    .await?;
+
+    let pool = PoolState::from_account(
+        mock_chain.committed_account(amm_id)?,
+        build.pool_x(),
+        build.pool_y(),
+    )?;
+    assert_eq!(
+        (pool.reserve_x, pool.reserve_y, pool.lp_supply),
+        (x3 - ax, y3 - ay, supply - lp_burn)
+    );
+    Ok(())
+}
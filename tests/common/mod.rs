@@ -0,0 +1,211 @@
+//! Helpers shared by the test targets: the MockChain fixture most pool tests start from,
+//! clients of the in-process `MockNode`, and the procedures the VM-level tests
+//! (`differential_test`, `bench_test`) run directly.
+
+// each test target compiles this module on its own and uses only part of it
+#![allow(dead_code)]
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Result, anyhow};
+use miden_amm::{
+    common::{AmmBuild, build_amm_account, liquidity_code},
+    mock_node::{MockNode, MockProver},
+};
+use miden_client::{
+    Client, Felt, Word,
+    account::Account,
+    assembly::{Assembler, CodeBuilder},
+    asset::{Asset, AssetVault},
+    auth::AuthSchemeId,
+    builder::ClientBuilder,
+    keystore::FilesystemKeyStore,
+    note::Note,
+    transaction::RawOutputNote,
+};
+use miden_client_sqlite_store::ClientBuilderSqliteExt;
+use miden_processor::{DefaultHost, ExecutionError, FastProcessor, Program, StackInputs};
+use miden_protocol::CoreLibrary;
+use miden_testing::{Auth, MockChainBuilder};
+
+pub const FEE_BPS: u64 = 30;
+
+// =================================================================================================
+// MOCK CHAIN FIXTURE
+// =================================================================================================
+
+pub fn auth() -> Auth {
+    Auth::BasicAuth {
+        auth_scheme: AuthSchemeId::Falcon512Poseidon2,
+    }
+}
+
+/// A distinct serial number per `n`.
+pub fn serial(n: u64) -> Word {
+    [
+        Felt::new_unchecked(n),
+        Felt::new_unchecked(n + 101),
+        Felt::new_unchecked(n + 202),
+        Felt::new_unchecked(n + 303),
+    ]
+    .into()
+}
+
+/// Adds the pair the pool tests trade: the TKX and TKY basic faucets.
+pub fn add_pair_faucets(builder: &mut MockChainBuilder) -> Result<(Account, Account)> {
+    let faucet_x = builder.add_existing_basic_faucet(auth(), "TKX", 1_000_000_000, Some(8))?;
+    let faucet_y = builder.add_existing_basic_faucet(auth(), "TKY", 1_000_000_000, Some(8))?;
+    Ok((faucet_x, faucet_y))
+}
+
+/// Adds the AMM over `faucet_x` / `faucet_y` (seed `[7; 32]`, [`FEE_BPS`]) as a genesis
+/// account.
+pub fn add_pool(
+    builder: &mut MockChainBuilder,
+    faucet_x: &Account,
+    faucet_y: &Account,
+) -> Result<AmmBuild> {
+    let build = build_amm_account([7u8; 32], faucet_x.id(), faucet_y.id(), FEE_BPS, true)?;
+    builder.add_account(build.account.clone())?;
+    Ok(build)
+}
+
+/// Adds `notes` to the genesis block, so transactions can consume them.
+pub fn add_notes<'a>(builder: &mut MockChainBuilder, notes: impl IntoIterator<Item = &'a Note>) {
+    for note in notes {
+        builder.add_output_note(RawOutputNote::Full(note.clone()));
+    }
+}
+
+/// The pool account with `donation` already in its vault, as if it had arrived outside the
+/// allowlisted notes (e.g. through a component upgrade).
+pub fn with_donation(build: &AmmBuild, donation: &[Asset]) -> Result<Account> {
+    let (id, _, storage, code, nonce, seed) = build.account.clone().into_parts();
+    Ok(Account::new(
+        id,
+        AssetVault::new(donation)?,
+        storage,
+        code,
+        nonce,
+        seed,
+    )?)
+}
+
+// =================================================================================================
+// MOCK NODE CLIENTS
+// =================================================================================================
+
+/// A fresh directory per test, so parallel tests never share a store or keystore.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("miden-amm-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("creating temp dir");
+    dir
+}
+
+/// A client connected to `node`, with its store and keystore in `dir`.
+pub async fn mock_client(
+    node: &MockNode,
+    dir: &Path,
+) -> Result<(Client<FilesystemKeyStore>, Arc<FilesystemKeyStore>)> {
+    let keystore = Arc::new(FilesystemKeyStore::new(dir.join("keystore"))?);
+    let mut client = ClientBuilder::new()
+        .rpc(Arc::new(node.clone()))
+        .sqlite_store(dir.join("store.sqlite3"))
+        .authenticator(keystore.clone())
+        .prover(Arc::new(MockProver::new(node)))
+        .build()
+        .await?;
+    client.sync_state().await?;
+    Ok((client, keystore))
+}
+
+// =================================================================================================
+// VM PROCEDURES
+// =================================================================================================
+
+/// Where a cycle counting program keeps the first clock reading; far above the procedures'
+/// memory.
+const CLK_ADDR: u32 = 1 << 24;
+
+/// A single procedure of an AMM component, callable with its inputs on the stack.
+pub struct VmProcedure {
+    program: Program,
+    host: DefaultHost,
+}
+
+impl VmProcedure {
+    /// Assembles a program that `exec`s `procedure` of `source`, linked as `namespace`, and
+    /// truncates the stack to its 16 visible elements.
+    pub fn new(namespace: &str, source: &str, procedure: &str) -> Result<Self> {
+        let module = module_name(namespace);
+        Self::assemble(namespace, source, &format!("exec.{module}::{procedure}"))
+    }
+
+    /// Like [`VmProcedure::new`], but the program returns the cycles between two `clk` reads
+    /// around the procedure instead of its output. An empty `procedure` measures the reads
+    /// alone.
+    pub fn cycle_counter(namespace: &str, source: &str, procedure: &str) -> Result<Self> {
+        let module = module_name(namespace);
+        let call = if procedure.is_empty() {
+            String::new()
+        } else {
+            format!("exec.{module}::{procedure}")
+        };
+        Self::assemble(
+            namespace,
+            source,
+            &format!("clk mem_store.{CLK_ADDR}\n    {call}\n    clk mem_load.{CLK_ADDR} sub"),
+        )
+    }
+
+    fn assemble(namespace: &str, source: &str, body: &str) -> Result<Self> {
+        let assembler: Assembler = CodeBuilder::new()
+            .with_linked_module(namespace, source)?
+            .into();
+        let program = assembler
+            .assemble_program(format!(
+                "use {namespace}\nuse miden::core::sys\n\
+                 begin\n    {body}\n    exec.sys::truncate_stack\nend\n"
+            ))
+            .map_err(|report| anyhow!("{report}"))?;
+        let mut host = DefaultHost::default();
+        host.load_library(&CoreLibrary::default())?;
+        Ok(VmProcedure { program, host })
+    }
+
+    /// Runs the procedure with `inputs` on the stack (first on top) and returns the top of the
+    /// output stack.
+    pub fn run(&mut self, inputs: &[u64]) -> Result<u64, ExecutionError> {
+        let inputs: Vec<Felt> = inputs.iter().map(|v| Felt::new_unchecked(*v)).collect();
+        let stack = StackInputs::new(&inputs).expect("at most 16 inputs");
+        let output = FastProcessor::new(stack).execute_sync(&self.program, &mut self.host)?;
+        Ok(output
+            .stack
+            .get_element(0)
+            .expect("non-empty stack")
+            .as_canonical_u64())
+    }
+}
+
+fn module_name(namespace: &str) -> &str {
+    namespace
+        .rsplit("::")
+        .next()
+        .expect("namespace has a module name")
+}
+
+/// The liquidity component with its private math procedures exported, so a program can
+/// `exec` them.
+pub fn liquidity_with_public_math() -> String {
+    let mut source = liquidity_code();
+    for procedure in ["isqrt_u128", "mul_div_u64"] {
+        let private = format!("\nproc {procedure}\n");
+        assert_eq!(source.matches(&private).count(), 1, "{procedure} not found");
+        source = source.replace(&private, &format!("\npub proc {procedure}\n"));
+    }
+    source
+}
@@ -0,0 +1,143 @@
+//! Differential fuzzing of the MASM math: `get_amount_y_out`, `isqrt_u128` and `mul_div_u64`
+//! run on the VM against `quote_swap_output`, `u128::isqrt` and `mul_div_u64` on generated
+//! inputs. Each procedure is assembled once and executed by the fast processor with the
+//! inputs on the stack; proptest shrinks a mismatch to a minimal input.
+
+mod common;
+
+use anyhow::{Result, anyhow};
+use common::{FEE_BPS, VmProcedure, liquidity_with_public_math};
+use miden_amm::{
+    common::{
+        AMM_CONTRACT_NS, FEE_DENOM, LIQUIDITY_CONTRACT_NS, PoolState, amm_code, mul_div_u64,
+        quote_swap_output,
+    },
+    errors::AmmError,
+};
+use miden_client::{Felt, asset::FungibleAsset};
+use proptest::{
+    prelude::*,
+    test_runner::{Config, TestRunner},
+};
+
+const CASES: u32 = 2_000;
+
+fn runner() -> TestRunner {
+    TestRunner::new(Config {
+        cases: CASES,
+        failure_persistence: None,
+        ..Config::default()
+    })
+}
+
+/// Field elements, weighted towards the edges of the u32 limbs, the fungible asset maximum
+/// and the field modulus.
+fn felt() -> impl Strategy<Value = u64> {
+    let max_amount = FungibleAsset::MAX_AMOUNT.as_u64();
+    prop_oneof![
+        3 => 0..Felt::ORDER,
+        2 => 0..1u64 << 32,
+        1 => prop::sample::select(vec![
+            0,
+            1,
+            u32::MAX as u64,
+            1 << 32,
+            max_amount - 1,
+            max_amount,
+            Felt::ORDER - 1,
+        ]),
+    ]
+}
+
+#[test]
+fn get_amount_y_out_matches_quote_swap_output() -> Result<()> {
+    let mut vm = VmProcedure::new(AMM_CONTRACT_NS, &amm_code(), "get_amount_y_out")?;
+    let fee = prop_oneof![Just(0), Just(30), Just(FEE_DENOM), 0..=FEE_DENOM];
+    let strategy = (felt(), felt().prop_filter("x > 0", |x| *x > 0), felt(), fee);
+    let vm = std::cell::RefCell::new(&mut vm);
+    runner()
+        .run(&strategy, |(dx, x, y, fee_bps)| {
+            let dy = vm.borrow_mut().run(&[dx, x, y, fee_bps]);
+            prop_assert_eq!(dy.ok(), Some(quote_swap_output(dx, x, y, fee_bps)));
+            Ok(())
+        })
+        .map_err(|err| anyhow!("{err}"))
+}
+
+#[test]
+fn isqrt_u128_matches_rust() -> Result<()> {
+    let mut vm = VmProcedure::new(
+        LIQUIDITY_CONTRACT_NS,
+        &liquidity_with_public_math(),
+        "isqrt_u128",
+    )?;
+    // the pool takes the root of dx * dy; any N below ORDER^2 has a root that is a felt
+    let order = Felt::ORDER as u128;
+    let n = prop_oneof![
+        (felt(), felt()).prop_map(|(a, b)| a as u128 * b as u128),
+        0..order * order,
+        (0..Felt::ORDER).prop_flat_map(|r| {
+            let square = r as u128 * r as u128;
+            prop::sample::select(vec![square, square.saturating_sub(1), square + 1])
+        }),
+    ];
+    let vm = std::cell::RefCell::new(&mut vm);
+    runner()
+        .run(&n, |n| {
+            let limbs = [0, 32, 64, 96].map(|shift| (n >> shift) as u32 as u64);
+            let root = vm.borrow_mut().run(&limbs);
+            prop_assert_eq!(root.ok(), Some(n.isqrt() as u64));
+            Ok(())
+        })
+        .map_err(|err| anyhow!("{err}"))
+}
+
+#[test]
+fn mul_div_u64_matches_rust() -> Result<()> {
+    let mut vm = VmProcedure::new(
+        LIQUIDITY_CONTRACT_NS,
+        &liquidity_with_public_math(),
+        "mul_div_u64",
+    )?;
+    // besides random felts: quotients that fit in a u64 but not in a felt
+    let strategy = prop_oneof![
+        (felt(), felt(), felt().prop_filter("d > 0", |d| *d > 0)),
+        (Felt::ORDER / 2 + 1..=u64::MAX / 2).prop_map(|a| (a, 2, 1)),
+    ];
+    let vm = std::cell::RefCell::new(&mut vm);
+    runner()
+        .run(&strategy, |(a, b, d)| {
+            let vm_result = vm.borrow_mut().run(&[a, b, d]);
+            match mul_div_u64(a, b, d) {
+                Some(q) => prop_assert_eq!(vm_result.ok(), Some(q)),
+                None => {
+                    let err = vm_result.err().map(|err| err.to_string());
+                    prop_assert_eq!(
+                        err.as_deref().and_then(AmmError::from_error_text),
+                        Some(AmmError::ValueOverflow),
+                        "{:?}",
+                        err
+                    );
+                }
+            }
+            Ok(())
+        })
+        .map_err(|err| anyhow!("{err}"))
+}
+
+/// The pool model rejects a deposit whose mint overflows on either side as the VM does, even
+/// when the other side would mint little.
+#[test]
+fn overflowing_mints_fail_like_the_vm() -> Result<()> {
+    let mut pool = PoolState::new(FEE_BPS);
+    pool.apply_add_liquidity(1, 4_000_000)?;
+    let before = pool;
+    // 2^60 * 2_000 / 1 is not a field element; the Y side mints 2_000
+    let err = pool.apply_add_liquidity(1 << 60, 4_000_000).unwrap_err();
+    assert_eq!(
+        AmmError::from_message(&err.to_string()),
+        Some(AmmError::ValueOverflow)
+    );
+    assert_eq!(pool, before);
+    Ok(())
+}
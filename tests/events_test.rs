@@ -0,0 +1,463 @@
+//! The AMM event log: events decoded from executed transactions, from the blocks that
+//! committed them and from the account's log, checked against the reference math; syncs and
+//! skims; and the decoder's refusal of incomplete, overwritten or misnumbered logs.
+
+mod common;
+
+use anyhow::Result;
+use common::{FEE_BPS, add_notes, add_pair_faucets, auth, serial, with_donation};
+use miden_amm::{
+    common::{
+        PayoutInfo, PoolState, build_amm_account, create_add_liquidity_note,
+        create_remove_liquidity_note, create_skim_note, create_swap_note, create_sync_note,
+        event_count_slot, event_log_slot,
+    },
+    events::{
+        AmmAction, AmmEvent, EVENT_LOG_CAPACITY, event_log, events_from_block, events_from_delta,
+        events_from_transaction,
+    },
+};
+use miden_client::{
+    Felt, account::AccountId, asset::FungibleAsset, note::Note, transaction::RawOutputNote,
+};
+use miden_protocol::{
+    account::{
+        StorageMapKey,
+        delta::{AccountDelta, AccountStorageDelta, AccountVaultDelta},
+    },
+    testing::account_id::ACCOUNT_ID_SENDER,
+};
+use miden_testing::MockChain;
+
+/// The notes one transaction consumes, each with its payout, and the events it must record.
+type Tx = (Vec<(Note, Note)>, Vec<AmmEvent>);
+
+fn event(seq: u64, sender: AccountId, action: AmmAction, pool: &PoolState) -> AmmEvent {
+    AmmEvent {
+        seq,
+        sender,
+        action,
+        reserve_x: pool.reserve_x,
+        reserve_y: pool.reserve_y,
+        lp_supply: pool.lp_supply,
+    }
+}
+
+/// alice deposits, bob swaps X in, carol Y in and bob X in again within one transaction,
+/// then alice withdraws half her LP. One transaction per block.
+#[tokio::test]
+async fn swaps_and_liquidity_changes_are_logged() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let alice = builder.add_existing_wallet_with_assets(auth(), [])?.id();
+    let bob = builder.add_existing_wallet_with_assets(auth(), [])?.id();
+    let carol = builder.add_existing_wallet_with_assets(auth(), [])?.id();
+    let build = build_amm_account([5u8; 32], faucet_x.id(), faucet_y.id(), FEE_BPS, true)?;
+    let amm_id = build.account.id();
+    builder.add_account(build.account.clone())?;
+    let x = |amount| FungibleAsset::new(faucet_x.id(), amount);
+    let y = |amount| FungibleAsset::new(faucet_y.id(), amount);
+
+    let mut txs: Vec<Tx> = Vec::new();
+    let mut pool = PoolState::new(FEE_BPS);
+    let mut seq = 0;
+    let mut n = 0;
+    let mut next = |target| {
+        n += 1;
+        (PayoutInfo::new(target, serial(1_000 + n)), serial(n))
+    };
+
+    let lp = pool.apply_add_liquidity(1_000_000, 4_000_000)?;
+    let (payout, note_serial) = next(alice);
+    let note = create_add_liquidity_note(
+        alice,
+        amm_id,
+        x(1_000_000)?,
+        y(4_000_000)?,
+        lp,
+        &payout,
+        build.add_liquidity_note_script.clone(),
+        note_serial,
+    )?;
+    let action = AmmAction::AddLiquidity {
+        amount_x: 1_000_000,
+        amount_y: 4_000_000,
+        lp_minted: lp,
+    };
+    txs.push((
+        vec![(
+            note,
+            payout.expected_note(amm_id, vec![FungibleAsset::new(amm_id, lp)?])?,
+        )],
+        vec![event(seq, alice, action, &pool)],
+    ));
+    seq += 1;
+
+    for (tx, sender, amount_in, x_in) in [
+        (1, bob, 50_000, true),
+        (2, carol, 300_000, false),
+        (2, bob, 20_000, true),
+    ] {
+        let amount_out = pool.apply_swap(amount_in, x_in)?;
+        let (asset_in, asset_out) = if x_in {
+            (x(amount_in)?, y(amount_out)?)
+        } else {
+            (y(amount_in)?, x(amount_out)?)
+        };
+        let (payout, note_serial) = next(sender);
+        let note = create_swap_note(
+            sender,
+            amm_id,
+            asset_in,
+            asset_out.faucet_id(),
+            amount_out,
+            &payout,
+            build.swap_note_script.clone(),
+            note_serial,
+        )?;
+        if txs.len() == tx {
+            txs.push((Vec::new(), Vec::new()));
+        }
+        let action = AmmAction::Swap {
+            x_in,
+            amount_in,
+            amount_out,
+        };
+        txs[tx]
+            .0
+            .push((note, payout.expected_note(amm_id, vec![asset_out])?));
+        txs[tx].1.push(event(seq, sender, action, &pool));
+        seq += 1;
+    }
+
+    let (ax, ay) = pool.apply_remove_liquidity(lp / 2)?;
+    let (payout, note_serial) = next(alice);
+    let note = create_remove_liquidity_note(
+        alice,
+        amm_id,
+        lp / 2,
+        ax,
+        ay,
+        &payout,
+        build.remove_liquidity_note_script.clone(),
+        note_serial,
+    )?;
+    let action = AmmAction::RemoveLiquidity {
+        lp_burned: lp / 2,
+        amount_x: ax,
+        amount_y: ay,
+    };
+    txs.push((
+        vec![(note, payout.expected_note(amm_id, vec![x(ax)?, y(ay)?])?)],
+        vec![event(seq, alice, action, &pool)],
+    ));
+
+    add_notes(
+        &mut builder,
+        txs.iter()
+            .flat_map(|(notes, _)| notes)
+            .map(|(note, _)| note),
+    );
+    let mut mock_chain = builder.build()?;
+
+    // the genesis block carries the account's full state: an empty log
+    assert!(events_from_block(&mock_chain.proven_blocks()[0], amm_id, 0)?.is_empty());
+
+    let mut event_count = 0;
+    for (notes, expected) in &txs {
+        let note_ids: Vec<_> = notes.iter().map(|(note, _)| note.id()).collect();
+        let payouts = notes
+            .iter()
+            .map(|(_, payout)| RawOutputNote::Full(payout.clone()))
+            .collect();
+        let executed = mock_chain
+            .build_tx_context(amm_id, &note_ids, &[])?
+            .extend_expected_output_notes(payouts)
+            .build()?
+            .execute()
+            .await?;
+        assert_eq!(&events_from_transaction(&executed)?, expected);
+
+        mock_chain.add_pending_executed_transaction(&executed)?;
+        let block = mock_chain.prove_next_block()?;
+        assert_eq!(&events_from_block(&block, amm_id, event_count)?, expected);
+        // an account the block did not update recorded nothing
+        assert!(events_from_block(&block, alice, 0)?.is_empty());
+        // nor does a count the block does not continue from
+        assert!(events_from_block(&block, amm_id, event_count + 1).is_err());
+        event_count += expected.len() as u64;
+    }
+
+    // the account's log holds the whole history, and the last event its final state
+    let account = mock_chain.committed_account(amm_id)?;
+    let log = event_log(account)?;
+    let all: Vec<AmmEvent> = txs.into_iter().flat_map(|(_, events)| events).collect();
+    assert_eq!(log, all);
+    assert_eq!(
+        PoolState::from_account(account, build.pool_x(), build.pool_y())?,
+        pool
+    );
+    assert_eq!(
+        account.storage().get_item(&event_count_slot())?[0].as_canonical_u64(),
+        all.len() as u64
+    );
+
+    let json = serde_json::to_string(&log[1])?;
+    assert!(json.contains(r#""kind":"swap""#), "{json}");
+    assert!(
+        json.contains(&format!(r#""sender":"{}""#, bob.to_hex())),
+        "{json}"
+    );
+    Ok(())
+}
+
+/// Executes the AMM consuming `notes`, checks it recorded `expected` and commits it.
+async fn logged(
+    mock_chain: &mut MockChain,
+    amm_id: AccountId,
+    notes: &[(Note, Option<Note>)],
+    expected: &[AmmEvent],
+) -> Result<()> {
+    let note_ids: Vec<_> = notes.iter().map(|(note, _)| note.id()).collect();
+    let payouts = notes
+        .iter()
+        .filter_map(|(_, payout)| payout.clone().map(RawOutputNote::Full))
+        .collect();
+    let executed = mock_chain
+        .build_tx_context(amm_id, &note_ids, &[])?
+        .extend_expected_output_notes(payouts)
+        .build()?
+        .execute()
+        .await?;
+    assert_eq!(events_from_transaction(&executed)?, expected);
+    mock_chain.add_pending_executed_transaction(&executed)?;
+    mock_chain.prove_next_block()?;
+    Ok(())
+}
+
+/// Two pools hold donations outside their reserves. After alice's deposit, bob syncs one
+/// and skims the other: the sync records what the reserves grew by, the skim what it paid.
+#[tokio::test]
+async fn syncs_and_skims_are_logged() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let alice = builder.add_existing_wallet_with_assets(auth(), [])?.id();
+    let bob = builder.add_existing_wallet_with_assets(auth(), [])?.id();
+    let x = |amount| FungibleAsset::new(faucet_x.id(), amount);
+    let y = |amount| FungibleAsset::new(faucet_y.id(), amount);
+    let synced = build_amm_account([6u8; 32], faucet_x.id(), faucet_y.id(), FEE_BPS, true)?;
+    let skimmed = build_amm_account([8u8; 32], faucet_x.id(), faucet_y.id(), FEE_BPS, true)?;
+    builder.add_account(with_donation(
+        &synced,
+        &[x(20_000)?.into(), y(80_000)?.into()],
+    )?)?;
+    builder.add_account(with_donation(&skimmed, &[x(50_000)?.into()])?)?;
+
+    let mut pool = PoolState::new(FEE_BPS);
+    let lp = pool.apply_add_liquidity(1_000_000, 4_000_000)?;
+    let mut deposits = Vec::new();
+    for (n, build) in [(1, &synced), (2, &skimmed)] {
+        let amm_id = build.account.id();
+        let payout = PayoutInfo::new(alice, serial(1_000 + n));
+        let note = create_add_liquidity_note(
+            alice,
+            amm_id,
+            x(1_000_000)?,
+            y(4_000_000)?,
+            lp,
+            &payout,
+            build.add_liquidity_note_script.clone(),
+            serial(n),
+        )?;
+        let claim = payout.expected_note(amm_id, vec![FungibleAsset::new(amm_id, lp)?])?;
+        deposits.push((note, Some(claim)));
+    }
+    let sync = create_sync_note(
+        bob,
+        synced.account.id(),
+        synced.sync_note_script.clone(),
+        serial(3),
+    )?;
+    let skim_payout = PayoutInfo::new(bob, serial(1_004));
+    let skim = create_skim_note(
+        bob,
+        skimmed.account.id(),
+        &skim_payout,
+        skimmed.skim_note_script.clone(),
+        serial(4),
+    )?;
+    let skim_claim = skim_payout.expected_note(skimmed.account.id(), vec![x(50_000)?])?;
+    add_notes(
+        &mut builder,
+        deposits.iter().map(|(note, _)| note).chain([&sync, &skim]),
+    );
+    let mut mock_chain = builder.build()?;
+
+    let deposited = AmmAction::AddLiquidity {
+        amount_x: 1_000_000,
+        amount_y: 4_000_000,
+        lp_minted: lp,
+    };
+    for (build, deposit) in [&synced, &skimmed].into_iter().zip(deposits) {
+        let expected = [event(0, alice, deposited, &pool)];
+        logged(&mut mock_chain, build.account.id(), &[deposit], &expected).await?;
+    }
+
+    let synced_pool = PoolState {
+        reserve_x: pool.reserve_x + 20_000,
+        reserve_y: pool.reserve_y + 80_000,
+        ..pool
+    };
+    let action = AmmAction::Sync {
+        amount_x: 20_000,
+        amount_y: 80_000,
+    };
+    let expected = [event(1, bob, action, &synced_pool)];
+    logged(
+        &mut mock_chain,
+        synced.account.id(),
+        &[(sync, None)],
+        &expected,
+    )
+    .await?;
+
+    let action = AmmAction::Skim {
+        amount_x: 50_000,
+        amount_y: 0,
+    };
+    let expected = [event(1, bob, action, &pool)];
+    logged(
+        &mut mock_chain,
+        skimmed.account.id(),
+        &[(skim, Some(skim_claim))],
+        &expected,
+    )
+    .await?;
+
+    // the last event of each log carries the pool's final state
+    for (build, state) in [(&synced, synced_pool), (&skimmed, pool)] {
+        let account = mock_chain.committed_account(build.account.id())?;
+        let last = *event_log(account)?.last().expect("two events");
+        assert_eq!(
+            (last.reserve_x, last.reserve_y, last.lp_supply),
+            (state.reserve_x, state.reserve_y, state.lp_supply)
+        );
+        assert_eq!(
+            PoolState::from_account(account, build.pool_x(), build.pool_y())?,
+            state
+        );
+    }
+    Ok(())
+}
+
+/// Writes the first `parts` log entries of an event at `seq` into `storage`.
+fn log_parts(
+    storage: &mut AccountStorageDelta,
+    seq: u64,
+    kind: u64,
+    sender: AccountId,
+    parts: usize,
+) {
+    let index = seq % EVENT_LOG_CAPACITY;
+    let words = [
+        [
+            Felt::new_unchecked(kind),
+            Felt::new_unchecked(seq),
+            sender.suffix(),
+            sender.prefix().as_felt(),
+        ],
+        [1_000, 3_988, 0, 0].map(Felt::new_unchecked),
+        [101_000, 396_012, 200_000, 0].map(Felt::new_unchecked),
+    ];
+    for (part, word) in words.into_iter().enumerate().take(parts) {
+        let key = [index, part as u64, 0, 0].map(Felt::new_unchecked);
+        storage
+            .set_map_item(
+                event_log_slot(),
+                StorageMapKey::new(key.into()),
+                word.into(),
+            )
+            .unwrap();
+    }
+}
+
+fn log_event(storage: &mut AccountStorageDelta, seq: u64, kind: u64, sender: AccountId) {
+    log_parts(storage, seq, kind, sender, 3);
+}
+
+#[test]
+fn decoder_orders_wrapped_events_and_refuses_lost_ones() -> Result<()> {
+    let sender = AccountId::try_from(ACCOUNT_ID_SENDER)?;
+    let delta = |storage: AccountStorageDelta| {
+        AccountDelta::new(
+            sender,
+            storage,
+            AccountVaultDelta::default(),
+            Felt::new_unchecked(1),
+        )
+    };
+    let count = |storage: &mut AccountStorageDelta, count: u64| {
+        let word = [count, 0, 0, 0].map(Felt::new_unchecked);
+        storage.set_item(event_count_slot(), word.into()).unwrap();
+    };
+
+    // seqs 255 and 256 wrap around to indexes 255 and 0
+    let mut storage = AccountStorageDelta::new();
+    log_event(&mut storage, 255, 1, sender);
+    log_event(&mut storage, 256, 2, sender);
+    count(&mut storage, 257);
+    let wrapped = delta(storage)?;
+    let events = events_from_delta(&wrapped, 255)?;
+    let seqs: Vec<u64> = events.iter().map(|event| event.seq).collect();
+    assert_eq!(seqs, vec![255, 256]);
+    assert_eq!(
+        events[1].action,
+        AmmAction::Swap {
+            x_in: false,
+            amount_in: 3_988,
+            amount_out: 1_000
+        }
+    );
+    assert_eq!((events[1].sender, events[1].lp_supply), (sender, 200_000));
+
+    // a count the delta does not continue from
+    let err = events_from_delta(&wrapped, 254).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "delta recorded events 254..257 but holds 2 events from seq 255"
+    );
+
+    // 256 events in the delta, but seqs 3..=256 were overwritten by seqs 259..=512
+    let mut storage = AccountStorageDelta::new();
+    for seq in 257..=512 {
+        log_event(&mut storage, seq, 3, sender);
+    }
+    count(&mut storage, 513);
+    let err = events_from_delta(&delta(storage)?, 1).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "delta recorded events 1..513 but holds 256 events from seq 257"
+    );
+
+    // the count advanced but the log did not
+    let mut storage = AccountStorageDelta::new();
+    count(&mut storage, 1);
+    let err = events_from_delta(&delta(storage)?, 0).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "delta recorded events 0..1 but holds 0 events from seq 1"
+    );
+
+    // an event without its pool-state entry
+    let mut storage = AccountStorageDelta::new();
+    log_parts(&mut storage, 0, 1, sender, 2);
+    let err = events_from_delta(&delta(storage)?, 0).unwrap_err();
+    assert_eq!(err.to_string(), "event log entry 0 is incomplete");
+
+    // kinds outside 1..=6 are not AMM events
+    let mut storage = AccountStorageDelta::new();
+    log_event(&mut storage, 0, 9, sender);
+    let err = events_from_delta(&delta(storage)?, 0).unwrap_err();
+    assert_eq!(err.to_string(), "unknown AMM event kind 9");
+    Ok(())
+}
@@ -0,0 +1,342 @@
+//! Allowlist governance: only the admin can queue a change to a live pool's note allowlist,
+//! anyone can apply it once the timelock has passed, and a note script runs only while it is
+//! listed.
+
+mod common;
+
+use anyhow::{Context, Result};
+use common::{FEE_BPS, add_pair_faucets, auth, serial, with_donation};
+use miden_amm::{
+    common::{
+        AmmBuild, AmmOptions, LIQUIDITY_CONTRACT_NS, PoolState, build_amm_account,
+        build_amm_account_with, create_sync_note, liquidity_code,
+    },
+    errors::AmmError,
+    governance::{
+        AllowlistAction, Governance, PendingAllowlistChange, allowed_note_scripts,
+        create_execute_allowlist_note, create_propose_allowlist_note, pending_allowlist_changes,
+    },
+    manifest::AmmManifest,
+    ntb::{NtbEmulator, NtbNoteState, submit_notes},
+    verify::{PoolMismatch, verify_pool_account},
+};
+use miden_client::{
+    account::{Account, AccountId},
+    assembly::CodeBuilder,
+    asset::FungibleAsset,
+    block::BlockNumber,
+    note::{Note, NoteScript},
+};
+use miden_testing::MockChain;
+
+const TIMELOCK: u32 = 5;
+
+/// A note type the pool was not deployed with: a sync that also leaves a marker on the stack.
+const SYNC_V2_NOTE_CODE: &str = "
+use external_contract::liquidity_contract
+
+@note_script
+pub proc main
+    call.liquidity_contract::sync
+    push.2 drop
+end
+";
+
+fn committed(mock_chain: &MockChain, build: &AmmBuild) -> Result<Account> {
+    Ok(mock_chain.committed_account(build.account.id())?.clone())
+}
+
+/// Submits `note` and runs the emulator; returns the AMM error the note was discarded with,
+/// or `None` if it was executed.
+async fn run(
+    mock_chain: &mut MockChain,
+    ntb: &mut NtbEmulator,
+    sender: AccountId,
+    note: &Note,
+) -> Result<Option<AmmError>> {
+    submit_notes(mock_chain, sender, std::slice::from_ref(note)).await?;
+    ntb.run_until_idle(mock_chain).await?;
+    let status = ntb.note_status(note.id()).context("note attempted")?;
+    if status.state == NtbNoteState::Committed {
+        return Ok(None);
+    }
+    assert_eq!(status.state, NtbNoteState::Discarded);
+    let error = status
+        .last_error
+        .as_deref()
+        .and_then(AmmError::from_error_text)
+        .context("discarded with an AMM error")?;
+    Ok(Some(error))
+}
+
+/// Executes `note` directly against the pool, bypassing the emulator's allowlist filter.
+async fn kernel_accepts(mock_chain: &MockChain, amm_id: AccountId, note: &Note) -> Result<bool> {
+    let executed = mock_chain
+        .build_tx_context(amm_id, &[note.id()], &[])?
+        .build()?
+        .execute()
+        .await;
+    Ok(executed.is_ok())
+}
+
+#[tokio::test]
+async fn allowlist_changes_are_admin_only_and_timelocked() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let admin = builder.add_existing_wallet_with_assets(auth(), [])?;
+    let mallory = builder.add_existing_wallet_with_assets(auth(), [])?;
+
+    let governance = Governance::new(admin.id(), TIMELOCK);
+    let build = build_amm_account_with(
+        [7u8; 32],
+        faucet_x.id(),
+        faucet_y.id(),
+        FEE_BPS,
+        &AmmOptions::default().with_governance(governance),
+        true,
+    )?;
+    let amm_id = build.account.id();
+    assert_eq!(build.governance, Some(governance));
+    assert_eq!(Governance::from_account(&build.account)?, Some(governance));
+    // 10_000 X the reserves do not count yet, so a sync has something to adopt
+    let donation = FungibleAsset::new(faucet_x.id(), 10_000)?;
+    builder.add_account(with_donation(&build, &[donation.into()])?)?;
+
+    // a pool without governance rejects every proposal
+    let frozen = build_amm_account([8u8; 32], faucet_x.id(), faucet_y.id(), FEE_BPS, true)?;
+    assert_eq!(Governance::from_account(&frozen.account)?, None);
+    builder.add_account(frozen.account.clone())?;
+
+    let mut mock_chain = builder.build()?;
+    let mut ntb = NtbEmulator::new().with_max_attempts(1);
+
+    let sync_v2: NoteScript = CodeBuilder::new()
+        .with_linked_module(LIQUIDITY_CONTRACT_NS, liquidity_code().as_str())?
+        .compile_note_script(SYNC_V2_NOTE_CODE)?;
+    let sync_v2_root = sync_v2.root();
+    let propose = |sender: AccountId, pool: &AmmBuild, action, n| {
+        create_propose_allowlist_note(
+            sender,
+            pool.account.id(),
+            sync_v2_root,
+            action,
+            pool.propose_allowlist_note_script.clone(),
+            serial(n),
+        )
+    };
+    let execute = |sender: AccountId, n| {
+        create_execute_allowlist_note(
+            sender,
+            amm_id,
+            sync_v2_root,
+            build.execute_allowlist_note_script.clone(),
+            serial(n),
+        )
+    };
+
+    // ---------------------------------------------------------------------------------
+    // an unlisted script: the NTB ignores the note and the auth component rejects it
+    // ---------------------------------------------------------------------------------
+    assert!(!allowed_note_scripts(&build.account)?.contains(&sync_v2_root));
+    let early_sync = create_sync_note(mallory.id(), amm_id, sync_v2.clone(), serial(1))?;
+    submit_notes(
+        &mut mock_chain,
+        mallory.id(),
+        std::slice::from_ref(&early_sync),
+    )
+    .await?;
+    assert!(ntb.run_until_idle(&mut mock_chain).await?.is_empty());
+    assert!(ntb.note_status(early_sync.id()).is_none());
+    assert!(!kernel_accepts(&mock_chain, amm_id, &early_sync).await?);
+
+    // ---------------------------------------------------------------------------------
+    // proposals: not from anyone but the admin, not on a pool without governance
+    // ---------------------------------------------------------------------------------
+    let by_mallory = propose(mallory.id(), &build, AllowlistAction::Add, 2)?;
+    assert_eq!(
+        run(&mut mock_chain, &mut ntb, mallory.id(), &by_mallory).await?,
+        Some(AmmError::NotGovernanceAdmin)
+    );
+    let on_frozen = propose(admin.id(), &frozen, AllowlistAction::Add, 3)?;
+    assert_eq!(
+        run(&mut mock_chain, &mut ntb, admin.id(), &on_frozen).await?,
+        Some(AmmError::GovernanceDisabled)
+    );
+    assert!(pending_allowlist_changes(&committed(&mock_chain, &build)?)?.is_empty());
+
+    // ---------------------------------------------------------------------------------
+    // the admin's proposal waits out the timelock; nothing is listed before
+    // ---------------------------------------------------------------------------------
+    let add = propose(admin.id(), &build, AllowlistAction::Add, 4)?;
+    assert_eq!(
+        run(&mut mock_chain, &mut ntb, admin.id(), &add).await?,
+        None
+    );
+    let proposed_in = ntb
+        .note_status(add.id())
+        .and_then(|status| status.last_attempt_block_num)
+        .context("proposal attempted")?;
+    let eta = BlockNumber::from(proposed_in.as_u32() + TIMELOCK);
+    let pending = PendingAllowlistChange {
+        script_root: sync_v2_root,
+        action: AllowlistAction::Add,
+        eta,
+    };
+    let pool = committed(&mock_chain, &build)?;
+    assert_eq!(pending_allowlist_changes(&pool)?, vec![pending.clone()]);
+    assert!(!allowed_note_scripts(&pool)?.contains(&sync_v2_root));
+
+    let too_early = execute(mallory.id(), 5)?;
+    assert_eq!(
+        run(&mut mock_chain, &mut ntb, mallory.id(), &too_early).await?,
+        Some(AmmError::TimelockNotElapsed)
+    );
+    let unrelated = create_execute_allowlist_note(
+        mallory.id(),
+        amm_id,
+        build.swap_note_script.root(),
+        build.execute_allowlist_note_script.clone(),
+        serial(6),
+    )?;
+    assert_eq!(
+        run(&mut mock_chain, &mut ntb, mallory.id(), &unrelated).await?,
+        Some(AmmError::NoPendingChange)
+    );
+    assert!(ntb.note_status(early_sync.id()).is_none());
+
+    // ---------------------------------------------------------------------------------
+    // after the timelock anyone executes it; the waiting sync note then runs
+    // ---------------------------------------------------------------------------------
+    while !pending.is_ready(mock_chain.latest_block_header().block_num()) {
+        mock_chain.prove_next_block()?;
+    }
+    let apply = execute(mallory.id(), 7)?;
+    submit_notes(&mut mock_chain, mallory.id(), std::slice::from_ref(&apply)).await?;
+    let executed = ntb.run_until_idle(&mut mock_chain).await?;
+    let executed: Vec<_> = executed.iter().map(|e| e.note_id).collect();
+    assert_eq!(executed, vec![apply.id(), early_sync.id()]);
+
+    let pool = committed(&mock_chain, &build)?;
+    assert!(pending_allowlist_changes(&pool)?.is_empty());
+    let allowed = allowed_note_scripts(&pool)?;
+    assert!(allowed.contains(&sync_v2_root));
+    assert!(allowed.contains(&build.swap_note_script.root()));
+    let state = PoolState::from_account(&pool, build.pool_x(), build.pool_y())?;
+    assert_eq!((state.reserve_x, state.reserve_y), (10_000, 0));
+
+    // the manifest still loads the governed pool; verification reports the listed script
+    let reloaded = AmmManifest::from_build(&build).into_build(pool.clone())?;
+    assert_eq!(reloaded.governance, Some(governance));
+    let report = verify_pool_account(&pool)?;
+    assert!(matches!(
+        report.mismatches.as_slice(),
+        [PoolMismatch::NoteAllowlist { .. }]
+    ));
+
+    // ---------------------------------------------------------------------------------
+    // removal goes through the same timelock and unlists the script again
+    // ---------------------------------------------------------------------------------
+    let remove = propose(admin.id(), &build, AllowlistAction::Remove, 8)?;
+    assert_eq!(
+        run(&mut mock_chain, &mut ntb, admin.id(), &remove).await?,
+        None
+    );
+    let pending = pending_allowlist_changes(&committed(&mock_chain, &build)?)?;
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].action, AllowlistAction::Remove);
+    while !pending[0].is_ready(mock_chain.latest_block_header().block_num()) {
+        mock_chain.prove_next_block()?;
+    }
+    let apply = execute(admin.id(), 9)?;
+    assert_eq!(
+        run(&mut mock_chain, &mut ntb, admin.id(), &apply).await?,
+        None
+    );
+
+    let pool = committed(&mock_chain, &build)?;
+    assert_eq!(
+        allowed_note_scripts(&pool)?,
+        allowed_note_scripts(&build.account)?
+    );
+    let late_sync = create_sync_note(mallory.id(), amm_id, sync_v2, serial(10))?;
+    submit_notes(
+        &mut mock_chain,
+        mallory.id(),
+        std::slice::from_ref(&late_sync),
+    )
+    .await?;
+    assert!(ntb.run_until_idle(&mut mock_chain).await?.is_empty());
+    assert!(!kernel_accepts(&mock_chain, amm_id, &late_sync).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn governance_notes_cannot_unlist_themselves() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let admin = builder.add_existing_wallet_with_assets(auth(), [])?;
+
+    let governance = Governance::new(admin.id(), TIMELOCK);
+    let build = build_amm_account_with(
+        [9u8; 32],
+        faucet_x.id(),
+        faucet_y.id(),
+        FEE_BPS,
+        &AmmOptions::default().with_governance(governance),
+        true,
+    )?;
+    let amm_id = build.account.id();
+    builder.add_account(build.account.clone())?;
+    let mut mock_chain = builder.build()?;
+    let mut ntb = NtbEmulator::new().with_max_attempts(1);
+
+    let propose_root = build.propose_allowlist_note_script.root();
+    let execute_root = build.execute_allowlist_note_script.root();
+    let propose_removal = |script_root, n| {
+        create_propose_allowlist_note(
+            admin.id(),
+            amm_id,
+            script_root,
+            AllowlistAction::Remove,
+            build.propose_allowlist_note_script.clone(),
+            serial(n),
+        )
+    };
+
+    // the propose note cannot queue its own removal
+    let unlist_propose = propose_removal(propose_root, 1)?;
+    assert_eq!(
+        run(&mut mock_chain, &mut ntb, admin.id(), &unlist_propose).await?,
+        Some(AmmError::RemovesGovernanceNote)
+    );
+    assert!(pending_allowlist_changes(&committed(&mock_chain, &build)?)?.is_empty());
+
+    // the execute note's removal can be queued, but never applied
+    let unlist_execute = propose_removal(execute_root, 2)?;
+    assert_eq!(
+        run(&mut mock_chain, &mut ntb, admin.id(), &unlist_execute).await?,
+        None
+    );
+    let pending = pending_allowlist_changes(&committed(&mock_chain, &build)?)?;
+    assert_eq!(pending.len(), 1);
+    while !pending[0].is_ready(mock_chain.latest_block_header().block_num()) {
+        mock_chain.prove_next_block()?;
+    }
+    let apply = create_execute_allowlist_note(
+        admin.id(),
+        amm_id,
+        execute_root,
+        build.execute_allowlist_note_script.clone(),
+        serial(3),
+    )?;
+    assert_eq!(
+        run(&mut mock_chain, &mut ntb, admin.id(), &apply).await?,
+        Some(AmmError::RemovesGovernanceNote)
+    );
+
+    let pool = committed(&mock_chain, &build)?;
+    let allowed = allowed_note_scripts(&pool)?;
+    assert!(allowed.contains(&propose_root));
+    assert!(allowed.contains(&execute_root));
+    Ok(())
+}
@@ -0,0 +1,272 @@
+//! The in-VM invariant guards: a pool whose pricing or LP accounting has been broken on
+//! purpose still cannot lower k on a swap, or the reserves per LP token on a mint or burn —
+//! the transaction fails with the guard's error instead.
+
+mod common;
+
+use std::collections::BTreeSet;
+
+use anyhow::{Context, Result};
+use common::{FEE_BPS, add_pair_faucets, auth, serial};
+use miden_amm::{
+    artifacts::{deploy_tx_script, governance_component_code, lp_metadata_component_code},
+    common::{
+        ADD_LIQUIDITY_NOTE_CODE, AMM_CONTRACT_NS, GOVERNANCE_NS, LIQUIDITY_CONTRACT_NS,
+        LP_METADATA_NS, PayoutInfo, REMOVE_LIQUIDITY_NOTE_CODE, SWAP_NOTE_CODE, amm_code,
+        build_amm_account, create_add_liquidity_note, create_remove_liquidity_note,
+        create_swap_note, liquidity_code, quote_initial_lp,
+    },
+    errors::AmmError,
+    ntb::{NtbEmulator, NtbNoteState, consume_notes, submit_notes},
+};
+use miden_client::{
+    account::{
+        Account, AccountBuilder, AccountComponent, AccountId, AccountType,
+        component::{AccountComponentMetadata, AuthNetworkAccount, BasicWallet},
+    },
+    assembly::CodeBuilder,
+    asset::FungibleAsset,
+    note::{Note, NoteScript},
+};
+use miden_testing::MockChain;
+
+/// `source` with `from` (which must occur exactly once) replaced by `to`.
+fn patch(source: &str, from: &str, to: &str) -> String {
+    assert_eq!(source.matches(from).count(), 1, "patch target {from:?}");
+    source.replace(from, to)
+}
+
+/// An AMM pool assembled from the given (possibly broken) component sources, with the
+/// storage of a regular pool and note scripts linked against the broken code.
+struct BrokenPool {
+    account: Account,
+    swap_note_script: NoteScript,
+    add_liquidity_note_script: NoteScript,
+    remove_liquidity_note_script: NoteScript,
+}
+
+fn broken_pool(
+    seed: [u8; 32],
+    faucet_x: AccountId,
+    faucet_y: AccountId,
+    amm_code: &str,
+    liquidity_code: &str,
+) -> Result<BrokenPool> {
+    let swap_note_script = CodeBuilder::new()
+        .with_linked_module(AMM_CONTRACT_NS, amm_code)?
+        .compile_note_script(SWAP_NOTE_CODE)?;
+    let add_liquidity_note_script = CodeBuilder::new()
+        .with_linked_module(LIQUIDITY_CONTRACT_NS, liquidity_code)?
+        .compile_note_script(ADD_LIQUIDITY_NOTE_CODE)?;
+    let remove_liquidity_note_script = CodeBuilder::new()
+        .with_linked_module(LIQUIDITY_CONTRACT_NS, liquidity_code)?
+        .compile_note_script(REMOVE_LIQUIDITY_NOTE_CODE)?;
+
+    // the storage of a regular pool; the auth slots come from the allowlist below
+    let reference = build_amm_account(seed, faucet_x, faucet_y, FEE_BPS, true)?;
+    let slots = reference
+        .account
+        .storage()
+        .slots()
+        .iter()
+        .filter(|slot| !slot.name().as_str().starts_with("miden::standards::auth"))
+        .cloned()
+        .collect();
+
+    let network_auth = AuthNetworkAccount::with_allowed_notes(BTreeSet::from([
+        swap_note_script.root(),
+        add_liquidity_note_script.root(),
+        remove_liquidity_note_script.root(),
+    ]))?
+    .with_allowed_tx_scripts(BTreeSet::from([deploy_tx_script().root()]));
+    let amm = AccountComponent::new(
+        CodeBuilder::new().compile_component_code(AMM_CONTRACT_NS, amm_code)?,
+        slots,
+        AccountComponentMetadata::new(AMM_CONTRACT_NS),
+    )?;
+    let liquidity = AccountComponent::new(
+        CodeBuilder::new().compile_component_code(LIQUIDITY_CONTRACT_NS, liquidity_code)?,
+        vec![],
+        AccountComponentMetadata::new(LIQUIDITY_CONTRACT_NS),
+    )?;
+    let lp_metadata = AccountComponent::new(
+        lp_metadata_component_code(),
+        vec![],
+        AccountComponentMetadata::new(LP_METADATA_NS),
+    )?;
+    let governance = AccountComponent::new(
+        governance_component_code(),
+        vec![],
+        AccountComponentMetadata::new(GOVERNANCE_NS),
+    )?;
+    let account = AccountBuilder::new(seed)
+        .account_type(AccountType::Public)
+        .with_auth_component(network_auth)
+        .with_component(BasicWallet)
+        .with_component(amm)
+        .with_component(liquidity)
+        .with_component(lp_metadata)
+        .with_component(governance)
+        .build_existing()
+        .context("building the broken pool")?;
+
+    Ok(BrokenPool {
+        account,
+        swap_note_script,
+        add_liquidity_note_script,
+        remove_liquidity_note_script,
+    })
+}
+
+/// Runs `note` through the emulator and returns the AMM error it was discarded with.
+async fn rejection(
+    mock_chain: &mut MockChain,
+    sender: AccountId,
+    note: &Note,
+) -> Result<Option<AmmError>> {
+    let mut ntb = NtbEmulator::new().with_max_attempts(1);
+    submit_notes(mock_chain, sender, std::slice::from_ref(note)).await?;
+    assert!(ntb.run_until_idle(mock_chain).await?.is_empty());
+    let status = ntb.note_status(note.id()).context("note attempted")?;
+    assert_eq!(status.state, NtbNoteState::Discarded);
+    Ok(status
+        .last_error
+        .as_deref()
+        .and_then(AmmError::from_error_text))
+}
+
+#[tokio::test]
+async fn broken_pools_cannot_lose_value() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let x = |amount| FungibleAsset::new(faucet_x.id(), amount);
+    let y = |amount| FungibleAsset::new(faucet_y.id(), amount);
+    let alice = builder
+        .add_existing_wallet_with_assets(auth(), [x(1_000_000)?.into(), y(4_000_000)?.into()])?;
+
+    let amm = amm_code();
+    let liquidity = liquidity_code();
+    // swaps 1:1, ignoring the reserves and the fee
+    let flat_price = patch(
+        &amm,
+        "pub proc get_amount_y_out\n",
+        "pub proc get_amount_y_out\n    movdn.3 drop drop drop\nend\n\nproc get_amount_y_out_exact\n",
+    );
+    let swap_pool = broken_pool(
+        [1u8; 32],
+        faucet_x.id(),
+        faucet_y.id(),
+        &flat_price,
+        &liquidity,
+    )?;
+    // mints for the larger side of an unbalanced deposit
+    let generous_mint = patch(&liquidity, "exec.u64::min", "exec.u64::max");
+    let mint_pool = broken_pool(
+        [2u8; 32],
+        faucet_x.id(),
+        faucet_y.id(),
+        &amm,
+        &generous_mint,
+    )?;
+    // pays X out as if the locked minimum liquidity belonged to nobody
+    let generous_burn = patch(
+        &liquidity,
+        "mem_load.SUPPLY_PTR mem_load.X_PTR mem_load.LP_PTR",
+        "mem_load.SUPPLY_PTR sub.1000 mem_load.X_PTR mem_load.LP_PTR",
+    );
+    let burn_pool = broken_pool(
+        [3u8; 32],
+        faucet_x.id(),
+        faucet_y.id(),
+        &amm,
+        &generous_burn,
+    )?;
+    for pool in [&swap_pool, &mint_pool, &burn_pool] {
+        builder.add_account(pool.account.clone())?;
+    }
+
+    let mut mock_chain = builder.build()?;
+    let mut ntb = NtbEmulator::new();
+
+    // ---------------------------------------------------------------------------------
+    // the first deposit has no previous share to keep and goes through on every pool
+    // ---------------------------------------------------------------------------------
+    let (lp_minted, _) = quote_initial_lp(100_000, 400_000);
+    for (pool, n) in [(&swap_pool, 1), (&mint_pool, 2), (&burn_pool, 3)] {
+        let amm_id = pool.account.id();
+        let payout = PayoutInfo::new(alice.id(), serial(1000 + n));
+        let note = create_add_liquidity_note(
+            alice.id(),
+            amm_id,
+            x(100_000)?,
+            y(400_000)?,
+            lp_minted,
+            &payout,
+            pool.add_liquidity_note_script.clone(),
+            serial(n),
+        )?;
+        submit_notes(&mut mock_chain, alice.id(), std::slice::from_ref(&note)).await?;
+        assert_eq!(ntb.run_until_idle(&mut mock_chain).await?.len(), 1);
+        consume_notes(
+            &mut mock_chain,
+            alice.id(),
+            &[payout.expected_note(amm_id, vec![FungibleAsset::new(amm_id, lp_minted)?])?],
+        )
+        .await?;
+    }
+
+    // ---------------------------------------------------------------------------------
+    // swap: 40_000 Y for 40_000 X would leave k at 60_000 * 440_000 < 100_000 * 400_000
+    // ---------------------------------------------------------------------------------
+    let swap = create_swap_note(
+        alice.id(),
+        swap_pool.account.id(),
+        y(40_000)?,
+        faucet_x.id(),
+        1,
+        &PayoutInfo::new(alice.id(), serial(2000)),
+        swap_pool.swap_note_script.clone(),
+        serial(4),
+    )?;
+    assert_eq!(
+        rejection(&mut mock_chain, alice.id(), &swap).await?,
+        Some(AmmError::KDecreased)
+    );
+
+    // ---------------------------------------------------------------------------------
+    // mint: 100_000 X + 100_000 Y minted as if the Y side were 400_000
+    // ---------------------------------------------------------------------------------
+    let mint = create_add_liquidity_note(
+        alice.id(),
+        mint_pool.account.id(),
+        x(100_000)?,
+        y(100_000)?,
+        1,
+        &PayoutInfo::new(alice.id(), serial(3000)),
+        mint_pool.add_liquidity_note_script.clone(),
+        serial(5),
+    )?;
+    assert_eq!(
+        rejection(&mut mock_chain, alice.id(), &mint).await?,
+        Some(AmmError::LpValueDecreased)
+    );
+
+    // ---------------------------------------------------------------------------------
+    // burn: half the minted LP pays out more than half of the X reserve's share
+    // ---------------------------------------------------------------------------------
+    let burn = create_remove_liquidity_note(
+        alice.id(),
+        burn_pool.account.id(),
+        lp_minted / 2,
+        1,
+        1,
+        &PayoutInfo::new(alice.id(), serial(4000)),
+        burn_pool.remove_liquidity_note_script.clone(),
+        serial(6),
+    )?;
+    assert_eq!(
+        rejection(&mut mock_chain, alice.id(), &burn).await?,
+        Some(AmmError::LpValueDecreased)
+    );
+    Ok(())
+}
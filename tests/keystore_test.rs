@@ -0,0 +1,195 @@
+//! The client helpers with a keystore other than `FilesystemKeyStore` and with ECDSA auth,
+//! run against the in-process `MockNode`.
+
+mod common;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result};
+use common::temp_dir;
+use miden_amm::{
+    common::{
+        create_basic_account, create_basic_account_with_scheme, create_basic_faucet_with_scheme,
+        mint_and_consume,
+    },
+    mock_node::{MockNode, MockProver},
+};
+use miden_client::{
+    Client,
+    account::AccountId,
+    asset::{AssetCallbackFlag, AssetVaultKey},
+    auth::{
+        AuthSchemeId, AuthSecretKey, PublicKey, PublicKeyCommitment, Signature, SigningInputs,
+        TransactionAuthenticator,
+    },
+    builder::ClientBuilder,
+    errors::AuthenticationError,
+    keystore::{FilesystemKeyStore, KeyStoreError, Keystore},
+};
+use miden_client_sqlite_store::ClientBuilderSqliteExt;
+use miden_testing::MockChain;
+
+/// Keys held in memory only, standing in for any non-filesystem keystore (HSM, tests).
+#[derive(Debug, Default)]
+struct MemoryKeyStore {
+    keys: Mutex<BTreeMap<PublicKeyCommitment, AuthSecretKey>>,
+    accounts: Mutex<BTreeMap<AccountId, BTreeSet<PublicKeyCommitment>>>,
+}
+
+/// The client builder falls back to a filesystem keystore when none is set; this keystore is
+/// always set explicitly, so the fallback starts out empty.
+impl From<FilesystemKeyStore> for MemoryKeyStore {
+    fn from(_: FilesystemKeyStore) -> Self {
+        Self::default()
+    }
+}
+
+impl TransactionAuthenticator for MemoryKeyStore {
+    async fn get_signature(
+        &self,
+        pub_key: PublicKeyCommitment,
+        signing_inputs: &SigningInputs,
+    ) -> Result<Signature, AuthenticationError> {
+        let keys = self.keys.lock().unwrap();
+        let key = keys
+            .get(&pub_key)
+            .ok_or(AuthenticationError::UnknownPublicKey(pub_key))?;
+        Ok(key.sign(signing_inputs.to_commitment()))
+    }
+
+    async fn get_public_key(&self, pub_key: PublicKeyCommitment) -> Option<Arc<PublicKey>> {
+        let keys = self.keys.lock().unwrap();
+        keys.get(&pub_key).map(|key| Arc::new(key.public_key()))
+    }
+}
+
+#[async_trait::async_trait]
+impl Keystore for MemoryKeyStore {
+    async fn add_key(
+        &self,
+        key: &AuthSecretKey,
+        account_id: AccountId,
+    ) -> Result<(), KeyStoreError> {
+        let commitment = key.public_key().to_commitment();
+        self.keys.lock().unwrap().insert(commitment, key.clone());
+        self.accounts
+            .lock()
+            .unwrap()
+            .entry(account_id)
+            .or_default()
+            .insert(commitment);
+        Ok(())
+    }
+
+    async fn remove_key(&self, pub_key: PublicKeyCommitment) -> Result<(), KeyStoreError> {
+        self.keys.lock().unwrap().remove(&pub_key);
+        for commitments in self.accounts.lock().unwrap().values_mut() {
+            commitments.remove(&pub_key);
+        }
+        Ok(())
+    }
+
+    async fn get_key(
+        &self,
+        pub_key: PublicKeyCommitment,
+    ) -> Result<Option<AuthSecretKey>, KeyStoreError> {
+        Ok(self.keys.lock().unwrap().get(&pub_key).cloned())
+    }
+
+    async fn get_account_key_commitments(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<BTreeSet<PublicKeyCommitment>, KeyStoreError> {
+        self.accounts
+            .lock()
+            .unwrap()
+            .get(account_id)
+            .cloned()
+            .ok_or_else(|| KeyStoreError::StorageError(format!("unknown account {account_id}")))
+    }
+
+    async fn get_account_id_by_key_commitment(
+        &self,
+        pub_key: PublicKeyCommitment,
+    ) -> Result<Option<AccountId>, KeyStoreError> {
+        let accounts = self.accounts.lock().unwrap();
+        Ok(accounts
+            .iter()
+            .find(|(_, commitments)| commitments.contains(&pub_key))
+            .map(|(account_id, _)| *account_id))
+    }
+}
+
+async fn balance_of<K: Keystore + Sync + 'static>(
+    client: &mut Client<K>,
+    account_id: AccountId,
+    faucet_id: AccountId,
+) -> Result<u64> {
+    client.sync_state().await?;
+    let account = client
+        .get_account(account_id)
+        .await?
+        .context("account not found")?;
+    Ok(account
+        .vault()
+        .get_balance(AssetVaultKey::new_fungible(
+            faucet_id,
+            AssetCallbackFlag::Disabled,
+        ))
+        .context("reading vault balance")?
+        .as_u64())
+}
+
+#[tokio::test]
+async fn helpers_run_with_an_in_memory_keystore_and_ecdsa_auth() -> Result<()> {
+    let node = MockNode::new(MockChain::new());
+    let dir = temp_dir("memory-keystore");
+    let keystore = Arc::new(MemoryKeyStore::default());
+    let mut client = ClientBuilder::new()
+        .rpc(Arc::new(node.clone()))
+        .sqlite_store(dir.join("store.sqlite3"))
+        .authenticator(keystore.clone())
+        .prover(Arc::new(MockProver::new(&node)))
+        .build()
+        .await?;
+    client.sync_state().await?;
+
+    let faucet = create_basic_faucet_with_scheme(
+        &mut client,
+        &keystore,
+        "TKE",
+        AuthSchemeId::EcdsaK256Keccak,
+    )
+    .await?;
+    let ecdsa_wallet =
+        create_basic_account_with_scheme(&mut client, &keystore, AuthSchemeId::EcdsaK256Keccak)
+            .await?;
+    let falcon_wallet = create_basic_account(&mut client, &keystore).await?;
+
+    // every key lives in the in-memory keystore, under the scheme it was created with
+    for (account, scheme) in [
+        (&faucet, AuthSchemeId::EcdsaK256Keccak),
+        (&ecdsa_wallet, AuthSchemeId::EcdsaK256Keccak),
+        (&falcon_wallet, AuthSchemeId::Falcon512Poseidon2),
+    ] {
+        let keys = keystore.get_keys_for_account(&account.id()).await?;
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].auth_scheme(), scheme);
+    }
+
+    // the faucet signs the mint and each wallet signs its consume with its own scheme
+    mint_and_consume(&mut client, faucet.id(), ecdsa_wallet.id(), 5_000).await?;
+    mint_and_consume(&mut client, faucet.id(), falcon_wallet.id(), 7_000).await?;
+    assert_eq!(
+        balance_of(&mut client, ecdsa_wallet.id(), faucet.id()).await?,
+        5_000
+    );
+    assert_eq!(
+        balance_of(&mut client, falcon_wallet.id(), faucet.id()).await?,
+        7_000
+    );
+    Ok(())
+}
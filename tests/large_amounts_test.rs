@@ -0,0 +1,119 @@
+//! Swaps at the top of the fungible asset range: the pool prices inputs whose `dx * (D - fee)`
+//! no longer fits in a u64 against reserves of the same scale, and the VM agrees with
+//! `quote_swap_output` to the unit.
+
+mod common;
+
+use anyhow::Result;
+use common::{FEE_BPS, add_pool, auth, serial};
+use miden_amm::{
+    common::{
+        AmmBuild, FEE_DENOM, PayoutInfo, PoolState, create_add_liquidity_note, create_swap_note,
+        quote_initial_lp, quote_swap_output,
+    },
+    ntb::{NtbEmulator, consume_notes, submit_notes},
+};
+use miden_client::asset::FungibleAsset;
+use miden_testing::MockChain;
+
+fn pool_state(mock_chain: &MockChain, build: &AmmBuild) -> Result<PoolState> {
+    PoolState::from_account(
+        mock_chain.committed_account(build.account.id())?,
+        build.pool_x(),
+        build.pool_y(),
+    )
+}
+
+#[tokio::test]
+async fn swaps_near_the_maximum_amount() -> Result<()> {
+    let max = FungibleAsset::MAX_AMOUNT.as_u64();
+    let mut builder = MockChain::builder();
+    let faucet_x = builder.add_existing_basic_faucet(auth(), "TKX", max, Some(18))?;
+    let faucet_y = builder.add_existing_basic_faucet(auth(), "TKY", max, Some(18))?;
+    let x = |amount| FungibleAsset::new(faucet_x.id(), amount);
+    let y = |amount| FungibleAsset::new(faucet_y.id(), amount);
+    let alice =
+        builder.add_existing_wallet_with_assets(auth(), [x(max)?.into(), y(max)?.into()])?;
+
+    let build = add_pool(&mut builder, &faucet_x, &faucet_y)?;
+    let amm_id = build.account.id();
+    let mut mock_chain = builder.build()?;
+    let mut ntb = NtbEmulator::new();
+
+    // ---------------------------------------------------------------------------------
+    // reserves of 2^61 X and 2^62 Y
+    // ---------------------------------------------------------------------------------
+    let (reserve_x, reserve_y) = (1u64 << 61, 1u64 << 62);
+    let (lp_minted, _) = quote_initial_lp(reserve_x, reserve_y);
+    let add_payout = PayoutInfo::new(alice.id(), serial(1000));
+    let add_note = create_add_liquidity_note(
+        alice.id(),
+        amm_id,
+        x(reserve_x)?,
+        y(reserve_y)?,
+        lp_minted,
+        &add_payout,
+        build.add_liquidity_note_script.clone(),
+        serial(1),
+    )?;
+    submit_notes(&mut mock_chain, alice.id(), std::slice::from_ref(&add_note)).await?;
+    assert_eq!(ntb.run_until_idle(&mut mock_chain).await?.len(), 1);
+    consume_notes(
+        &mut mock_chain,
+        alice.id(),
+        &[add_payout.expected_note(amm_id, vec![FungibleAsset::new(amm_id, lp_minted)?])?],
+    )
+    .await?;
+
+    // ---------------------------------------------------------------------------------
+    // X -> Y with dx * (D - fee) ~ 2^75, filling the X vault up to the maximum amount
+    // ---------------------------------------------------------------------------------
+    let mut expected = pool_state(&mock_chain, &build)?;
+    let dx = max - reserve_x;
+    assert!((dx as u128) * ((FEE_DENOM - FEE_BPS) as u128) > u64::MAX as u128);
+    let dy = quote_swap_output(dx, reserve_x, reserve_y, FEE_BPS);
+    assert_eq!(expected.apply_swap(dx, true)?, dy);
+
+    let swap_payout = PayoutInfo::new(alice.id(), serial(2000));
+    let swap = create_swap_note(
+        alice.id(),
+        amm_id,
+        x(dx)?,
+        faucet_y.id(),
+        dy,
+        &swap_payout,
+        build.swap_note_script.clone(),
+        serial(2),
+    )?;
+    submit_notes(&mut mock_chain, alice.id(), std::slice::from_ref(&swap)).await?;
+    let executed = ntb.run_until_idle(&mut mock_chain).await?;
+    let swap_claim = swap_payout.expected_note(amm_id, vec![y(dy)?])?;
+    assert_eq!(executed[0].output_notes, vec![swap_claim.id()]);
+    assert_eq!(pool_state(&mock_chain, &build)?, expected);
+    consume_notes(&mut mock_chain, alice.id(), &[swap_claim]).await?;
+
+    // ---------------------------------------------------------------------------------
+    // Y -> X back: a large input against an X reserve at the maximum amount
+    // ---------------------------------------------------------------------------------
+    let dy_in = max - expected.reserve_y;
+    let dx_out = quote_swap_output(dy_in, expected.reserve_y, expected.reserve_x, FEE_BPS);
+    assert_eq!(expected.apply_swap(dy_in, false)?, dx_out);
+
+    let back_payout = PayoutInfo::new(alice.id(), serial(3000));
+    let back = create_swap_note(
+        alice.id(),
+        amm_id,
+        y(dy_in)?,
+        faucet_x.id(),
+        dx_out,
+        &back_payout,
+        build.swap_note_script.clone(),
+        serial(3),
+    )?;
+    submit_notes(&mut mock_chain, alice.id(), std::slice::from_ref(&back)).await?;
+    let executed = ntb.run_until_idle(&mut mock_chain).await?;
+    let back_claim = back_payout.expected_note(amm_id, vec![x(dx_out)?])?;
+    assert_eq!(executed[0].output_notes, vec![back_claim.id()]);
+    assert_eq!(pool_state(&mock_chain, &build)?, expected);
+    Ok(())
+}
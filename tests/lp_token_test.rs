@@ -0,0 +1,158 @@
+//! The pool carries LP token metadata in the standard fungible-faucet slots: wallets read the
+//! pair-derived name and symbol, the token supply follows `lp_supply`, and the metadata
+//! component adds no way to mint.
+
+mod common;
+
+use anyhow::{Context, Result};
+use common::{FEE_BPS, add_pair_faucets, auth, serial};
+use miden_amm::{
+    common::{
+        AmmBuild, AmmOptions, LpToken, PayoutInfo, PoolState, build_amm_account,
+        build_amm_account_with, create_add_liquidity_note, create_remove_liquidity_note,
+        quote_initial_lp, quote_remove_liquidity,
+    },
+    ntb::{NtbEmulator, consume_notes, submit_notes},
+};
+use miden_client::{
+    Word,
+    account::{Account, component::FungibleFaucet},
+    asset::{FungibleAsset, TokenSymbol},
+};
+use miden_testing::MockChain;
+
+/// The LP token as a wallet sees it, plus the pool's own `lp_supply`.
+fn lp_view(account: &Account, build: &AmmBuild) -> Result<(FungibleFaucet, u64)> {
+    let token = FungibleFaucet::try_from(account.storage()).context("reading LP metadata")?;
+    let state = PoolState::from_account(account, build.pool_x(), build.pool_y())?;
+    Ok((token, state.lp_supply))
+}
+
+#[test]
+fn lp_token_names_follow_the_pair() -> Result<()> {
+    let lp = LpToken::for_pair(&TokenSymbol::new("TKX")?, &TokenSymbol::new("TKY")?, 6)?;
+    assert_eq!(lp.name.as_str(), "LP-TKX-TKY");
+    assert_eq!(lp.symbol.to_string(), "LPTKXTKY");
+    assert_eq!(lp.decimals, 6);
+
+    // long symbols keep the full name, the symbol is cut to 12 letters
+    let lp = LpToken::for_pair(&TokenSymbol::new("ABCDEFGH")?, &TokenSymbol::new("XYZ")?, 8)?;
+    assert_eq!(lp.name.as_str(), "LP-ABCDEFGH-XYZ");
+    assert_eq!(lp.symbol.to_string(), "LPABCDEFGHXY");
+
+    let default = LpToken::default();
+    assert_eq!(default.symbol.to_string(), "LP");
+    Ok(())
+}
+
+#[tokio::test]
+async fn lp_metadata_tracks_the_supply() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let x = |amount| FungibleAsset::new(faucet_x.id(), amount);
+    let y = |amount| FungibleAsset::new(faucet_y.id(), amount);
+    let alice = builder
+        .add_existing_wallet_with_assets(auth(), [x(1_000_000)?.into(), y(4_000_000)?.into()])?;
+
+    let lp_token = LpToken::from_faucets(&faucet_x, &faucet_y)?;
+    assert_eq!(
+        lp_token.decimals,
+        FungibleFaucet::try_from(faucet_x.storage())?.decimals()
+    );
+    let build = build_amm_account_with(
+        [7u8; 32],
+        faucet_x.id(),
+        faucet_y.id(),
+        FEE_BPS,
+        &AmmOptions::default().with_lp_token(lp_token.clone()),
+        true,
+    )?;
+    let amm_id = build.account.id();
+    assert_eq!(build.lp_token, lp_token);
+    builder.add_account(build.account.clone())?;
+
+    // ---------------------------------------------------------------------------------
+    // metadata only: the standard getters, none of the faucet's mint / burn / setters
+    // ---------------------------------------------------------------------------------
+    let (token, _) = lp_view(&build.account, &build)?;
+    assert_eq!(token.token_name().as_str(), "LP-TKX-TKY");
+    assert_eq!(token.symbol().to_string(), "LPTKXTKY");
+    assert_eq!(token.decimals(), lp_token.decimals);
+    assert_eq!(token.token_supply().as_u64(), 0);
+    assert_eq!(LpToken::from_account(&build.account)?, lp_token);
+
+    let roots: Vec<Word> = build.account.code().procedure_roots().collect();
+    for forbidden in [
+        FungibleFaucet::mint_and_send_root(),
+        FungibleFaucet::receive_and_burn_root(),
+        FungibleFaucet::set_max_supply_root(),
+        FungibleFaucet::set_description_root(),
+    ] {
+        assert!(!roots.contains(&forbidden.into()));
+    }
+
+    // pools built without pair metadata still expose a valid token
+    let plain = build_amm_account([8u8; 32], faucet_x.id(), faucet_y.id(), FEE_BPS, true)?;
+    assert_eq!(LpToken::from_account(&plain.account)?, LpToken::default());
+
+    let mut mock_chain = builder.build()?;
+    let mut ntb = NtbEmulator::new();
+
+    // ---------------------------------------------------------------------------------
+    // deposit: the token supply is the minted LP plus the locked minimum
+    // ---------------------------------------------------------------------------------
+    let (lp_minted, supply) = quote_initial_lp(100_000, 400_000);
+    let add_payout = PayoutInfo::new(alice.id(), serial(1000));
+    let add_note = create_add_liquidity_note(
+        alice.id(),
+        amm_id,
+        x(100_000)?,
+        y(400_000)?,
+        lp_minted,
+        &add_payout,
+        build.add_liquidity_note_script.clone(),
+        serial(1),
+    )?;
+    submit_notes(&mut mock_chain, alice.id(), std::slice::from_ref(&add_note)).await?;
+    assert_eq!(ntb.run_until_idle(&mut mock_chain).await?.len(), 1);
+    consume_notes(
+        &mut mock_chain,
+        alice.id(),
+        &[add_payout.expected_note(amm_id, vec![FungibleAsset::new(amm_id, lp_minted)?])?],
+    )
+    .await?;
+
+    let (token, lp_supply) = lp_view(mock_chain.committed_account(amm_id)?, &build)?;
+    assert_eq!(lp_supply, supply);
+    assert_eq!(token.token_supply().as_u64(), supply);
+    assert_eq!(token.symbol().to_string(), "LPTKXTKY");
+
+    // ---------------------------------------------------------------------------------
+    // burn: the supply drops with lp_supply
+    // ---------------------------------------------------------------------------------
+    let lp_burn = lp_minted / 2;
+    let (ax, ay) = quote_remove_liquidity(lp_burn, 100_000, 400_000, supply);
+    let remove_payout = PayoutInfo::new(alice.id(), serial(2000));
+    let remove_note = create_remove_liquidity_note(
+        alice.id(),
+        amm_id,
+        lp_burn,
+        ax,
+        ay,
+        &remove_payout,
+        build.remove_liquidity_note_script.clone(),
+        serial(2),
+    )?;
+    submit_notes(
+        &mut mock_chain,
+        alice.id(),
+        std::slice::from_ref(&remove_note),
+    )
+    .await?;
+    assert_eq!(ntb.run_until_idle(&mut mock_chain).await?.len(), 1);
+
+    let (token, lp_supply) = lp_view(mock_chain.committed_account(amm_id)?, &build)?;
+    assert_eq!(lp_supply, supply - lp_burn);
+    assert_eq!(token.token_supply().as_u64(), supply - lp_burn);
+    Ok(())
+}
@@ -0,0 +1,132 @@
+//! Deployment manifests: written at deploy time, reloaded without recompiling, and checked
+//! against the deployed account.
+
+mod common;
+
+use anyhow::Result;
+use common::{FEE_BPS, add_pair_faucets, auth, serial};
+use miden_amm::{
+    common::{
+        PayoutInfo, PoolState, build_amm_account, create_add_liquidity_note, quote_initial_lp,
+    },
+    manifest::AmmManifest,
+    ntb::{NtbEmulator, submit_notes},
+};
+use miden_client::{Deserializable, Serializable, asset::FungibleAsset};
+use miden_testing::{MockChain, TxContextInput};
+
+#[tokio::test]
+async fn manifest_reloads_a_deployed_pool() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let alice = builder.add_existing_wallet_with_assets(
+        auth(),
+        [
+            FungibleAsset::new(faucet_x.id(), 100_000)?.into(),
+            FungibleAsset::new(faucet_y.id(), 400_000)?.into(),
+        ],
+    )?;
+    let mut mock_chain = builder.build()?;
+
+    // ---------------------------------------------------------------------------------
+    // deploy, writing the manifest alongside
+    // ---------------------------------------------------------------------------------
+    let build = build_amm_account([7u8; 32], faucet_x.id(), faucet_y.id(), FEE_BPS, false)?;
+    let amm_id = build.account.id();
+    let deploy = mock_chain
+        .build_tx_context(TxContextInput::Account(build.account.clone()), &[], &[])?
+        .tx_script(build.deploy_tx_script.clone())
+        .build()?
+        .execute()
+        .await?;
+    mock_chain.add_pending_executed_transaction(&deploy)?;
+    mock_chain.prove_next_block()?;
+
+    let dir = std::env::temp_dir().join(format!("miden-amm-manifest-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("amm.manifest");
+    let manifest = AmmManifest::from_build(&build);
+    manifest.write_to_file(&path)?;
+
+    // ---------------------------------------------------------------------------------
+    // reload: same scripts, verified against the on-chain account
+    // ---------------------------------------------------------------------------------
+    let reloaded = AmmManifest::read_from_file(&path)?;
+    assert_eq!(reloaded, manifest);
+    assert_eq!(reloaded.init_seed, [7u8; 32]);
+    let on_chain = mock_chain.committed_account(amm_id)?.clone();
+    let reloaded_build = reloaded.into_build(on_chain.clone())?;
+    assert_eq!(
+        reloaded_build.swap_note_script.root(),
+        build.swap_note_script.root()
+    );
+
+    // notes built from the reloaded scripts are picked up and executed by the NTB
+    let (lp_minted, supply) = quote_initial_lp(100_000, 400_000);
+    let add_note = create_add_liquidity_note(
+        alice.id(),
+        amm_id,
+        FungibleAsset::new(faucet_x.id(), 100_000)?,
+        FungibleAsset::new(faucet_y.id(), 400_000)?,
+        lp_minted,
+        &PayoutInfo::new(alice.id(), serial(1000)),
+        reloaded_build.add_liquidity_note_script.clone(),
+        serial(1),
+    )?;
+    submit_notes(&mut mock_chain, alice.id(), &[add_note]).await?;
+    assert_eq!(
+        NtbEmulator::new()
+            .run_until_idle(&mut mock_chain)
+            .await?
+            .len(),
+        1
+    );
+    let pool = PoolState::from_account(
+        mock_chain.committed_account(amm_id)?,
+        faucet_x.id(),
+        faucet_y.id(),
+    )?;
+    assert_eq!(pool.lp_supply, supply);
+
+    // ---------------------------------------------------------------------------------
+    // mismatches are reported
+    // ---------------------------------------------------------------------------------
+    // a pool with the same scripts but another fee in its config slot
+    let other_fee = build_amm_account([7u8; 32], faucet_x.id(), faucet_y.id(), 50, false)?;
+    let mut wrong_fee = manifest.clone();
+    wrong_fee.amm_id = other_fee.account.id();
+    let err = wrong_fee.verify_account(&other_fee.account).unwrap_err();
+    assert!(err.to_string().contains("fee is 50 bps"), "{err:#}");
+
+    // a script the pool does not allowlist
+    let mut wrong_script = manifest.clone();
+    wrong_script.swap_note_script = build.add_liquidity_note_script.clone();
+    let err = wrong_script.verify_account(&on_chain).unwrap_err();
+    assert!(
+        err.to_string().contains("note allowlist mismatch"),
+        "{err:#}"
+    );
+
+    // another account entirely
+    let err = manifest.verify_account(&faucet_x).unwrap_err();
+    assert!(err.to_string().contains("manifest is for"), "{err:#}");
+
+    // ---------------------------------------------------------------------------------
+    // corrupted or foreign files are rejected on load
+    // ---------------------------------------------------------------------------------
+    let mut bytes = manifest.to_bytes();
+    bytes[0] = b'X';
+    assert!(AmmManifest::read_from_bytes(&bytes).is_err());
+
+    let mut bytes = manifest.to_bytes();
+    bytes[4] = 99;
+    assert!(AmmManifest::read_from_bytes(&bytes).is_err());
+
+    // the first recorded root no longer matches the swap script that follows it
+    let mut bytes = manifest.to_bytes();
+    let root_offset = 4 + 1 + 15 + 32 + 15 + 15 + 1 + 1 + 8;
+    bytes[root_offset] ^= 1;
+    let err = AmmManifest::read_from_bytes(&bytes).unwrap_err();
+    assert!(err.to_string().contains("swap note script"), "{err}");
+    Ok(())
+}
@@ -40,8 +40,7 @@ fn lp_supply_of(account: &Account) -> u64 {
     let word: Word = account
         .storage()
         .get_item(&lp_supply_slot())
-        .expect("lp_supply slot exists")
-        .into();
+        .expect("lp_supply slot exists");
     word[0].as_canonical_u64()
 }
 
@@ -66,7 +65,7 @@ async fn consume_amm_note(
         produced.contains(&expected_id),
         "expected payout note {expected_id:?} not among produced output notes {produced:?}"
     );
-    amm_account.apply_delta(&executed.account_delta())?;
+    amm_account.apply_delta(executed.account_delta())?;
     mock_chain.add_pending_executed_transaction(&executed)?;
     mock_chain.prove_next_block()?;
     Ok(())
@@ -117,7 +116,7 @@ async fn uniswap_v2_lifecycle() -> Result<()> {
     )?;
 
     // 2) second deposit: 50_000 X + 200_000 Y  ->  pro-rata mint of 100_000 LP
-    let lp2_minted = quote_lp_mint(50_000, 200_000, 100_000, 400_000, supply1);
+    let lp2_minted = quote_lp_mint(50_000, 200_000, 100_000, 400_000, supply1).unwrap();
     assert_eq!(lp2_minted, 100_000);
     let add2_payout = PayoutInfo::new(alice.id(), serial(2000));
     let add2_note = create_add_liquidity_note(
@@ -583,7 +582,7 @@ async fn remove_liquidity_payout_bound_to_sender() -> Result<()> {
         !produced.contains(&alice_directed.id()),
         "a bob-sent remove note must not pay out to alice"
     );
-    amm_account.apply_delta(&executed.account_delta())?;
+    amm_account.apply_delta(executed.account_delta())?;
     assert_eq!(lp_supply_of(&amm_account), supply - lp_burn);
     Ok(())
 }
@@ -0,0 +1,364 @@
+//! Client-level tests against the in-process `MockNode`: the live-network client helpers and
+//! the payout tracker run unchanged, with a real `Client` (sqlite store, filesystem keystore)
+//! talking to a MockChain-backed node whose NTB emulator executes the AMM's network notes.
+
+mod common;
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use common::{FEE_BPS, mock_client, temp_dir};
+use miden_amm::{
+    common::{
+        AmmBuild, PayoutInfo, PoolState, WaitOptions, build_amm_account, create_add_liquidity_note,
+        create_basic_account, create_basic_faucet, create_swap_note, mint_and_consume,
+        quote_initial_lp, quote_swap_output, wait_for_note, wait_for_tx,
+    },
+    errors::AmmError,
+    mock_node::MockNode,
+    payouts::{
+        ClaimReplays, PayoutStore, claim_pending_payouts, run_payout_claimer,
+        submit_tracked_amm_note,
+    },
+};
+use miden_client::{
+    Client,
+    account::{Account, AccountId},
+    asset::{AssetCallbackFlag, AssetVaultKey, FungibleAsset},
+    crypto::FeltRng,
+    keystore::FilesystemKeyStore,
+    note::NoteType,
+    rpc::{NetworkNoteStatus, NodeRpcClient, RpcError},
+    transaction::{PaymentNoteDescription, TransactionRequestBuilder},
+};
+use miden_testing::MockChain;
+use tokio::time::Duration;
+
+async fn balance_of(
+    client: &mut Client<FilesystemKeyStore>,
+    account_id: AccountId,
+    faucet_id: AccountId,
+) -> Result<u64> {
+    client.sync_state().await?;
+    let account = client
+        .get_account(account_id)
+        .await?
+        .context("account not found")?;
+    Ok(account
+        .vault()
+        .get_balance(AssetVaultKey::new_fungible(
+            faucet_id,
+            AssetCallbackFlag::Disabled,
+        ))
+        .context("reading vault balance")?
+        .as_u64())
+}
+
+/// Creates alice and the pair's faucets, funds alice, and deploys the AMM through `client`.
+async fn deploy_pool(
+    client: &mut Client<FilesystemKeyStore>,
+    keystore: &Arc<FilesystemKeyStore>,
+) -> Result<(Account, Account, Account, AmmBuild)> {
+    let alice = create_basic_account(client, keystore).await?;
+    let faucet_x = create_basic_faucet(client, keystore, "TKX").await?;
+    let faucet_y = create_basic_faucet(client, keystore, "TKY").await?;
+    mint_and_consume(client, faucet_x.id(), alice.id(), 1_000_000).await?;
+    mint_and_consume(client, faucet_y.id(), alice.id(), 4_000_000).await?;
+
+    let build = build_amm_account([7u8; 32], faucet_x.id(), faucet_y.id(), FEE_BPS, false)?;
+    client.add_account(&build.account, false).await?;
+    let deploy = TransactionRequestBuilder::new()
+        .custom_script(build.deploy_tx_script.clone())
+        .build()?;
+    let deploy_tx = client
+        .submit_new_transaction(build.account.id(), deploy)
+        .await?;
+    wait_for_tx(client, deploy_tx).await?;
+    Ok((alice, faucet_x, faucet_y, build))
+}
+
+/// Every transaction commits in the block after its submission, so the polling helpers
+/// return on their first check.
+#[tokio::test]
+async fn client_helpers_run_against_the_mock_node() -> Result<()> {
+    let node = MockNode::new(MockChain::new());
+    let dir = temp_dir("mock-node-helpers");
+    let (mut client, keystore) = mock_client(&node, &dir).await?;
+
+    let alice = create_basic_account(&mut client, &keystore).await?;
+    let bob = create_basic_account(&mut client, &keystore).await?;
+    let faucet = create_basic_faucet(&mut client, &keystore, "TKX").await?;
+    let tip = node.chain_tip();
+
+    mint_and_consume(&mut client, faucet.id(), alice.id(), 10_000).await?;
+    assert_eq!(
+        balance_of(&mut client, alice.id(), faucet.id()).await?,
+        10_000
+    );
+    // the mint and the consume each committed in a block of their own
+    assert_eq!(node.chain_tip(), tip + 2);
+
+    // a P2ID payment shows up as consumable for bob once its transaction is committed
+    let payment = TransactionRequestBuilder::new().build_pay_to_id(
+        PaymentNoteDescription::new(
+            vec![FungibleAsset::new(faucet.id(), 2_500)?.into()],
+            alice.id(),
+            bob.id(),
+        ),
+        NoteType::Public,
+        client.rng(),
+    )?;
+    let note = payment
+        .expected_output_own_notes()
+        .into_iter()
+        .next()
+        .context("payment note")?;
+    let tx_id = client.submit_new_transaction(alice.id(), payment).await?;
+    wait_for_tx(&mut client, tx_id).await?;
+    wait_for_note(&mut client, bob.id(), &note).await?;
+    assert_eq!(
+        balance_of(&mut client, alice.id(), faucet.id()).await?,
+        7_500
+    );
+
+    // a plain P2ID note is not a network note: the node has no status for it
+    let status = node.get_network_note_status(note.id()).await;
+    assert!(matches!(status, Err(RpcError::NoteNotFound(id)) if id == note.id()));
+    Ok(())
+}
+
+/// Deploys the AMM through the client, submits tracked AMM notes that the node's NTB
+/// executes, and claims the resulting private payouts with the payout claimer.
+#[tokio::test]
+async fn payouts_are_claimed_from_mock_node_network_transactions() -> Result<()> {
+    let node = MockNode::new(MockChain::new());
+    let dir = temp_dir("mock-node-payouts");
+    let (mut client, keystore) = mock_client(&node, &dir).await?;
+    let store = PayoutStore::open_next_to(dir.join("store.sqlite3"))?;
+
+    let (alice, faucet_x, faucet_y, build) = deploy_pool(&mut client, &keystore).await?;
+    let amm_id = build.account.id();
+
+    // ---------------------------------------------------------------------------------
+    // add liquidity: the NTB executes the note inside the submission, the claimer then
+    // consumes the LP payout
+    // ---------------------------------------------------------------------------------
+    let (lp_minted, supply) = quote_initial_lp(100_000, 400_000);
+    let add_payout = PayoutInfo::new(alice.id(), client.rng().draw_word());
+    let add_note = create_add_liquidity_note(
+        alice.id(),
+        amm_id,
+        FungibleAsset::new(faucet_x.id(), 100_000)?,
+        FungibleAsset::new(faucet_y.id(), 400_000)?,
+        lp_minted,
+        &add_payout,
+        build.add_liquidity_note_script.clone(),
+        client.rng().draw_word(),
+    )?;
+    let add_note_id = add_note.id();
+    submit_tracked_amm_note(
+        &mut client,
+        &store,
+        alice.id(),
+        amm_id,
+        add_note,
+        &add_payout,
+        vec![FungibleAsset::new(amm_id, lp_minted)?],
+    )
+    .await?;
+    let status = node.get_network_note_status(add_note_id).await?;
+    assert!(matches!(
+        status.status,
+        NetworkNoteStatus::NullifierCommitted
+    ));
+    assert_eq!(status.attempt_count, 1);
+
+    let pools = [build.clone()];
+    let options =
+        WaitOptions::default().with_backoff(Duration::from_millis(10), Duration::from_millis(10));
+    let report = run_payout_claimer(&mut client, &node, &store, &pools, &options).await?;
+    assert_eq!(report.claimed.len(), 1);
+    assert!(report.is_done());
+    assert_eq!(
+        balance_of(&mut client, alice.id(), amm_id).await?,
+        lp_minted
+    );
+
+    // ---------------------------------------------------------------------------------
+    // a fair swap is claimed; a greedy one is discarded and its payout is given up on
+    // ---------------------------------------------------------------------------------
+    let dy = quote_swap_output(30_000, 100_000, 400_000, FEE_BPS);
+    let y_before = balance_of(&mut client, alice.id(), faucet_y.id()).await?;
+    let swap_payout = PayoutInfo::new(alice.id(), client.rng().draw_word());
+    let swap_note = create_swap_note(
+        alice.id(),
+        amm_id,
+        FungibleAsset::new(faucet_x.id(), 30_000)?,
+        faucet_y.id(),
+        dy,
+        &swap_payout,
+        build.swap_note_script.clone(),
+        client.rng().draw_word(),
+    )?;
+    submit_tracked_amm_note(
+        &mut client,
+        &store,
+        alice.id(),
+        amm_id,
+        swap_note,
+        &swap_payout,
+        vec![FungibleAsset::new(faucet_y.id(), dy)?],
+    )
+    .await?;
+
+    let greedy_payout = PayoutInfo::new(alice.id(), client.rng().draw_word());
+    let greedy_note = create_swap_note(
+        alice.id(),
+        amm_id,
+        FungibleAsset::new(faucet_x.id(), 1_000)?,
+        faucet_y.id(),
+        1_000_000,
+        &greedy_payout,
+        build.swap_note_script.clone(),
+        client.rng().draw_word(),
+    )?;
+    let greedy_id = greedy_note.id();
+    let greedy_payout_id = submit_tracked_amm_note(
+        &mut client,
+        &store,
+        alice.id(),
+        amm_id,
+        greedy_note,
+        &greedy_payout,
+        vec![FungibleAsset::new(faucet_y.id(), 1_000_000)?],
+    )
+    .await?;
+    let status = node.get_network_note_status(greedy_id).await?;
+    assert!(matches!(status.status, NetworkNoteStatus::Discarded));
+    assert_eq!(status.attempt_count, 3);
+    assert_eq!(
+        status
+            .last_error
+            .as_deref()
+            .and_then(AmmError::from_error_text),
+        Some(AmmError::Slippage)
+    );
+
+    // the first pass submits the fair swap's claim and gives up on the greedy payout
+    let mut replays = ClaimReplays::default();
+    let pass = claim_pending_payouts(&mut client, &node, &store, &pools, &mut replays).await?;
+    assert_eq!(pass.in_flight.len(), 1);
+    assert_eq!(pass.unresolvable, vec![greedy_payout_id]);
+    let pass = claim_pending_payouts(&mut client, &node, &store, &pools, &mut replays).await?;
+    assert_eq!(pass.claimed.len(), 1);
+    assert!(pass.is_done());
+    let given_up = store.unresolvable()?;
+    assert_eq!(given_up.len(), 1);
+    assert!(
+        given_up[0]
+            .last_error
+            .as_deref()
+            .is_some_and(|e| e.starts_with("AMM note discarded"))
+    );
+    assert_eq!(
+        balance_of(&mut client, alice.id(), faucet_y.id()).await?,
+        y_before + dy
+    );
+
+    client.sync_state().await?;
+    let amm = client
+        .get_account(amm_id)
+        .await?
+        .context("AMM account not tracked")?;
+    let pool = PoolState::from_account(&amm, faucet_x.id(), faucet_y.id())?;
+    assert_eq!(
+        (pool.reserve_x, pool.reserve_y, pool.lp_supply),
+        (130_000, 400_000 - dy, supply)
+    );
+    Ok(())
+}
+
+/// A payout recorded at the quote's minimum output differs from what the AMM pays: its
+/// claim fails, the claimer replays the pool's history and claims the amounts actually paid.
+#[tokio::test]
+async fn payouts_differing_from_their_quote_are_resolved() -> Result<()> {
+    let node = MockNode::new(MockChain::new());
+    let dir = temp_dir("mock-node-resolve");
+    let (mut client, keystore) = mock_client(&node, &dir).await?;
+    let store = PayoutStore::open_next_to(dir.join("store.sqlite3"))?;
+    let (alice, faucet_x, faucet_y, build) = deploy_pool(&mut client, &keystore).await?;
+    let amm_id = build.account.id();
+    let pools = [build.clone()];
+
+    let (lp_minted, _) = quote_initial_lp(100_000, 400_000);
+    let add_payout = PayoutInfo::new(alice.id(), client.rng().draw_word());
+    let add_note = create_add_liquidity_note(
+        alice.id(),
+        amm_id,
+        FungibleAsset::new(faucet_x.id(), 100_000)?,
+        FungibleAsset::new(faucet_y.id(), 400_000)?,
+        lp_minted,
+        &add_payout,
+        build.add_liquidity_note_script.clone(),
+        client.rng().draw_word(),
+    )?;
+    submit_tracked_amm_note(
+        &mut client,
+        &store,
+        alice.id(),
+        amm_id,
+        add_note,
+        &add_payout,
+        vec![FungibleAsset::new(amm_id, lp_minted)?],
+    )
+    .await?;
+
+    // the swap pays `dy`, but the tracker is told the slippage bound
+    let dy = quote_swap_output(30_000, 100_000, 400_000, FEE_BPS);
+    let min_out = dy - 1_000;
+    let y_before = balance_of(&mut client, alice.id(), faucet_y.id()).await?;
+    let swap_payout = PayoutInfo::new(alice.id(), client.rng().draw_word());
+    let swap_note = create_swap_note(
+        alice.id(),
+        amm_id,
+        FungibleAsset::new(faucet_x.id(), 30_000)?,
+        faucet_y.id(),
+        min_out,
+        &swap_payout,
+        build.swap_note_script.clone(),
+        client.rng().draw_word(),
+    )?;
+    let quoted_id = submit_tracked_amm_note(
+        &mut client,
+        &store,
+        alice.id(),
+        amm_id,
+        swap_note,
+        &swap_payout,
+        vec![FungibleAsset::new(faucet_y.id(), min_out)?],
+    )
+    .await?;
+
+    let options =
+        WaitOptions::default().with_backoff(Duration::from_millis(10), Duration::from_millis(10));
+    let report = run_payout_claimer(&mut client, &node, &store, &pools, &options).await?;
+    assert!(report.is_done());
+    assert!(report.unresolvable.is_empty());
+    assert_eq!(report.claimed.len(), 2);
+    assert!(!report.claimed.contains(&quoted_id));
+
+    let resolved_id = swap_payout
+        .expected_note(amm_id, vec![FungibleAsset::new(faucet_y.id(), dy)?])?
+        .id();
+    assert!(report.claimed.contains(&resolved_id));
+    let record = store
+        .get(resolved_id)?
+        .context("resolved payout not recorded")?;
+    assert!(record.claimed);
+    assert_eq!(record.assets, vec![FungibleAsset::new(faucet_y.id(), dy)?]);
+    assert_eq!(
+        balance_of(&mut client, alice.id(), faucet_y.id()).await?,
+        y_before + dy
+    );
+    Ok(())
+}
@@ -0,0 +1,263 @@
+//! Offline counterpart of `amm_swap_ntx.rs`: the same deploy → add liquidity → swap →
+//! remove liquidity flow, with every AMM interaction submitted as a network note from a
+//! wallet and executed by the `NtbEmulator` instead of the testnet's network transaction
+//! builder.
+
+mod common;
+
+use anyhow::{Context, Result};
+use common::{FEE_BPS, add_pair_faucets, auth, serial};
+use miden_amm::{
+    common::{
+        PayoutInfo, PoolState, build_amm_account, create_add_liquidity_note,
+        create_remove_liquidity_note, create_swap_note, quote_initial_lp, quote_remove_liquidity,
+        quote_swap_output,
+    },
+    errors::AmmError,
+    ntb::{NtbEmulator, NtbNoteState, consume_notes, submit_notes},
+};
+use miden_client::{
+    account::AccountId,
+    asset::FungibleAsset,
+    note::{Note, NoteRecipient, NoteType, PartialNoteMetadata},
+    rpc::domain::status::NetworkNoteStatus,
+};
+use miden_standards::note::P2idNote;
+use miden_testing::{MockChain, TxContextInput};
+
+fn balance_of(mock_chain: &MockChain, account_id: AccountId, faucet_id: AccountId) -> Result<u64> {
+    let key = FungibleAsset::new(faucet_id, 1)?.vault_key();
+    Ok(mock_chain
+        .committed_account(account_id)?
+        .vault()
+        .get_balance(key)
+        .context("reading vault balance")?
+        .as_u64())
+}
+
+#[tokio::test]
+async fn amm_lifecycle_runs_through_the_ntb_emulator() -> Result<()> {
+    // ---------------------------------------------------------------------------------
+    // actors: alice and bob funded with both pool assets
+    // ---------------------------------------------------------------------------------
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let x = |amount| FungibleAsset::new(faucet_x.id(), amount);
+    let y = |amount| FungibleAsset::new(faucet_y.id(), amount);
+    let alice = builder
+        .add_existing_wallet_with_assets(auth(), [x(1_000_000)?.into(), y(4_000_000)?.into()])?;
+    let bob = builder
+        .add_existing_wallet_with_assets(auth(), [x(100_000)?.into(), y(100_000)?.into()])?;
+    let mut mock_chain = builder.build()?;
+    let mut ntb = NtbEmulator::new();
+
+    // ---------------------------------------------------------------------------------
+    // deploy the AMM network account with its deploy script, as the live test does
+    // ---------------------------------------------------------------------------------
+    let build = build_amm_account([7u8; 32], faucet_x.id(), faucet_y.id(), FEE_BPS, false)?;
+    let amm_id = build.account.id();
+    let deploy = mock_chain
+        .build_tx_context(TxContextInput::Account(build.account.clone()), &[], &[])?
+        .tx_script(build.deploy_tx_script.clone())
+        .build()?
+        .execute()
+        .await?;
+    mock_chain.add_pending_executed_transaction(&deploy)?;
+    mock_chain.prove_next_block()?;
+
+    // ---------------------------------------------------------------------------------
+    // add liquidity: 100_000 TKX + 400_000 TKY
+    // ---------------------------------------------------------------------------------
+    let (lp_minted, supply) = quote_initial_lp(100_000, 400_000);
+    let add_payout = PayoutInfo::new(alice.id(), serial(1000));
+    let add_note = create_add_liquidity_note(
+        alice.id(),
+        amm_id,
+        x(100_000)?,
+        y(400_000)?,
+        lp_minted,
+        &add_payout,
+        build.add_liquidity_note_script.clone(),
+        serial(1),
+    )?;
+    submit_notes(&mut mock_chain, alice.id(), std::slice::from_ref(&add_note)).await?;
+
+    let executed = ntb.run_until_idle(&mut mock_chain).await?;
+    assert_eq!(executed.len(), 1);
+    let add_claim =
+        add_payout.expected_note(amm_id, vec![FungibleAsset::new(amm_id, lp_minted)?])?;
+    assert_eq!(executed[0].output_notes, vec![add_claim.id()]);
+    assert_eq!(
+        ntb.note_status(add_note.id()).map(|s| s.state),
+        Some(NtbNoteState::Committed)
+    );
+    consume_notes(&mut mock_chain, alice.id(), &[add_claim]).await?;
+    assert_eq!(balance_of(&mock_chain, alice.id(), amm_id)?, lp_minted);
+
+    // ---------------------------------------------------------------------------------
+    // two swaps are submitted before the NTB runs; it executes them in chain order, so
+    // alice's output depends on bob's trade. A third note is greedy and a fourth is not
+    // allowlisted.
+    // ---------------------------------------------------------------------------------
+    let bob_dy = quote_swap_output(20_000, 100_000, 400_000, FEE_BPS);
+    let bob_payout = PayoutInfo::new(bob.id(), serial(2000));
+    let bob_swap = create_swap_note(
+        bob.id(),
+        amm_id,
+        x(20_000)?,
+        faucet_y.id(),
+        bob_dy,
+        &bob_payout,
+        build.swap_note_script.clone(),
+        serial(2),
+    )?;
+    let bob_greedy = create_swap_note(
+        bob.id(),
+        amm_id,
+        x(1_000)?,
+        faucet_y.id(),
+        1_000_000,
+        &PayoutInfo::new(bob.id(), serial(2001)),
+        build.swap_note_script.clone(),
+        serial(3),
+    )?;
+    // same attachment, storage and assets, but a script the AMM does not allowlist
+    let template = create_swap_note(
+        bob.id(),
+        amm_id,
+        y(1_000)?,
+        faucet_x.id(),
+        0,
+        &PayoutInfo::new(bob.id(), serial(2002)),
+        build.swap_note_script.clone(),
+        serial(4),
+    )?;
+    let orphan = Note::with_attachments(
+        template.assets().clone(),
+        PartialNoteMetadata::new(bob.id(), NoteType::Public).with_tag(template.metadata().tag()),
+        NoteRecipient::new(
+            template.recipient().serial_num(),
+            P2idNote::script(),
+            template.recipient().storage().clone(),
+        ),
+        template.attachments().clone(),
+    );
+
+    let (x1, y1) = (120_000, 400_000 - bob_dy);
+    let alice_dy = quote_swap_output(30_000, x1, y1, FEE_BPS);
+    let alice_payout = PayoutInfo::new(alice.id(), serial(3000));
+    let alice_swap = create_swap_note(
+        alice.id(),
+        amm_id,
+        x(30_000)?,
+        faucet_y.id(),
+        alice_dy,
+        &alice_payout,
+        build.swap_note_script.clone(),
+        serial(5),
+    )?;
+
+    // bob's notes are committed in an earlier block than alice's
+    submit_notes(
+        &mut mock_chain,
+        bob.id(),
+        &[bob_swap.clone(), bob_greedy.clone(), orphan.clone()],
+    )
+    .await?;
+    submit_notes(
+        &mut mock_chain,
+        alice.id(),
+        std::slice::from_ref(&alice_swap),
+    )
+    .await?;
+
+    let executed = ntb.run_until_idle(&mut mock_chain).await?;
+    let order: Vec<_> = executed.iter().map(|e| e.note_id).collect();
+    assert_eq!(order, vec![bob_swap.id(), alice_swap.id()]);
+
+    // the greedy note is retried every pass until it is discarded
+    let greedy = ntb
+        .note_status(bob_greedy.id())
+        .expect("greedy note was attempted");
+    assert_eq!(greedy.state, NtbNoteState::Discarded);
+    assert_eq!(greedy.attempt_count, 3);
+    assert_eq!(
+        greedy
+            .last_error
+            .as_deref()
+            .and_then(AmmError::from_error_text),
+        Some(AmmError::Slippage)
+    );
+    assert!(matches!(greedy.info().status, NetworkNoteStatus::Discarded));
+    // the NTB never picks up notes whose script the account does not allowlist
+    assert!(ntb.note_status(orphan.id()).is_none());
+
+    let y_before = balance_of(&mock_chain, alice.id(), faucet_y.id())?;
+    consume_notes(
+        &mut mock_chain,
+        alice.id(),
+        &[alice_payout.expected_note(amm_id, vec![y(alice_dy)?])?],
+    )
+    .await?;
+    assert_eq!(
+        balance_of(&mock_chain, alice.id(), faucet_y.id())?,
+        y_before + alice_dy
+    );
+    consume_notes(
+        &mut mock_chain,
+        bob.id(),
+        &[bob_payout.expected_note(amm_id, vec![y(bob_dy)?])?],
+    )
+    .await?;
+    // bob's orphaned note still holds the 1_000 TKY it carried
+    assert_eq!(
+        balance_of(&mock_chain, bob.id(), faucet_y.id())?,
+        100_000 - 1_000 + bob_dy
+    );
+
+    // ---------------------------------------------------------------------------------
+    // remove liquidity: burn half of alice's LP
+    // ---------------------------------------------------------------------------------
+    let (x2, y2) = (x1 + 30_000, y1 - alice_dy);
+    let lp_burn = lp_minted / 2;
+    let (ax, ay) = quote_remove_liquidity(lp_burn, x2, y2, supply);
+    let remove_payout = PayoutInfo::new(alice.id(), serial(4000));
+    let remove_note = create_remove_liquidity_note(
+        alice.id(),
+        amm_id,
+        lp_burn,
+        ax,
+        ay,
+        &remove_payout,
+        build.remove_liquidity_note_script.clone(),
+        serial(6),
+    )?;
+    submit_notes(
+        &mut mock_chain,
+        alice.id(),
+        std::slice::from_ref(&remove_note),
+    )
+    .await?;
+    assert_eq!(ntb.run_until_idle(&mut mock_chain).await?.len(), 1);
+    consume_notes(
+        &mut mock_chain,
+        alice.id(),
+        &[remove_payout.expected_note(amm_id, vec![x(ax)?, y(ay)?])?],
+    )
+    .await?;
+
+    let pool = PoolState::from_account(
+        mock_chain.committed_account(amm_id)?,
+        faucet_x.id(),
+        faucet_y.id(),
+    )?;
+    assert_eq!(
+        (pool.reserve_x, pool.reserve_y, pool.lp_supply),
+        (x2 - ax, y2 - ay, supply - lp_burn)
+    );
+    assert_eq!(
+        balance_of(&mock_chain, alice.id(), amm_id)?,
+        lp_minted - lp_burn
+    );
+    Ok(())
+}
@@ -0,0 +1,156 @@
+//! Persistence tests for the pending-payout tracker: every recorded payout must survive a
+//! reopen of the database and rebuild to exactly the expected payout note.
+
+use anyhow::Result;
+use miden_amm::{
+    common::PayoutInfo,
+    payouts::{PAYOUT_STORE_FILE, PayoutStore},
+};
+use miden_client::{
+    Felt, Word, account::AccountId, asset::FungibleAsset, note::NoteId, transaction::TransactionId,
+};
+use miden_protocol::testing::account_id::{
+    ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET, ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1,
+    ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_2, ACCOUNT_ID_SENDER,
+};
+
+fn word(n: u64) -> Word {
+    [
+        Felt::new_unchecked(n),
+        Felt::new_unchecked(n + 1),
+        Felt::new_unchecked(n + 2),
+        Felt::new_unchecked(n + 3),
+    ]
+    .into()
+}
+
+/// A fresh database directory per test, so parallel tests never share a file.
+fn temp_store_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("miden-amm-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("creating temp dir");
+    dir
+}
+
+#[test]
+fn recorded_payouts_survive_reopen() -> Result<()> {
+    let dir = temp_store_dir("reopen");
+    let amm_id = AccountId::try_from(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET)?;
+    let faucet_x = AccountId::try_from(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1)?;
+    let faucet_y = AccountId::try_from(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_2)?;
+    let alice = AccountId::try_from(ACCOUNT_ID_SENDER)?;
+
+    let payout = PayoutInfo::new(alice, word(1000));
+    let assets = vec![
+        FungibleAsset::new(faucet_x, 123)?,
+        FungibleAsset::new(faucet_y, 456)?,
+    ];
+    let expected = payout.expected_note(amm_id, assets.clone())?;
+    let source = NoteId::from_raw(word(7));
+
+    let payout_id = {
+        let store = PayoutStore::open_next_to(dir.join("store.sqlite3"))?;
+        let id = store.record(amm_id, &payout, assets.clone(), source)?;
+        // recording the same payout again is a no-op
+        store.record(amm_id, &payout, assets.clone(), source)?;
+        id
+    };
+    assert_eq!(payout_id, expected.id());
+    assert!(dir.join(PAYOUT_STORE_FILE).exists());
+
+    // a restart: everything needed to rebuild the private note is still there
+    let store = PayoutStore::open(dir.join(PAYOUT_STORE_FILE))?;
+    let pending = store.pending()?;
+    assert_eq!(pending.len(), 1);
+    let record = &pending[0];
+    assert_eq!(record.amm_id, amm_id);
+    assert_eq!(record.target, alice);
+    assert_eq!(record.serial_num, word(1000));
+    assert_eq!(record.assets, assets);
+    assert_eq!(record.source_note_id, source);
+    assert_eq!(record.expected_note()?.id(), expected.id());
+    assert!(!record.claimed);
+    Ok(())
+}
+
+#[test]
+fn claim_progress_is_persisted() -> Result<()> {
+    let dir = temp_store_dir("progress");
+    let amm_id = AccountId::try_from(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET)?;
+    let alice = AccountId::try_from(ACCOUNT_ID_SENDER)?;
+    let store = PayoutStore::open_next_to(dir.join("store.sqlite3"))?;
+
+    let first = store.record(
+        amm_id,
+        &PayoutInfo::new(alice, word(1)),
+        vec![FungibleAsset::new(amm_id, 10)?],
+        NoteId::from_raw(word(100)),
+    )?;
+    let second = store.record(
+        amm_id,
+        &PayoutInfo::new(alice, word(2)),
+        vec![FungibleAsset::new(amm_id, 20)?],
+        NoteId::from_raw(word(200)),
+    )?;
+
+    store.record_attempt(first, "note not found")?;
+    store.record_attempt(first, "note not found")?;
+    let claim_tx = TransactionId::from_raw(word(300));
+    store.set_claim_tx(second, Some(claim_tx))?;
+
+    let record = store.get(first)?.expect("first payout recorded");
+    assert_eq!(record.attempts, 2);
+    assert_eq!(record.last_error.as_deref(), Some("note not found"));
+    assert_eq!(record.claim_tx_id, None);
+    assert_eq!(
+        store
+            .get(second)?
+            .expect("second payout recorded")
+            .claim_tx_id,
+        Some(claim_tx)
+    );
+
+    // claimed payouts drop out of the pending set but stay queryable
+    store.mark_claimed(second)?;
+    let pending: Vec<_> = store.pending()?.iter().map(|p| p.serial_num).collect();
+    assert_eq!(pending, vec![word(1)]);
+    assert!(store.get(second)?.expect("second payout recorded").claimed);
+
+    // resolving re-keys the payout to the note with the paid amounts
+    let resolved = store.resolve(first, vec![FungibleAsset::new(amm_id, 12)?])?;
+    let expected = PayoutInfo::new(alice, word(1))
+        .expected_note(amm_id, vec![FungibleAsset::new(amm_id, 12)?])?;
+    assert_eq!(resolved, expected.id());
+    assert!(store.get(first)?.is_none());
+    let record = store.get(resolved)?.expect("resolved payout recorded");
+    assert_eq!(record.attempts, 2);
+    assert_eq!(record.expected_note()?.id(), resolved);
+
+    // a payout resolved to one recorded already (e.g. recovered) merges into that record
+    let stale = store.record(
+        amm_id,
+        &PayoutInfo::new(alice, word(1)),
+        vec![FungibleAsset::new(amm_id, 11)?],
+        NoteId::from_raw(word(100)),
+    )?;
+    assert_eq!(
+        store.resolve(stale, vec![FungibleAsset::new(amm_id, 12)?])?,
+        resolved
+    );
+    assert!(store.get(stale)?.is_none());
+    assert_eq!(store.pending()?.len(), 1);
+    let record = store.get(resolved)?.expect("resolved payout recorded");
+    assert_eq!(record.attempts, 2);
+
+    // unresolvable payouts leave the pending set too, with the reason kept
+    store.mark_unresolvable(resolved, "AMM note discarded")?;
+    assert!(store.pending()?.is_empty());
+    let given_up = store.unresolvable()?;
+    assert_eq!(given_up.len(), 1);
+    assert!(given_up[0].unresolvable);
+    assert_eq!(
+        given_up[0].last_error.as_deref(),
+        Some("AMM note discarded")
+    );
+    Ok(())
+}
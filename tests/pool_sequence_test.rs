@@ -0,0 +1,449 @@
+//! Stateful property tests: random sequences of deposits, swaps and burns by several actors,
+//! run through a pure Rust pool model (`PoolState`, i.e. the `quote_*` mirrors) and, for a
+//! few of them, through the MockChain. Every step checks that k never decreases on a swap,
+//! that the LP supply is the locked minimum plus what was minted minus what was burned, that
+//! a burn pays out at most its pro-rata share, and that the chain ends up in the model state.
+
+mod common;
+
+use anyhow::{Context, Result, anyhow};
+use common::{FEE_BPS, add_notes, add_pool, auth, serial};
+use miden_amm::{
+    common::{
+        AmmBuild, MIN_LIQUIDITY, PayoutInfo, PoolState, create_add_liquidity_note,
+        create_remove_liquidity_note, create_swap_note,
+    },
+    errors::AmmError,
+};
+use miden_client::{
+    account::AccountId, asset::FungibleAsset, note::Note, transaction::RawOutputNote,
+};
+use miden_testing::MockChain;
+use proptest::{
+    prelude::*,
+    test_runner::{Config, TestCaseError, TestRunner},
+};
+
+const ACTORS: usize = 3;
+
+// =================================================================================================
+// OPERATIONS AND THE MODEL
+// =================================================================================================
+
+/// One pool operation by one actor. A burn is a share (in bps) of the actor's LP balance at
+/// that point of the sequence.
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Add {
+        actor: usize,
+        dx: u64,
+        dy: u64,
+    },
+    Swap {
+        actor: usize,
+        amount: u64,
+        x_in: bool,
+    },
+    Remove {
+        actor: usize,
+        share_bps: u64,
+    },
+}
+
+/// What the pool does with an [`Op`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Outcome {
+    Minted(u64),
+    Swapped(u64),
+    Burned {
+        lp: u64,
+        ax: u64,
+        ay: u64,
+    },
+    Rejected(AmmError),
+    /// A burn by an actor without LP tokens: there is nothing to put in the note.
+    Skipped,
+}
+
+/// The pool model plus the LP accounting it cannot see: the balance of every actor and the
+/// running totals of minted and burned LP.
+#[derive(Clone, Debug)]
+struct Model {
+    pool: PoolState,
+    lp: [u64; ACTORS],
+    minted: u64,
+    burned: u64,
+}
+
+fn k(pool: &PoolState) -> u128 {
+    pool.reserve_x as u128 * pool.reserve_y as u128
+}
+
+/// `after` holds at least as much of both reserves per LP token as `before`.
+fn reserves_per_lp_kept(before: &PoolState, after: &PoolState) -> bool {
+    let (s, s_after) = (before.lp_supply as u128, after.lp_supply as u128);
+    after.reserve_x as u128 * s >= before.reserve_x as u128 * s_after
+        && after.reserve_y as u128 * s >= before.reserve_y as u128 * s_after
+}
+
+impl Model {
+    fn new() -> Self {
+        Model {
+            pool: PoolState::new(FEE_BPS),
+            lp: [0; ACTORS],
+            minted: 0,
+            burned: 0,
+        }
+    }
+
+    /// The LP a burn of `share_bps` of `actor`'s balance sends; `None` without a balance.
+    fn burn_size(&self, actor: usize, share_bps: u64) -> Option<u64> {
+        (self.lp[actor] != 0).then(|| (self.lp[actor] * share_bps / 10_000).max(1))
+    }
+
+    /// Applies `op` to the model, checks the invariants around it and returns its outcome.
+    fn apply(&mut self, op: Op) -> Result<Outcome> {
+        let before = self.pool;
+        let mut pool = before;
+        let outcome = match op {
+            Op::Add { actor, dx, dy } => match pool.apply_add_liquidity(dx, dy) {
+                Ok(lp) => {
+                    if before.lp_supply != 0 {
+                        anyhow::ensure!(
+                            reserves_per_lp_kept(&before, &pool),
+                            "deposit diluted the LPs: {before:?} -> {pool:?}"
+                        );
+                    }
+                    self.lp[actor] += lp;
+                    self.minted += lp;
+                    Outcome::Minted(lp)
+                }
+                Err(err) => rejection(err)?,
+            },
+            Op::Swap { amount, x_in, .. } => match pool.apply_swap(amount, x_in) {
+                Ok(out) => {
+                    anyhow::ensure!(
+                        k(&pool) >= k(&before),
+                        "k decreased: {before:?} -> {pool:?}"
+                    );
+                    anyhow::ensure!(pool.lp_supply == before.lp_supply, "swap changed LP supply");
+                    Outcome::Swapped(out)
+                }
+                Err(err) => rejection(err)?,
+            },
+            Op::Remove { actor, share_bps } => {
+                let Some(lp) = self.burn_size(actor, share_bps) else {
+                    return Ok(Outcome::Skipped);
+                };
+                match pool.apply_remove_liquidity(lp) {
+                    Ok((ax, ay)) => {
+                        let (lp, s) = (lp as u128, before.lp_supply as u128);
+                        anyhow::ensure!(
+                            ax as u128 * s <= lp * before.reserve_x as u128
+                                && ay as u128 * s <= lp * before.reserve_y as u128,
+                            "burn of {lp} paid ({ax}, {ay}), more than its share of {before:?}"
+                        );
+                        anyhow::ensure!(
+                            reserves_per_lp_kept(&before, &pool),
+                            "burn took value from the other LPs: {before:?} -> {pool:?}"
+                        );
+                        self.lp[actor] -= lp as u64;
+                        self.burned += lp as u64;
+                        Outcome::Burned {
+                            lp: lp as u64,
+                            ax,
+                            ay,
+                        }
+                    }
+                    Err(err) => rejection(err)?,
+                }
+            }
+        };
+        if let Outcome::Rejected(_) = outcome {
+            anyhow::ensure!(pool == before, "rejected {op:?} changed the pool");
+        }
+        self.pool = pool;
+
+        if self.pool.lp_supply != 0 {
+            let held: u64 = self.lp.iter().sum();
+            anyhow::ensure!(
+                self.pool.lp_supply == MIN_LIQUIDITY + self.minted - self.burned
+                    && self.pool.lp_supply == MIN_LIQUIDITY + held,
+                "LP supply {} != {MIN_LIQUIDITY} + minted {} - burned {} (held {held})",
+                self.pool.lp_supply,
+                self.minted,
+                self.burned
+            );
+        }
+        Ok(outcome)
+    }
+}
+
+/// A model failure must be one the pool itself reports.
+fn rejection(err: anyhow::Error) -> Result<Outcome> {
+    AmmError::from_message(&err.to_string())
+        .map(Outcome::Rejected)
+        .with_context(|| format!("model failed with a non-pool error: {err:#}"))
+}
+
+// =================================================================================================
+// STRATEGIES
+// =================================================================================================
+
+/// Amounts from dust (which the pool mostly rejects) up to a sizeable share of the reserves.
+fn amount() -> impl Strategy<Value = u64> {
+    prop_oneof![1 => 1..100u64, 1 => 1..10_000u64, 2 => 1..1_000_000u64]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let actor = 0..ACTORS;
+    prop_oneof![
+        1 => (actor.clone(), amount(), amount()).prop_map(|(actor, dx, dy)| Op::Add { actor, dx, dy }),
+        2 => (actor.clone(), amount(), any::<bool>())
+            .prop_map(|(actor, amount, x_in)| Op::Swap { actor, amount, x_in }),
+        1 => (actor, 1..=10_000u64).prop_map(|(actor, share_bps)| Op::Remove { actor, share_bps }),
+    ]
+}
+
+/// A first deposit large enough to open the pool, followed by `len` random operations.
+fn sequence(len: std::ops::Range<usize>) -> impl Strategy<Value = Vec<Op>> {
+    let first = (0..ACTORS, 10_000..1_000_000u64, 10_000..1_000_000u64)
+        .prop_map(|(actor, dx, dy)| Op::Add { actor, dx, dy });
+    (first, prop::collection::vec(op(), len)).prop_map(|(first, mut ops)| {
+        ops.insert(0, first);
+        ops
+    })
+}
+
+fn failure(err: anyhow::Error) -> TestCaseError {
+    TestCaseError::fail(format!("{err:#}"))
+}
+
+// =================================================================================================
+// MODEL SEQUENCES
+// =================================================================================================
+
+#[test]
+fn model_sequences_keep_the_invariants() -> Result<()> {
+    let mut runner = TestRunner::new(Config {
+        cases: 1_000,
+        failure_persistence: None,
+        ..Config::default()
+    });
+    runner
+        .run(&sequence(1..40), |ops| {
+            let mut model = Model::new();
+            for op in ops {
+                model.apply(op).map_err(failure)?;
+            }
+            Ok(())
+        })
+        .map_err(|err| anyhow!("{err}"))
+}
+
+// =================================================================================================
+// MOCKCHAIN SEQUENCES
+// =================================================================================================
+
+/// The note an [`Op`] turns into, with the payout the pool is expected to send for it (`None`
+/// when the model rejects the op).
+struct Step {
+    note: Note,
+    payout: Option<Note>,
+    outcome: Outcome,
+    state_after: PoolState,
+}
+
+/// Replays `ops` through the model and crafts one note per op, with the model's results as
+/// the exact slippage bounds.
+fn steps(
+    ops: &[Op],
+    build: &AmmBuild,
+    actors: &[AccountId],
+    faucets: (AccountId, AccountId),
+) -> Result<Vec<Step>> {
+    let amm_id = build.account.id();
+    let x = |amount| FungibleAsset::new(faucets.0, amount);
+    let y = |amount| FungibleAsset::new(faucets.1, amount);
+    let mut model = Model::new();
+    let mut steps = Vec::new();
+    for (i, op) in ops.iter().enumerate() {
+        let burn = match *op {
+            Op::Remove { actor, share_bps } => model.burn_size(actor, share_bps),
+            _ => None,
+        };
+        let outcome = model.apply(*op)?;
+        let n = i as u64 + 1;
+        let (note, payout, assets) = match (*op, outcome) {
+            (_, Outcome::Skipped) => continue,
+            (Op::Add { actor, dx, dy }, outcome) => {
+                let minted = match outcome {
+                    Outcome::Minted(lp) => Some(lp),
+                    _ => None,
+                };
+                let payout = PayoutInfo::new(actors[actor], serial(1000 + n));
+                let note = create_add_liquidity_note(
+                    actors[actor],
+                    amm_id,
+                    x(dx)?,
+                    y(dy)?,
+                    minted.unwrap_or(0),
+                    &payout,
+                    build.add_liquidity_note_script.clone(),
+                    serial(n),
+                )?;
+                let assets = minted
+                    .map(|lp| FungibleAsset::new(amm_id, lp))
+                    .transpose()?;
+                (note, payout, assets.map(|lp| vec![lp]))
+            }
+            (
+                Op::Swap {
+                    actor,
+                    amount,
+                    x_in,
+                },
+                outcome,
+            ) => {
+                let out = match outcome {
+                    Outcome::Swapped(out) => Some(out),
+                    _ => None,
+                };
+                let (asset_in, faucet_out) = if x_in {
+                    (x(amount)?, faucets.1)
+                } else {
+                    (y(amount)?, faucets.0)
+                };
+                let payout = PayoutInfo::new(actors[actor], serial(1000 + n));
+                let note = create_swap_note(
+                    actors[actor],
+                    amm_id,
+                    asset_in,
+                    faucet_out,
+                    out.unwrap_or(0),
+                    &payout,
+                    build.swap_note_script.clone(),
+                    serial(n),
+                )?;
+                let assets = out
+                    .map(|out| FungibleAsset::new(faucet_out, out))
+                    .transpose()?;
+                (note, payout, assets.map(|out| vec![out]))
+            }
+            (Op::Remove { actor, .. }, outcome) => {
+                let (min_x, min_y) = match outcome {
+                    Outcome::Burned { ax, ay, .. } => (ax, ay),
+                    _ => (0, 0),
+                };
+                let payout = PayoutInfo::new(actors[actor], serial(1000 + n));
+                let note = create_remove_liquidity_note(
+                    actors[actor],
+                    amm_id,
+                    burn.context("a burn that was not skipped has a size")?,
+                    min_x,
+                    min_y,
+                    &payout,
+                    build.remove_liquidity_note_script.clone(),
+                    serial(n),
+                )?;
+                let assets = match outcome {
+                    Outcome::Burned { ax, ay, .. } => Some(vec![x(ax)?, y(ay)?]),
+                    _ => None,
+                };
+                (note, payout, assets)
+            }
+        };
+        let payout = assets
+            .map(|assets| payout.expected_note(amm_id, assets))
+            .transpose()?;
+        steps.push(Step {
+            note,
+            payout,
+            outcome,
+            state_after: model.pool,
+        });
+    }
+    Ok(steps)
+}
+
+/// Runs `ops` on a fresh MockChain and checks every step against the model.
+async fn run_on_chain(ops: &[Op]) -> Result<()> {
+    let mut builder = MockChain::builder();
+    let faucet_x = builder.add_existing_basic_faucet(auth(), "TKX", 1_000_000_000_000, Some(8))?;
+    let faucet_y = builder.add_existing_basic_faucet(auth(), "TKY", 1_000_000_000_000, Some(8))?;
+    let actors = (0..ACTORS)
+        .map(|_| Ok(builder.add_existing_wallet_with_assets(auth(), [])?.id()))
+        .collect::<Result<Vec<_>>>()?;
+    let build = add_pool(&mut builder, &faucet_x, &faucet_y)?;
+    let amm_id = build.account.id();
+
+    let steps = steps(ops, &build, &actors, (faucet_x.id(), faucet_y.id()))?;
+    add_notes(&mut builder, steps.iter().map(|step| &step.note));
+    let mut mock_chain = builder.build()?;
+
+    for (i, step) in steps.iter().enumerate() {
+        let ctx = mock_chain
+            .build_tx_context(amm_id, &[step.note.id()], &[])?
+            .extend_expected_output_notes(
+                step.payout
+                    .iter()
+                    .cloned()
+                    .map(RawOutputNote::Full)
+                    .collect(),
+            )
+            .build()?;
+        let result = ctx.execute().await;
+        match (&step.payout, step.outcome) {
+            (Some(payout), _) => {
+                let executed =
+                    result.with_context(|| format!("step {i} ({:?}) failed", step.outcome))?;
+                let produced: Vec<_> = executed.output_notes().iter().map(|n| n.id()).collect();
+                anyhow::ensure!(
+                    produced == vec![payout.id()],
+                    "step {i}: expected payout {:?}, produced {produced:?}",
+                    payout.id()
+                );
+                mock_chain.add_pending_executed_transaction(&executed)?;
+                mock_chain.prove_next_block()?;
+            }
+            (None, Outcome::Rejected(expected)) => {
+                let err = match result {
+                    Ok(_) => anyhow::bail!("step {i}: the pool accepted an op the model rejects"),
+                    Err(err) => format!("{:#}", anyhow::Error::from(err)),
+                };
+                anyhow::ensure!(
+                    AmmError::from_error_text(&err) == Some(expected),
+                    "step {i}: expected {expected:?}, got {err}"
+                );
+            }
+            (None, outcome) => anyhow::bail!("step {i}: no payout for {outcome:?}"),
+        }
+        let state = PoolState::from_account(
+            mock_chain.committed_account(amm_id)?,
+            build.pool_x(),
+            build.pool_y(),
+        )?;
+        anyhow::ensure!(
+            state == step.state_after,
+            "step {i}: chain {state:?} != model {:?}",
+            step.state_after
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn chain_sequences_match_the_model() -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut runner = TestRunner::new(Config {
+        cases: 4,
+        max_shrink_iters: 32,
+        failure_persistence: None,
+        ..Config::default()
+    });
+    runner
+        .run(&sequence(4..8), |ops| {
+            runtime.block_on(run_on_chain(&ops)).map_err(failure)
+        })
+        .map_err(|err| anyhow!("{err}"))
+}
@@ -0,0 +1,329 @@
+//! LP position valuation: the multi-actor fee-accrual scenario of `mock_chain_tests.rs`
+//! valued from alice's side (and her withdrawal executed on MockChain), the textbook
+//! impermanent loss on a fee-less pool, and fee attribution across merged deposits.
+
+mod common;
+
+use anyhow::Result;
+use common::{FEE_BPS, add_notes, auth, serial};
+use miden_amm::{
+    common::{
+        MIN_LIQUIDITY, PayoutInfo, PoolState, build_amm_account, create_add_liquidity_note,
+        create_remove_liquidity_note, create_swap_note,
+    },
+    position::LpPosition,
+    quote::{DepositQuote, SwapQuote},
+};
+use miden_client::{
+    account::AccountId, asset::FungibleAsset, note::Note, transaction::RawOutputNote,
+};
+use miden_testing::MockChain;
+
+/// `a` within `tolerance` (relative) of `b`.
+fn close(a: f64, b: f64, tolerance: f64) -> bool {
+    (a - b).abs() <= tolerance * b.abs()
+}
+
+/// A pool seeded with a balanced deposit by another LP, so the positions under test are not
+/// charged the locked minimum liquidity.
+fn seeded_pool(fee_bps: u64) -> PoolState {
+    let mut pool = PoolState::new(fee_bps);
+    pool.apply_add_liquidity(1_000_000, 1_000_000).unwrap();
+    pool
+}
+
+/// Executes one AMM note that must produce `payout`, and seals a block.
+async fn execute(
+    mock_chain: &mut MockChain,
+    amm_id: AccountId,
+    note: &Note,
+    payout: Note,
+) -> Result<()> {
+    let executed = mock_chain
+        .build_tx_context(amm_id, &[note.id()], &[])?
+        .extend_expected_output_notes(vec![RawOutputNote::Full(payout)])
+        .build()?
+        .execute()
+        .await?;
+    mock_chain.add_pending_executed_transaction(&executed)?;
+    mock_chain.prove_next_block()?;
+    Ok(())
+}
+
+/// alice supplies 100_000 A + 400_000 B, bob swaps 50_000 A in, charlie 100_000 B in, and
+/// alice exits with exactly what her position says is redeemable.
+#[tokio::test]
+async fn fee_accrual_scenario_values_alices_position() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let faucet_a = builder.add_existing_basic_faucet(auth(), "TKA", 1_000_000_000, Some(8))?;
+    let faucet_b = builder.add_existing_basic_faucet(auth(), "TKB", 1_000_000_000, Some(8))?;
+    let alice = builder.add_existing_wallet_with_assets(auth(), [])?.id();
+    let bob = builder.add_existing_wallet_with_assets(auth(), [])?.id();
+    let charlie = builder.add_existing_wallet_with_assets(auth(), [])?.id();
+    let build = build_amm_account([9u8; 32], faucet_a.id(), faucet_b.id(), FEE_BPS, true)?;
+    let amm_id = build.account.id();
+    builder.add_account(build.account.clone())?;
+    let a = |amount| FungibleAsset::new(faucet_a.id(), amount);
+    let b = |amount| FungibleAsset::new(faucet_b.id(), amount);
+
+    let deposit = DepositQuote::new(&PoolState::new(FEE_BPS), 100_000, 400_000, 0)?;
+    let position = LpPosition::from_quote(&deposit);
+    assert_eq!(
+        (
+            position.deposited_x,
+            position.deposited_y,
+            position.lp_amount
+        ),
+        (100_000, 400_000, 200_000 - MIN_LIQUIDITY)
+    );
+
+    // right after the deposit: no fees, and the only "loss" is the locked minimum liquidity
+    let value = position.value(&deposit.pool_after)?;
+    assert_eq!((value.fees_x, value.fees_y), (0.0, 0.0));
+    assert_eq!(value.price, 4.0);
+    assert!(close(
+        value.impermanent_loss,
+        -(MIN_LIQUIDITY as f64) / 200_000.0,
+        1e-9
+    ));
+
+    let add_payout = PayoutInfo::new(alice, serial(1000));
+    let add_note = create_add_liquidity_note(
+        alice,
+        amm_id,
+        a(100_000)?,
+        b(400_000)?,
+        deposit.lp_out,
+        &add_payout,
+        build.add_liquidity_note_script.clone(),
+        serial(1),
+    )?;
+    let bob_swap = SwapQuote::new(&deposit.pool_after, 50_000, true, 0)?;
+    let bob_payout = PayoutInfo::new(bob, serial(2000));
+    let bob_note = create_swap_note(
+        bob,
+        amm_id,
+        a(50_000)?,
+        faucet_b.id(),
+        bob_swap.min_amount_out,
+        &bob_payout,
+        build.swap_note_script.clone(),
+        serial(2),
+    )?;
+    let charlie_swap = SwapQuote::new(&bob_swap.pool_after, 100_000, false, 0)?;
+    let charlie_payout = PayoutInfo::new(charlie, serial(3000));
+    let charlie_note = create_swap_note(
+        charlie,
+        amm_id,
+        b(100_000)?,
+        faucet_a.id(),
+        charlie_swap.min_amount_out,
+        &charlie_payout,
+        build.swap_note_script.clone(),
+        serial(3),
+    )?;
+    let pool = charlie_swap.pool_after;
+
+    // alice's position after both swaps
+    let value = position.value(&pool)?;
+    let (alice_a, alice_b) = (value.redeemable_x, value.redeemable_y);
+    assert!(value.fees_x > 0.0 && value.fees_y > 0.0, "{value:?}");
+    // the fees are the growth of sqrt(k)/S since the deposit, applied to her share
+    let k = |p: &PoolState| ((p.reserve_x as u128 * p.reserve_y as u128) as f64).sqrt();
+    let growth = k(&pool) / k(&deposit.pool_after);
+    assert!(close(
+        value.fees_x,
+        alice_a as f64 * (1.0 - 1.0 / growth),
+        1e-9
+    ));
+    // the price moved from 4 to about 3.36: a loss versus holding, which the fees offset
+    // only in part
+    assert!(value.price < 3.5);
+    assert!(value.impermanent_loss < -0.003, "{value:?}");
+    assert!(value.value_y < value.hold_value_y);
+    assert!(value.value_y > value.hold_value_y * (1.0 + value.impermanent_loss));
+    assert!(close(value.value_x * value.price, value.value_y, 1e-12));
+    assert!(close(
+        value.hold_value_x * value.price,
+        value.hold_value_y,
+        1e-12
+    ));
+
+    let remove_payout = PayoutInfo::new(alice, serial(4000));
+    let remove_note = create_remove_liquidity_note(
+        alice,
+        amm_id,
+        position.lp_amount,
+        alice_a,
+        alice_b,
+        &remove_payout,
+        build.remove_liquidity_note_script.clone(),
+        serial(4),
+    )?;
+
+    add_notes(
+        &mut builder,
+        [&add_note, &bob_note, &charlie_note, &remove_note],
+    );
+    let mut mock_chain = builder.build()?;
+    let lp = FungibleAsset::new(amm_id, deposit.lp_out)?;
+    execute(
+        &mut mock_chain,
+        amm_id,
+        &add_note,
+        add_payout.expected_note(amm_id, vec![lp])?,
+    )
+    .await?;
+    let out = b(bob_swap.amount_out)?;
+    execute(
+        &mut mock_chain,
+        amm_id,
+        &bob_note,
+        bob_payout.expected_note(amm_id, vec![out])?,
+    )
+    .await?;
+    let out = a(charlie_swap.amount_out)?;
+    execute(
+        &mut mock_chain,
+        amm_id,
+        &charlie_note,
+        charlie_payout.expected_note(amm_id, vec![out])?,
+    )
+    .await?;
+    let on_chain = PoolState::from_account(
+        mock_chain.committed_account(amm_id)?,
+        build.pool_x(),
+        build.pool_y(),
+    )?;
+    assert_eq!(on_chain, pool);
+
+    // the withdrawal pays exactly the redeemable amounts
+    let payout = remove_payout.expected_note(amm_id, vec![a(alice_a)?, b(alice_b)?])?;
+    execute(&mut mock_chain, amm_id, &remove_note, payout).await?;
+    Ok(())
+}
+
+#[test]
+fn impermanent_loss_follows_the_price_ratio_without_fees() -> Result<()> {
+    let deposit = DepositQuote::new(&seeded_pool(0), 500_000, 500_000, 0)?;
+    let position = LpPosition::from_quote(&deposit);
+
+    for amount_in in [10_000, 200_000, 1_000_000, 3_000_000] {
+        let swap = SwapQuote::new(&deposit.pool_after, amount_in, true, 0)?;
+        let value = position.value(&swap.pool_after)?;
+        // price ratio r = p1 / p0 with p0 = 1: IL = 2·sqrt(r) / (1 + r) − 1
+        let r = value.price;
+        let expected = 2.0 * r.sqrt() / (1.0 + r) - 1.0;
+        assert!(
+            (value.impermanent_loss - expected).abs() < 1e-5,
+            "swap {amount_in}: {} vs {expected}",
+            value.impermanent_loss
+        );
+        // a fee-less pool earns nothing beyond rounding
+        assert!(value.fees_value_y < 1e-5 * value.value_y, "{value:?}");
+    }
+    Ok(())
+}
+
+#[test]
+fn round_trip_swaps_leave_only_fee_income() -> Result<()> {
+    let deposit = DepositQuote::new(&seeded_pool(FEE_BPS), 500_000, 500_000, 0)?;
+    let position = LpPosition::from_quote(&deposit);
+
+    // swap X in, then Y in until the reserves are balanced again
+    let mut pool = deposit.pool_after;
+    for _ in 0..5 {
+        pool.apply_swap(200_000, true)?;
+        let balanced = (pool.reserve_x as f64 * pool.reserve_y as f64).sqrt();
+        let dy = (balanced - pool.reserve_y as f64) / (1.0 - FEE_BPS as f64 / 10_000.0);
+        pool.apply_swap(dy as u64, false)?;
+    }
+    let value = position.value(&pool)?;
+    assert!(close(value.price, 1.0, 1e-3), "{value:?}");
+    assert!(value.impermanent_loss.abs() < 1e-6, "{value:?}");
+    // everything above holding is fee income
+    assert!(value.fees_value_y > 0.0);
+    assert!(close(
+        value.value_y - value.hold_value_y,
+        value.fees_value_y,
+        1e-3
+    ));
+    Ok(())
+}
+
+#[test]
+fn later_deposits_earn_fees_from_their_own_entry() -> Result<()> {
+    let first = DepositQuote::new(&seeded_pool(FEE_BPS), 100_000, 100_000, 0)?;
+    let mut pool = first.pool_after;
+    pool.apply_swap(300_000, true)?;
+    pool.apply_swap(300_000, false)?;
+
+    let second = DepositQuote::new(&pool, 100_000, 100_000, 0)?;
+    let mut merged = LpPosition::from_quote(&first);
+    merged.add_deposit(
+        second.amount_x,
+        second.amount_y,
+        second.lp_out,
+        &second.pool_after,
+    )?;
+    assert_eq!((merged.deposited_x, merged.deposited_y), (200_000, 200_000));
+    assert_eq!(merged.lp_amount, first.lp_out + second.lp_out);
+
+    // right after the second deposit the merged fees are the first deposit's alone
+    let pool = second.pool_after;
+    let alone = LpPosition::from_quote(&first).value(&pool)?;
+    let merged_value = merged.value(&pool)?;
+    assert!(alone.fees_value_y > 0.0);
+    assert!(
+        close(merged_value.fees_value_y, alone.fees_value_y, 1e-3),
+        "{} vs {}",
+        merged_value.fees_value_y,
+        alone.fees_value_y
+    );
+    Ok(())
+}
+
+#[test]
+fn valuation_needs_a_live_pool_holding_the_position() -> Result<()> {
+    let deposit = DepositQuote::new(&seeded_pool(FEE_BPS), 100_000, 100_000, 0)?;
+    let position = LpPosition::from_quote(&deposit);
+
+    let err = position.value(&PoolState::new(FEE_BPS)).unwrap_err();
+    assert_eq!(err.to_string(), "pool has no LP supply");
+    let oversized = LpPosition {
+        lp_amount: 2_000_000,
+        ..position
+    };
+    let err = oversized.value(&deposit.pool_after).unwrap_err();
+    assert_eq!(err.to_string(), "burn amount exceeds LP supply");
+
+    // a price of zero (or infinity) would make every value in units of X meaningless
+    let drained = PoolState {
+        reserve_y: 0,
+        ..deposit.pool_after
+    };
+    let err = position.value(&drained).unwrap_err();
+    assert_eq!(err.to_string(), "pool has LP supply but empty reserves");
+    Ok(())
+}
+
+#[test]
+fn deposits_minting_nothing_are_rejected() -> Result<()> {
+    let deposit = DepositQuote::new(&seeded_pool(FEE_BPS), 100_000, 100_000, 0)?;
+    let mut empty = LpPosition::new(0, 0, 0, &deposit.pool_after);
+    let err = empty
+        .add_deposit(10, 10, 0, &deposit.pool_after)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "deposit mints zero LP tokens");
+    assert!(!empty.entry_value_per_share.is_nan());
+
+    let mut position = LpPosition::from_quote(&deposit);
+    let before = position;
+    assert!(
+        position
+            .add_deposit(10, 10, 0, &deposit.pool_after)
+            .is_err()
+    );
+    assert_eq!(position, before);
+    Ok(())
+}
@@ -0,0 +1,312 @@
+//! Swap and liquidity quotes: the amounts equal the reference math, the bounds follow the
+//! tolerance, and on MockChain the bounds hold against an unmoved pool and reject a pool
+//! moved past the tolerance.
+
+mod common;
+
+use anyhow::{Context, Result};
+use common::{FEE_BPS, add_notes, add_pair_faucets, add_pool, auth, serial};
+use miden_amm::{
+    common::{
+        MIN_LIQUIDITY, PayoutInfo, PoolState, create_add_liquidity_note,
+        create_remove_liquidity_note, create_swap_note, quote_remove_liquidity, quote_swap_output,
+    },
+    errors::AmmError,
+    quote::{DepositQuote, SwapQuote, WithdrawQuote, min_amount_out},
+};
+use miden_client::{
+    account::AccountId, asset::FungibleAsset, note::Note, transaction::RawOutputNote,
+};
+use miden_testing::MockChain;
+
+fn pool() -> PoolState {
+    let mut pool = PoolState::new(FEE_BPS);
+    pool.apply_add_liquidity(1_000_000, 4_000_000).unwrap();
+    pool
+}
+
+fn model_error(err: anyhow::Error) -> Option<AmmError> {
+    AmmError::from_message(&err.to_string())
+}
+
+#[test]
+fn swap_quotes_price_both_directions() -> Result<()> {
+    let pool = pool();
+
+    let quote = SwapQuote::new(&pool, 100_000, true, 50)?;
+    assert_eq!(
+        quote.amount_out,
+        quote_swap_output(100_000, 1_000_000, 4_000_000, FEE_BPS)
+    );
+    assert_eq!(quote.fee_paid, 300);
+    assert_eq!(quote.spot_price, 4.0);
+    assert_eq!(quote.execution_price, quote.amount_out as f64 / 100_000.0);
+    assert_eq!(quote.min_amount_out, quote.amount_out * 9_950 / 10_000);
+    let mut after = pool;
+    after.apply_swap(100_000, true)?;
+    assert_eq!(quote.pool_after, after);
+
+    // constant product: the impact is dx' / (x + dx') with dx' the input after the fee, up
+    // to the floor of the output
+    let impact = |dx: f64, x: f64| {
+        let dx = dx * (1.0 - FEE_BPS as f64 / 10_000.0);
+        dx / (x + dx)
+    };
+    assert!(
+        (quote.price_impact - impact(100_000.0, 1_000_000.0)).abs() < 1.0 / quote.amount_out as f64
+    );
+
+    let quote = SwapQuote::new(&pool, 100_000, false, 0)?;
+    assert_eq!(
+        quote.amount_out,
+        quote_swap_output(100_000, 4_000_000, 1_000_000, FEE_BPS)
+    );
+    assert_eq!(quote.spot_price, 0.25);
+    assert_eq!(quote.min_amount_out, quote.amount_out);
+    assert!(
+        (quote.price_impact - impact(100_000.0, 4_000_000.0)).abs() < 1.0 / quote.amount_out as f64
+    );
+
+    // bigger trades move the price more
+    let impacts: Vec<f64> = [1_000, 10_000, 100_000, 1_000_000]
+        .into_iter()
+        .map(|dx| SwapQuote::new(&pool, dx, true, 0).map(|q| q.price_impact))
+        .collect::<Result<_>>()?;
+    assert!(impacts.windows(2).all(|w| w[0] < w[1]), "{impacts:?}");
+    assert!(impacts[0] < 0.002 && impacts[3] > 0.45, "{impacts:?}");
+
+    // the quote fails where the AMM would
+    let err = SwapQuote::new(&pool, 1, false, 0).unwrap_err();
+    assert_eq!(model_error(err), Some(AmmError::ZeroOutput));
+    let err = SwapQuote::new(&PoolState::new(FEE_BPS), 1_000, true, 0).unwrap_err();
+    assert_eq!(model_error(err), Some(AmmError::ZeroOutput));
+
+    // nothing in, or an empty side, fails before any price is computed
+    let err = SwapQuote::new(&pool, 0, true, 0).unwrap_err();
+    assert_eq!(model_error(err), Some(AmmError::ZeroOutput));
+    let err = SwapQuote::new(&PoolState::new(FEE_BPS), 0, false, 0).unwrap_err();
+    assert_eq!(model_error(err), Some(AmmError::ZeroOutput));
+    let one_sided = PoolState {
+        reserve_x: 0,
+        ..pool
+    };
+    let err = SwapQuote::new(&one_sided, 1_000, true, 0).unwrap_err();
+    assert_eq!(model_error(err), Some(AmmError::KDecreased));
+    Ok(())
+}
+
+#[test]
+fn tolerance_bounds_round_down() -> Result<()> {
+    assert_eq!(min_amount_out(1_000, 0)?, 1_000);
+    assert_eq!(min_amount_out(1_000, 50)?, 995);
+    assert_eq!(min_amount_out(999, 50)?, 994);
+    assert_eq!(min_amount_out(1_000, 10_000)?, 0);
+    assert_eq!(min_amount_out(u64::MAX, 0)?, u64::MAX);
+    assert!(min_amount_out(1_000, 10_001).is_err());
+    assert!(SwapQuote::new(&pool(), 1_000, true, 10_001).is_err());
+    Ok(())
+}
+
+#[test]
+fn liquidity_quotes_match_the_mint_and_burn_math() -> Result<()> {
+    // first deposit: sqrt(dx·dy) minus the locked minimum liquidity
+    let empty = PoolState::new(FEE_BPS);
+    let quote = DepositQuote::new(&empty, 1_000_000, 4_000_000, 100)?;
+    assert_eq!(quote.lp_out, 2_000_000 - MIN_LIQUIDITY);
+    assert_eq!(quote.min_lp_out, quote.lp_out * 9_900 / 10_000);
+    assert_eq!(quote.pool_after, pool());
+    assert_eq!(quote.pool_share, quote.lp_out as f64 / 2_000_000.0);
+
+    // an unbalanced deposit mints for its scarcer side
+    let pool = pool();
+    let quote = DepositQuote::new(&pool, 100_000, 800_000, 0)?;
+    assert_eq!(quote.lp_out, 200_000);
+    assert_eq!(quote.min_lp_out, 200_000);
+    assert_eq!(quote.pool_share, 200_000.0 / 2_200_000.0);
+    let err = DepositQuote::new(&pool, 0, 800_000, 0).unwrap_err();
+    assert_eq!(model_error(err), Some(AmmError::ZeroLpMinted));
+    let err = DepositQuote::new(&empty, 10, 10, 0).unwrap_err();
+    assert_eq!(
+        model_error(err),
+        Some(AmmError::InsufficientInitialLiquidity)
+    );
+    // an unfillable mint (2^60 * S on the X side is not a field element) is an error too
+    let mut shallow = empty;
+    shallow.apply_add_liquidity(1, 4_000_000)?;
+    let err = DepositQuote::new(&shallow, 1 << 60, 4_000_000, 0).unwrap_err();
+    assert_eq!(model_error(err), Some(AmmError::ValueOverflow));
+
+    let quote = WithdrawQuote::new(&pool, 500_000, 25)?;
+    let (ax, ay) = quote_remove_liquidity(500_000, 1_000_000, 4_000_000, 2_000_000);
+    assert_eq!((quote.amount_x, quote.amount_y), (ax, ay));
+    assert_eq!(
+        (quote.min_x_out, quote.min_y_out),
+        (ax * 9_975 / 10_000, ay * 9_975 / 10_000)
+    );
+    assert_eq!(quote.pool_after.lp_supply, 1_500_000);
+    let err = WithdrawQuote::new(&pool, 2_000_001, 0).unwrap_err();
+    assert_eq!(model_error(err), Some(AmmError::LpExceedsSupply));
+    Ok(())
+}
+
+/// Executes `note` against the AMM; on success commits it and returns `None`, otherwise the
+/// AMM error it failed with.
+async fn execute(
+    mock_chain: &mut MockChain,
+    amm_id: AccountId,
+    note: &Note,
+    payout: Option<Note>,
+) -> Result<Option<AmmError>> {
+    let result = mock_chain
+        .build_tx_context(amm_id, &[note.id()], &[])?
+        .extend_expected_output_notes(payout.into_iter().map(RawOutputNote::Full).collect())
+        .build()?
+        .execute()
+        .await;
+    match result {
+        Ok(executed) => {
+            mock_chain.add_pending_executed_transaction(&executed)?;
+            mock_chain.prove_next_block()?;
+            Ok(None)
+        }
+        Err(err) => {
+            let text = format!("{:#}", anyhow::Error::from(err));
+            AmmError::from_error_text(&text)
+                .map(Some)
+                .with_context(|| format!("undecoded failure: {text}"))
+        }
+    }
+}
+
+#[tokio::test]
+async fn quoted_bounds_hold_until_the_pool_moves_past_the_tolerance() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let alice = builder.add_existing_wallet_with_assets(auth(), [])?.id();
+    let bob = builder.add_existing_wallet_with_assets(auth(), [])?.id();
+    let build = add_pool(&mut builder, &faucet_x, &faucet_y)?;
+    let amm_id = build.account.id();
+    let x = |amount| FungibleAsset::new(faucet_x.id(), amount);
+    let y = |amount| FungibleAsset::new(faucet_y.id(), amount);
+    let payout = |target, n| PayoutInfo::new(target, serial(1_000 + n));
+
+    let deposit = DepositQuote::new(&PoolState::new(FEE_BPS), 1_000_000, 4_000_000, 50)?;
+    let add_note = create_add_liquidity_note(
+        alice,
+        amm_id,
+        x(1_000_000)?,
+        y(4_000_000)?,
+        deposit.min_lp_out,
+        &payout(alice, 1),
+        build.add_liquidity_note_script.clone(),
+        serial(1),
+    )?;
+    let pool = deposit.pool_after;
+
+    // bob quotes a swap and a withdrawal against the pool after alice's deposit; a 5%
+    // swap lands first, moving the price by about 10%
+    let front_run = SwapQuote::new(&pool, 50_000, true, 0)?;
+    let moved = front_run.pool_after;
+    let front_run_note = create_swap_note(
+        alice,
+        amm_id,
+        x(50_000)?,
+        faucet_y.id(),
+        front_run.min_amount_out,
+        &payout(alice, 2),
+        build.swap_note_script.clone(),
+        serial(2),
+    )?;
+
+    let swap = |tolerance_bps, n| -> Result<(Note, u64)> {
+        let quote = SwapQuote::new(&pool, 100_000, true, tolerance_bps)?;
+        let note = create_swap_note(
+            bob,
+            amm_id,
+            x(100_000)?,
+            faucet_y.id(),
+            quote.min_amount_out,
+            &payout(bob, n),
+            build.swap_note_script.clone(),
+            serial(n),
+        )?;
+        Ok((note, quote.min_amount_out))
+    };
+    let (strict_swap, _) = swap(0, 3)?;
+    let (loose_swap, loose_min) = swap(1_500, 4)?;
+
+    let withdraw = |tolerance_bps, n| -> Result<Note> {
+        let quote = WithdrawQuote::new(&pool, 100_000, tolerance_bps)?;
+        create_remove_liquidity_note(
+            alice,
+            amm_id,
+            100_000,
+            quote.min_x_out,
+            quote.min_y_out,
+            &payout(alice, n),
+            build.remove_liquidity_note_script.clone(),
+            serial(n),
+        )
+    };
+    let strict_withdraw = withdraw(0, 5)?;
+    let loose_withdraw = withdraw(1_500, 6)?;
+
+    add_notes(
+        &mut builder,
+        [
+            &add_note,
+            &front_run_note,
+            &strict_swap,
+            &loose_swap,
+            &strict_withdraw,
+            &loose_withdraw,
+        ],
+    );
+    let mut mock_chain = builder.build()?;
+
+    let lp = payout(alice, 1)
+        .expected_note(amm_id, vec![FungibleAsset::new(amm_id, deposit.lp_out)?])?;
+    assert_eq!(
+        execute(&mut mock_chain, amm_id, &add_note, Some(lp)).await?,
+        None
+    );
+    let out = payout(alice, 2).expected_note(amm_id, vec![y(front_run.amount_out)?])?;
+    assert_eq!(
+        execute(&mut mock_chain, amm_id, &front_run_note, Some(out)).await?,
+        None
+    );
+
+    assert_eq!(
+        execute(&mut mock_chain, amm_id, &strict_swap, None).await?,
+        Some(AmmError::Slippage)
+    );
+    let quote = SwapQuote::new(&moved, 100_000, true, 0)?;
+    assert!(quote.amount_out >= loose_min);
+    let out = payout(bob, 4).expected_note(amm_id, vec![y(quote.amount_out)?])?;
+    assert_eq!(
+        execute(&mut mock_chain, amm_id, &loose_swap, Some(out)).await?,
+        None
+    );
+    let moved = quote.pool_after;
+
+    // the swaps left more X and less Y per LP token: the strict Y bound fails
+    assert_eq!(
+        execute(&mut mock_chain, amm_id, &strict_withdraw, None).await?,
+        Some(AmmError::Slippage)
+    );
+    let quote = WithdrawQuote::new(&moved, 100_000, 0)?;
+    let out =
+        payout(alice, 6).expected_note(amm_id, vec![x(quote.amount_x)?, y(quote.amount_y)?])?;
+    assert_eq!(
+        execute(&mut mock_chain, amm_id, &loose_withdraw, Some(out)).await?,
+        None
+    );
+
+    let on_chain = PoolState::from_account(
+        mock_chain.committed_account(amm_id)?,
+        build.pool_x(),
+        build.pool_y(),
+    )?;
+    assert_eq!(on_chain, quote.pool_after);
+    Ok(())
+}
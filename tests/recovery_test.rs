@@ -0,0 +1,354 @@
+//! Deterministic serial derivation and payout recovery. The recovery tests execute real AMM
+//! transactions on a MockChain with derived serial numbers, then forget everything except
+//! the seed and rebuild every payout from the consumed notes and produced note ids alone,
+//! either replayed directly or scanned from the chain through `MockNode`.
+
+mod common;
+
+use anyhow::Result;
+use common::{FEE_BPS, add_notes, add_pair_faucets, add_pool, auth, with_donation};
+use miden_amm::{
+    common::{
+        AmmBuild, PoolState, build_amm_account, create_add_liquidity_note,
+        create_remove_liquidity_note, create_swap_note, create_sync_note, quote_initial_lp,
+        quote_remove_liquidity, quote_swap_output,
+    },
+    mock_node::MockNode,
+    ntb::{NtbEmulator, submit_notes},
+    recovery::{PayoutReplay, RecoveredPayout, SerialDeriver, SerialKind, recover_payouts},
+};
+use miden_client::{
+    account::{Account, AccountId},
+    asset::FungibleAsset,
+    note::{Note, NoteId},
+    transaction::RawOutputNote,
+};
+use miden_protocol::testing::account_id::{ACCOUNT_ID_PRIVATE_SENDER, ACCOUNT_ID_SENDER};
+use miden_testing::MockChain;
+
+/// Executes one AMM note, applies the delta and seals a block. Returns the ids of the notes
+/// the transaction produced — the only thing recovery learns about private payouts.
+async fn execute_note(
+    mock_chain: &mut MockChain,
+    amm_account: &mut Account,
+    note: &Note,
+    expected_payout: Note,
+) -> Result<Vec<NoteId>> {
+    let ctx = mock_chain
+        .build_tx_context(amm_account.id(), &[note.id()], &[])?
+        .extend_expected_output_notes(vec![RawOutputNote::Full(expected_payout)])
+        .build()?;
+    let executed = ctx.execute().await?;
+    let produced = executed.output_notes().iter().map(|n| n.id()).collect();
+    amm_account.apply_delta(executed.account_delta())?;
+    mock_chain.add_pending_executed_transaction(&executed)?;
+    mock_chain.prove_next_block()?;
+    Ok(produced)
+}
+
+#[test]
+fn derived_serials_are_deterministic_and_domain_separated() -> Result<()> {
+    let alice = AccountId::try_from(ACCOUNT_ID_SENDER)?;
+    let bob = AccountId::try_from(ACCOUNT_ID_PRIVATE_SENDER)?;
+    let deriver = SerialDeriver::from_seed(b"alice wallet seed");
+
+    assert_eq!(
+        deriver.serial(alice, SerialKind::Payout, 3),
+        SerialDeriver::from_seed(b"alice wallet seed").serial(alice, SerialKind::Payout, 3)
+    );
+    let serials = [
+        deriver.serial(alice, SerialKind::Payout, 0),
+        deriver.serial(alice, SerialKind::Payout, 1),
+        deriver.serial(alice, SerialKind::AmmNote, 0),
+        deriver.serial(bob, SerialKind::Payout, 0),
+        SerialDeriver::from_seed(b"another seed").serial(alice, SerialKind::Payout, 0),
+    ];
+    for (i, a) in serials.iter().enumerate() {
+        for b in &serials[i + 1..] {
+            assert_ne!(a, b, "derived serials must not collide");
+        }
+    }
+    assert_eq!(deriver.payout(alice, 1).serial_num, serials[1]);
+    assert_eq!(deriver.amm_note_serial(alice, 0), serials[2]);
+    Ok(())
+}
+
+/// The chain after [`alice_and_bob_trade`], and what recovery must find on it.
+struct History {
+    mock_chain: MockChain,
+    build: AmmBuild,
+    amm_account: Account,
+    alice: AccountId,
+    alice_seed: SerialDeriver,
+    bob_seed: SerialDeriver,
+    /// The AMM notes in execution order, with the ids of the notes each one produced.
+    consumed: Vec<(Note, Vec<NoteId>)>,
+    /// The payout each AMM note produced.
+    payouts: Vec<Note>,
+}
+
+/// Alice deposits, swaps and withdraws using derived serials; bob swaps in between with his
+/// own seed. Each note executes in its own block.
+async fn alice_and_bob_trade() -> Result<History> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let alice = builder.add_existing_wallet_with_assets(auth(), [])?;
+    let bob = builder.add_existing_wallet_with_assets(auth(), [])?;
+
+    let build = add_pool(&mut builder, &faucet_x, &faucet_y)?;
+    let amm_id = build.account.id();
+
+    let alice_seed = SerialDeriver::from_seed(b"alice wallet seed");
+    let bob_seed = SerialDeriver::from_seed(b"bob wallet seed");
+
+    // alice: first deposit (payout counter 0)
+    let (lp, supply) = quote_initial_lp(100_000, 400_000);
+    let add_payout = alice_seed.payout(alice.id(), 0);
+    let add_note = create_add_liquidity_note(
+        alice.id(),
+        amm_id,
+        FungibleAsset::new(faucet_x.id(), 100_000)?,
+        FungibleAsset::new(faucet_y.id(), 400_000)?,
+        lp,
+        &add_payout,
+        build.add_liquidity_note_script.clone(),
+        alice_seed.amm_note_serial(alice.id(), 0),
+    )?;
+
+    // bob: swaps 20_000 Y -> X (his payout counter 0)
+    let bob_out = quote_swap_output(20_000, 400_000, 100_000, FEE_BPS);
+    let bob_payout = bob_seed.payout(bob.id(), 0);
+    let bob_swap = create_swap_note(
+        bob.id(),
+        amm_id,
+        FungibleAsset::new(faucet_y.id(), 20_000)?,
+        faucet_x.id(),
+        bob_out,
+        &bob_payout,
+        build.swap_note_script.clone(),
+        bob_seed.amm_note_serial(bob.id(), 0),
+    )?;
+
+    // alice: swaps 10_000 X -> Y with a loose slippage bound (payout counter 1); the exact
+    // output depends on bob's swap, which recovery must account for
+    let (x1, y1) = (100_000 - bob_out, 420_000);
+    let alice_out = quote_swap_output(10_000, x1, y1, FEE_BPS);
+    let swap_payout = alice_seed.payout(alice.id(), 1);
+    let swap_note = create_swap_note(
+        alice.id(),
+        amm_id,
+        FungibleAsset::new(faucet_x.id(), 10_000)?,
+        faucet_y.id(),
+        1,
+        &swap_payout,
+        build.swap_note_script.clone(),
+        alice_seed.amm_note_serial(alice.id(), 1),
+    )?;
+
+    // alice: withdraws half her LP (payout counter 2)
+    let (x2, y2) = (x1 + 10_000, y1 - alice_out);
+    let (ax, ay) = quote_remove_liquidity(lp / 2, x2, y2, supply);
+    let remove_payout = alice_seed.payout(alice.id(), 2);
+    let remove_note = create_remove_liquidity_note(
+        alice.id(),
+        amm_id,
+        lp / 2,
+        0,
+        0,
+        &remove_payout,
+        build.remove_liquidity_note_script.clone(),
+        alice_seed.amm_note_serial(alice.id(), 2),
+    )?;
+
+    let history = [&add_note, &bob_swap, &swap_note, &remove_note];
+    add_notes(&mut builder, history);
+    let mut mock_chain = builder.build()?;
+    let mut amm_account = build.account.clone();
+
+    let expected = [
+        add_payout.expected_note(amm_id, vec![FungibleAsset::new(amm_id, lp)?])?,
+        bob_payout.expected_note(amm_id, vec![FungibleAsset::new(faucet_x.id(), bob_out)?])?,
+        swap_payout.expected_note(amm_id, vec![FungibleAsset::new(faucet_y.id(), alice_out)?])?,
+        remove_payout.expected_note(
+            amm_id,
+            vec![
+                FungibleAsset::new(faucet_x.id(), ax)?,
+                FungibleAsset::new(faucet_y.id(), ay)?,
+            ],
+        )?,
+    ];
+    let mut consumed = Vec::new();
+    for (note, payout) in history.into_iter().zip(expected.clone()) {
+        let produced = execute_note(&mut mock_chain, &mut amm_account, note, payout).await?;
+        consumed.push((note.clone(), produced));
+    }
+    Ok(History {
+        mock_chain,
+        build,
+        amm_account,
+        alice: alice.id(),
+        alice_seed,
+        bob_seed,
+        consumed,
+        payouts: expected.to_vec(),
+    })
+}
+
+/// Checks that exactly alice's three payouts were recovered: her deposit, her swap (whose
+/// amount depends on bob's swap) and her withdrawal, and none of bob's.
+fn assert_alice_recovered(history: &History, recovered: &[RecoveredPayout]) -> Result<()> {
+    let counters: Vec<u64> = recovered.iter().map(|p| p.counter).collect();
+    assert_eq!(counters, vec![0, 1, 2]);
+    let recovered_ids: Vec<NoteId> = recovered
+        .iter()
+        .map(|p| p.note().map(|n| n.id()))
+        .collect::<Result<_>>()?;
+    let payouts = &history.payouts;
+    assert_eq!(
+        recovered_ids,
+        vec![payouts[0].id(), payouts[2].id(), payouts[3].id()]
+    );
+    assert_eq!(recovered[1].source_note_id, history.consumed[2].0.id());
+    Ok(())
+}
+
+/// Replaying the consumed notes rebuilds alice's payouts from her seed alone.
+#[tokio::test]
+async fn payouts_are_recovered_from_seed_and_chain_history() -> Result<()> {
+    let history = alice_and_bob_trade().await?;
+
+    // ---- local state is gone; only the seed and the chain remain -------------------------
+
+    let mut replay = PayoutReplay::new(&history.build, &history.alice_seed, history.alice, 10);
+    for (note, produced) in &history.consumed {
+        replay.apply_consumed_note(note, produced)?;
+    }
+    assert_eq!(
+        replay.pool(),
+        PoolState::from_account(
+            &history.amm_account,
+            history.build.pool_x_faucet,
+            history.build.pool_y_faucet
+        )?,
+        "replayed pool state must match the executed account"
+    );
+    assert_alice_recovered(&history, &replay.into_recovered())?;
+
+    // a different seed recovers nothing for alice
+    let mut replay = PayoutReplay::new(&history.build, &history.bob_seed, history.alice, 10);
+    for (note, produced) in &history.consumed {
+        replay.apply_consumed_note(note, produced)?;
+    }
+    assert!(replay.recovered().is_empty());
+    Ok(())
+}
+
+/// The live path: the same payouts, found by scanning the node's notes and AMM transactions.
+#[tokio::test]
+async fn payouts_are_recovered_from_the_node() -> Result<()> {
+    let history = alice_and_bob_trade().await?;
+    let node = MockNode::new(history.mock_chain.clone());
+    let tip = node.chain_tip();
+
+    let recovered = recover_payouts(
+        &node,
+        &history.build,
+        &history.alice_seed,
+        history.alice,
+        10,
+        tip,
+    )
+    .await?;
+    assert_alice_recovered(&history, &recovered)?;
+
+    let recovered = recover_payouts(
+        &node,
+        &history.build,
+        &history.bob_seed,
+        history.alice,
+        10,
+        tip,
+    )
+    .await?;
+    assert!(recovered.is_empty());
+    Ok(())
+}
+
+/// A sync adopts a donation into the reserves, so the swap after it pays out of the larger
+/// reserves: recovery follows the sync (and the donation, from the pool's genesis delta).
+#[tokio::test]
+async fn payouts_after_a_sync_are_recovered() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let x = |amount| FungibleAsset::new(faucet_x.id(), amount);
+    let y = |amount| FungibleAsset::new(faucet_y.id(), amount);
+    let alice = builder
+        .add_existing_wallet_with_assets(auth(), [x(1_000_000)?.into(), y(4_000_000)?.into()])?;
+    let bob = builder.add_existing_wallet_with_assets(auth(), [])?;
+
+    // the pool starts with a donation in its vault, outside the reserves
+    let build = build_amm_account([7u8; 32], faucet_x.id(), faucet_y.id(), FEE_BPS, true)?;
+    let amm_id = build.account.id();
+    builder.add_account(with_donation(
+        &build,
+        &[x(20_000)?.into(), y(80_000)?.into()],
+    )?)?;
+    let mut mock_chain = builder.build()?;
+    let mut ntb = NtbEmulator::new();
+    let alice_seed = SerialDeriver::from_seed(b"alice wallet seed");
+
+    let (lp, _) = quote_initial_lp(100_000, 400_000);
+    let add_payout = alice_seed.payout(alice.id(), 0);
+    let add_note = create_add_liquidity_note(
+        alice.id(),
+        amm_id,
+        x(100_000)?,
+        y(400_000)?,
+        lp,
+        &add_payout,
+        build.add_liquidity_note_script.clone(),
+        alice_seed.amm_note_serial(alice.id(), 0),
+    )?;
+    let sync_note = create_sync_note(
+        bob.id(),
+        amm_id,
+        build.sync_note_script.clone(),
+        alice_seed.amm_note_serial(bob.id(), 0),
+    )?;
+    let swap_payout = alice_seed.payout(alice.id(), 1);
+    let swap_note = create_swap_note(
+        alice.id(),
+        amm_id,
+        x(10_000)?,
+        faucet_y.id(),
+        1,
+        &swap_payout,
+        build.swap_note_script.clone(),
+        alice_seed.amm_note_serial(alice.id(), 1),
+    )?;
+    for (sender, note) in [
+        (alice.id(), &add_note),
+        (bob.id(), &sync_note),
+        (alice.id(), &swap_note),
+    ] {
+        submit_notes(&mut mock_chain, sender, std::slice::from_ref(note)).await?;
+        assert_eq!(ntb.run_until_idle(&mut mock_chain).await?.len(), 1);
+    }
+
+    // ---- local state is gone; only the seed and the chain remain -------------------------
+
+    let node = MockNode::new(mock_chain);
+    let recovered =
+        recover_payouts(&node, &build, &alice_seed, alice.id(), 10, node.chain_tip()).await?;
+    let out = quote_swap_output(10_000, 120_000, 480_000, FEE_BPS);
+    let expected = [
+        add_payout.expected_note(amm_id, vec![FungibleAsset::new(amm_id, lp)?])?,
+        swap_payout.expected_note(amm_id, vec![y(out)?])?,
+    ];
+    let recovered_ids: Vec<NoteId> = recovered
+        .iter()
+        .map(|p| p.note().map(|n| n.id()))
+        .collect::<Result<_>>()?;
+    assert_eq!(recovered_ids, vec![expected[0].id(), expected[1].id()]);
+    Ok(())
+}
@@ -0,0 +1,234 @@
+//! Tracked reserves: the pool prices against its `reserves` slot, so balances that reach the
+//! vault outside its own accounting do not move the price until a sync note adopts them, and
+//! a skim note pays them out instead.
+
+mod common;
+
+use anyhow::{Context, Result};
+use common::{FEE_BPS, add_pair_faucets, auth, serial, with_donation};
+use miden_amm::{
+    common::{
+        AmmBuild, PayoutInfo, PoolState, build_amm_account, create_add_liquidity_note,
+        create_remove_liquidity_note, create_skim_note, create_swap_note, create_sync_note,
+        pool_excess, quote_initial_lp, quote_remove_liquidity, quote_swap_output,
+    },
+    errors::AmmError,
+    ntb::{NtbEmulator, NtbNoteState, consume_notes, submit_notes},
+};
+use miden_client::{
+    account::AccountId,
+    asset::FungibleAsset,
+    note::{Note, NoteAssets, NoteType, PartialNoteMetadata},
+};
+use miden_testing::MockChain;
+
+fn pool_state(mock_chain: &MockChain, build: &AmmBuild) -> Result<PoolState> {
+    PoolState::from_account(
+        mock_chain.committed_account(build.account.id())?,
+        build.pool_x(),
+        build.pool_y(),
+    )
+}
+
+fn excess(mock_chain: &MockChain, build: &AmmBuild) -> Result<(u64, u64)> {
+    pool_excess(
+        mock_chain.committed_account(build.account.id())?,
+        build.pool_x(),
+        build.pool_y(),
+    )
+}
+
+/// Runs `note` through the emulator and returns the AMM error it was discarded with.
+async fn rejection(
+    mock_chain: &mut MockChain,
+    sender: AccountId,
+    note: &Note,
+) -> Result<Option<AmmError>> {
+    let mut ntb = NtbEmulator::new().with_max_attempts(1);
+    submit_notes(mock_chain, sender, std::slice::from_ref(note)).await?;
+    assert!(ntb.run_until_idle(mock_chain).await?.is_empty());
+    let status = ntb.note_status(note.id()).context("note attempted")?;
+    assert_eq!(status.state, NtbNoteState::Discarded);
+    Ok(status
+        .last_error
+        .as_deref()
+        .and_then(AmmError::from_error_text))
+}
+
+#[tokio::test]
+async fn donations_do_not_move_the_price() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let x = |amount| FungibleAsset::new(faucet_x.id(), amount);
+    let y = |amount| FungibleAsset::new(faucet_y.id(), amount);
+    let alice = builder
+        .add_existing_wallet_with_assets(auth(), [x(1_000_000)?.into(), y(4_000_000)?.into()])?;
+    let bob = builder.add_existing_wallet_with_assets(auth(), [])?;
+
+    // one pool is skimmed, the other synced
+    let skimmed = build_amm_account([7u8; 32], faucet_x.id(), faucet_y.id(), FEE_BPS, true)?;
+    builder.add_account(with_donation(&skimmed, &[x(50_000)?.into()])?)?;
+    let synced = build_amm_account([8u8; 32], faucet_x.id(), faucet_y.id(), FEE_BPS, true)?;
+    builder.add_account(with_donation(
+        &synced,
+        &[x(20_000)?.into(), y(80_000)?.into()],
+    )?)?;
+
+    let mut mock_chain = builder.build()?;
+    let mut ntb = NtbEmulator::new();
+
+    // ---------------------------------------------------------------------------------
+    // the first deposit sets the reserves; the donation stays outside them
+    // ---------------------------------------------------------------------------------
+    let (lp_minted, supply) = quote_initial_lp(100_000, 400_000);
+    for (build, n) in [(&skimmed, 1), (&synced, 2)] {
+        let amm_id = build.account.id();
+        let payout = PayoutInfo::new(alice.id(), serial(1000 + n));
+        let note = create_add_liquidity_note(
+            alice.id(),
+            amm_id,
+            x(100_000)?,
+            y(400_000)?,
+            lp_minted,
+            &payout,
+            build.add_liquidity_note_script.clone(),
+            serial(n),
+        )?;
+        submit_notes(&mut mock_chain, alice.id(), std::slice::from_ref(&note)).await?;
+        assert_eq!(ntb.run_until_idle(&mut mock_chain).await?.len(), 1);
+        consume_notes(
+            &mut mock_chain,
+            alice.id(),
+            &[payout.expected_note(amm_id, vec![FungibleAsset::new(amm_id, lp_minted)?])?],
+        )
+        .await?;
+    }
+    let pool = pool_state(&mock_chain, &skimmed)?;
+    assert_eq!((pool.reserve_x, pool.reserve_y), (100_000, 400_000));
+    assert_eq!(excess(&mock_chain, &skimmed)?, (50_000, 0));
+    assert_eq!(excess(&mock_chain, &synced)?, (20_000, 80_000));
+
+    // ---------------------------------------------------------------------------------
+    // a swap is priced against the reserves, not the 150_000 X in the vault
+    // ---------------------------------------------------------------------------------
+    let skimmed_id = skimmed.account.id();
+    let dx = quote_swap_output(40_000, 400_000, 100_000, FEE_BPS);
+    let swap_payout = PayoutInfo::new(alice.id(), serial(2000));
+    let swap = create_swap_note(
+        alice.id(),
+        skimmed_id,
+        y(40_000)?,
+        faucet_x.id(),
+        dx,
+        &swap_payout,
+        skimmed.swap_note_script.clone(),
+        serial(3),
+    )?;
+    submit_notes(&mut mock_chain, alice.id(), std::slice::from_ref(&swap)).await?;
+    let executed = ntb.run_until_idle(&mut mock_chain).await?;
+    let swap_claim = swap_payout.expected_note(skimmed_id, vec![x(dx)?])?;
+    assert_eq!(executed[0].output_notes, vec![swap_claim.id()]);
+    let pool = pool_state(&mock_chain, &skimmed)?;
+    assert_eq!((pool.reserve_x, pool.reserve_y), (100_000 - dx, 440_000));
+    assert_eq!(excess(&mock_chain, &skimmed)?, (50_000, 0));
+
+    // ---------------------------------------------------------------------------------
+    // skim: bob takes the excess, the reserves stay; a second skim finds nothing
+    // ---------------------------------------------------------------------------------
+    let skim_payout = PayoutInfo::new(bob.id(), serial(3000));
+    let skim = create_skim_note(
+        bob.id(),
+        skimmed_id,
+        &skim_payout,
+        skimmed.skim_note_script.clone(),
+        serial(4),
+    )?;
+    submit_notes(&mut mock_chain, bob.id(), std::slice::from_ref(&skim)).await?;
+    let executed = ntb.run_until_idle(&mut mock_chain).await?;
+    let skim_claim = skim_payout.expected_note(skimmed_id, vec![x(50_000)?])?;
+    assert_eq!(executed[0].output_notes, vec![skim_claim.id()]);
+    consume_notes(&mut mock_chain, bob.id(), &[skim_claim]).await?;
+    assert_eq!(
+        pool_state(&mock_chain, &skimmed)?,
+        PoolState {
+            reserve_x: 100_000 - dx,
+            reserve_y: 440_000,
+            lp_supply: supply,
+            fee_bps: FEE_BPS,
+        }
+    );
+    assert_eq!(excess(&mock_chain, &skimmed)?, (0, 0));
+
+    let again = create_skim_note(
+        bob.id(),
+        skimmed_id,
+        &PayoutInfo::new(bob.id(), serial(3001)),
+        skimmed.skim_note_script.clone(),
+        serial(5),
+    )?;
+    assert_eq!(
+        rejection(&mut mock_chain, bob.id(), &again).await?,
+        Some(AmmError::NothingToSkim)
+    );
+
+    // ---------------------------------------------------------------------------------
+    // sync and skim notes carry no assets
+    // ---------------------------------------------------------------------------------
+    let sync = create_sync_note(
+        alice.id(),
+        skimmed_id,
+        skimmed.sync_note_script.clone(),
+        serial(6),
+    )?;
+    let funded_sync = Note::with_attachments(
+        NoteAssets::new(vec![x(1_000)?.into()])?,
+        PartialNoteMetadata::new(alice.id(), NoteType::Public).with_tag(sync.metadata().tag()),
+        sync.recipient().clone(),
+        sync.attachments().clone(),
+    );
+    assert_eq!(
+        rejection(&mut mock_chain, alice.id(), &funded_sync).await?,
+        Some(AmmError::UnexpectedAssets)
+    );
+
+    // ---------------------------------------------------------------------------------
+    // sync: the donation joins the reserves and goes to the LPs
+    // ---------------------------------------------------------------------------------
+    let synced_id = synced.account.id();
+    let sync = create_sync_note(
+        bob.id(),
+        synced_id,
+        synced.sync_note_script.clone(),
+        serial(7),
+    )?;
+    submit_notes(&mut mock_chain, bob.id(), std::slice::from_ref(&sync)).await?;
+    let executed = ntb.run_until_idle(&mut mock_chain).await?;
+    assert!(executed[0].output_notes.is_empty());
+    let pool = pool_state(&mock_chain, &synced)?;
+    assert_eq!((pool.reserve_x, pool.reserve_y), (120_000, 480_000));
+    assert_eq!(pool.lp_supply, supply);
+    assert_eq!(excess(&mock_chain, &synced)?, (0, 0));
+
+    let (ax, ay) = quote_remove_liquidity(lp_minted, 120_000, 480_000, supply);
+    let remove_payout = PayoutInfo::new(alice.id(), serial(4000));
+    let remove = create_remove_liquidity_note(
+        alice.id(),
+        synced_id,
+        lp_minted,
+        ax,
+        ay,
+        &remove_payout,
+        synced.remove_liquidity_note_script.clone(),
+        serial(8),
+    )?;
+    submit_notes(&mut mock_chain, alice.id(), std::slice::from_ref(&remove)).await?;
+    let executed = ntb.run_until_idle(&mut mock_chain).await?;
+    let remove_claim = remove_payout.expected_note(synced_id, vec![x(ax)?, y(ay)?])?;
+    assert_eq!(executed[0].output_notes, vec![remove_claim.id()]);
+    let pool = pool_state(&mock_chain, &synced)?;
+    assert_eq!(
+        (pool.reserve_x, pool.reserve_y),
+        (120_000 - ax, 480_000 - ay)
+    );
+    Ok(())
+}
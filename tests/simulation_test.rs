@@ -0,0 +1,188 @@
+//! Dry-run decoding: the payout extracted from a locally executed AMM transaction and the
+//! `AmmError` recovered from a rejected one, first straight from MockChain, then through
+//! `simulate_amm_note` with a client of the in-process `MockNode`.
+
+mod common;
+
+use anyhow::Result;
+use common::{FEE_BPS, add_notes, add_pair_faucets, add_pool, auth, mock_client, serial, temp_dir};
+use miden_amm::{
+    common::{
+        PayoutInfo, create_add_liquidity_note, create_swap_note, quote_initial_lp,
+        quote_swap_output,
+    },
+    errors::AmmError,
+    mock_node::MockNode,
+    simulation::{SimulatedPayout, SimulationOutcome, simulate_amm_note},
+};
+use miden_client::{
+    account::{Account, AccountId},
+    asset::FungibleAsset,
+    note::{Note, NoteType},
+    transaction::RawOutputNote,
+};
+use miden_testing::MockChain;
+
+/// A pool funded by alice's deposit, and two swaps committed to the chain but not executed:
+/// one fair, one demanding a unit more than the pool pays.
+struct FundedPool {
+    mock_chain: MockChain,
+    amm_account: Account,
+    faucet_x: AccountId,
+    faucet_y: AccountId,
+    dy: u64,
+    swap_note: Note,
+    /// The payout the fair swap produces.
+    swap_expected: Note,
+    greedy_swap_note: Note,
+}
+
+async fn funded_pool() -> Result<FundedPool> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let alice = builder.add_existing_wallet_with_assets(auth(), [])?;
+
+    let build = add_pool(&mut builder, &faucet_x, &faucet_y)?;
+    let amm_id = build.account.id();
+
+    let (lp1, _) = quote_initial_lp(100_000, 400_000);
+    let add_payout = PayoutInfo::new(alice.id(), serial(1000));
+    let add_note = create_add_liquidity_note(
+        alice.id(),
+        amm_id,
+        FungibleAsset::new(faucet_x.id(), 100_000)?,
+        FungibleAsset::new(faucet_y.id(), 400_000)?,
+        lp1,
+        &add_payout,
+        build.add_liquidity_note_script.clone(),
+        serial(1),
+    )?;
+
+    let dy = quote_swap_output(30_000, 100_000, 400_000, FEE_BPS);
+    let swap_payout = PayoutInfo::new(alice.id(), serial(2000));
+    let swap_note = create_swap_note(
+        alice.id(),
+        amm_id,
+        FungibleAsset::new(faucet_x.id(), 30_000)?,
+        faucet_y.id(),
+        dy,
+        &swap_payout,
+        build.swap_note_script.clone(),
+        serial(2),
+    )?;
+    let greedy_swap_note = create_swap_note(
+        alice.id(),
+        amm_id,
+        FungibleAsset::new(faucet_x.id(), 30_000)?,
+        faucet_y.id(),
+        dy + 1,
+        &PayoutInfo::new(alice.id(), serial(3000)),
+        build.swap_note_script.clone(),
+        serial(3),
+    )?;
+
+    add_notes(&mut builder, [&add_note, &swap_note, &greedy_swap_note]);
+    let mut mock_chain = builder.build()?;
+    let mut amm_account = build.account.clone();
+
+    let executed = mock_chain
+        .build_tx_context(amm_id, &[add_note.id()], &[])?
+        .extend_expected_output_notes(vec![RawOutputNote::Full(
+            add_payout.expected_note(amm_id, vec![FungibleAsset::new(amm_id, lp1)?])?,
+        )])
+        .build()?
+        .execute()
+        .await?;
+    let deposit = SimulatedPayout::from_executed(&executed)?;
+    assert_eq!(deposit.amount_of(amm_id), lp1);
+    amm_account.apply_delta(executed.account_delta())?;
+    mock_chain.add_pending_executed_transaction(&executed)?;
+    mock_chain.prove_next_block()?;
+
+    let swap_expected =
+        swap_payout.expected_note(amm_id, vec![FungibleAsset::new(faucet_y.id(), dy)?])?;
+    Ok(FundedPool {
+        mock_chain,
+        amm_account,
+        faucet_x: faucet_x.id(),
+        faucet_y: faucet_y.id(),
+        dy,
+        swap_note,
+        swap_expected,
+        greedy_swap_note,
+    })
+}
+
+/// Against a funded pool, a fair swap simulates to exactly the quoted payout, and the same
+/// swap demanding one unit more decodes to the MASM slippage error.
+#[tokio::test]
+async fn simulation_reports_payout_or_masm_error() -> Result<()> {
+    let FundedPool {
+        mock_chain,
+        amm_account,
+        faucet_x,
+        faucet_y,
+        dy,
+        swap_note,
+        swap_expected: expected,
+        greedy_swap_note,
+    } = funded_pool().await?;
+
+    // accepted: the simulated payout is the note the user will later claim
+    let executed = mock_chain
+        .build_tx_context(amm_account.id(), &[swap_note.id()], &[])?
+        .extend_expected_output_notes(vec![RawOutputNote::Full(expected.clone())])
+        .build()?
+        .execute()
+        .await?;
+    let payout = SimulatedPayout::from_executed(&executed)?;
+    assert_eq!(payout.note_id, expected.id());
+    assert_eq!(payout.note_type, NoteType::Private);
+    assert_eq!(payout.amount_of(faucet_y), dy);
+    assert_eq!(payout.amount_of(faucet_x), 0);
+
+    // rejected: the executor error decodes to the MASM assertion
+    let err = mock_chain
+        .build_tx_context(amm_account.id(), &[greedy_swap_note.id()], &[])?
+        .build()?
+        .execute()
+        .await
+        .expect_err("slippage-violating swap must fail");
+    assert_eq!(
+        AmmError::from_execution_error(&err),
+        Some(AmmError::Slippage)
+    );
+    Ok(())
+}
+
+/// `simulate_amm_note` through a client of `MockNode`: the client imports the AMM account,
+/// then reports the fair swap's payout and the greedy swap's slippage error. Neither note is
+/// consumed.
+#[tokio::test]
+async fn simulate_amm_note_runs_against_the_node() -> Result<()> {
+    let pool = funded_pool().await?;
+    let amm_id = pool.amm_account.id();
+    let node = MockNode::new(pool.mock_chain);
+    let dir = temp_dir("simulation");
+    let (mut client, _) = mock_client(&node, &dir).await?;
+    assert!(client.get_account(amm_id).await?.is_none());
+
+    let SimulationOutcome::Accepted(payout) =
+        simulate_amm_note(&mut client, &pool.swap_note).await?
+    else {
+        panic!("the fair swap must be accepted");
+    };
+    assert_eq!(payout.note_id, pool.swap_expected.id());
+    assert_eq!(payout.amount_of(pool.faucet_y), pool.dy);
+
+    // the AMM account now lives in the client's store
+    assert!(client.get_account(amm_id).await?.is_some());
+
+    let outcome = simulate_amm_note(&mut client, &pool.greedy_swap_note).await?;
+    assert_eq!(outcome, SimulationOutcome::Rejected(AmmError::Slippage));
+
+    // nothing was submitted: the pool is where the deposit left it
+    let committed = node.mock_chain().committed_account(amm_id)?.clone();
+    assert_eq!(committed.to_commitment(), pool.amm_account.to_commitment());
+    Ok(())
+}
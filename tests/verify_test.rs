@@ -0,0 +1,124 @@
+//! On-chain code verification: a pool built by this crate verifies; accounts that only look
+//! like one are reported component by component.
+
+mod common;
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use common::{add_pair_faucets, add_pool};
+use miden_amm::{
+    common::{AMM_CONTRACT_NS, GOVERNANCE_NS, LIQUIDITY_CONTRACT_NS, LP_METADATA_NS},
+    mock_node::MockNode,
+    verify::{PoolMismatch, verify_pool, verify_pool_account},
+};
+use miden_client::{
+    account::{AccountBuilder, AccountType},
+    note::P2idNote,
+};
+use miden_standards::account::{auth::AuthNetworkAccount, wallets::BasicWallet};
+use miden_testing::MockChain;
+
+#[tokio::test]
+async fn deployed_pool_verifies_and_lookalikes_do_not() -> Result<()> {
+    let mut builder = MockChain::builder();
+    let (faucet_x, faucet_y) = add_pair_faucets(&mut builder)?;
+    let build = add_pool(&mut builder, &faucet_x, &faucet_y)?;
+
+    // a network account allowlisting the AMM's scripts plus P2ID, without the AMM components
+    let mut notes = BTreeSet::from([
+        build.swap_note_script.root(),
+        build.add_liquidity_note_script.root(),
+        build.remove_liquidity_note_script.root(),
+        build.sync_note_script.root(),
+        build.skim_note_script.root(),
+        build.propose_allowlist_note_script.root(),
+        build.execute_allowlist_note_script.root(),
+    ]);
+    notes.insert(P2idNote::script().root());
+    let lookalike = AccountBuilder::new([9u8; 32])
+        .account_type(AccountType::Public)
+        .with_auth_component(
+            AuthNetworkAccount::with_allowed_notes(notes)?
+                .with_allowed_tx_scripts(BTreeSet::from([build.deploy_tx_script.root()])),
+        )
+        .with_component(BasicWallet)
+        .build_existing()?;
+    builder.add_account(lookalike.clone())?;
+    let node = MockNode::new(builder.build()?);
+
+    // ---------------------------------------------------------------------------------
+    // the real pool, fetched through the node
+    // ---------------------------------------------------------------------------------
+    let report = verify_pool(&node, build.account.id()).await?;
+    assert!(report.is_verified(), "{:?}", report.mismatches);
+    assert_eq!(report.amm_id, build.account.id());
+
+    // ---------------------------------------------------------------------------------
+    // the lookalike: right auth and wallet, no AMM code, one extra allowlisted script
+    // ---------------------------------------------------------------------------------
+    let report = verify_pool(&node, lookalike.id()).await?;
+    assert!(!report.is_verified());
+    let mismatches = &report.mismatches;
+    assert!(matches!(mismatches[0], PoolMismatch::CodeCommitment { .. }));
+    let missing: Vec<_> = mismatches
+        .iter()
+        .filter_map(|m| match m {
+            PoolMismatch::MissingProcedures { component, .. } => Some(*component),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        missing,
+        vec![
+            AMM_CONTRACT_NS,
+            LIQUIDITY_CONTRACT_NS,
+            LP_METADATA_NS,
+            GOVERNANCE_NS
+        ]
+    );
+    assert!(
+        mismatches
+            .iter()
+            .any(|m| matches!(m, PoolMismatch::MissingStorageSlot(_)))
+    );
+    let Some(PoolMismatch::NoteAllowlist { expected, actual }) = mismatches
+        .iter()
+        .find(|m| matches!(m, PoolMismatch::NoteAllowlist { .. }))
+    else {
+        panic!("note allowlist mismatch not reported: {mismatches:?}");
+    };
+    assert_eq!(
+        actual.difference(expected).copied().collect::<Vec<_>>(),
+        vec![P2idNote::script().root()]
+    );
+    assert!(
+        !mismatches
+            .iter()
+            .any(|m| matches!(m, PoolMismatch::TxScriptAllowlist { .. }))
+    );
+
+    // ---------------------------------------------------------------------------------
+    // an ordinary faucet: no allowlists at all
+    // ---------------------------------------------------------------------------------
+    let report = verify_pool_account(&faucet_x)?;
+    assert!(
+        report
+            .mismatches
+            .iter()
+            .any(|m| matches!(m, PoolMismatch::UnexpectedProcedures(_)))
+    );
+    let Some(PoolMismatch::TxScriptAllowlist { actual, .. }) = report
+        .mismatches
+        .iter()
+        .find(|m| matches!(m, PoolMismatch::TxScriptAllowlist { .. }))
+    else {
+        panic!("tx-script allowlist mismatch not reported");
+    };
+    assert!(actual.is_empty());
+    assert_eq!(
+        report.mismatches[0].to_string().split_whitespace().next(),
+        Some("code")
+    );
+    Ok(())
+}
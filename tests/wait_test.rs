@@ -0,0 +1,106 @@
+//! The client wait helpers' deadlines, backoff and cancellation, run against the in-process
+//! `MockNode`.
+
+mod common;
+
+use anyhow::{Context, Result};
+use common::{mock_client, temp_dir};
+use miden_amm::{
+    common::{
+        CancelToken, WaitOptions, WaitOutcome, create_basic_account, create_basic_faucet,
+        mint_and_consume_with, wait_for_note_with, wait_for_tx_with,
+    },
+    mock_node::MockNode,
+};
+use miden_client::{
+    asset::FungibleAsset,
+    note::{Note, NoteType},
+    transaction::{PaymentNoteDescription, TransactionRequestBuilder},
+};
+use miden_testing::MockChain;
+use tokio::time::{Duration, Instant};
+
+/// Short delays so the tests poll a handful of times within a second.
+fn fast() -> WaitOptions {
+    WaitOptions::default()
+        .with_timeout(Duration::from_secs(60))
+        .with_backoff(Duration::from_millis(20), Duration::from_millis(100))
+}
+
+#[tokio::test]
+async fn waits_end_with_done_timed_out_or_cancelled() -> Result<()> {
+    let node = MockNode::new(MockChain::new());
+    let dir = temp_dir("wait-outcomes");
+    let (mut client, keystore) = mock_client(&node, &dir).await?;
+
+    let alice = create_basic_account(&mut client, &keystore).await?;
+    let bob = create_basic_account(&mut client, &keystore).await?;
+    let faucet = create_basic_faucet(&mut client, &keystore, "TKX").await?;
+
+    // ---------------------------------------------------------------------------------
+    // done: every transaction commits in the block after its submission
+    // ---------------------------------------------------------------------------------
+    let consume_tx = mint_and_consume_with(&mut client, faucet.id(), alice.id(), 1_000, &fast())
+        .await?
+        .into_result()?;
+    let outcome = wait_for_tx_with(&mut client, consume_tx, &fast()).await?;
+    assert_eq!(outcome, WaitOutcome::Done(node.chain_tip()));
+
+    // a payment note that is built but never submitted never becomes consumable
+    let unsent: Note = TransactionRequestBuilder::new()
+        .build_pay_to_id(
+            PaymentNoteDescription::new(
+                vec![FungibleAsset::new(faucet.id(), 100)?.into()],
+                alice.id(),
+                bob.id(),
+            ),
+            NoteType::Public,
+            client.rng(),
+        )?
+        .expected_output_own_notes()
+        .into_iter()
+        .next()
+        .context("payment note")?;
+
+    // ---------------------------------------------------------------------------------
+    // timed out: the last pause is cut short at the deadline
+    // ---------------------------------------------------------------------------------
+    let options = fast().with_timeout(Duration::from_millis(300));
+    let start = Instant::now();
+    let outcome = wait_for_note_with(&mut client, bob.id(), &unsent, &options).await?;
+    let WaitOutcome::TimedOut { waited, polls } = outcome else {
+        panic!("expected a timeout, got {outcome:?}");
+    };
+    assert!(waited >= Duration::from_millis(300));
+    assert!(start.elapsed() < Duration::from_secs(30));
+    assert!(polls >= 2);
+    let err = outcome.into_result().unwrap_err();
+    assert!(err.to_string().starts_with("timed out after"), "{err}");
+
+    // ---------------------------------------------------------------------------------
+    // cancelled: from another task mid-wait, or before the first poll
+    // ---------------------------------------------------------------------------------
+    let cancel = CancelToken::new();
+    let canceller = cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        canceller.cancel();
+    });
+    let options = fast()
+        .with_timeout(Duration::from_secs(600))
+        .with_cancel(cancel.clone());
+    let outcome = wait_for_note_with(&mut client, bob.id(), &unsent, &options).await?;
+    assert!(
+        matches!(outcome, WaitOutcome::Cancelled { polls } if polls >= 1),
+        "{outcome:?}"
+    );
+    assert!(cancel.is_cancelled());
+
+    let outcome = wait_for_tx_with(&mut client, consume_tx, &options).await?;
+    assert_eq!(outcome, WaitOutcome::Cancelled { polls: 0 });
+    assert_eq!(
+        outcome.into_result().unwrap_err().to_string(),
+        "cancelled after 0 polls"
+    );
+    Ok(())
+}